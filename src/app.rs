@@ -1,12 +1,45 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use eframe::{App, CreationContext};
-use egui::{RichText, vec2};
+use egui::{Direction, Key, KeyboardShortcut, Modifiers, RichText, vec2};
+use egui_toast::{Toast, ToastOptions, ToastStyle, Toasts};
+use parking_lot::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 
 use crate::{
-    IS_DEBUG, project_manager::open_project, ui::{panels::lib::{Panel, PanelStates, create_panels}, windows::WindowsManager},
+    IS_DEBUG,
+    internals::{midi::MIDI_EXTENSIONS, playback::AudioOutput, timeline::Tick},
+    project_manager::{
+        PROJECT_EXTENSION, Project,
+        bundle::{BUNDLE_EXTENSION, save_bundle, save_bundle_archive},
+        history::History,
+        midi::{export_midi, import_midi},
+        new_project, open_project,
+        recovery::{
+            AutosaveSettings, begin_session, create_session_dir, end_session, newest_snapshot,
+            recovery_dir, spawn_autosave,
+        },
+        relink::find_missing_media,
+        save_project,
+    },
+    ui::{
+        panels::lib::{
+            Panel, PanelStates, create_panels, display_error_as_toast, restore_missing_panels,
+        },
+        windows::{WindowContext, WindowsManager},
+    },
 };
 
+/// The maximum amount of entries kept in the "Open Recent" list.
+const RECENT_PROJECTS_LIMIT: usize = 10;
+
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Application {
@@ -25,6 +58,21 @@ pub struct Application {
     /// This field indicates which floating windows are enabled (visible).
     #[serde(skip)]
     opened_windows: WindowsManager,
+
+    /// Toasts which are not bound to any panel (e.g. project loading errors).
+    #[serde(skip)]
+    toasts: Arc<Mutex<Toasts>>,
+
+    /// Settings of the recovery snapshots, these are shared with the autosave task.
+    autosave: Arc<RwLock<AutosaveSettings>>,
+
+    /// The background task writing the recovery snapshots.
+    #[serde(skip)]
+    autosave_task: Option<JoinHandle<()>>,
+
+    /// The output device the playback engine is played on.
+    #[serde(skip)]
+    audio_output: AudioOutput,
 }
 
 impl Default for Application {
@@ -44,17 +92,228 @@ impl Default for Application {
 
             // A struct indicating which windows are enabled
             opened_windows: WindowsManager::default(),
+
+            // Application wide toasts
+            toasts: Arc::new(Mutex::new(Toasts::new().direction(Direction::TopDown))),
+
+            // Snapshots are written in the background
+            autosave: Arc::new(RwLock::new(AutosaveSettings::default())),
+            autosave_task: None,
+
+            // The output device is opened once the application has been created
+            audio_output: AudioOutput::Null,
         }
     }
 }
 
 impl Application {
     pub fn new(cc: &CreationContext) -> Self {
-        if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+        let mut app: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+
+        // Layouts stored by older versions do not contain every panel
+        restore_missing_panels(&mut app.panels);
+
+        app.start_session();
+
+        // Without an output device the playlist can still be edited, it just cannot be heard
+        app.audio_output = display_error_as_toast(
+            AudioOutput::open(&app.panel_states.playback),
+            ToastStyle::default(),
+            app.toasts.clone(),
+        )
+        .unwrap_or_default();
+
+        app
+    }
+
+    /// Offers the newest recovery snapshot if the last session has crashed, then starts writing new snapshots.
+    fn start_session(&mut self) {
+        let dir = recovery_dir();
+
+        if let Some(true) = display_error_as_toast(
+            begin_session(&dir),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        ) && let Some(snapshot) = newest_snapshot(&dir)
+        {
+            self.opened_windows.recovery = true;
+            self.opened_windows.recovery_state.snapshot = Some(snapshot);
+        }
+
+        // The snapshots of this session are kept apart, so that the ones of the crashed session stay until they are recovered
+        let Some(session_dir) = display_error_as_toast(
+            create_session_dir(&dir),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        ) else {
+            return;
+        };
+
+        self.opened_windows.recovery_state.session_dir = Some(session_dir.clone());
+        self.autosave_task = Some(spawn_autosave(
+            self.panel_states.clone(),
+            self.autosave.clone(),
+            session_dir,
+        ));
+    }
+
+    /// Loads the project at `path` into the panel states and remembers it as the currently opened project.
+    fn load_project(&mut self, path: PathBuf) {
+        match display_error_as_toast(
+            open_project(&path),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        ) {
+            Some(project) => {
+                // Let the user relink the files which have been moved since the project was saved
+                let missing = find_missing_media(&project);
+
+                self.opened_windows.missing_media = !missing.is_empty();
+                self.opened_windows.missing_media_state.missing = missing;
+                self.opened_windows.missing_media_state.search_folders =
+                    path.parent().map(Path::to_path_buf).into_iter().collect();
+
+                project.restore(&self.panel_states);
+
+                self.remember_recent(path.clone());
+                self.save_path = Some(path);
+            }
+            // If the project couldnt be opened there is no reason to keep it in the recent list
+            None => self.recently_opened.retain(|recent| *recent != path),
+        }
+    }
+
+    /// Saves the current project to `path` and remembers it as the currently opened project.
+    /// Archives are saved as archives again, so that a project opened from an archive stays self-contained.
+    fn save_project_to(&mut self, path: PathBuf) {
+        let project = Project::capture(&self.panel_states);
+
+        let result = if path.extension() == Some(BUNDLE_EXTENSION.as_ref()) {
+            save_bundle_archive(&path, &project)
+        } else {
+            save_project(&path, &project)
+        };
+
+        if display_error_as_toast(result, ToastStyle::default(), self.toasts.clone()).is_some() {
+            self.toasts.lock().add(
+                Toast::new()
+                    .kind(egui_toast::ToastKind::Success)
+                    .text(format!("Saved project to `{}`", path.display()))
+                    .options(ToastOptions::default().duration_in_seconds(3.)),
+            );
+
+            self.remember_recent(path.clone());
+            self.save_path = Some(path);
+        }
+    }
+
+    /// Asks the user where to save the project, then saves it there.
+    fn save_project_as(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Beatroot Project", &[PROJECT_EXTENSION])
+            .save_file()
+        {
+            self.save_project_to(path.with_extension(PROJECT_EXTENSION));
+        }
+    }
+
+    /// Asks the user where to save the bundle, then collects every referenced sample next to it.
+    /// If `archive` is set everything is written into a single file instead.
+    fn save_bundle_as(&mut self, archive: bool) {
+        let dialog = match archive {
+            true => rfd::FileDialog::new().add_filter("Beatroot Bundle", &[BUNDLE_EXTENSION]),
+            false => rfd::FileDialog::new().add_filter("Beatroot Project", &[PROJECT_EXTENSION]),
+        };
+
+        let Some(path) = dialog.save_file() else {
+            return;
+        };
+
+        if archive {
+            self.save_project_to(path.with_extension(BUNDLE_EXTENSION));
+            return;
+        }
+
+        let path = path.with_extension(PROJECT_EXTENSION);
+
+        if let Some(collected) = display_error_as_toast(
+            save_bundle(&path, &Project::capture(&self.panel_states)),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        ) {
+            // From now on the project should use the collected samples, playback and the history are kept
+            Project::map_state_media_paths(&self.panel_states, |path| {
+                collected
+                    .get(path)
+                    .cloned()
+                    .unwrap_or_else(|| path.to_path_buf())
+            });
+
+            self.toasts.lock().add(
+                Toast::new()
+                    .kind(egui_toast::ToastKind::Success)
+                    .text(format!("Saved bundle to `{}`", path.display()))
+                    .options(ToastOptions::default().duration_in_seconds(3.)),
+            );
+
+            self.remember_recent(path.clone());
+            self.save_path = Some(path);
+        }
+    }
+
+    /// Asks the user for a MIDI file, then places its notes on new tracks at the start of the song.
+    fn import_midi_file(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("MIDI File", MIDI_EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+
+        let row = self.panel_states.playlist_panel.read().tracks.len();
+
+        display_error_as_toast(
+            import_midi(&self.panel_states, &path, row, Tick(0)),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        );
+    }
+
+    /// Asks the user where to save the MIDI file, then writes the notes of the song into it.
+    fn export_midi_file(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("MIDI File", MIDI_EXTENSIONS)
+            .save_file()
+        else {
+            return;
+        };
+
+        let path = path.with_extension(MIDI_EXTENSIONS[0]);
+
+        if display_error_as_toast(
+            export_midi(&self.panel_states.playlist_panel.read(), &path),
+            ToastStyle::default(),
+            self.toasts.clone(),
+        )
+        .is_some()
+        {
+            self.toasts.lock().add(
+                Toast::new()
+                    .kind(egui_toast::ToastKind::Success)
+                    .text(format!("Exported MIDI to `{}`", path.display()))
+                    .options(ToastOptions::default().duration_in_seconds(3.)),
+            );
         }
+    }
 
-        Default::default()
+    /// Move the path to the front of the recently opened projects.
+    fn remember_recent(&mut self, path: PathBuf) {
+        self.recently_opened.retain(|recent| *recent != path);
+        self.recently_opened.insert(0, path);
+        self.recently_opened.truncate(RECENT_PROJECTS_LIMIT);
     }
 }
 
@@ -63,51 +322,161 @@ impl App for Application {
         eframe::set_value(storage, eframe::APP_KEY, self);
     }
 
+    fn on_exit(&mut self) {
+        if let Some(task) = self.autosave_task.take() {
+            task.abort();
+        }
+
+        // The session ended properly, there is nothing to recover on the next launch
+        if let Some(session_dir) = &self.opened_windows.recovery_state.session_dir {
+            let _ = end_session(session_dir);
+        }
+    }
+
     fn update(&mut self, _ctx: &egui::Context, _frame: &mut eframe::Frame) {}
 
     fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        // Text fields handle their own undo and redo
+        if !ui.ctx().egui_wants_keyboard_input() {
+            // Redo has to be checked first, since the undo shortcut would match it too
+            if ui.input_mut(|input| input.consume_shortcut(&REDO_SHORTCUT)) {
+                History::redo(&self.panel_states);
+            } else if ui.input_mut(|input| input.consume_shortcut(&UNDO_SHORTCUT)) {
+                History::undo(&self.panel_states);
+            }
+        }
+
         // Create the main options bar
         egui::Panel::top("application_options").show_inside(ui, |ui| {
             ui.horizontal(|ui| {
                 ui.menu_button("File", |ui| {
-                    if ui.button("New Project").clicked() {}
+                    if ui.button("New Project").clicked() {
+                        new_project().restore(&self.panel_states);
+                        self.save_path = None;
+                    }
 
                     ui.separator();
 
-                    if ui.button("Open").clicked() {
-                        if let Some(path) = rfd::FileDialog::new()
-                            .add_filter("Beatroot Project", &["btrt"])
+                    if ui.button("Open").clicked()
+                        && let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Beatroot Project", &[PROJECT_EXTENSION, BUNDLE_EXTENSION])
                             .pick_file()
-                        {
-                            open_project(&path);
-                        }
+                    {
+                        self.load_project(path);
                     }
+
                     ui.menu_button("Open Recent", |ui| {
                         ui.allocate_ui(vec2(250., 0.), |ui| {
                             ui.label("Recent Projects");
                             ui.separator();
+
+                            // We cannot open the project while iterating over the list, since opening modifies it
+                            let mut clicked_path = None;
+
                             for (idx, path) in self.recently_opened.iter().enumerate() {
                                 if ui
                                     .button(RichText::from(format!("{idx}. {}", path.display())))
                                     .clicked()
                                 {
-                                    open_project(path);
+                                    clicked_path = Some(path.clone());
                                 }
                             }
+
+                            if let Some(path) = clicked_path {
+                                self.load_project(path);
+                            }
                         });
                     });
 
                     ui.separator();
 
-                    if ui.button("Save As").clicked() {}
-                    if ui.button("Save").clicked() {}
+                    if ui.button("Save As").clicked() {
+                        self.save_project_as();
+                    }
+                    if ui.button("Save").clicked() {
+                        match self.save_path.clone() {
+                            Some(path) => self.save_project_to(path),
+                            None => self.save_project_as(),
+                        }
+                    }
+                    ui.menu_button("Save As Bundle", |ui| {
+                        if ui
+                            .button("Folder")
+                            .on_hover_text("Copies every sample into a folder next to the project.")
+                            .clicked()
+                        {
+                            self.save_bundle_as(false);
+                        }
+                        if ui
+                            .button("Archive")
+                            .on_hover_text(
+                                "Writes the project and every sample into a single file.",
+                            )
+                            .clicked()
+                        {
+                            self.save_bundle_as(true);
+                        }
+                    });
+
+                    ui.separator();
+
+                    ui.menu_button("Import", |ui| {
+                        if ui.button("MIDI").clicked() {
+                            self.import_midi_file();
+                        }
+                    });
+                    ui.menu_button("Export", |ui| {
+                        if ui.button("Audio").clicked() {
+                            self.opened_windows.export = true;
+                        }
+                        if ui.button("MIDI").clicked() {
+                            self.export_midi_file();
+                        }
+                    });
+                });
+
+                ui.menu_button("Edit", |ui| {
+                    let (can_undo, can_redo) = {
+                        let history = self.panel_states.history.read();
+
+                        (
+                            !history.undo_entries().is_empty(),
+                            !history.redo_entries().is_empty(),
+                        )
+                    };
+
+                    if ui
+                        .add_enabled(
+                            can_undo,
+                            egui::Button::new("Undo")
+                                .shortcut_text(ui.ctx().format_shortcut(&UNDO_SHORTCUT)),
+                        )
+                        .clicked()
+                    {
+                        History::undo(&self.panel_states);
+                    }
+
+                    if ui
+                        .add_enabled(
+                            can_redo,
+                            egui::Button::new("Redo")
+                                .shortcut_text(ui.ctx().format_shortcut(&REDO_SHORTCUT)),
+                        )
+                        .clicked()
+                    {
+                        History::redo(&self.panel_states);
+                    }
                 });
 
-                ui.menu_button("View", |_ui| {});
+                ui.menu_button("View", |ui| {
+                    ui.checkbox(&mut self.opened_windows.history, "History");
+                });
 
                 if ui.button("Plugins").clicked() {}
 
-                if ui.button("Settings").clicked() {}
+                if ui.button("Settings").clicked() {
+                    self.opened_windows.settings = !self.opened_windows.settings;
+                }
 
                 ui.menu_button("Help", |ui| {
                     ui.label("Build information");
@@ -117,7 +486,6 @@ impl App for Application {
                     ui.separator();
                     ui.hyperlink_to("API documentation", "https://www.google.com")
                 });
-
             });
         });
 
@@ -131,5 +499,18 @@ impl App for Application {
                 panel.toasts.lock().show(ui);
             }
         }
+
+        // Display the enabled floating windows
+        self.opened_windows.display_windows(
+            ui,
+            &WindowContext {
+                panel_states: &self.panel_states,
+                toasts: self.toasts.clone(),
+                autosave: &self.autosave,
+            },
+        );
+
+        // Display the application wide toasts
+        self.toasts.lock().show(ui);
     }
 }
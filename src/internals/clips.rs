@@ -0,0 +1,618 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    f32::consts::FRAC_PI_2,
+    ops::Range,
+};
+
+use crate::{
+    internals::{
+        notes::NoteSequence,
+        patterns::PatternId,
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::{TrackId, TrackList},
+        utils::SerializedStore,
+    },
+    ui::panels::playlist::SampleInstance,
+};
+
+/// Identifies a clip for as long as it exists, moving the clip does not change its id.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct ClipId(pub u64);
+
+/// The shortest a clip can be trimmed to, in the unit of the length of the clip.
+pub const MIN_CLIP_LENGTH: f64 = 0.01;
+
+/// The loudest a clip can be made by its gain, this is about +12 dB.
+pub const MAX_CLIP_GAIN: f32 = 4.;
+
+/// How the gain of a fade changes from silence to the full gain of the clip.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum FadeCurve {
+    #[default]
+    Linear,
+
+    /// Keeps the loudness steady when a fade out overlaps the fade in of another clip.
+    #[strum(to_string = "Equal power")]
+    EqualPower,
+
+    /// Starts slowly, then rises quickly.
+    Exponential,
+
+    /// Rises quickly, then slows down.
+    Logarithmic,
+}
+
+impl FadeCurve {
+    /// The gain of the fade at `progress`, `0.0` is silent and `1.0` is the full gain.
+    pub fn gain(&self, progress: f32) -> f32 {
+        let progress = progress.clamp(0., 1.);
+
+        match self {
+            FadeCurve::Linear => progress,
+            FadeCurve::EqualPower => (progress * FRAC_PI_2).sin(),
+            FadeCurve::Exponential => progress * progress,
+            FadeCurve::Logarithmic => 1. - (1. - progress) * (1. - progress),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Fade {
+    /// The length of the fade in seconds, `0.0` means that there is no fade.
+    pub length: f64,
+    pub curve: FadeCurve,
+}
+
+/// How a clip plays its sample, these are set with the handles of the clip.
+/// Pattern and note clips can only be trimmed and have their gain changed.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ClipShape {
+    /// The amount skipped from the start of the sample, these are counted from the end of the sample if it is reversed.
+    /// This is measured in the unit of the length of the clip, so pattern and note clips skip beats of their steps or notes.
+    pub offset: f64,
+
+    /// Multiplies the samples of the clip, `1.0` leaves them untouched.
+    pub gain: f32,
+
+    /// Plays the sample backwards.
+    pub reverse: bool,
+
+    pub fade_in: Fade,
+    pub fade_out: Fade,
+}
+
+impl Default for ClipShape {
+    fn default() -> Self {
+        Self {
+            offset: 0.,
+            gain: 1.,
+            reverse: false,
+            fade_in: Fade::default(),
+            fade_out: Fade::default(),
+        }
+    }
+}
+
+impl ClipShape {
+    /// The gain `seconds` into a clip which is `length` seconds long, the fades are included.
+    pub fn gain_at(&self, seconds: f64, length: f64) -> f32 {
+        let mut gain = self.gain;
+
+        if seconds < self.fade_in.length {
+            gain *= self
+                .fade_in
+                .curve
+                .gain((seconds / self.fade_in.length) as f32);
+        }
+
+        let until_end = length - seconds;
+
+        if until_end < self.fade_out.length {
+            gain *= self
+                .fade_out
+                .curve
+                .gain((until_end / self.fade_out.length) as f32);
+        }
+
+        gain
+    }
+
+    /// The position in the sample which is played `seconds` into the clip, `sample_length` is the length of the whole sample.
+    pub fn sample_position(&self, seconds: f64, sample_length: f64) -> f64 {
+        match self.reverse {
+            true => sample_length - self.offset - seconds,
+            false => self.offset + seconds,
+        }
+    }
+
+    /// The time into the clip at which the position of the sample is played, this is the inverse of [`ClipShape::sample_position`].
+    pub fn clip_position(&self, sample_position: f64, sample_length: f64) -> f64 {
+        match self.reverse {
+            true => sample_length - self.offset - sample_position,
+            false => sample_position - self.offset,
+        }
+    }
+}
+
+/// What a clip plays.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum ClipSource {
+    Sample(SampleInstance),
+
+    /// Plays the steps of the pattern, editing the pattern changes every clip of it.
+    Pattern(PatternId),
+
+    /// Plays the notes with the instrument of the track, the notes belong to the clip alone.
+    Notes(NoteSequence),
+}
+
+impl ClipSource {
+    /// Whether the length of the clip is measured in beats, so that the clip follows the tempo like the steps of a pattern do.
+    /// Audio is measured in seconds instead, so that it keeps its length when the tempo changes.
+    pub fn follows_tempo(&self) -> bool {
+        !matches!(self, ClipSource::Sample(_))
+    }
+
+    pub fn sample(&self) -> Option<&SampleInstance> {
+        match self {
+            ClipSource::Sample(sample) => Some(sample),
+            _ => None,
+        }
+    }
+
+    pub fn pattern(&self) -> Option<PatternId> {
+        match self {
+            ClipSource::Pattern(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub fn notes(&self) -> Option<&NoteSequence> {
+        match self {
+            ClipSource::Notes(notes) => Some(notes),
+            _ => None,
+        }
+    }
+
+    pub fn notes_mut(&mut self) -> Option<&mut NoteSequence> {
+        match self {
+            ClipSource::Notes(notes) => Some(notes),
+            _ => None,
+        }
+    }
+}
+
+impl From<SampleInstance> for ClipSource {
+    fn from(sample: SampleInstance) -> Self {
+        ClipSource::Sample(sample)
+    }
+}
+
+impl From<PatternId> for ClipSource {
+    fn from(id: PatternId) -> Self {
+        ClipSource::Pattern(id)
+    }
+}
+
+impl From<NoteSequence> for ClipSource {
+    fn from(notes: NoteSequence) -> Self {
+        ClipSource::Notes(notes)
+    }
+}
+
+/// A sample, a pattern or a sequence of notes placed on the timeline.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Clip {
+    pub id: ClipId,
+    pub track: TrackId,
+
+    /// The position the clip starts on.
+    pub start: Tick,
+
+    /// How long the clip plays, in seconds for audio and in beats for pattern and note clips (see [`ClipSource::follows_tempo`]).
+    pub length: f64,
+
+    pub source: ClipSource,
+
+    #[serde(default)]
+    pub shape: ClipShape,
+}
+
+impl Clip {
+    /// The length of the whole sample in seconds, the clip can not be longer than this.
+    /// Patterns are looped and notes can be drawn anywhere, so pattern and note clips can be as long as needed.
+    pub fn sample_length(&self) -> f64 {
+        match &self.source {
+            ClipSource::Sample(sample) => sample
+                .properties
+                .length()
+                .as_secs_f64()
+                .max(self.shape.offset + self.length),
+            ClipSource::Pattern(_) | ClipSource::Notes(_) => f64::INFINITY,
+        }
+    }
+
+    /// The time of the beat in the unit of the length of the clip.
+    fn time_at(&self, beat: f64, tempo: &TempoMap) -> f64 {
+        match self.source.follows_tempo() {
+            true => beat,
+            false => tempo.seconds_at(beat),
+        }
+    }
+
+    /// The beat at the time, this is the inverse of [`Clip::time_at`].
+    fn beat_at(&self, time: f64, tempo: &TempoMap) -> f64 {
+        match self.source.follows_tempo() {
+            true => time,
+            false => tempo.beats_at(time),
+        }
+    }
+
+    /// Moves the start of the clip to the position, the rest of the clip stays in place.
+    /// The start can not be moved before the start of the sample, or after the end of the clip.
+    pub fn trim_start(&mut self, position: Tick, tempo: &TempoMap) {
+        let start_time = self.time_at(self.start.as_beats(), tempo);
+        let end_time = start_time + self.length;
+
+        let time = self
+            .time_at(position.as_beats(), tempo)
+            .max(start_time - self.shape.offset)
+            .min(end_time - MIN_CLIP_LENGTH);
+
+        // The clamped start might not be on a tick, the offset follows the rounded start so that the audio stays in place
+        self.start = Tick::from_beats(self.beat_at(time, tempo));
+
+        let time = self.time_at(self.start.as_beats(), tempo);
+
+        self.shape.offset = (self.shape.offset + time - start_time).max(0.);
+        self.length = end_time - time;
+        self.clamp_fades();
+    }
+
+    /// Moves the end of the clip to the position, it can not be moved after the end of the sample.
+    pub fn trim_end(&mut self, position: Tick, tempo: &TempoMap) {
+        let time =
+            self.time_at(position.as_beats(), tempo) - self.time_at(self.start.as_beats(), tempo);
+
+        self.length = time
+            .max(MIN_CLIP_LENGTH)
+            .min(self.sample_length() - self.shape.offset);
+        self.clamp_fades();
+    }
+
+    /// Plays the clip backwards, the clip keeps playing the same part of the sample.
+    /// Pattern and note clips can not be reversed.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        if self.shape.reverse != reverse && !self.source.follows_tempo() {
+            self.shape.offset = (self.sample_length() - self.shape.offset - self.length).max(0.);
+            self.shape.reverse = reverse;
+        }
+    }
+
+    /// Cuts the clip in two at the position, the first part keeps the id of the clip and the second part gets `id`.
+    /// The first part keeps the fade in and the second part the fade out.
+    /// Returns `None` if the position is not inside of the clip.
+    pub fn split(&self, position: Tick, id: ClipId, tempo: &TempoMap) -> Option<(Clip, Clip)> {
+        let time =
+            self.time_at(position.as_beats(), tempo) - self.time_at(self.start.as_beats(), tempo);
+
+        if time < MIN_CLIP_LENGTH || self.length - time < MIN_CLIP_LENGTH {
+            return None;
+        }
+
+        let mut first = self.clone();
+
+        first.length = time;
+        first.shape.fade_out.length = 0.;
+        first.clamp_fades();
+
+        let mut second = Clip { id, ..self.clone() };
+
+        second.trim_start(position, tempo);
+        second.shape.fade_in.length = 0.;
+        second.clamp_fades();
+
+        Some((first, second))
+    }
+
+    /// Shortens the fades so that they fit into the clip together, the fade out is shortened first.
+    pub fn clamp_fades(&mut self) {
+        self.shape.fade_in.length = self.shape.fade_in.length.clamp(0., self.length);
+        self.shape.fade_out.length = self
+            .shape
+            .fade_out
+            .length
+            .clamp(0., self.length - self.shape.fade_in.length);
+    }
+
+    /// The amount of beats the clip takes up, this follows the tempo changes under the clip.
+    pub fn length_in_beats(&self, tempo: &TempoMap) -> f64 {
+        match self.source.follows_tempo() {
+            true => self.length,
+            false => tempo.beats_in(self.start.as_beats(), self.length),
+        }
+    }
+
+    /// The position the clip stops playing on.
+    pub fn end(&self, tempo: &TempoMap) -> Tick {
+        Tick::from_beats(self.start.as_beats() + self.length_in_beats(tempo))
+    }
+}
+
+/// A clip moved from `(row, start)` to `(row, start)`, the rows are the positions of the tracks in the playlist.
+pub type ClipMove = (ClipId, (usize, Tick), (usize, Tick));
+
+/// Stores the clips of the playlist, the clips of every track are kept sorted by their start.
+/// Clips are allowed to overlap, even if they start on the same beat of the same track.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SerializedStore<Clip>", into = "SerializedStore<Clip>")]
+pub struct ClipStore {
+    clips: HashMap<ClipId, Clip>,
+
+    /// The ids of the clips on each track, ordered by their start.
+    tracks: BTreeMap<TrackId, Vec<ClipId>>,
+
+    /// The id given to the next inserted clip, ids are never handed out twice.
+    /// The counter is only persisted with the app state, opening a project file restarts it after the highest id in the project.
+    /// The ids of clips removed before the project was saved can then be handed out again, the history which could still refer to them is cleared on opening.
+    next_id: u64,
+}
+
+impl ClipStore {
+    /// Creates a new clip with a fresh id, returns the id of the clip.
+    pub fn insert(
+        &mut self,
+        track: TrackId,
+        start: Tick,
+        length: f64,
+        source: impl Into<ClipSource>,
+    ) -> ClipId {
+        let id = self.allocate_id();
+
+        self.insert_clip(Clip {
+            id,
+            track,
+            start,
+            length,
+            source: source.into(),
+            shape: ClipShape::default(),
+        });
+
+        id
+    }
+
+    /// Reserves an id for a clip which is going to be inserted with [`ClipStore::insert_clip`].
+    pub fn allocate_id(&mut self) -> ClipId {
+        let id = ClipId(self.next_id);
+
+        self.next_id += 1;
+
+        id
+    }
+
+    /// Inserts a clip which already has an id, e.g. when an edit is undone or a project is loaded.
+    /// A clip with the same id is replaced.
+    pub fn insert_clip(&mut self, clip: Clip) {
+        self.remove(clip.id);
+
+        self.next_id = self.next_id.max(clip.id.0 + 1);
+
+        let track = self.tracks.entry(clip.track).or_default();
+        let idx = track.partition_point(|id| {
+            let other = &self.clips[id];
+
+            (other.start, other.id) < (clip.start, clip.id)
+        });
+
+        track.insert(idx, clip.id);
+        self.clips.insert(clip.id, clip);
+    }
+
+    pub fn remove(&mut self, id: ClipId) -> Option<Clip> {
+        let clip = self.clips.remove(&id)?;
+
+        if let Some(track) = self.tracks.get_mut(&clip.track) {
+            track.retain(|other| *other != id);
+
+            if track.is_empty() {
+                self.tracks.remove(&clip.track);
+            }
+        }
+
+        Some(clip)
+    }
+
+    /// Moves the clip to another track and start, returns the clip as it was before the move.
+    pub fn move_clip(&mut self, id: ClipId, track: TrackId, start: Tick) -> Option<Clip> {
+        let clip = self.remove(id)?;
+
+        self.insert_clip(Clip {
+            track,
+            start,
+            ..clip.clone()
+        });
+
+        Some(clip)
+    }
+
+    pub fn get(&self, id: ClipId) -> Option<&Clip> {
+        self.clips.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.clips.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clips.is_empty()
+    }
+
+    /// Every clip ordered by the id of its track, then by its start.
+    pub fn iter(&self) -> impl Iterator<Item = &Clip> {
+        self.tracks
+            .values()
+            .flat_map(|track| track.iter().map(|id| &self.clips[id]))
+    }
+
+    /// The path of the samples can be changed, the position of the clips cannot since that would break the ordering.
+    pub fn samples_mut(&mut self) -> impl Iterator<Item = &mut SampleInstance> {
+        self.clips
+            .values_mut()
+            .filter_map(|clip| match &mut clip.source {
+                ClipSource::Sample(sample) => Some(sample),
+                _ => None,
+            })
+    }
+
+    /// The clips playing the pattern.
+    pub fn pattern_clips(&self, pattern: PatternId) -> impl Iterator<Item = &Clip> {
+        self.iter()
+            .filter(move |clip| clip.source.pattern() == Some(pattern))
+    }
+
+    /// The tracks which contain at least a single clip, ordered by their id.
+    pub fn tracks(&self) -> impl Iterator<Item = TrackId> {
+        self.tracks.keys().copied()
+    }
+
+    /// The clips of the track ordered by their start.
+    pub fn track_clips(&self, track: TrackId) -> impl Iterator<Item = &Clip> {
+        self.tracks
+            .get(&track)
+            .into_iter()
+            .flat_map(|track| track.iter().map(|id| &self.clips[id]))
+    }
+
+    /// The position the last clip stops playing on, the start of the song if there are no clips.
+    pub fn end(&self, tempo: &TempoMap) -> Tick {
+        self.clips
+            .values()
+            .map(|clip| clip.end(tempo))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Where the clips end up if they are moved together by the amount of rows and ticks.
+    /// The offsets are limited so that none of the clips is moved before the first track or the start of the song,
+    /// this way the clips always keep their positions relative to each other.
+    /// The clips may end up on the empty rows after the last track.
+    pub fn moved_positions(
+        &self,
+        ids: impl IntoIterator<Item = ClipId>,
+        tracks: &TrackList,
+        rows: i64,
+        ticks: i64,
+    ) -> Vec<ClipMove> {
+        let clips: Vec<(&Clip, usize)> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let clip = self.get(id)?;
+
+                Some((clip, tracks.row(clip.track)?))
+            })
+            .collect();
+
+        let first_row = clips.iter().map(|(_, row)| *row).min().unwrap_or_default() as i64;
+        let first_start = clips
+            .iter()
+            .map(|(clip, _)| clip.start.0)
+            .min()
+            .unwrap_or_default() as i64;
+
+        let rows = rows.max(-first_row);
+        let ticks = ticks.max(-first_start);
+
+        clips
+            .into_iter()
+            .map(|(clip, row)| {
+                (
+                    clip.id,
+                    (row, clip.start),
+                    (
+                        (row as i64 + rows) as usize,
+                        Tick((clip.start.0 as i64 + ticks) as u64),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// The clips of the tracks which are audible at any point of the range, ordered like the tracks, then by their start.
+    pub fn query(
+        &self,
+        tracks: impl IntoIterator<Item = TrackId>,
+        ticks: Range<Tick>,
+        tempo: &TempoMap,
+    ) -> impl Iterator<Item = &Clip> {
+        let Range { start, end } = ticks;
+
+        tracks
+            .into_iter()
+            .filter_map(|track| self.tracks.get(&track))
+            .flat_map(move |track| {
+                // The clips starting after the range can be skipped, the ones before it are checked by their end
+                let starting_before_end = track.partition_point(|id| self.clips[id].start < end);
+
+                track[..starting_before_end]
+                    .iter()
+                    .map(|id| &self.clips[id])
+                    .filter(move |clip| clip.end(tempo) > start)
+            })
+    }
+}
+
+impl From<Vec<Clip>> for ClipStore {
+    fn from(clips: Vec<Clip>) -> Self {
+        let mut store = Self::default();
+
+        for clip in clips {
+            store.insert_clip(clip);
+        }
+
+        store
+    }
+}
+
+impl From<SerializedStore<Clip>> for ClipStore {
+    fn from(serialized: SerializedStore<Clip>) -> Self {
+        let mut store = Self::from(serialized.items);
+
+        store.next_id = store.next_id.max(serialized.next_id);
+
+        store
+    }
+}
+
+impl From<ClipStore> for SerializedStore<Clip> {
+    fn from(store: ClipStore) -> Self {
+        Self {
+            items: store.iter().cloned().collect(),
+            next_id: store.next_id,
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use std::{ffi::OsString, fs, path::PathBuf};
+use std::{
+    ffi::OsString,
+    fs,
+    io::{BufReader, Read},
+    path::{Component, Path, PathBuf},
+};
 
 use crate::internals::utils::CacheState;
 
@@ -63,3 +68,120 @@ pub fn create_entry_map(path: &PathBuf) -> anyhow::Result<FsMap> {
 
     Ok(FsMap { name, objects })
 }
+
+/// Identifies the contents of a file, so that it can be found again after being moved or renamed.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, PartialEq, Eq, Hash)]
+pub struct MediaFingerprint {
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// FNV-1a hash of the contents of the file.
+    pub hash: u64,
+}
+
+/// Reads the whole file and creates its fingerprint.
+pub fn fingerprint_file(path: &Path) -> anyhow::Result<MediaFingerprint> {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut buffer = [0; 64 * 1024];
+
+    let mut size = 0;
+    let mut hash = FNV_OFFSET_BASIS;
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+
+        if read == 0 {
+            break;
+        }
+
+        for byte in &buffer[..read] {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+
+        size += read as u64;
+    }
+
+    Ok(MediaFingerprint { size, hash })
+}
+
+/// Creates a path which points to `path` when joined onto `base`.
+/// Returns `None` if either of the paths is relative or if they dont share a root (e.g. they are on different drives).
+pub fn relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() || !base.is_absolute() {
+        return None;
+    }
+
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+
+    // Both paths have to start from the same root
+    match (path_components.peek(), base_components.peek()) {
+        (Some(Component::Prefix(lhs)), Some(Component::Prefix(rhs))) if lhs != rhs => return None,
+        _ => (),
+    }
+
+    // Skip the part of the paths which is the same
+    while let (Some(lhs), Some(rhs)) = (path_components.peek(), base_components.peek()) {
+        if lhs != rhs {
+            break;
+        }
+
+        path_components.next();
+        base_components.next();
+    }
+
+    // Step out of the rest of the base then step into the rest of the path
+    let mut relative: PathBuf = base_components.map(|_| Component::ParentDir).collect();
+    relative.extend(path_components);
+
+    Some(relative)
+}
+
+/// Recursively lists every file inside of the folder, unreadable entries are skipped.
+pub fn list_files_recursively(folder: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let mut folders = vec![folder.to_path_buf()];
+
+    while let Some(folder) = folders.pop() {
+        let Ok(entries) = fs::read_dir(&folder) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(ty) = entry.file_type() else {
+                continue;
+            };
+
+            if ty.is_dir() {
+                folders.push(entry.path());
+            } else if ty.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files
+}
+
+/// Resolves `.` and `..` components without touching the filesystem.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                // Only step back if there is something to step back from
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -0,0 +1,560 @@
+use std::{f32::consts::TAU, ops::Range, sync::Arc};
+
+use crate::{
+    internals::{
+        notes::MIDDLE_C,
+        playback::OUTPUT_CHANNELS,
+        sample::DecodedSample,
+        synth::{Synth, SynthVoice},
+    },
+    ui::panels::playlist::SampleInstance,
+};
+
+/// The frequency of the A above the middle C, the other keys are tuned relative to it.
+pub const A4_FREQUENCY: f32 = 440.;
+
+/// The key of the A above the middle C.
+const A4_KEY: u8 = 69;
+
+/// The amount of choke groups the samplers can be put into.
+pub const CHOKE_GROUPS: u8 = 8;
+
+/// How long a choked note takes to fade out, in seconds. This is short, but long enough not to click.
+pub const CHOKE_FADE: f64 = 0.005;
+
+/// The frequency of the key in equal temperament.
+pub fn key_frequency(key: u8) -> f32 {
+    A4_FREQUENCY * 2_f32.powf((key as f32 - A4_KEY as f32) / 12.)
+}
+
+/// What plays the notes of the note clips of a track, every track has its own instrument.
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum Instrument {
+    /// A plain sine wave, this is what tracks play their notes with until another instrument is picked.
+    #[default]
+    Sine,
+
+    /// Plays a sample pitched by the keys of the notes.
+    Sampler(Sampler),
+
+    /// A subtractive synth with two oscillators and a filter.
+    Synth(Synth),
+}
+
+impl Instrument {
+    /// How long the note sounds in seconds, including the time it takes to fade out after the key has been released after `gate` seconds.
+    /// `audio` is the decoded sample of the instrument, if it plays one.
+    pub fn duration(&self, key: u8, gate: f64, audio: Option<&DecodedSample>) -> f64 {
+        match self {
+            Instrument::Sine => gate + SineVoice::RELEASE,
+            Instrument::Sampler(sampler) => sampler.duration(key, gate, audio),
+            Instrument::Synth(synth) => synth.duration(gate),
+        }
+    }
+
+    /// Starts playing the key, the key is held for `gate` frames and released afterwards.
+    /// Returns `None` if the instrument cannot play anything, e.g. a sampler without a decoded sample.
+    pub fn voice(
+        &self,
+        key: u8,
+        velocity: f32,
+        gate: usize,
+        sample_rate: u32,
+        audio: Option<&Arc<DecodedSample>>,
+    ) -> Option<InstrumentVoice> {
+        match self {
+            Instrument::Sine => Some(InstrumentVoice::Sine(SineVoice::new(
+                key,
+                velocity,
+                gate,
+                sample_rate,
+            ))),
+            Instrument::Sampler(sampler) => Some(InstrumentVoice::Sampler(SamplerVoice::new(
+                sampler,
+                audio?.clone(),
+                key,
+                velocity,
+                gate,
+                sample_rate,
+            ))),
+            Instrument::Synth(synth) => Some(InstrumentVoice::Synth(Box::new(SynthVoice::new(
+                synth,
+                key,
+                velocity,
+                gate,
+                sample_rate,
+            )))),
+        }
+    }
+
+    /// The sample the instrument plays, it has to be decoded before the notes are played.
+    pub fn sample(&self) -> Option<&SampleInstance> {
+        match self {
+            Instrument::Sampler(sampler) => sampler.sample.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn sample_mut(&mut self) -> Option<&mut SampleInstance> {
+        match self {
+            Instrument::Sampler(sampler) => sampler.sample.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// The amount of notes the instrument plays at once, `None` if there is no limit.
+    pub fn polyphony(&self) -> Option<usize> {
+        match self {
+            Instrument::Sampler(sampler) => Some(sampler.polyphony.max(1)),
+            Instrument::Synth(synth) => Some(synth.polyphony.max(1)),
+            Instrument::Sine => None,
+        }
+    }
+
+    /// Starting a note stops the notes of the other instruments in the same choke group.
+    pub fn choke_group(&self) -> Option<u8> {
+        match self {
+            Instrument::Sampler(sampler) => sampler.choke_group,
+            _ => None,
+        }
+    }
+}
+
+/// The attack, the decay, the sustain and the release of a note.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Envelope {
+    /// The seconds it takes to reach the full level after the key has been pressed.
+    pub attack: f64,
+
+    /// The seconds it takes to fall from the full level to the sustain level.
+    pub decay: f64,
+
+    /// The level held until the key is released, `1.0` is the full level.
+    pub sustain: f32,
+
+    /// The seconds it takes to fade out after the key has been released.
+    pub release: f64,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            attack: 0.002,
+            decay: 0.1,
+            sustain: 1.,
+            release: 0.1,
+        }
+    }
+}
+
+impl Envelope {
+    /// The level of the envelope `time` seconds after the key has been pressed, the key is released after `gate` seconds.
+    pub fn level(&self, time: f64, gate: f64) -> f32 {
+        if time < gate {
+            return self.held(time);
+        }
+
+        if self.release <= 0. {
+            return 0.;
+        }
+
+        // The release starts from wherever the envelope has got to, so that short notes do not click
+        self.held(gate) * (1. - ((time - gate) / self.release) as f32).max(0.)
+    }
+
+    /// The level of the envelope while the key is held.
+    fn held(&self, time: f64) -> f32 {
+        if time < self.attack {
+            (time / self.attack) as f32
+        } else if time < self.attack + self.decay {
+            1. - (1. - self.sustain) * ((time - self.attack) / self.decay) as f32
+        } else {
+            self.sustain
+        }
+    }
+}
+
+/// Whether releasing the key stops the sampler.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum SamplerMode {
+    /// The sample plays while the key is held, then fades out with the release of the envelope.
+    #[default]
+    Gate,
+
+    /// The whole sample is played no matter how long the key is held, e.g. for drums.
+    #[strum(to_string = "One-shot")]
+    OneShot,
+}
+
+/// The part of the sample repeated while the key is held, in seconds from the start of the sample.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LoopPoints {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Plays a sample pitched by the keys of the notes.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Sampler {
+    /// Nothing is played until a sample is loaded.
+    pub sample: Option<SampleInstance>,
+
+    /// The key which plays the sample at its own pitch.
+    pub root: u8,
+    pub envelope: Envelope,
+    pub mode: SamplerMode,
+
+    /// The loop is ignored in one-shot mode, since the key being held does not matter there.
+    pub loop_points: Option<LoopPoints>,
+
+    /// The amount of notes played at once, the oldest notes are stopped to make room for new ones.
+    pub polyphony: usize,
+
+    /// Starting a note stops the notes of the samplers of the other tracks in the same group, e.g. a closed hi-hat chokes an open one.
+    pub choke_group: Option<u8>,
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Self {
+            sample: None,
+            root: MIDDLE_C,
+            envelope: Envelope::default(),
+            mode: SamplerMode::default(),
+            loop_points: None,
+            polyphony: 16,
+            choke_group: None,
+        }
+    }
+}
+
+impl Sampler {
+    /// The amount of frames of the sample advanced by a second of the key, the higher the key the faster the sample is played.
+    fn pitch(&self, key: u8) -> f64 {
+        2_f64.powf((key as f64 - self.root as f64) / 12.)
+    }
+
+    /// The part of the sample which is repeated in frames of the audio, if it is looped and the loop fits into the audio.
+    fn loop_frames(&self, audio: &DecodedSample) -> Option<Range<f64>> {
+        let loop_points = self
+            .loop_points
+            .filter(|_| self.mode == SamplerMode::Gate)?;
+        let sample_rate = audio.sample_rate as f64;
+        let frames = audio.frames() as f64;
+
+        let start = (loop_points.start * sample_rate).clamp(0., frames);
+        let end = (loop_points.end * sample_rate).clamp(0., frames);
+
+        (start < end).then_some(start..end)
+    }
+
+    fn duration(&self, key: u8, gate: f64, audio: Option<&DecodedSample>) -> f64 {
+        let Some(audio) = audio.filter(|audio| audio.sample_rate > 0) else {
+            return 0.;
+        };
+
+        let sample_seconds = audio.frames() as f64 / audio.sample_rate as f64 / self.pitch(key);
+
+        match self.mode {
+            SamplerMode::OneShot => sample_seconds,
+            SamplerMode::Gate if self.loop_frames(audio).is_some() => gate + self.envelope.release,
+            SamplerMode::Gate => sample_seconds.min(gate + self.envelope.release),
+        }
+    }
+}
+
+/// A note being played by an instrument.
+#[derive(Debug, Clone)]
+pub enum InstrumentVoice {
+    Sine(SineVoice),
+    Sampler(SamplerVoice),
+
+    /// The synth keeps a block of rendered frames, which makes it much larger than the other voices.
+    Synth(Box<SynthVoice>),
+}
+
+impl InstrumentVoice {
+    /// Adds the next frames of the note to the interleaved stereo output, returns whether the note has finished.
+    pub fn mix(&mut self, output: &mut [f32]) -> bool {
+        match self {
+            InstrumentVoice::Sine(voice) => voice.mix(output),
+            InstrumentVoice::Sampler(voice) => voice.mix(output),
+            InstrumentVoice::Synth(voice) => voice.mix(output),
+        }
+    }
+
+    /// Skips the frames without playing them, this is used to start a note from its middle.
+    pub fn skip(&mut self, frames: usize) {
+        match self {
+            InstrumentVoice::Sine(voice) => voice.skip(frames),
+            InstrumentVoice::Sampler(voice) => voice.skip(frames),
+            InstrumentVoice::Synth(voice) => voice.skip(frames),
+        }
+    }
+
+    /// Quickly fades out the note `after` frames from now, this is used by choke groups and when the polyphony runs out.
+    pub fn choke(&mut self, after: usize) {
+        match self {
+            InstrumentVoice::Sine(voice) => voice.choke(after),
+            InstrumentVoice::Sampler(voice) => voice.choke(after),
+            InstrumentVoice::Synth(voice) => voice.choke(after),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self {
+            InstrumentVoice::Sine(voice) => voice.is_finished(),
+            InstrumentVoice::Sampler(voice) => voice.is_finished(),
+            InstrumentVoice::Synth(voice) => voice.is_finished(),
+        }
+    }
+}
+
+/// A sine wave which fades in and out quickly, so that the notes do not click.
+#[derive(Debug, Clone)]
+pub struct SineVoice {
+    /// The phase advanced by every frame, a whole period is `1.0`.
+    step: f32,
+    phase: f32,
+    gain: f32,
+
+    /// The amount of frames played so far.
+    frame: usize,
+
+    /// The frame the key is released on.
+    gate: usize,
+    attack_frames: usize,
+    release_frames: usize,
+}
+
+impl SineVoice {
+    /// The length of the fade in, in seconds.
+    const ATTACK: f64 = 0.005;
+
+    /// The length of the fade out after the key is released, in seconds.
+    const RELEASE: f64 = 0.05;
+
+    /// Keeps a few notes played together from clipping.
+    const GAIN: f32 = 0.25;
+
+    fn new(key: u8, velocity: f32, gate: usize, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+
+        Self {
+            step: key_frequency(key) / sample_rate as f32,
+            phase: 0.,
+            gain: velocity * Self::GAIN,
+            frame: 0,
+            gate,
+            attack_frames: ((Self::ATTACK * sample_rate as f64) as usize).max(1),
+            release_frames: ((Self::RELEASE * sample_rate as f64) as usize).max(1),
+        }
+    }
+
+    /// The gain of the envelope on the frame.
+    fn envelope(&self, frame: usize) -> f32 {
+        let attack = (frame as f32 / self.attack_frames as f32).min(1.);
+
+        // The release starts from wherever the attack has got to, so that very short notes do not click either
+        let held = (self.gate as f32 / self.attack_frames as f32).min(1.);
+
+        match frame.checked_sub(self.gate) {
+            None => attack,
+            Some(released) => held * (1. - released as f32 / self.release_frames as f32).max(0.),
+        }
+    }
+
+    fn mix(&mut self, output: &mut [f32]) -> bool {
+        for out_frame in output.chunks_exact_mut(OUTPUT_CHANNELS) {
+            if self.is_finished() {
+                return true;
+            }
+
+            let sample = (self.phase * TAU).sin() * self.gain * self.envelope(self.frame);
+
+            for out in out_frame {
+                *out += sample;
+            }
+
+            self.phase = (self.phase + self.step).fract();
+            self.frame += 1;
+        }
+
+        self.is_finished()
+    }
+
+    fn skip(&mut self, frames: usize) {
+        self.phase = (self.phase + (self.step as f64 * frames as f64).fract() as f32).fract();
+        self.frame += frames;
+    }
+
+    /// The sine is released early, its release is already short enough not to click.
+    fn choke(&mut self, after: usize) {
+        self.gate = self.gate.min(self.frame + after);
+    }
+
+    fn is_finished(&self) -> bool {
+        self.frame >= self.gate + self.release_frames
+    }
+}
+
+/// A sample played by a sampler, the sample is resampled on the fly to the pitch of the key.
+#[derive(Debug, Clone)]
+pub struct SamplerVoice {
+    audio: Arc<DecodedSample>,
+
+    /// The frames of the audio advanced by every output frame.
+    step: f64,
+
+    /// The position in the frames of the audio, this is fractional because of the resampling.
+    position: f64,
+    gain: f32,
+    envelope: Envelope,
+    sample_rate: f64,
+
+    /// The amount of output frames played so far.
+    frame: usize,
+
+    /// The frame the key is released on, `None` in one-shot mode.
+    gate: Option<usize>,
+
+    /// The part of the audio which is repeated, in frames of the audio.
+    loop_frames: Option<Range<f64>>,
+
+    /// The frame the voice has been choked on.
+    choked: Option<usize>,
+    choke_frames: usize,
+}
+
+impl SamplerVoice {
+    fn new(
+        sampler: &Sampler,
+        audio: Arc<DecodedSample>,
+        key: u8,
+        velocity: f32,
+        gate: usize,
+        sample_rate: u32,
+    ) -> Self {
+        let sample_rate = sample_rate.max(1) as f64;
+
+        Self {
+            step: sampler.pitch(key) * audio.sample_rate as f64 / sample_rate,
+            position: 0.,
+            gain: velocity,
+            envelope: sampler.envelope,
+            sample_rate,
+            frame: 0,
+            gate: match sampler.mode {
+                SamplerMode::Gate => Some(gate),
+                SamplerMode::OneShot => None,
+            },
+            loop_frames: sampler.loop_frames(&audio),
+            choked: None,
+            choke_frames: ((CHOKE_FADE * sample_rate) as usize).max(1),
+            audio,
+        }
+    }
+
+    /// The gain of the envelope and of the choke on the frame.
+    fn level(&self, frame: usize) -> f32 {
+        let time = frame as f64 / self.sample_rate;
+        let gate = self
+            .gate
+            .map_or(f64::INFINITY, |gate| gate as f64 / self.sample_rate);
+
+        let choke = match self.choked.and_then(|choked| frame.checked_sub(choked)) {
+            Some(choked) => (1. - choked as f32 / self.choke_frames as f32).max(0.),
+            None => 1.,
+        };
+
+        self.envelope.level(time, gate) * choke
+    }
+
+    /// Moves the position forward, the loop is wrapped around.
+    fn advance(&mut self, frames: f64) {
+        self.position += frames;
+
+        if let Some(loop_frames) = &self.loop_frames
+            && self.position >= loop_frames.end
+        {
+            let length = loop_frames.end - loop_frames.start;
+
+            self.position = loop_frames.start + (self.position - loop_frames.start) % length;
+        }
+    }
+
+    fn mix(&mut self, output: &mut [f32]) -> bool {
+        let frames = self.audio.frames();
+
+        for out_frame in output.chunks_exact_mut(OUTPUT_CHANNELS) {
+            if self.is_finished() {
+                return true;
+            }
+
+            let idx = self.position as usize;
+
+            // Linear interpolation between the two closest frames
+            let fraction = (self.position - idx as f64) as f32;
+            let gain = self.gain * self.level(self.frame);
+
+            for (channel, out) in out_frame.iter_mut().enumerate() {
+                let current = self.audio.sample(idx, channel);
+                let next = match idx + 1 < frames {
+                    true => self.audio.sample(idx + 1, channel),
+                    false => current,
+                };
+
+                *out += (current + (next - current) * fraction) * gain;
+            }
+
+            self.advance(self.step);
+            self.frame += 1;
+        }
+
+        self.is_finished()
+    }
+
+    fn skip(&mut self, frames: usize) {
+        self.advance(self.step * frames as f64);
+        self.frame += frames;
+    }
+
+    fn choke(&mut self, after: usize) {
+        let choked = self.frame + after;
+
+        self.choked = Some(self.choked.map_or(choked, |previous| previous.min(choked)));
+    }
+
+    fn is_finished(&self) -> bool {
+        let released = self.gate.is_some_and(|gate| {
+            let release = (self.envelope.release * self.sample_rate) as usize;
+
+            self.frame >= gate + release
+        });
+        let choked = self
+            .choked
+            .is_some_and(|choked| self.frame >= choked + self.choke_frames);
+
+        self.position >= self.audio.frames() as f64 || released || choked
+    }
+}
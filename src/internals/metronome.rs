@@ -0,0 +1,147 @@
+use std::{f32::consts::TAU, path::PathBuf, sync::Arc};
+
+use crate::internals::sample::DecodedSample;
+
+/// The sample rate the built-in clicks are synthesized in, they are resampled by the voices like any other sample.
+const CLICK_SAMPLE_RATE: u32 = 44100;
+
+/// The gain of the regular beats compared to the accented first beat, when the click is a sample.
+const UNACCENTED_SAMPLE_GAIN: f32 = 0.5;
+
+/// The sound the metronome clicks with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ClickSound {
+    /// A short sine beep, the accented beep is an octave higher.
+    #[default]
+    Beep,
+
+    /// A shorter and drier click.
+    Woodblock,
+
+    /// A sample of the user, the accented beats are louder than the rest.
+    Sample(PathBuf),
+}
+
+impl ClickSound {
+    /// The sounds which are synthesized, these are always available.
+    pub fn built_in() -> [ClickSound; 2] {
+        [ClickSound::Beep, ClickSound::Woodblock]
+    }
+}
+
+impl std::fmt::Display for ClickSound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClickSound::Beep => write!(f, "Beep"),
+            ClickSound::Woodblock => write!(f, "Woodblock"),
+            ClickSound::Sample(path) => write!(
+                f,
+                "{}",
+                path.file_stem().unwrap_or_default().to_string_lossy()
+            ),
+        }
+    }
+}
+
+/// The metronome of the playlist, it follows the tempo and the meter of the song.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MetronomeSettings {
+    /// Whether the metronome clicks along with the playback.
+    pub enabled: bool,
+    pub sound: ClickSound,
+
+    /// The gain of the clicks, this does not depend on the master of the mixer.
+    pub volume: f32,
+
+    /// The amount of bars clicked before the playback starts, the count-in is played even if the metronome is turned off.
+    pub count_in_bars: usize,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sound: ClickSound::default(),
+            volume: 0.7,
+            count_in_bars: 0,
+        }
+    }
+}
+
+/// The most bars the count-in can be set to.
+pub const MAX_COUNT_IN_BARS: usize = 2;
+
+impl MetronomeSettings {
+    /// Creates the sounds of the metronome, samples are decoded with `decode`.
+    /// Returns `None` if the sample of the metronome could not be decoded.
+    pub fn metronome(
+        &self,
+        decode: impl FnOnce(&PathBuf) -> Option<Arc<DecodedSample>>,
+    ) -> Option<Metronome> {
+        let (accent, click) = match &self.sound {
+            ClickSound::Beep => (
+                Arc::new(synthesize_click(1760., 0.06, 60.)),
+                Arc::new(synthesize_click(880., 0.06, 60.)),
+            ),
+            ClickSound::Woodblock => (
+                Arc::new(synthesize_click(1200., 0.03, 150.)),
+                Arc::new(synthesize_click(800., 0.03, 150.)),
+            ),
+            ClickSound::Sample(path) => {
+                let accent = decode(path)?;
+                let click = DecodedSample {
+                    samples: accent
+                        .samples
+                        .iter()
+                        .map(|sample| sample * UNACCENTED_SAMPLE_GAIN)
+                        .collect(),
+                    ..(*accent).clone()
+                };
+
+                (accent, Arc::new(click))
+            }
+        };
+
+        Some(Metronome {
+            accent,
+            click,
+            volume: self.volume,
+        })
+    }
+}
+
+/// The sounds played by the metronome.
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    /// Played on the first beat of every bar.
+    pub accent: Arc<DecodedSample>,
+    pub click: Arc<DecodedSample>,
+    pub volume: f32,
+}
+
+impl Metronome {
+    pub fn sound(&self, accented: bool) -> Arc<DecodedSample> {
+        match accented {
+            true => self.accent.clone(),
+            false => self.click.clone(),
+        }
+    }
+}
+
+/// A mono sine wave which fades out exponentially, `decay` is how fast it fades.
+fn synthesize_click(frequency: f32, seconds: f32, decay: f32) -> DecodedSample {
+    let frames = (seconds * CLICK_SAMPLE_RATE as f32) as usize;
+
+    DecodedSample {
+        sample_rate: CLICK_SAMPLE_RATE,
+        channels: 1,
+        samples: (0..frames)
+            .map(|frame| {
+                let time = frame as f32 / CLICK_SAMPLE_RATE as f32;
+
+                (TAU * frequency * time).sin() * (-decay * time).exp()
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    path::Path,
+};
+
+use anyhow::{Context, bail};
+
+use crate::internals::{
+    notes::Note,
+    tempo::Meter,
+    timeline::{PPQ, Tick},
+};
+
+/// The file extensions of Standard MIDI Files.
+pub const MIDI_EXTENSIONS: &[&str] = &["mid", "midi"];
+
+/// The channel General MIDI plays drums on, counted from 0.
+pub const DRUM_CHANNEL: u8 = 9;
+
+/// The amount of channels of MIDI.
+const CHANNEL_COUNT: u8 = 16;
+
+/// The highest denominator of a meter the timeline can divide a beat into.
+const MAX_METER_DENOMINATOR_POWER: u8 = 4;
+
+/// Whether the file is a Standard MIDI File, judging by its extension.
+pub fn is_midi_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        MIDI_EXTENSIONS
+            .iter()
+            .any(|midi| extension.eq_ignore_ascii_case(midi))
+    })
+}
+
+/// The notes a single channel of a MIDI track plays.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MidiTrack {
+    pub name: String,
+
+    /// Counted from 0, the drums of General MIDI are on [`DRUM_CHANNEL`].
+    pub channel: u8,
+
+    /// The positions of the notes are measured in the ticks of the timeline.
+    pub notes: Vec<Note>,
+}
+
+/// The parts of a Standard MIDI File Beatroot understands, the other events are left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MidiSong {
+    pub tracks: Vec<MidiTrack>,
+
+    /// The tempo in beats per minute from the tick on, ordered by their tick.
+    pub tempo_changes: Vec<(Tick, f32)>,
+
+    /// The meter from the tick on, ordered by their tick.
+    pub meter_changes: Vec<(Tick, Meter)>,
+}
+
+/// Reads the bytes of a MIDI file from the start.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            bail!("The MIDI file is truncated.");
+        }
+
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn peek(&self) -> anyhow::Result<u8> {
+        self.bytes
+            .first()
+            .copied()
+            .context("The MIDI file is truncated.")
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let bytes = self.take(2)?;
+
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// A variable length quantity, 7 bits are stored in every byte and the highest bit marks that more bytes follow.
+    fn vlq(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0;
+
+        // The quantities are at most 4 bytes long
+        for _ in 0..4 {
+            let byte = self.u8()?;
+
+            value = (value << 7) | (byte & 0x7F) as u64;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        bail!("The MIDI file contains an invalid variable length quantity.")
+    }
+
+    /// The type and the body of the next chunk.
+    fn chunk(&mut self) -> anyhow::Result<([u8; 4], Reader<'a>)> {
+        let kind = self.take(4)?;
+        let len = u32::from_be_bytes(self.take(4)?.try_into()?) as usize;
+
+        Ok((
+            kind.try_into()?,
+            Reader {
+                bytes: self.take(len)?,
+            },
+        ))
+    }
+}
+
+/// Reads the notes, the tempo and the meter of a Standard MIDI File of any type.
+/// Every channel of every track which plays notes becomes a track of its own.
+pub fn read_midi(bytes: &[u8]) -> anyhow::Result<MidiSong> {
+    let mut reader = Reader { bytes };
+
+    let Ok((kind, mut header)) = reader.chunk() else {
+        bail!("This file is not a Standard MIDI File.");
+    };
+
+    if kind != *b"MThd" {
+        bail!("This file is not a Standard MIDI File.");
+    }
+
+    let format = header.u16()?;
+    let track_count = header.u16()?;
+    let division = header.u16()?;
+
+    if format > 2 {
+        bail!("Unknown MIDI file format {format}.");
+    }
+
+    if division & 0x8000 != 0 {
+        bail!("MIDI files timed in SMPTE frames are not supported.");
+    }
+
+    if division == 0 {
+        bail!("The MIDI file has no ticks per quarter note.");
+    }
+
+    let mut song = MidiSong::default();
+
+    for index in 0..track_count as usize {
+        // Chunks of unknown types have to be skipped
+        let track = loop {
+            let (kind, chunk) = reader.chunk()?;
+
+            if kind == *b"MTrk" {
+                break chunk;
+            }
+        };
+
+        read_track(track, index, division as u64, &mut song)?;
+    }
+
+    song.tempo_changes.sort_by_key(|(tick, _)| *tick);
+    song.meter_changes.sort_by_key(|(tick, _)| *tick);
+
+    Ok(song)
+}
+
+/// Reads the events of a track chunk into the song.
+fn read_track(
+    mut reader: Reader,
+    index: usize,
+    division: u64,
+    song: &mut MidiSong,
+) -> anyhow::Result<()> {
+    // The ticks of the file are converted into the ticks of the timeline
+    let to_tick = |tick: u64| Tick((tick * PPQ + division / 2) / division);
+
+    let mut tick = 0;
+    let mut running_status = None;
+    let mut name = None;
+    let mut notes: BTreeMap<u8, Vec<Note>> = BTreeMap::new();
+
+    // The keys which are held, along with the tick and the velocity they have been pressed with
+    let mut held: HashMap<(u8, u8), VecDeque<(u64, u8)>> = HashMap::new();
+
+    let mut add_note = |channel: u8, key: u8, (start, velocity): (u64, u8), end: u64| {
+        let start = to_tick(start);
+
+        notes.entry(channel).or_default().push(Note {
+            key,
+            start,
+            length: Tick(to_tick(end).0.saturating_sub(start.0).max(1)),
+            velocity: velocity as f32 / 127.,
+        });
+    };
+
+    while !reader.is_empty() {
+        tick += reader.vlq()?;
+
+        // Channel events may leave out their status if it is the same as the previous one
+        let status = match reader.peek()? {
+            data if data < 0x80 => {
+                running_status.context("The MIDI file has an event without a status.")?
+            }
+            _ => reader.u8()?,
+        };
+
+        match status {
+            0xFF => {
+                let kind = reader.u8()?;
+                let len = reader.vlq()? as usize;
+                let data = reader.take(len)?;
+
+                match kind {
+                    0x03 => name = Some(String::from_utf8_lossy(data).trim().to_string()),
+                    0x51 if len == 3 => {
+                        let micros = u32::from_be_bytes([0, data[0], data[1], data[2]]).max(1);
+
+                        // The tempo is stored in whole microseconds per beat, which cannot hold most tempos exactly
+                        let bpm = (60_000_000. / micros as f64 * 100.).round() / 100.;
+
+                        song.tempo_changes.push((to_tick(tick), bpm as f32));
+                    }
+                    0x58 if len >= 2 => {
+                        // A clamped denominator would change the length of the bars, so the file is refused instead
+                        if data[1] > MAX_METER_DENOMINATOR_POWER {
+                            bail!(
+                                "The MIDI file contains a meter with a denominator above {}, which is not supported.",
+                                1 << MAX_METER_DENOMINATOR_POWER
+                            );
+                        }
+
+                        song.meter_changes.push((
+                            to_tick(tick),
+                            Meter {
+                                numerator: data[0].max(1),
+                                denominator: 1 << data[1],
+                            },
+                        ));
+                    }
+
+                    // End of track
+                    0x2F => break,
+                    _ => {}
+                }
+            }
+            0xF0 | 0xF7 => {
+                let len = reader.vlq()? as usize;
+
+                reader.take(len)?;
+            }
+            0x80..=0xEF => {
+                running_status = Some(status);
+
+                let channel = status & 0x0F;
+
+                match status & 0xF0 {
+                    0x80 | 0x90 => {
+                        let key = reader.u8()? & 0x7F;
+                        let velocity = reader.u8()? & 0x7F;
+
+                        // A note on without any velocity releases the key
+                        if status & 0xF0 == 0x90 && velocity > 0 {
+                            held.entry((channel, key))
+                                .or_default()
+                                .push_back((tick, velocity));
+                        } else if let Some(pressed) = held
+                            .get_mut(&(channel, key))
+                            .and_then(|pressed| pressed.pop_front())
+                        {
+                            add_note(channel, key, pressed, tick);
+                        }
+                    }
+                    0xC0 | 0xD0 => {
+                        reader.u8()?;
+                    }
+                    _ => {
+                        reader.take(2)?;
+                    }
+                }
+            }
+            _ => bail!("The MIDI file contains an invalid event {status:#04x}."),
+        }
+    }
+
+    // Keys which are never released are held until the end of the track
+    for ((channel, key), pressed) in held {
+        for pressed in pressed {
+            add_note(channel, key, pressed, tick);
+        }
+    }
+
+    let channel_count = notes.len();
+
+    for (channel, mut notes) in notes {
+        notes.sort_by_key(|note| (note.start, note.key));
+
+        let name = match (&name, channel_count) {
+            (Some(name), 1) if !name.is_empty() => name.clone(),
+            (Some(name), _) if !name.is_empty() => format!("{name} (Channel {})", channel + 1),
+            (_, 1) => format!("Track {}", index + 1),
+            _ => format!("Channel {}", channel + 1),
+        };
+
+        song.tracks.push(MidiTrack {
+            name,
+            channel,
+            notes,
+        });
+    }
+
+    Ok(())
+}
+
+/// Writes the song into a Standard MIDI File of type 1.
+/// The first track holds the tempo and the meter, every track of the song follows as a track of its own.
+pub fn write_midi(song: &MidiSong) -> anyhow::Result<Vec<u8>> {
+    let track_count = u16::try_from(song.tracks.len() + 1)
+        .ok()
+        .context("There are too many tracks to write a MIDI file.")?;
+
+    let mut bytes = Vec::new();
+    let mut header = vec![0, 1];
+
+    header.extend(track_count.to_be_bytes());
+    header.extend((PPQ as u16).to_be_bytes());
+    write_chunk(&mut bytes, b"MThd", &header);
+
+    let mut conductor = Vec::new();
+
+    for (tick, meter) in &song.meter_changes {
+        let power = meter.denominator.max(1).trailing_zeros() as u8;
+
+        // The clocks per metronome click and the 32nd notes per quarter note are always the default ones
+        conductor.push(Event::meta(
+            *tick,
+            0x58,
+            vec![meter.numerator, power, 24, 8],
+        ));
+    }
+
+    for (tick, bpm) in &song.tempo_changes {
+        let micros = (60_000_000. / bpm.max(1.) as f64).round() as u32;
+
+        conductor.push(Event::meta(*tick, 0x51, micros.to_be_bytes()[1..].to_vec()));
+    }
+
+    write_chunk(&mut bytes, b"MTrk", &track_events(conductor));
+
+    for track in &song.tracks {
+        let channel = track.channel.min(CHANNEL_COUNT - 1);
+        let mut events = vec![Event::meta(Tick(0), 0x03, track.name.as_bytes().to_vec())];
+
+        for note in &track.notes {
+            let velocity = ((note.velocity * 127.).round() as u8).clamp(1, 127);
+            let key = note.key.min(127);
+
+            events.push(Event {
+                tick: note.start,
+                release: false,
+                data: vec![0x90 | channel, key, velocity],
+            });
+            events.push(Event {
+                tick: note.end(),
+                release: true,
+                data: vec![0x80 | channel, key, 0],
+            });
+        }
+
+        write_chunk(&mut bytes, b"MTrk", &track_events(events));
+    }
+
+    Ok(bytes)
+}
+
+/// An event of a track which is about to be written.
+struct Event {
+    tick: Tick,
+
+    /// Releases are written before the other events of their tick, so that repeated notes do not overlap.
+    release: bool,
+    data: Vec<u8>,
+}
+
+impl Event {
+    fn meta(tick: Tick, kind: u8, data: Vec<u8>) -> Self {
+        let mut bytes = vec![0xFF, kind];
+
+        write_vlq(&mut bytes, data.len() as u64);
+        bytes.extend(data);
+
+        Self {
+            tick,
+            release: false,
+            data: bytes,
+        }
+    }
+}
+
+/// The body of a track chunk, the events are sorted and the track is ended after the last one.
+fn track_events(mut events: Vec<Event>) -> Vec<u8> {
+    events.sort_by_key(|event| (event.tick, !event.release));
+
+    let mut bytes = Vec::new();
+    let mut tick = Tick(0);
+
+    for event in events {
+        write_vlq(&mut bytes, event.tick.0 - tick.0);
+        bytes.extend(event.data);
+        tick = event.tick;
+    }
+
+    // End of track
+    bytes.extend([0, 0xFF, 0x2F, 0]);
+
+    bytes
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], body: &[u8]) {
+    bytes.extend(kind);
+    bytes.extend((body.len() as u32).to_be_bytes());
+    bytes.extend(body);
+}
+
+fn write_vlq(bytes: &mut Vec<u8>, value: u64) {
+    // The lowest 7 bits are written last, every byte except for the last one has its highest bit set
+    let mut groups = vec![(value & 0x7F) as u8];
+    let mut value = value >> 7;
+
+    while value > 0 {
+        groups.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.extend(groups.iter().rev());
+}
@@ -1,5 +1,18 @@
+pub mod clips;
 pub mod fs;
+pub mod instruments;
 pub mod library;
 pub mod mem;
+pub mod metronome;
+pub mod midi;
+pub mod notes;
+pub mod patterns;
+pub mod playback;
+pub mod render;
 pub mod sample;
+pub mod synth;
+pub mod tempo;
+pub mod timeline;
+pub mod tracks;
 pub mod utils;
+pub mod wav;
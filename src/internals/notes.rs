@@ -0,0 +1,194 @@
+use egui::Color32;
+
+use crate::internals::timeline::{SnapGrid, Tick};
+
+/// The amount of keys a note can be played on, these are the keys of MIDI.
+pub const KEY_COUNT: u8 = 128;
+
+/// The key of the middle C (`C4`).
+pub const MIDDLE_C: u8 = 60;
+
+/// The names of the keys of an octave, starting from C.
+pub const KEY_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// The velocity of newly drawn notes.
+pub const DEFAULT_VELOCITY: f32 = 0.8;
+
+/// The name of the key along with its octave, e.g. `C4` for the middle C.
+pub fn key_name(key: u8) -> String {
+    format!("{}{}", KEY_NAMES[key as usize % 12], key as i32 / 12 - 1)
+}
+
+/// Whether the key is one of the black keys of the keyboard.
+pub fn is_black_key(key: u8) -> bool {
+    matches!(key % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// A key held down for a while.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Note {
+    /// The MIDI key of the note, see [`MIDDLE_C`].
+    pub key: u8,
+
+    /// The position the note starts on, counted from the start of the notes of the clip.
+    pub start: Tick,
+    pub length: Tick,
+
+    /// Multiplies the loudness of the note, `1.0` plays it at its full volume.
+    pub velocity: f32,
+}
+
+impl Note {
+    /// The position the key is released on.
+    pub fn end(&self) -> Tick {
+        Tick(self.start.0 + self.length.0)
+    }
+}
+
+/// A note played by a clip, returned by [`NoteSequence::played`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlayedNote {
+    /// The beat the note starts on, counted from the start of the clip.
+    pub beat: f64,
+
+    /// How long the key is held in beats, the notes are cut off at the end of the clip.
+    pub length: f64,
+    pub key: u8,
+    pub velocity: f32,
+}
+
+/// The notes of a note clip, these are drawn in the piano roll.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NoteSequence {
+    pub name: String,
+    pub color: Color32,
+
+    /// The notes in the order they have been drawn, they are allowed to overlap.
+    pub notes: Vec<Note>,
+}
+
+impl NoteSequence {
+    pub fn new(name: String, color: Color32) -> Self {
+        Self {
+            name,
+            color,
+            notes: Vec::new(),
+        }
+    }
+
+    /// The position the last note is released on.
+    pub fn end(&self) -> Tick {
+        self.notes.iter().map(Note::end).max().unwrap_or_default()
+    }
+
+    /// The notes played by a clip which starts `offset` beats into the notes and is `length` beats long, ordered by their start.
+    /// The notes starting before the clip are not played, the ones lasting longer than the clip are cut off at its end.
+    pub fn played(&self, offset: f64, length: f64) -> Vec<PlayedNote> {
+        let end = offset + length;
+
+        let mut played: Vec<PlayedNote> = self
+            .notes
+            .iter()
+            .filter(|note| (offset..end).contains(&note.start.as_beats()))
+            .map(|note| PlayedNote {
+                beat: note.start.as_beats() - offset,
+                length: note.end().as_beats().min(end) - note.start.as_beats(),
+                key: note.key,
+                velocity: note.velocity,
+            })
+            .collect();
+
+        played.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        played
+    }
+
+    /// Moves the start and the end of the notes to the closest lines of the grid, the notes are never shortened below a single line.
+    /// Notes which do not exist are skipped, nothing happens if the grid is turned off.
+    pub fn quantize(&mut self, notes: impl IntoIterator<Item = usize>, grid: SnapGrid) {
+        let Some(step) = grid.ticks() else {
+            return;
+        };
+
+        let round = |ticks: u64| (ticks + step / 2) / step * step;
+
+        for idx in notes {
+            let Some(note) = self.notes.get_mut(idx) else {
+                continue;
+            };
+
+            let start = round(note.start.0);
+            let end = round(note.end().0).max(start + step);
+
+            note.start = Tick(start);
+            note.length = Tick(end - start);
+        }
+    }
+}
+
+/// The intervals of the scales the piano roll can highlight.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum ScaleKind {
+    #[default]
+    Major,
+    Minor,
+    #[strum(to_string = "Harmonic minor")]
+    HarmonicMinor,
+    Dorian,
+    Mixolydian,
+    #[strum(to_string = "Major pentatonic")]
+    MajorPentatonic,
+    #[strum(to_string = "Minor pentatonic")]
+    MinorPentatonic,
+    Blues,
+}
+
+impl ScaleKind {
+    /// The keys of the scale in semitones from its root.
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            ScaleKind::Major => &[0, 2, 4, 5, 7, 9, 11],
+            ScaleKind::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            ScaleKind::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            ScaleKind::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            ScaleKind::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            ScaleKind::MajorPentatonic => &[0, 2, 4, 7, 9],
+            ScaleKind::MinorPentatonic => &[0, 3, 5, 7, 10],
+            ScaleKind::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+}
+
+/// A scale starting from a key of the octave.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Scale {
+    /// The key the scale starts from, `0` is C and `11` is B.
+    pub root: u8,
+    pub kind: ScaleKind,
+}
+
+impl Scale {
+    /// Whether the key is in the scale, in any octave.
+    pub fn contains(&self, key: u8) -> bool {
+        let interval = (key + 12 - self.root % 12) % 12;
+
+        self.kind.intervals().contains(&interval)
+    }
+
+    pub fn is_root(&self, key: u8) -> bool {
+        key % 12 == self.root % 12
+    }
+}
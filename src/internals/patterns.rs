@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+
+use egui::Color32;
+
+use crate::{
+    internals::{timeline::PPQ, utils::SerializedStore},
+    ui::panels::playlist::SampleInstance,
+};
+
+/// The step counts a pattern can be set to.
+pub const STEP_COUNTS: &[usize] = &[16, 32];
+
+/// Every step of a pattern is a sixteenth note.
+pub const STEP_TICKS: u64 = PPQ / 4;
+
+/// The velocity of newly set steps.
+pub const DEFAULT_VELOCITY: f32 = 0.8;
+
+/// Identifies a pattern for as long as it exists, the clips of the pattern refer to it by its id.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct PatternId(pub u64);
+
+/// A step of a row which triggers the sample of the row.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Step {
+    /// Multiplies the sample, `1.0` plays it at its full volume.
+    pub velocity: f32,
+
+    /// Muted steps keep their velocity, but they are not played.
+    pub muted: bool,
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self {
+            velocity: DEFAULT_VELOCITY,
+            muted: false,
+        }
+    }
+}
+
+/// A sample of the pattern along with the steps it is triggered on.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PatternRow {
+    pub sample: SampleInstance,
+
+    /// `None` where the sample is not triggered, there is an entry for every step of the pattern.
+    pub steps: Vec<Option<Step>>,
+}
+
+/// A step sequence which can be placed into the playlist any number of times.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Pattern {
+    pub id: PatternId,
+    pub name: String,
+    pub color: Color32,
+    pub rows: Vec<PatternRow>,
+
+    /// The amount of steps of every row.
+    step_count: usize,
+}
+
+/// A step which is played, returned by [`Pattern::hits`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    /// The beat the step is played on, counted from the start of the clip.
+    pub beat: f64,
+
+    /// The index of the row of the step.
+    pub row: usize,
+    pub velocity: f32,
+}
+
+impl Pattern {
+    /// Creates an empty pattern with the least amount of steps.
+    pub fn new(id: PatternId, name: String, color: Color32) -> Self {
+        Self {
+            id,
+            name,
+            color,
+            rows: Vec::new(),
+            step_count: STEP_COUNTS[0],
+        }
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.step_count
+    }
+
+    /// Changes the amount of steps of every row, the steps after the new end are removed.
+    pub fn set_step_count(&mut self, step_count: usize) {
+        self.step_count = step_count.max(1);
+
+        for row in self.rows.iter_mut() {
+            row.steps.resize(self.step_count, None);
+        }
+    }
+
+    /// Adds a row for the sample to the end of the pattern, none of its steps are set.
+    pub fn add_row(&mut self, sample: SampleInstance) {
+        self.rows.push(PatternRow {
+            sample,
+            steps: vec![None; self.step_count],
+        });
+    }
+
+    /// The length of a single pass of the pattern in beats.
+    pub fn length_in_beats(&self) -> f64 {
+        (self.step_count as u64 * STEP_TICKS) as f64 / PPQ as f64
+    }
+
+    /// The steps played by a clip which starts `offset` beats into the pattern and is `length` beats long.
+    /// The pattern is looped if the clip is longer than the pattern, the hits are ordered by their beat.
+    pub fn hits(&self, offset: f64, length: f64) -> Vec<Hit> {
+        let pattern_length = self.length_in_beats();
+        let step_beats = STEP_TICKS as f64 / PPQ as f64;
+        let end = offset + length;
+        let mut hits = Vec::new();
+
+        let first_pass = (offset / pattern_length).floor() as u64;
+        let last_pass = (end / pattern_length).ceil() as u64;
+
+        for pass in first_pass..last_pass {
+            for step in 0..self.step_count {
+                let beat = pass as f64 * pattern_length + step as f64 * step_beats;
+
+                if !(offset..end).contains(&beat) {
+                    continue;
+                }
+
+                for (row_idx, row) in self.rows.iter().enumerate() {
+                    if let Some(Some(played)) = row.steps.get(step)
+                        && !played.muted
+                    {
+                        hits.push(Hit {
+                            beat: beat - offset,
+                            row: row_idx,
+                            velocity: played.velocity,
+                        });
+                    }
+                }
+            }
+        }
+
+        hits
+    }
+}
+
+/// Stores the patterns of the playlist ordered by their id.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SerializedStore<Pattern>", into = "SerializedStore<Pattern>")]
+pub struct PatternStore {
+    patterns: BTreeMap<PatternId, Pattern>,
+
+    /// The id given to the next created pattern, ids are never handed out twice.
+    /// The counter is only persisted with the app state, opening a project file restarts it after the highest id in the project.
+    /// The ids of patterns removed before the project was saved can then be handed out again, the history which could still refer to them is cleared on opening.
+    next_id: u64,
+}
+
+impl PatternStore {
+    /// Creates an empty pattern with a fresh id, the pattern is named after the amount of patterns.
+    /// The pattern is not inserted, so that it can be inserted by an edit.
+    pub fn create(&mut self, color: Color32) -> Pattern {
+        let id = PatternId(self.next_id);
+
+        self.next_id += 1;
+
+        Pattern::new(id, format!("Pattern {}", self.patterns.len() + 1), color)
+    }
+
+    /// Inserts the pattern, a pattern with the same id is replaced.
+    pub fn insert(&mut self, pattern: Pattern) {
+        self.next_id = self.next_id.max(pattern.id.0 + 1);
+
+        self.patterns.insert(pattern.id, pattern);
+    }
+
+    pub fn remove(&mut self, id: PatternId) -> Option<Pattern> {
+        self.patterns.remove(&id)
+    }
+
+    pub fn get(&self, id: PatternId) -> Option<&Pattern> {
+        self.patterns.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Every pattern ordered by its id.
+    pub fn iter(&self) -> impl Iterator<Item = &Pattern> {
+        self.patterns.values()
+    }
+
+    /// The samples of the rows, the path of the samples can be changed.
+    pub fn samples_mut(&mut self) -> impl Iterator<Item = &mut SampleInstance> {
+        self.patterns
+            .values_mut()
+            .flat_map(|pattern| pattern.rows.iter_mut().map(|row| &mut row.sample))
+    }
+}
+
+impl From<Vec<Pattern>> for PatternStore {
+    fn from(patterns: Vec<Pattern>) -> Self {
+        let mut store = Self::default();
+
+        for pattern in patterns {
+            store.insert(pattern);
+        }
+
+        store
+    }
+}
+
+impl From<SerializedStore<Pattern>> for PatternStore {
+    fn from(serialized: SerializedStore<Pattern>) -> Self {
+        let mut store = Self::from(serialized.items);
+
+        store.next_id = store.next_id.max(serialized.next_id);
+
+        store
+    }
+}
+
+impl From<PatternStore> for SerializedStore<Pattern> {
+    fn from(store: PatternStore) -> Self {
+        Self {
+            items: store.patterns.into_values().collect(),
+            next_id: store.next_id,
+        }
+    }
+}
@@ -0,0 +1,1136 @@
+use std::{
+    collections::HashMap,
+    num::NonZero,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use rodio::{ChannelCount, MixerDeviceSink, SampleRate, Source};
+
+use crate::{
+    internals::{
+        clips::{ClipShape, ClipSource},
+        instruments::{Instrument, InstrumentVoice},
+        metronome::{Metronome, MetronomeSettings},
+        sample::{DecodedSample, decode_sample},
+        tempo::TempoMap,
+        timeline::{PPQ, Tick},
+        tracks::TrackId,
+    },
+    ui::panels::{
+        mixer::{ChannelStrip, MixerState},
+        playlist::{PlaylistState, SampleInstance},
+    },
+};
+
+/// Every sample is mixed into stereo, regardless of its own channel count.
+pub const OUTPUT_CHANNELS: usize = 2;
+
+/// The sample rate used when there is no output device to ask.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+/// The amount of frames rendered at once when the output device asks for more audio.
+const BLOCK_FRAMES: usize = 512;
+
+/// The amount of metronome clicks which can be audible at once before the audio thread has to allocate.
+const CLICK_CAPACITY: usize = 64;
+
+/// A sample placed on the timeline of the transport.
+#[derive(Debug, Clone)]
+pub struct ScheduledSample {
+    /// The beat the sample starts on.
+    pub beat: f64,
+    pub track: TrackId,
+    pub audio: Arc<DecodedSample>,
+
+    /// How long the sample plays in seconds, it stops earlier if the audio ends before.
+    pub length: f64,
+    pub shape: ClipShape,
+}
+
+impl ScheduledSample {
+    /// The amount of seconds the sample is audible for.
+    pub fn seconds(&self) -> f64 {
+        let audio_seconds = self.audio.frames() as f64 / self.audio.sample_rate.max(1) as f64;
+
+        self.length.min(audio_seconds - self.shape.offset).max(0.)
+    }
+}
+
+/// A sample started by a clip of the playlist, before its audio has been decoded.
+#[derive(Debug, Clone)]
+pub struct Trigger<'a> {
+    /// The beat the sample starts on.
+    pub beat: f64,
+    pub track: TrackId,
+    pub sample: &'a SampleInstance,
+
+    /// How long the sample plays in seconds.
+    pub length: f64,
+    pub shape: ClipShape,
+}
+
+impl Trigger<'_> {
+    /// Places the decoded audio of the sample on the timeline.
+    pub fn schedule(&self, audio: Arc<DecodedSample>) -> ScheduledSample {
+        ScheduledSample {
+            beat: self.beat,
+            track: self.track,
+            audio,
+            length: self.length,
+            shape: self.shape,
+        }
+    }
+}
+
+/// The samples started by the clips of the playlist.
+/// Pattern clips start the sample of a row on every step, so the steps are played as their pattern currently is.
+pub fn clip_triggers(playlist: &PlaylistState) -> Vec<Trigger<'_>> {
+    let mut triggers = Vec::new();
+
+    for clip in playlist.clips.iter() {
+        match &clip.source {
+            ClipSource::Sample(sample) => triggers.push(Trigger {
+                beat: clip.start.as_beats(),
+                track: clip.track,
+                sample,
+                length: clip.length,
+                shape: clip.shape,
+            }),
+            ClipSource::Pattern(id) => {
+                let Some(pattern) = playlist.patterns.get(*id) else {
+                    continue;
+                };
+
+                triggers.extend(
+                    pattern
+                        .hits(clip.shape.offset, clip.length)
+                        .into_iter()
+                        .map(|hit| Trigger {
+                            beat: clip.start.as_beats() + hit.beat,
+                            track: clip.track,
+                            sample: &pattern.rows[hit.row].sample,
+
+                            // The steps play their whole sample, even if it lasts longer than the clip
+                            length: f64::INFINITY,
+                            shape: ClipShape {
+                                gain: clip.shape.gain * hit.velocity,
+                                ..Default::default()
+                            },
+                        }),
+                );
+            }
+            // The notes are played by the instrument of the track instead, see [`clip_notes`]
+            ClipSource::Notes(_) => {}
+        }
+    }
+
+    triggers
+}
+
+/// A note of a note clip placed on the timeline of the transport.
+#[derive(Debug, Clone)]
+pub struct ScheduledNote {
+    /// The beat the key is pressed on.
+    pub beat: f64,
+
+    /// How long the key is held in beats.
+    pub length: f64,
+    pub track: TrackId,
+    pub key: u8,
+
+    /// The velocity of the note multiplied by the gain of its clip.
+    pub velocity: f32,
+    pub instrument: Arc<Instrument>,
+
+    /// The decoded sample of the instrument, if it plays one.
+    /// The notes are scheduled without it, the sample is decoded by whoever plays the notes so that every file is only decoded once.
+    pub audio: Option<Arc<DecodedSample>>,
+}
+
+impl ScheduledNote {
+    /// The beat the note stops sounding on, this is usually after the key has been released since the instrument takes a while to fade out.
+    pub fn end(&self, tempo: &TempoMap) -> f64 {
+        let gate = tempo.seconds_at(self.beat + self.length) - tempo.seconds_at(self.beat);
+        let seconds = self
+            .instrument
+            .duration(self.key, gate, self.audio.as_deref());
+
+        self.beat + tempo.beats_in(self.beat, seconds)
+    }
+}
+
+/// The notes played by the note clips of the playlist, every note is played by the instrument of its track.
+pub fn clip_notes(playlist: &PlaylistState) -> Vec<ScheduledNote> {
+    let mut notes = Vec::new();
+
+    for clip in playlist.clips.iter() {
+        let Some(sequence) = clip.source.notes() else {
+            continue;
+        };
+
+        let instrument = Arc::new(
+            playlist
+                .tracks
+                .find(clip.track)
+                .map(|track| track.instrument.clone())
+                .unwrap_or_default(),
+        );
+
+        notes.extend(
+            sequence
+                .played(clip.shape.offset, clip.length)
+                .into_iter()
+                .map(|note| ScheduledNote {
+                    beat: clip.start.as_beats() + note.beat,
+                    length: note.length,
+                    track: clip.track,
+                    key: note.key,
+                    velocity: note.velocity * clip.shape.gain,
+                    instrument: instrument.clone(),
+                    audio: None,
+                }),
+        );
+    }
+
+    notes
+}
+
+/// A sample which is currently audible.
+#[derive(Debug, Clone)]
+struct Voice {
+    audio: Arc<DecodedSample>,
+
+    /// The track the voice is mixed into.
+    track: TrackId,
+
+    /// The position of the voice in the frames of its own audio counted from the start of the clip, this is fractional because of the resampling.
+    frame: f64,
+
+    /// The amount of frames of its own audio the voice plays.
+    length: f64,
+    shape: ClipShape,
+
+    /// The amount of output frames to wait before the voice starts, this makes the samples start sample accurately inside a block.
+    delay: usize,
+}
+
+impl Voice {
+    /// Plays the sample from `seconds` into it.
+    fn new(sample: &ScheduledSample, seconds: f64, delay: usize) -> Self {
+        let sample_rate = sample.audio.sample_rate as f64;
+
+        Self {
+            audio: sample.audio.clone(),
+            track: sample.track,
+            frame: seconds * sample_rate,
+            length: sample.seconds() * sample_rate,
+            shape: sample.shape,
+            delay,
+        }
+    }
+
+    /// Plays the whole audio without any changes, this is used by the metronome.
+    fn click(audio: Arc<DecodedSample>, delay: usize) -> Self {
+        Self {
+            length: audio.frames() as f64,
+            audio,
+            track: TrackId::default(),
+            frame: 0.,
+            shape: ClipShape::default(),
+            delay,
+        }
+    }
+
+    /// Adds the voice to the interleaved stereo output, returns whether the voice has finished.
+    fn mix(&mut self, output: &mut [f32], sample_rate: u32) -> bool {
+        let audio_rate = self.audio.sample_rate as f64;
+        let step = audio_rate / sample_rate as f64;
+        let frames = self.audio.frames();
+
+        for out_frame in output.chunks_exact_mut(OUTPUT_CHANNELS).skip(self.delay) {
+            if self.frame >= self.length {
+                return true;
+            }
+
+            let position = self
+                .shape
+                .sample_position(self.frame / audio_rate, frames as f64 / audio_rate)
+                * audio_rate;
+            let gain = self
+                .shape
+                .gain_at(self.frame / audio_rate, self.length / audio_rate);
+
+            // Reversed clips start at the last frame, the position might be slightly outside of the audio
+            let position = position.clamp(0., frames.saturating_sub(1) as f64);
+            let idx = position as usize;
+
+            // Linear interpolation between the two closest frames
+            let fraction = (position - idx as f64) as f32;
+
+            for (channel, out) in out_frame.iter_mut().enumerate() {
+                let current = self.audio.sample(idx, channel);
+                let next = match idx + 1 < frames {
+                    true => self.audio.sample(idx + 1, channel),
+                    false => current,
+                };
+
+                *out += (current + (next - current) * fraction) * gain;
+            }
+
+            self.frame += step;
+        }
+
+        self.delay = self.delay.saturating_sub(output.len() / OUTPUT_CHANNELS);
+
+        self.frame >= self.length
+    }
+}
+
+/// A note which is currently audible.
+#[derive(Debug, Clone)]
+struct NoteVoice {
+    /// The track the note is mixed into.
+    track: TrackId,
+    voice: InstrumentVoice,
+
+    /// The amount of output frames to wait before the note starts.
+    delay: usize,
+
+    /// The limits of the instrument, see [`Instrument::polyphony`] and [`Instrument::choke_group`].
+    polyphony: Option<usize>,
+    choke_group: Option<u8>,
+
+    /// Whether the note is being faded out, choked notes do not count towards the polyphony.
+    choked: bool,
+}
+
+impl NoteVoice {
+    /// Fades out the note `after` frames from the start of the next block.
+    fn choke(&mut self, after: usize) {
+        self.voice.choke(after.saturating_sub(self.delay));
+        self.choked = true;
+    }
+
+    /// Adds the note to the interleaved stereo output, returns whether the note has finished.
+    fn mix(&mut self, output: &mut [f32]) -> bool {
+        let start = (self.delay * OUTPUT_CHANNELS).min(output.len());
+
+        self.delay = self.delay.saturating_sub(output.len() / OUTPUT_CHANNELS);
+
+        self.voice.mix(&mut output[start..])
+    }
+}
+
+/// The state of the playback which is advanced by the audio output.
+/// The position is stored in beats, so that changing the tempo while playing does not make the cursor jump.
+#[derive(Debug, Clone)]
+pub struct Transport {
+    sample_rate: u32,
+    tempo: TempoMap,
+    position: f64,
+    playing: bool,
+
+    /// The beats which are played over and over again, the playback only wraps if it reaches the end of the loop.
+    loop_range: Option<Range<f64>>,
+    samples: Vec<ScheduledSample>,
+    voices: Vec<Voice>,
+    notes: Vec<ScheduledNote>,
+    note_voices: Vec<NoteVoice>,
+    mixer: MixerState,
+
+    /// Clicks on every beat while playing, `None` if the metronome is turned off.
+    metronome: Option<Metronome>,
+
+    /// The clicks of the metronome and of the count-in which are currently audible, they are mixed after the master.
+    clicks: Vec<Voice>,
+    click_volume: f32,
+
+    /// The amount of frames left from the count-in, the position does not move until the count-in has finished.
+    count_in: usize,
+
+    /// Every track is mixed into this buffer before its channel strip is applied, this is kept to avoid allocating in every block.
+    track_buffer: Vec<f32>,
+
+    /// The tracks which have audible voices in the current block, this is kept for the same reason.
+    tracks: Vec<TrackId>,
+
+    /// The loudest samples of the tracks since the peaks were last taken, after their channel strip has been applied.
+    /// The entries are inserted by [`Transport::set_tracks`], so that rendering never has to insert them.
+    peaks: HashMap<TrackId, [f32; 2]>,
+    master_peak: [f32; 2],
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl Transport {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            tempo: TempoMap::default(),
+            position: 0.,
+            playing: false,
+            loop_range: None,
+            samples: Vec::new(),
+            voices: Vec::new(),
+            notes: Vec::new(),
+            note_voices: Vec::new(),
+            mixer: MixerState::default(),
+            metronome: None,
+            clicks: Vec::with_capacity(CLICK_CAPACITY),
+            click_volume: 0.,
+            count_in: 0,
+            track_buffer: Vec::new(),
+            tracks: Vec::new(),
+            peaks: HashMap::new(),
+            master_peak: [0.; 2],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// The position of the playback in beats.
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn set_tempo(&mut self, tempo: TempoMap) {
+        self.tempo = tempo;
+    }
+
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    /// Sets the range of beats which is looped, `None` turns off looping.
+    pub fn set_loop(&mut self, loop_range: Option<Range<f64>>) {
+        self.loop_range = loop_range.filter(|range| range.start < range.end);
+    }
+
+    /// Replaces the samples of the timeline, the samples which are already audible keep playing.
+    pub fn set_samples(&mut self, mut samples: Vec<ScheduledSample>) {
+        // The samples are sorted so that the ones starting in a block can be found without going through all of them
+        samples.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        self.samples = samples;
+    }
+
+    /// Replaces the notes of the timeline, the notes which are already audible keep playing until they are released.
+    pub fn set_notes(&mut self, mut notes: Vec<ScheduledNote>) {
+        notes.sort_by(|a, b| a.beat.total_cmp(&b.beat));
+
+        self.notes = notes;
+    }
+
+    /// Sets the sounds the metronome clicks with, `None` turns off the metronome.
+    pub fn set_metronome(&mut self, metronome: Option<Metronome>) {
+        if let Some(metronome) = &metronome {
+            self.click_volume = metronome.volume;
+        }
+
+        self.metronome = metronome;
+    }
+
+    /// Clicks the bars with the tempo and the meter of the position before the playback continues.
+    /// Any previous count-in is cancelled, nothing is counted in if `bars` is 0.
+    pub fn count_in(&mut self, bars: usize, metronome: &Metronome) {
+        self.clicks.clear();
+        self.count_in = 0;
+
+        let meter = self.tempo.bar_at(Tick::from_beats(self.position)).meter;
+        let beat_seconds =
+            60. / self.tempo.bpm_at(self.position) as f64 * meter.beat_ticks() as f64 / PPQ as f64;
+        let beat_frames = (beat_seconds * self.sample_rate as f64).round() as usize;
+        let beats = bars * meter.numerator as usize;
+
+        self.clicks.extend((0..beats).map(|beat| {
+            Voice::click(
+                metronome.sound(beat % meter.numerator as usize == 0),
+                beat * beat_frames,
+            )
+        }));
+        self.click_volume = metronome.volume;
+        self.count_in = beats * beat_frames;
+    }
+
+    /// Whether the count-in is being played, the song starts once it has finished.
+    pub fn is_counting_in(&self) -> bool {
+        self.count_in > 0
+    }
+
+    /// Sets the channel strips the tracks and the master are mixed with.
+    pub fn set_mixer(&mut self, mixer: MixerState) {
+        self.mixer = mixer;
+    }
+
+    /// Sets the tracks of the playlist, only the peaks of these tracks are measured.
+    pub fn set_tracks(&mut self, tracks: &[TrackId]) {
+        self.peaks.retain(|track, _| tracks.contains(track));
+
+        for track in tracks {
+            self.peaks.entry(*track).or_default();
+        }
+    }
+
+    /// Returns the peaks of the tracks and the master since the last call, then resets them.
+    pub fn take_peaks(&mut self) -> (HashMap<TrackId, [f32; 2]>, [f32; 2]) {
+        // The entries are reset instead of removed, so that rendering does not have to insert them again
+        let peaks = self.peaks.clone();
+
+        for peak in self.peaks.values_mut() {
+            *peak = [0.; 2];
+        }
+
+        (peaks, std::mem::take(&mut self.master_peak))
+    }
+
+    /// Moves the playback to the beat, the samples which have started before the beat are started from their middle.
+    pub fn seek(&mut self, beat: f64) {
+        self.position = beat.max(0.);
+
+        let seconds = self.tempo.seconds_at(self.position);
+
+        // This is called when the playback wraps around the loop, so the voices are reused instead of being collected again
+        let started = self
+            .samples
+            .partition_point(|sample| sample.beat < self.position);
+
+        self.voices.clear();
+        self.voices.extend(
+            self.samples[..started]
+                .iter()
+                .map(|sample| Voice::new(sample, seconds - self.tempo.seconds_at(sample.beat), 0))
+                .filter(|voice| voice.frame < voice.length),
+        );
+
+        // The notes are started in order, so the ones choked by the later notes are choked again
+        self.note_voices.clear();
+
+        for idx in 0..self.notes.partition_point(|note| note.beat < self.position) {
+            let note = &self.notes[idx];
+
+            if let Some(voice) =
+                self.note_voice(note, seconds - self.tempo.seconds_at(note.beat), 0)
+                && !voice.voice.is_finished()
+            {
+                self.start_note(voice);
+            }
+        }
+    }
+
+    /// Presses the key of the note `seconds` after the note has started.
+    /// Returns `None` if the instrument cannot play the note.
+    fn note_voice(&self, note: &ScheduledNote, seconds: f64, delay: usize) -> Option<NoteVoice> {
+        let sample_rate = self.sample_rate as f64;
+        let gate =
+            self.tempo.seconds_at(note.beat + note.length) - self.tempo.seconds_at(note.beat);
+
+        let mut voice = note.instrument.voice(
+            note.key,
+            note.velocity,
+            (gate * sample_rate).round() as usize,
+            self.sample_rate,
+            note.audio.as_ref(),
+        )?;
+
+        voice.skip((seconds * sample_rate).round() as usize);
+
+        Some(NoteVoice {
+            track: note.track,
+            voice,
+            delay,
+            polyphony: note.instrument.polyphony(),
+            choke_group: note.instrument.choke_group(),
+            choked: false,
+        })
+    }
+
+    /// Starts the note, the notes in its choke group and the oldest notes above the polyphony of its instrument are choked when it starts.
+    fn start_note(&mut self, note: NoteVoice) {
+        if let Some(group) = note.choke_group {
+            for other in self.note_voices.iter_mut().filter(|other| {
+                other.track != note.track && other.choke_group == Some(group) && !other.choked
+            }) {
+                other.choke(note.delay);
+            }
+        }
+
+        if let Some(polyphony) = note.polyphony {
+            let is_playing = |other: &&mut NoteVoice| other.track == note.track && !other.choked;
+            let playing = self.note_voices.iter_mut().filter(is_playing).count();
+
+            // The notes are in the order they have been started in, so the oldest ones are choked
+            let excess = (playing + 1).saturating_sub(polyphony);
+
+            for other in self.note_voices.iter_mut().filter(is_playing).take(excess) {
+                other.choke(note.delay);
+            }
+        }
+
+        self.note_voices.push(note);
+    }
+
+    /// Renders the next interleaved stereo frames of the playback into `output`, then advances the position.
+    /// The output is silent if the transport is not playing.
+    pub fn render(&mut self, mut output: &mut [f32]) {
+        output.fill(0.);
+
+        if !self.playing {
+            return;
+        }
+
+        // Only the clicks are audible during the count-in
+        if self.count_in > 0 {
+            let frames = self.count_in.min(output.len() / OUTPUT_CHANNELS);
+            let (count_in, rest) = output.split_at_mut(frames * OUTPUT_CHANNELS);
+
+            self.mix_clicks(count_in);
+            self.count_in -= frames;
+
+            output = rest;
+        }
+
+        while !output.is_empty() {
+            let frames = output.len() / OUTPUT_CHANNELS;
+
+            // The block is split at the end of the loop, the rest of it is rendered from the start of the loop
+            let wrap = self
+                .loop_range
+                .clone()
+                .filter(|range| self.position < range.end)
+                .map(|range| {
+                    let seconds =
+                        self.tempo.seconds_at(range.end) - self.tempo.seconds_at(self.position);
+
+                    // At least a frame is rendered, so that extremely short loops do not get stuck
+                    let until_end = ((seconds * self.sample_rate as f64).round() as usize).max(1);
+
+                    (range.start, until_end)
+                })
+                .filter(|(_, until_end)| *until_end <= frames);
+
+            let block_frames = wrap.map_or(frames, |(_, until_end)| until_end);
+            let (block, rest) = output.split_at_mut(block_frames * OUTPUT_CHANNELS);
+
+            self.render_block(block);
+
+            if let Some((loop_start, _)) = wrap {
+                self.seek(loop_start);
+            }
+
+            output = rest;
+        }
+    }
+
+    /// Mixes the voices into the next interleaved stereo frames, then advances the position.
+    fn render_block(&mut self, output: &mut [f32]) {
+        let seconds = self.tempo.seconds_at(self.position);
+        let end = self
+            .tempo
+            .beats_at(seconds + (output.len() / OUTPUT_CHANNELS) as f64 / self.sample_rate as f64);
+
+        // Start the samples which begin inside this block
+        let first = self
+            .samples
+            .partition_point(|sample| sample.beat < self.position);
+        let last = self.samples.partition_point(|sample| sample.beat < end);
+
+        for sample in &self.samples[first..last] {
+            self.voices.push(Voice::new(
+                sample,
+                0.,
+                ((self.tempo.seconds_at(sample.beat) - seconds) * self.sample_rate as f64) as usize,
+            ));
+        }
+
+        // Press the keys of the notes which begin inside this block
+        let first = self.notes.partition_point(|note| note.beat < self.position);
+        let last = self.notes.partition_point(|note| note.beat < end);
+
+        for idx in first..last {
+            let note = &self.notes[idx];
+            let delay =
+                ((self.tempo.seconds_at(note.beat) - seconds) * self.sample_rate as f64) as usize;
+
+            if let Some(voice) = self.note_voice(note, 0., delay) {
+                self.start_note(voice);
+            }
+        }
+
+        // The metronome clicks on the beats of the meter, the first beat of every bar is accented
+        if let Some(metronome) = &self.metronome {
+            for bar in self.tempo.bars_from(Tick::from_beats(self.position)) {
+                if bar.start.as_beats() >= end {
+                    break;
+                }
+
+                for beat in 0..bar.meter.numerator as u64 {
+                    let beat_position =
+                        Tick(bar.start.0 + beat * bar.meter.beat_ticks()).as_beats();
+
+                    if (self.position..end).contains(&beat_position) {
+                        self.clicks.push(Voice::click(
+                            metronome.sound(beat == 0),
+                            ((self.tempo.seconds_at(beat_position) - seconds)
+                                * self.sample_rate as f64) as usize,
+                        ));
+                    }
+                }
+            }
+        }
+
+        let sample_rate = self.sample_rate;
+
+        self.tracks.clear();
+        self.tracks.extend(
+            self.voices
+                .iter()
+                .map(|voice| voice.track)
+                .chain(self.note_voices.iter().map(|note| note.track)),
+        );
+        self.tracks.sort_unstable();
+        self.tracks.dedup();
+
+        self.track_buffer.resize(output.len(), 0.);
+
+        // Every track is mixed on its own, so that its channel strip can be applied before it is added to the master
+        for &track in &self.tracks {
+            self.track_buffer.fill(0.);
+
+            let track_buffer = &mut self.track_buffer;
+
+            self.voices
+                .retain_mut(|voice| voice.track != track || !voice.mix(track_buffer, sample_rate));
+            self.note_voices
+                .retain_mut(|note| note.track != track || !note.mix(track_buffer));
+
+            // Tracks which have not been set yet are mixed without measuring their peak
+            let mut unmeasured = [0.; 2];
+            let peak = self.peaks.get_mut(&track).unwrap_or(&mut unmeasured);
+
+            apply_gains(&mut self.track_buffer, self.mixer.track_gains(track), peak);
+
+            for (out, sample) in output.iter_mut().zip(&self.track_buffer) {
+                *out += sample;
+            }
+        }
+
+        apply_gains(output, self.mixer.master.gains(), &mut self.master_peak);
+
+        self.mix_clicks(output);
+
+        self.position = end;
+    }
+
+    /// Adds the clicks of the metronome to the output, they are not affected by the mixer.
+    fn mix_clicks(&mut self, output: &mut [f32]) {
+        if self.clicks.is_empty() {
+            return;
+        }
+
+        let sample_rate = self.sample_rate;
+
+        self.track_buffer.resize(output.len(), 0.);
+        self.track_buffer.fill(0.);
+
+        let track_buffer = &mut self.track_buffer;
+
+        self.clicks
+            .retain_mut(|voice| !voice.mix(track_buffer, sample_rate));
+
+        for (out, sample) in output.iter_mut().zip(&self.track_buffer) {
+            *out += sample * self.click_volume;
+        }
+    }
+}
+
+/// Multiplies the interleaved stereo samples with the gains of the channels, the loudest resulting samples are stored in `peak`.
+fn apply_gains(samples: &mut [f32], gains: [f32; 2], peak: &mut [f32; 2]) {
+    for frame in samples.chunks_exact_mut(OUTPUT_CHANNELS) {
+        for ((sample, gain), peak) in frame.iter_mut().zip(gains).zip(peak.iter_mut()) {
+            *sample *= gain;
+            *peak = peak.max(sample.abs());
+        }
+    }
+}
+
+/// A file in the cache of the engine.
+#[derive(Debug, Clone)]
+enum CachedSample {
+    /// The file is being decoded in the background.
+    Decoding,
+
+    /// A file which could not be decoded is stored as `None`, so that it is not retried every frame.
+    Decoded(Option<Arc<DecodedSample>>),
+}
+
+/// What the engine has been synced with the last time, so that only the changes have to be applied.
+#[derive(Debug, Default)]
+struct SyncedState {
+    /// The generation of the project and the amount of files decoded at the time, `None` if the engine has never been synced.
+    timeline: Option<(u64, u64)>,
+
+    /// The tempo is compared on its own, so that it follows the playlist even if it has been changed outside of the history.
+    tempo: TempoMap,
+
+    /// The channel strips of the tracks and of the master, the mixer is compared on its own so that moving a fader does not rebuild the timeline.
+    mixer: (HashMap<TrackId, ChannelStrip>, ChannelStrip),
+    metronome: MetronomeSettings,
+    loop_range: Option<Range<f64>>,
+}
+
+/// The audio engine playing the playlist.
+/// The engine itself does not own an output, it is rendered by an [`AudioOutput`] (or manually when testing).
+#[derive(Debug, Clone, Default)]
+pub struct PlaybackEngine {
+    transport: Arc<Mutex<Transport>>,
+
+    cache: Arc<Mutex<HashMap<PathBuf, CachedSample>>>,
+
+    /// Counts the files which have finished decoding in the background, the timeline is rebuilt once it changes.
+    decodes: Arc<AtomicU64>,
+
+    synced: Arc<Mutex<SyncedState>>,
+
+    /// The sounds of the metronome created for the settings, these are only recreated if the settings change.
+    metronome: Arc<Mutex<Option<(MetronomeSettings, Metronome)>>>,
+}
+
+impl PlaybackEngine {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            transport: Arc::new(Mutex::new(Transport::new(sample_rate))),
+            ..Default::default()
+        }
+    }
+
+    /// The position of the playback in beats.
+    pub fn position(&self) -> f64 {
+        self.transport.lock().position()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.transport.lock().is_playing()
+    }
+
+    pub fn play(&self) {
+        self.transport.lock().set_playing(true);
+    }
+
+    pub fn pause(&self) {
+        self.transport.lock().set_playing(false);
+    }
+
+    /// Pauses the playback and rewinds it to the start.
+    pub fn stop(&self) {
+        let mut transport = self.transport.lock();
+
+        transport.set_playing(false);
+        transport.seek(0.);
+    }
+
+    pub fn seek(&self, beat: f64) {
+        self.transport.lock().seek(beat);
+    }
+
+    /// Updates the timeline and the mixer of the engine, this is cheap to call every frame.
+    /// The timeline is only rebuilt if the `generation` of the project (see [`crate::project_manager::history::History::timeline_generation`]) has changed,
+    /// or if samples have finished decoding since the last sync. The mixer is applied on its own whenever its strips change.
+    /// Samples are decoded in the background the first time they are played, they are left out of the timeline until they are ready.
+    /// The notes are played by the instruments of their tracks, the samples of the samplers are decoded the same way.
+    pub fn sync(&self, playlist: &PlaylistState, mixer: &MixerState, generation: u64) {
+        let mut synced = self.synced.lock();
+
+        let timeline = Some((generation, self.decodes.load(Ordering::Acquire)));
+        let loop_range = playlist
+            .active_loop()
+            .map(|range| range.start.as_beats()..range.end.as_beats());
+
+        let is_first = synced.timeline.is_none();
+        let is_timeline_changed = synced.timeline != timeline;
+        let is_tempo_changed = is_first || synced.tempo != playlist.tempo;
+        let is_mixer_changed =
+            is_first || synced.mixer.0 != mixer.channels || synced.mixer.1 != mixer.master;
+        let is_metronome_changed = is_timeline_changed || synced.metronome != playlist.metronome;
+        let is_loop_changed = is_first || synced.loop_range != loop_range;
+
+        // Everything is built before the transport is locked, the audio output has to wait for the lock
+        let samples = is_timeline_changed.then(|| {
+            clip_triggers(playlist)
+                .into_iter()
+                .filter_map(|trigger| Some(trigger.schedule(self.decoded(&trigger.sample.path)?)))
+                .collect()
+        });
+
+        let notes = is_timeline_changed.then(|| {
+            clip_notes(playlist)
+                .into_iter()
+                .map(|mut note| {
+                    note.audio = note
+                        .instrument
+                        .sample()
+                        .and_then(|sample| self.decoded(&sample.path));
+
+                    note
+                })
+                .collect()
+        });
+
+        let tracks = is_timeline_changed.then(|| {
+            playlist
+                .tracks
+                .iter()
+                .map(|track| track.id)
+                .collect::<Vec<_>>()
+        });
+
+        let tempo = is_tempo_changed.then(|| playlist.tempo.clone());
+        // The displayed peaks are left out, the transport only uses the strips
+        let mixer = is_mixer_changed.then(|| MixerState {
+            channels: mixer.channels.clone(),
+            master: mixer.master,
+            ..Default::default()
+        });
+
+        let metronome = is_metronome_changed.then(|| {
+            playlist
+                .metronome
+                .enabled
+                .then(|| self.metronome(&playlist.metronome))
+                .flatten()
+        });
+
+        synced.timeline = timeline;
+
+        if let Some(tempo) = &tempo {
+            synced.tempo.clone_from(tempo);
+        }
+
+        if let Some(mixer) = &mixer {
+            synced.mixer = (mixer.channels.clone(), mixer.master);
+        }
+
+        synced.metronome.clone_from(&playlist.metronome);
+        synced.loop_range.clone_from(&loop_range);
+
+        if !is_timeline_changed
+            && !is_tempo_changed
+            && !is_mixer_changed
+            && !is_metronome_changed
+            && !is_loop_changed
+        {
+            return;
+        }
+
+        let mut transport = self.transport.lock();
+
+        if let Some(tempo) = tempo {
+            transport.set_tempo(tempo);
+        }
+
+        if let Some(metronome) = metronome {
+            transport.set_metronome(metronome);
+        }
+
+        if let Some(samples) = samples {
+            transport.set_samples(samples);
+        }
+
+        if let Some(notes) = notes {
+            transport.set_notes(notes);
+        }
+
+        if let Some(tracks) = tracks {
+            transport.set_tracks(&tracks);
+        }
+
+        if let Some(mixer) = mixer {
+            transport.set_mixer(mixer);
+        }
+
+        if is_loop_changed {
+            transport.set_loop(loop_range);
+        }
+    }
+
+    /// Plays the count-in of the metronome from the current position, the playback continues once it has finished.
+    /// The engine has to be synced first, so that the count-in follows the tempo of the playlist.
+    pub fn count_in(&self, settings: &MetronomeSettings) {
+        let Some(metronome) = self.metronome(settings) else {
+            return;
+        };
+
+        self.transport
+            .lock()
+            .count_in(settings.count_in_bars, &metronome);
+    }
+
+    /// Whether the count-in is being played.
+    pub fn is_counting_in(&self) -> bool {
+        self.transport.lock().is_counting_in()
+    }
+
+    /// Fetches the sounds of the metronome from the cache, they are created if the settings have changed.
+    fn metronome(&self, settings: &MetronomeSettings) -> Option<Metronome> {
+        let mut cached = self.metronome.lock();
+
+        if let Some((cached_settings, metronome)) = &*cached
+            && cached_settings == settings
+        {
+            return Some(metronome.clone());
+        }
+
+        let metronome = settings.metronome(|path| self.decoded(path))?;
+
+        *cached = Some((settings.clone(), metronome.clone()));
+
+        Some(metronome)
+    }
+
+    /// The peaks of the tracks and the master since the last call, these are displayed by the meters of the mixer.
+    pub fn take_peaks(&self) -> (HashMap<TrackId, [f32; 2]>, [f32; 2]) {
+        self.transport.lock().take_peaks()
+    }
+
+    /// Fetches the decoded contents of the file from the cache.
+    /// A file which is not present yet is decoded in the background, `None` is returned until it is ready.
+    pub fn decoded(&self, path: &PathBuf) -> Option<Arc<DecodedSample>> {
+        let mut cache = self.cache.lock();
+
+        match cache.get(path) {
+            Some(CachedSample::Decoded(decoded)) => return decoded.clone(),
+            Some(CachedSample::Decoding) => return None,
+            None => (),
+        }
+
+        cache.insert(path.clone(), CachedSample::Decoding);
+        drop(cache);
+
+        let cache = self.cache.clone();
+        let decodes = self.decodes.clone();
+        let path = path.clone();
+
+        // The cache is not locked while decoding, so that neither the ui nor the other files have to wait for it
+        std::thread::spawn(move || {
+            let decoded = decode_sample(&path)
+                .ok()
+                .filter(|decoded| decoded.sample_rate > 0 && decoded.frames() > 0)
+                .map(Arc::new);
+
+            cache.lock().insert(path, CachedSample::Decoded(decoded));
+            decodes.fetch_add(1, Ordering::Release);
+        });
+
+        None
+    }
+
+    /// Whether files are still being decoded in the background.
+    pub fn is_decoding(&self) -> bool {
+        self.cache
+            .lock()
+            .values()
+            .any(|cached| matches!(cached, CachedSample::Decoding))
+    }
+
+    /// Renders the next interleaved stereo frames, this is what the output device calls.
+    pub fn render(&self, output: &mut [f32]) {
+        self.transport.lock().render(output);
+    }
+
+    /// Creates a source which can be played by rodio, the source renders the engine until it is dropped.
+    pub fn source(&self) -> EngineSource {
+        EngineSource {
+            engine: self.clone(),
+            buffer: vec![0.; BLOCK_FRAMES * OUTPUT_CHANNELS],
+            cursor: BLOCK_FRAMES * OUTPUT_CHANNELS,
+        }
+    }
+}
+
+/// An endless rodio source rendering the engine block by block.
+pub struct EngineSource {
+    engine: PlaybackEngine,
+    buffer: Vec<f32>,
+
+    /// The index of the next sample in the buffer to be played.
+    cursor: usize,
+}
+
+impl Iterator for EngineSource {
+    type Item = rodio::Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor >= self.buffer.len() {
+            self.engine.render(&mut self.buffer);
+            self.cursor = 0;
+        }
+
+        let sample = self.buffer[self.cursor];
+
+        self.cursor += 1;
+
+        Some(sample as rodio::Sample)
+    }
+}
+
+impl Source for EngineSource {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> ChannelCount {
+        NonZero::new(OUTPUT_CHANNELS as u16).unwrap()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        NonZero::new(self.engine.transport.lock().sample_rate())
+            .unwrap_or(NonZero::new(DEFAULT_SAMPLE_RATE).unwrap())
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Where the engine is played to.
+#[derive(Debug, Default)]
+pub enum AudioOutput {
+    /// The default output device of the system.
+    Device(MixerDeviceSink),
+
+    /// Nothing is played, the engine has to be rendered manually. This is used when there is no output device (e.g. in tests).
+    #[default]
+    Null,
+}
+
+impl AudioOutput {
+    /// Opens the default output device and starts playing the engine on it.
+    pub fn open(engine: &PlaybackEngine) -> anyhow::Result<Self> {
+        let mut sink = rodio::DeviceSinkBuilder::open_default_sink()?;
+
+        sink.log_on_drop(false);
+
+        // Render in the rate of the device, so that rodio does not have to resample the engine
+        engine.transport.lock().sample_rate = sink.config().sample_rate().get();
+
+        sink.mixer().add(engine.source());
+
+        Ok(Self::Device(sink))
+    }
+}
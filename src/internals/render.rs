@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
+
+use crate::{
+    internals::{
+        metronome::Metronome,
+        playback::{
+            OUTPUT_CHANNELS, ScheduledNote, ScheduledSample, Transport, clip_notes, clip_triggers,
+        },
+        sample::{DecodedSample, decode_sample},
+        tempo::TempoMap,
+        tracks::TrackId,
+        wav::{BitDepth, write_wav},
+    },
+    ui::panels::{
+        mixer::MixerState,
+        playlist::{PlaylistState, SampleInstance, TrackCustomization},
+    },
+};
+
+/// The sample rates offered when exporting.
+pub const EXPORT_SAMPLE_RATES: &[u32] = &[44100, 48000, 88200, 96000];
+
+/// The amount of frames rendered at once, this does not affect the result.
+const RENDER_BLOCK_FRAMES: usize = 4096;
+
+/// The part of the song which gets rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RenderRange {
+    /// From the start of the playlist until the last sample has finished and the last clip has ended.
+    #[default]
+    Song,
+
+    /// The bars are counted from 1, both of the bars are included.
+    Bars { first: usize, last: usize },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RenderSettings {
+    pub sample_rate: u32,
+    pub bit_depth: BitDepth,
+    pub range: RenderRange,
+
+    /// The metronome of the playlist is left out of the render, unless this is set.
+    #[serde(default)]
+    pub include_metronome: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44100,
+            bit_depth: BitDepth::default(),
+            range: RenderRange::default(),
+            include_metronome: false,
+        }
+    }
+}
+
+/// Everything the playlist plays, ready to be rendered.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    pub samples: Vec<ScheduledSample>,
+    pub notes: Vec<ScheduledNote>,
+}
+
+impl Schedule {
+    /// The part of the schedule which is played on the track.
+    pub fn track(&self, track: TrackId) -> Schedule {
+        Schedule {
+            samples: self
+                .samples
+                .iter()
+                .filter(|sample| sample.track == track)
+                .cloned()
+                .collect(),
+            notes: self
+                .notes
+                .iter()
+                .filter(|note| note.track == track)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// The tracks which play at least a single sample or note.
+    pub fn tracks(&self) -> BTreeSet<TrackId> {
+        self.samples
+            .iter()
+            .map(|sample| sample.track)
+            .chain(self.notes.iter().map(|note| note.track))
+            .collect()
+    }
+}
+
+/// Decodes every sample of the playlist in the sample rate of the render, including the samples of the patterns and of the samplers.
+/// Every file is only decoded once, no matter how many times it is used.
+pub fn schedule_playlist(playlist: &PlaylistState, sample_rate: u32) -> anyhow::Result<Schedule> {
+    let mut decoded: HashMap<PathBuf, Arc<DecodedSample>> = HashMap::new();
+
+    let mut decode = |sample: &SampleInstance| -> anyhow::Result<Arc<DecodedSample>> {
+        if let Some(audio) = decoded.get(&sample.path) {
+            return Ok(Arc::clone(audio));
+        }
+
+        let audio = decode_sample(&sample.path)
+            .and_then(|audio| audio.resampled(sample_rate))
+            .map_err(|err| anyhow!("Could not render `{}`: {err}", sample.path.display()))?;
+        let audio = Arc::new(audio);
+
+        decoded.insert(sample.path.clone(), audio.clone());
+
+        Ok(audio)
+    };
+
+    let mut samples = Vec::new();
+
+    for trigger in clip_triggers(playlist) {
+        samples.push(trigger.schedule(decode(trigger.sample)?));
+    }
+
+    let mut notes = Vec::new();
+
+    for mut note in clip_notes(playlist) {
+        if let Some(sample) = note.instrument.sample() {
+            note.audio = Some(decode(sample)?);
+        }
+
+        notes.push(note);
+    }
+
+    Ok(Schedule { samples, notes })
+}
+
+/// The beat the last sample or note of the schedule stops playing on.
+pub fn song_length_in_beats(schedule: &Schedule, tempo: &TempoMap) -> f64 {
+    schedule
+        .samples
+        .iter()
+        .map(|sample| sample.beat + tempo.beats_in(sample.beat, sample.seconds()))
+        .chain(schedule.notes.iter().map(|note| note.end(tempo)))
+        .fold(0., f64::max)
+}
+
+/// Mixes the scheduled samples and notes into interleaved stereo through the mixer, from the `start` beat until the `end` beat.
+/// The clicks of the metronome are added if there is one, the count-in is never rendered.
+pub fn render_schedule(
+    schedule: Schedule,
+    mixer: &MixerState,
+    tempo: &TempoMap,
+    metronome: Option<Metronome>,
+    sample_rate: u32,
+    start: f64,
+    end: f64,
+) -> Vec<f32> {
+    let mut transport = Transport::new(sample_rate);
+
+    transport.set_tempo(tempo.clone());
+    transport.set_metronome(metronome);
+    transport.set_samples(schedule.samples);
+    transport.set_notes(schedule.notes);
+    transport.set_mixer(mixer.clone());
+    transport.seek(start);
+    transport.set_playing(true);
+
+    let seconds = (tempo.seconds_at(end) - tempo.seconds_at(start)).max(0.);
+    let total_frames = (seconds * sample_rate as f64).round() as usize;
+
+    let mut output = vec![0.; total_frames * OUTPUT_CHANNELS];
+
+    for block in output.chunks_mut(RENDER_BLOCK_FRAMES * OUTPUT_CHANNELS) {
+        transport.render(block);
+    }
+
+    output
+}
+
+/// The first and the last beat of the rendered range.
+/// The song lasts until the last sample or note has finished, but at least until the end of the last clip, so that the silent steps at the end of a pattern are kept.
+fn render_bounds(range: RenderRange, schedule: &Schedule, playlist: &PlaylistState) -> (f64, f64) {
+    let tempo = &playlist.tempo;
+
+    match range {
+        RenderRange::Song => (
+            0.,
+            song_length_in_beats(schedule, tempo).max(playlist.clips.end(tempo).as_beats()),
+        ),
+        RenderRange::Bars { first, last } => (
+            tempo.bar(first.max(1) - 1).start.as_beats(),
+            tempo.bar(last.max(first).max(1) - 1).end().as_beats(),
+        ),
+    }
+}
+
+/// Renders the playlist into interleaved stereo, faster than real time.
+pub fn render_playlist(
+    playlist: &PlaylistState,
+    mixer: &MixerState,
+    settings: &RenderSettings,
+) -> anyhow::Result<Vec<f32>> {
+    let schedule = schedule_playlist(playlist, settings.sample_rate)?;
+    let (start, end) = render_bounds(settings.range, &schedule, playlist);
+
+    let metronome = match settings.include_metronome {
+        true => Some(
+            playlist
+                .metronome
+                .metronome(|path| {
+                    decode_sample(path)
+                        .and_then(|audio| audio.resampled(settings.sample_rate))
+                        .ok()
+                        .map(Arc::new)
+                })
+                .ok_or_else(|| anyhow!("Could not decode the sample of the metronome"))?,
+        ),
+        false => None,
+    };
+
+    Ok(render_schedule(
+        schedule,
+        mixer,
+        &playlist.tempo,
+        metronome,
+        settings.sample_rate,
+        start,
+        end,
+    ))
+}
+
+/// Renders the playlist, then writes it into a wav file.
+pub fn export_wav(
+    path: &Path,
+    playlist: &PlaylistState,
+    mixer: &MixerState,
+    settings: &RenderSettings,
+) -> anyhow::Result<()> {
+    let rendered = render_playlist(playlist, mixer, settings)?;
+
+    write_wav(
+        path,
+        &rendered,
+        OUTPUT_CHANNELS as u16,
+        settings.sample_rate,
+        settings.bit_depth,
+    )
+}
+
+/// The rendered audio of a single track.
+#[derive(Debug, Clone)]
+pub struct Stem {
+    /// The row of the track in the playlist.
+    pub track: usize,
+
+    /// The label of the track.
+    pub name: String,
+
+    /// Interleaved stereo samples.
+    pub samples: Vec<f32>,
+}
+
+/// Renders every track into its own stem, every stem has the same start and length so that they line up.
+/// The tracks are rendered from the first track until the last used one, if `skip_empty_tracks` is set the tracks without samples or notes are left out.
+/// The volume and the pan of the tracks are kept, but they are not muted by the other tracks being soloed.
+pub fn render_stems(
+    playlist: &PlaylistState,
+    mixer: &MixerState,
+    settings: &RenderSettings,
+    skip_empty_tracks: bool,
+) -> anyhow::Result<Vec<Stem>> {
+    let schedule = schedule_playlist(playlist, settings.sample_rate)?;
+
+    // The bounds of the whole song are used for every stem
+    let (start, end) = render_bounds(settings.range, &schedule, playlist);
+
+    let used_tracks = schedule.tracks();
+
+    // The stems are in the same order as the tracks of the playlist
+    let used_rows: Vec<usize> = playlist
+        .tracks
+        .iter()
+        .enumerate()
+        .filter(|(_, track)| used_tracks.contains(&track.id))
+        .map(|(row, _)| row)
+        .collect();
+
+    let rows: Vec<usize> = match (skip_empty_tracks, used_rows.last()) {
+        (true, _) => used_rows,
+        (false, Some(last)) => (0..=*last).collect(),
+        (false, None) => vec![],
+    };
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let track = playlist.tracks.get(row)?;
+
+            Some(Stem {
+                track: row,
+                name: track.customization.label_text.clone(),
+                samples: render_schedule(
+                    schedule.track(track.id),
+                    &mixer.isolated(track.id),
+                    &playlist.tempo,
+                    None,
+                    settings.sample_rate,
+                    start,
+                    end,
+                ),
+            })
+        })
+        .collect())
+}
+
+/// Picks a file name for every stem, characters which are not allowed in file names are replaced.
+/// Tracks with the same label get a number appended to their name, e.g. `Drums (1).wav`.
+fn stem_file_names(stems: &[Stem]) -> Vec<String> {
+    // Names are compared in lowercase since some filesystems are case insensitive
+    let mut taken = HashSet::new();
+
+    stems
+        .iter()
+        .map(|stem| {
+            let mut label: String = stem
+                .name
+                .trim()
+                .chars()
+                .map(|char| match char {
+                    '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                    char if char.is_control() => '_',
+                    char => char,
+                })
+                .collect();
+
+            if label.is_empty() {
+                label = TrackCustomization::named_default(stem.track).label_text;
+            }
+
+            let mut name = format!("{label}.wav");
+            let mut nth = 0;
+
+            while !taken.insert(name.to_lowercase()) {
+                nth += 1;
+                name = format!("{label} ({nth}).wav");
+            }
+
+            name
+        })
+        .collect()
+}
+
+/// Renders the stems of the playlist and writes them into the folder, returns the paths of the written files.
+pub fn export_stems(
+    folder: &Path,
+    playlist: &PlaylistState,
+    mixer: &MixerState,
+    settings: &RenderSettings,
+    skip_empty_tracks: bool,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let stems = render_stems(playlist, mixer, settings, skip_empty_tracks)?;
+
+    fs::create_dir_all(folder)?;
+
+    stem_file_names(&stems)
+        .into_iter()
+        .zip(stems)
+        .map(|(name, stem)| {
+            let path = folder.join(name);
+
+            write_wav(
+                &path,
+                &stem.samples,
+                OUTPUT_CHANNELS as u16,
+                settings.sample_rate,
+                settings.bit_depth,
+            )?;
+
+            Ok(path)
+        })
+        .collect()
+}
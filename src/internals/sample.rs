@@ -1,4 +1,8 @@
 use anyhow::anyhow;
+use rubato::{
+    Async, FixedAsync, Resampler, SincInterpolationParameters, SincInterpolationType,
+    WindowFunction, audioadapter_buffers::direct::InterleavedSlice, calculate_cutoff,
+};
 use std::{fs, path::PathBuf};
 use symphonia::core::audio::sample::Sample;
 use symphonia::core::{
@@ -23,7 +27,7 @@ impl<'mss> SampleHandle<'mss> {
     }
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct SampleProperties {
     pub sample_rate: u32,
     pub length: i128,
@@ -72,7 +76,84 @@ pub fn fetch_sample_properties(path: &PathBuf) -> anyhow::Result<SamplePropertie
     })
 }
 
-pub fn generate_sample_waveform(path: &PathBuf) -> anyhow::Result<Vec<[f32; 2]>> {
+/// The decoded contents of an audio file.
+#[derive(derive_more::Debug, Clone, Default)]
+pub struct DecodedSample {
+    pub sample_rate: u32,
+    pub channels: usize,
+
+    /// The samples of every channel interleaved.
+    #[debug(skip)]
+    pub samples: Vec<f32>,
+}
+
+impl DecodedSample {
+    /// The amount of samples a single channel has.
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// Fetches the sample of the channel in the given frame, files with less channels repeat their last channel.
+    pub fn sample(&self, frame: usize, channel: usize) -> f32 {
+        let channel = channel.min(self.channels.saturating_sub(1));
+
+        self.samples
+            .get(frame * self.channels + channel)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Converts the sample to another sample rate with a sinc resampler.
+    /// This is slow, so it is only meant to be used for offline rendering.
+    pub fn resampled(&self, sample_rate: u32) -> anyhow::Result<DecodedSample> {
+        if self.sample_rate == sample_rate || self.frames() == 0 {
+            return Ok(DecodedSample {
+                sample_rate,
+                ..self.clone()
+            });
+        }
+
+        let window = WindowFunction::Blackman2;
+        let parameters = SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: calculate_cutoff(128, window),
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 256,
+            window,
+        };
+
+        let mut resampler = Async::<f32>::new_sinc(
+            sample_rate as f64 / self.sample_rate as f64,
+            1.,
+            &parameters,
+            1024,
+            self.channels,
+            FixedAsync::Input,
+        )?;
+
+        let frames = self.frames();
+        let mut output = vec![0.; resampler.process_all_needed_output_len(frames) * self.channels];
+        let output_capacity = output.len() / self.channels;
+
+        let input = InterleavedSlice::new(&self.samples, self.channels, frames)?;
+        let mut output_adapter =
+            InterleavedSlice::new_mut(&mut output, self.channels, output_capacity)?;
+
+        let (_, output_frames) =
+            resampler.process_all_into_buffer(&input, &mut output_adapter, frames, None)?;
+
+        output.truncate(output_frames * self.channels);
+
+        Ok(DecodedSample {
+            sample_rate,
+            channels: self.channels,
+            samples: output,
+        })
+    }
+}
+
+/// Decodes the whole audio file into memory.
+pub fn decode_sample(path: &PathBuf) -> anyhow::Result<DecodedSample> {
     let file = fs::File::open(path)?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
@@ -98,6 +179,7 @@ pub fn generate_sample_waveform(path: &PathBuf) -> anyhow::Result<Vec<[f32; 2]>>
         symphonia::default::get_codecs().make_audio_decoder(audio_params, &dec_opts)?;
 
     let track_id = default_track.id;
+    let sample_rate = audio_params.sample_rate.unwrap_or_default();
 
     let mut samples: Vec<f32> = Vec::default();
     let mut channel_count = 0;
@@ -121,8 +203,18 @@ pub fn generate_sample_waveform(path: &PathBuf) -> anyhow::Result<Vec<[f32; 2]>>
         }
     }
 
+    Ok(DecodedSample {
+        sample_rate,
+        channels: channel_count,
+        samples,
+    })
+}
+
+pub fn generate_sample_waveform(path: &PathBuf) -> anyhow::Result<Vec<[f32; 2]>> {
+    let decoded = decode_sample(path)?;
+
     // Avg out input on all channels
-    let avg_channel_input = avg_values_in_window(&samples, channel_count);
+    let avg_channel_input = avg_values_in_window(&decoded.samples, decoded.channels);
 
     // Average out input over windows of the input
     let value_pairs = min_max_in_window(&avg_channel_input, 1024);
@@ -0,0 +1,618 @@
+use std::{
+    f32::consts::{PI, TAU},
+    simd::{Select, StdFloat, cmp::SimdPartialOrd, f32x8, num::SimdUint, u32x8},
+};
+
+use crate::internals::{
+    instruments::{CHOKE_FADE, Envelope, key_frequency},
+    playback::OUTPUT_CHANNELS,
+};
+
+/// The amount of frames computed at once by the kernels of the synth.
+const LANES: usize = f32x8::LEN;
+
+/// The amount of frames rendered between updates of the envelopes and of the LFO.
+/// The gain is ramped across the block, so that the updates do not click.
+const BLOCK: usize = 64;
+
+/// The lowest and the highest frequencies the cutoff of the filter can be set to.
+pub const MIN_CUTOFF: f32 = 20.;
+pub const MAX_CUTOFF: f32 = 20000.;
+
+/// How far the LFO bends the pitch at full depth, in semitones.
+const LFO_PITCH_RANGE: f32 = 2.;
+
+/// How far the LFO moves the cutoff at full depth, in octaves.
+const LFO_CUTOFF_RANGE: f32 = 4.;
+
+/// How many octaves the cutoff can be moved by the filter envelope, in either direction.
+pub const MAX_ENVELOPE_AMOUNT: f32 = 8.;
+
+/// The kernels interleave the mono output of the synth into two channels.
+const _: () = assert!(OUTPUT_CHANNELS == 2);
+
+/// The shape of the wave of an oscillator.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum Waveform {
+    #[default]
+    Saw,
+    Square,
+    Sine,
+
+    /// White noise, the key does not change it.
+    Noise,
+}
+
+/// One of the two oscillators of the synth.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Oscillator {
+    pub waveform: Waveform,
+
+    /// Moves the oscillator by whole octaves.
+    pub octave: i8,
+
+    /// Moves the oscillator by cents, slightly detuned oscillators sound fuller together.
+    pub detune: f32,
+
+    /// The loudness of the oscillator, `0.0` turns it off.
+    pub level: f32,
+}
+
+impl Oscillator {
+    /// The frequency of the oscillator relative to the frequency of the key.
+    fn ratio(&self) -> f32 {
+        2_f32.powf(self.octave as f32 + self.detune / 1200.)
+    }
+}
+
+/// Which frequencies the filter lets through.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum FilterMode {
+    #[default]
+    #[strum(to_string = "Low-pass")]
+    LowPass,
+    #[strum(to_string = "High-pass")]
+    HighPass,
+    #[strum(to_string = "Band-pass")]
+    BandPass,
+    Notch,
+}
+
+/// Shapes the sound of the oscillators, the cutoff is moved by the filter envelope.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Filter {
+    pub mode: FilterMode,
+
+    /// The frequency the filter starts cutting at, in Hz.
+    pub cutoff: f32,
+
+    /// Boosts the frequencies around the cutoff, `1.0` is close to ringing on its own.
+    pub resonance: f32,
+
+    /// How far the filter envelope moves the cutoff at its full level, in octaves.
+    pub envelope_amount: f32,
+}
+
+/// The shape of the wave of the LFO.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+}
+
+/// What the LFO modulates.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum LfoTarget {
+    /// Vibrato.
+    #[default]
+    Pitch,
+
+    /// Wobbles the filter.
+    Cutoff,
+
+    /// Tremolo.
+    Volume,
+}
+
+/// A slow oscillator which modulates the sound, it starts over with every note.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Lfo {
+    pub shape: LfoShape,
+
+    /// The frequency of the LFO, in Hz.
+    pub rate: f32,
+
+    /// How much the target is modulated, `0.0` turns the LFO off.
+    pub depth: f32,
+    pub target: LfoTarget,
+}
+
+impl Lfo {
+    /// The value of the LFO `time` seconds into the note, between `-1.0` and `1.0`.
+    fn value(&self, time: f64) -> f32 {
+        let phase = (time * self.rate as f64).fract() as f32;
+
+        match self.shape {
+            LfoShape::Sine => (phase * TAU).sin(),
+            LfoShape::Triangle => 1. - 4. * (phase - 0.5).abs(),
+            LfoShape::Square if phase < 0.5 => 1.,
+            LfoShape::Square => -1.,
+            LfoShape::Saw => 2. * phase - 1.,
+        }
+    }
+}
+
+/// A polyphonic subtractive synth, two oscillators are mixed and played through a filter.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Synth {
+    pub oscillators: [Oscillator; 2],
+    pub filter: Filter,
+
+    /// The loudness of the notes.
+    pub amp_envelope: Envelope,
+
+    /// Moves the cutoff of the filter by [`Filter::envelope_amount`].
+    pub filter_envelope: Envelope,
+    pub lfo: Lfo,
+
+    /// The amount of notes played at once, the oldest notes are stopped to make room for new ones.
+    pub polyphony: usize,
+}
+
+impl Default for Synth {
+    fn default() -> Self {
+        Self {
+            oscillators: [
+                Oscillator {
+                    waveform: Waveform::Saw,
+                    octave: 0,
+                    detune: 0.,
+                    level: 0.8,
+                },
+                Oscillator {
+                    waveform: Waveform::Saw,
+                    octave: 0,
+                    detune: 7.,
+                    level: 0.5,
+                },
+            ],
+            filter: Filter {
+                mode: FilterMode::LowPass,
+                cutoff: 1500.,
+                resonance: 0.2,
+                envelope_amount: 2.,
+            },
+            amp_envelope: Envelope::default(),
+            filter_envelope: Envelope {
+                attack: 0.002,
+                decay: 0.4,
+                sustain: 0.,
+                release: 0.2,
+            },
+            lfo: Lfo {
+                shape: LfoShape::Sine,
+                rate: 5.,
+                depth: 0.,
+                target: LfoTarget::Pitch,
+            },
+            polyphony: 8,
+        }
+    }
+}
+
+impl Synth {
+    /// How long the note sounds in seconds, the key is released after `gate` seconds.
+    pub fn duration(&self, gate: f64) -> f64 {
+        gate + self.amp_envelope.release
+    }
+}
+
+/// A note being played by a synth. The notes are rendered a block at a time, the modulation is updated between the blocks.
+#[derive(Debug, Clone)]
+pub struct SynthVoice {
+    synth: Synth,
+    oscillators: [OscillatorVoice; 2],
+    filter: FilterVoice,
+
+    /// The frequency of the key, in Hz.
+    frequency: f32,
+    gain: f32,
+    sample_rate: f32,
+
+    /// The amount of frames played so far.
+    frame: usize,
+
+    /// The frame the key is released on.
+    gate: usize,
+
+    /// The frame the voice has been choked on.
+    choked: Option<usize>,
+    choke_frames: usize,
+
+    /// The rendered frames, the ones before `played` have already been played.
+    block: [f32; BLOCK],
+    played: usize,
+}
+
+impl SynthVoice {
+    /// Keeps a few notes played together from clipping.
+    const GAIN: f32 = 0.25;
+
+    pub fn new(synth: &Synth, key: u8, velocity: f32, gate: usize, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1) as f32;
+
+        Self {
+            synth: *synth,
+            oscillators: [
+                OscillatorVoice::new(key as u32 * 2 + 1),
+                OscillatorVoice::new(key as u32 * 2 + 2),
+            ],
+            filter: FilterVoice::default(),
+            frequency: key_frequency(key),
+            gain: velocity * Self::GAIN,
+            sample_rate,
+            frame: 0,
+            gate,
+            choked: None,
+            choke_frames: ((CHOKE_FADE * sample_rate as f64) as usize).max(1),
+            block: [0.; BLOCK],
+            played: BLOCK,
+        }
+    }
+
+    /// The LFO scaled by its depth on the frame.
+    fn lfo(&self, frame: usize) -> f32 {
+        self.synth.lfo.value(frame as f64 / self.sample_rate as f64) * self.synth.lfo.depth
+    }
+
+    /// The gain of the amp envelope, of the choke and of the tremolo on the frame.
+    fn amp(&self, frame: usize) -> f32 {
+        let time = frame as f64 / self.sample_rate as f64;
+        let gate = self.gate as f64 / self.sample_rate as f64;
+
+        let choke = match self.choked.and_then(|choked| frame.checked_sub(choked)) {
+            Some(choked) => (1. - choked as f32 / self.choke_frames as f32).max(0.),
+            None => 1.,
+        };
+
+        // The tremolo dips down from the full level by the depth
+        let tremolo = match self.synth.lfo.target {
+            LfoTarget::Volume => 1. - (self.synth.lfo.depth - self.lfo(frame)) / 2.,
+            _ => 1.,
+        };
+
+        self.gain * self.synth.amp_envelope.level(time, gate) * choke * tremolo
+    }
+
+    /// Renders the next block, starting at the current frame.
+    fn render_block(&mut self) {
+        let start = self.frame;
+        let time = start as f64 / self.sample_rate as f64;
+        let gate = self.gate as f64 / self.sample_rate as f64;
+        let lfo = self.lfo(start);
+
+        let pitch = match self.synth.lfo.target {
+            LfoTarget::Pitch => 2_f32.powf(lfo * LFO_PITCH_RANGE / 12.),
+            _ => 1.,
+        };
+
+        self.block = [0.; BLOCK];
+
+        for (oscillator, voice) in self.synth.oscillators.iter().zip(&mut self.oscillators) {
+            if oscillator.level > 0. {
+                let step = self.frequency * oscillator.ratio() * pitch / self.sample_rate;
+
+                voice.render(oscillator, step, &mut self.block);
+            }
+        }
+
+        let filter = &self.synth.filter;
+        let mut octaves = filter.envelope_amount * self.synth.filter_envelope.level(time, gate);
+
+        if self.synth.lfo.target == LfoTarget::Cutoff {
+            octaves += lfo * LFO_CUTOFF_RANGE;
+        }
+
+        // Cutoffs close to the Nyquist frequency make the filter unstable
+        let cutoff = (filter.cutoff * 2_f32.powf(octaves))
+            .clamp(MIN_CUTOFF, (MAX_CUTOFF).min(self.sample_rate * 0.45));
+
+        self.filter
+            .process(filter, cutoff, self.sample_rate, &mut self.block);
+
+        let (from, to) = (self.amp(start), self.amp(start + BLOCK));
+
+        ramp_gain(&mut self.block, from, to);
+
+        self.played = 0;
+    }
+
+    /// Adds the next frames of the note to the interleaved stereo output, returns whether the note has finished.
+    pub fn mix(&mut self, mut output: &mut [f32]) -> bool {
+        while output.len() >= OUTPUT_CHANNELS {
+            if self.is_finished() {
+                return true;
+            }
+
+            if self.played == BLOCK {
+                self.render_block();
+            }
+
+            // The note stops on its last frame, no matter where the block ends
+            let frames = (BLOCK - self.played)
+                .min(output.len() / OUTPUT_CHANNELS)
+                .min(self.end() - self.frame);
+            let (played, rest) = output.split_at_mut(frames * OUTPUT_CHANNELS);
+
+            add_to_stereo(played, &self.block[self.played..self.played + frames]);
+
+            self.played += frames;
+            self.frame += frames;
+            output = rest;
+        }
+
+        self.is_finished()
+    }
+
+    /// Skips the frames without playing them, the oscillators keep their phases.
+    pub fn skip(&mut self, frames: usize) {
+        let rendered = BLOCK - self.played;
+
+        if frames <= rendered {
+            self.played += frames;
+        } else {
+            // The oscillators have already advanced to the end of the rendered block
+            for (oscillator, voice) in self.synth.oscillators.iter().zip(&mut self.oscillators) {
+                let step = self.frequency * oscillator.ratio() / self.sample_rate;
+
+                voice.phase = (voice.phase
+                    + (step as f64 * (frames - rendered) as f64).fract() as f32)
+                    .fract();
+            }
+
+            self.filter = FilterVoice::default();
+            self.played = BLOCK;
+        }
+
+        self.frame += frames;
+    }
+
+    /// Quickly fades out the note `after` frames from now.
+    pub fn choke(&mut self, after: usize) {
+        let choked = self.frame + after;
+
+        self.choked = Some(self.choked.map_or(choked, |previous| previous.min(choked)));
+    }
+
+    /// The frame after the last frame of the note.
+    fn end(&self) -> usize {
+        let release = (self.synth.amp_envelope.release * self.sample_rate as f64) as usize;
+        let end = self.gate + release;
+
+        match self.choked {
+            Some(choked) => end.min(choked + self.choke_frames),
+            None => end,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.end()
+    }
+}
+
+/// The state of an oscillator while it plays a note.
+#[derive(Debug, Clone)]
+struct OscillatorVoice {
+    /// A whole period is `1.0`.
+    phase: f32,
+
+    /// The state of the noise generator of every lane, this is never zero.
+    noise: u32x8,
+}
+
+impl OscillatorVoice {
+    /// The noise is seeded by the note, so that rendering the song twice gives the same output.
+    fn new(seed: u32) -> Self {
+        let lanes = u32x8::from_array([1, 2, 3, 4, 5, 6, 7, 8]);
+
+        Self {
+            phase: 0.,
+            noise: (lanes * u32x8::splat(0x9E37_79B9))
+                ^ u32x8::splat(seed.wrapping_mul(0x85EB_CA6B))
+                | u32x8::splat(1),
+        }
+    }
+
+    /// The next white noise of every lane, between `-1.0` and `1.0`.
+    fn next_noise(&mut self) -> f32x8 {
+        // Xorshift, every lane has its own sequence
+        let mut noise = self.noise;
+
+        noise ^= noise << 13;
+        noise ^= noise >> 17;
+        noise ^= noise << 5;
+        self.noise = noise;
+
+        (noise >> 8).cast::<f32>() * f32x8::splat(2. / (1 << 24) as f32) - f32x8::splat(1.)
+    }
+
+    /// Adds the oscillator to the block, `step` is the phase advanced by every frame.
+    fn render(&mut self, oscillator: &Oscillator, step: f32, block: &mut [f32; BLOCK]) {
+        // Above half of the sample rate the wave would alias into nonsense
+        let step = step.clamp(0., 0.5);
+        let offsets = f32x8::from_array([0., 1., 2., 3., 4., 5., 6., 7.]) * f32x8::splat(step);
+        let dt = f32x8::splat(step);
+        let level = f32x8::splat(oscillator.level);
+
+        for lanes in block.chunks_exact_mut(LANES) {
+            let phases = (f32x8::splat(self.phase) + offsets).fract();
+
+            let wave = match oscillator.waveform {
+                Waveform::Saw => saw(phases, dt),
+                Waveform::Square => square(phases, dt),
+                Waveform::Sine => (phases * f32x8::splat(TAU)).sin(),
+                Waveform::Noise => self.next_noise(),
+            };
+
+            (f32x8::from_slice(lanes) + wave * level).copy_to_slice(lanes);
+
+            self.phase = (self.phase + step * LANES as f32).fract();
+        }
+    }
+}
+
+/// Smooths the jump of a wave at the start of its period, which keeps the saw and the square from aliasing.
+fn poly_blep(phases: f32x8, dt: f32x8) -> f32x8 {
+    let one = f32x8::splat(1.);
+
+    // Just after the jump
+    let after = phases / dt;
+    let after = after + after - after * after - one;
+
+    // Just before the jump
+    let before = (phases - one) / dt;
+    let before = before * before + before + before + one;
+
+    phases.simd_lt(dt).select(
+        after,
+        phases.simd_gt(one - dt).select(before, f32x8::splat(0.)),
+    )
+}
+
+fn saw(phases: f32x8, dt: f32x8) -> f32x8 {
+    phases + phases - f32x8::splat(1.) - poly_blep(phases, dt)
+}
+
+fn square(phases: f32x8, dt: f32x8) -> f32x8 {
+    let half = f32x8::splat(0.5);
+    let naive = phases
+        .simd_lt(half)
+        .select(f32x8::splat(1.), f32x8::splat(-1.));
+
+    // The square jumps up at the start of its period and down in its middle
+    naive + poly_blep(phases, dt) - poly_blep((phases + half).fract(), dt)
+}
+
+/// Multiplies the block by a gain moving from `from` to `to` across it.
+fn ramp_gain(block: &mut [f32; BLOCK], from: f32, to: f32) {
+    let slope = (to - from) / BLOCK as f32;
+    let mut gains = f32x8::splat(from)
+        + f32x8::from_array([0., 1., 2., 3., 4., 5., 6., 7.]) * f32x8::splat(slope);
+    let advance = f32x8::splat(slope * LANES as f32);
+
+    for lanes in block.chunks_exact_mut(LANES) {
+        (f32x8::from_slice(lanes) * gains).copy_to_slice(lanes);
+        gains += advance;
+    }
+}
+
+/// Adds the mono frames to both channels of the interleaved output.
+fn add_to_stereo(output: &mut [f32], frames: &[f32]) {
+    let mut output_lanes = output.chunks_exact_mut(LANES * OUTPUT_CHANNELS);
+    let mut frame_lanes = frames.chunks_exact(LANES);
+
+    for (output, frames) in (&mut output_lanes).zip(&mut frame_lanes) {
+        let frames = f32x8::from_slice(frames);
+        let (first, second) = frames.interleave(frames);
+        let (first_output, second_output) = output.split_at_mut(LANES);
+
+        (f32x8::from_slice(first_output) + first).copy_to_slice(first_output);
+        (f32x8::from_slice(second_output) + second).copy_to_slice(second_output);
+    }
+
+    // The frames which do not fill a whole lane
+    for (output, frame) in output_lanes
+        .into_remainder()
+        .chunks_exact_mut(OUTPUT_CHANNELS)
+        .zip(frame_lanes.remainder())
+    {
+        for out in output {
+            *out += frame;
+        }
+    }
+}
+
+/// A state variable filter, its coefficients are updated once per block.
+#[derive(Debug, Clone, Default)]
+struct FilterVoice {
+    low: f32,
+    band: f32,
+}
+
+impl FilterVoice {
+    /// How close the highest resonance takes the filter to ringing on its own.
+    const MAX_RESONANCE: f32 = 0.97;
+
+    fn process(&mut self, filter: &Filter, cutoff: f32, sample_rate: f32, block: &mut [f32]) {
+        let g = (PI * cutoff / sample_rate).tan();
+        let k = 2. - 2. * filter.resonance.clamp(0., 1.) * Self::MAX_RESONANCE;
+        let a1 = 1. / (1. + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+
+        // The filter depends on the previous frames, so it is the only part of the synth which runs frame by frame
+        for sample in block.iter_mut() {
+            let input = *sample;
+            let v3 = input - self.low;
+            let band = a1 * self.band + a2 * v3;
+            let low = self.low + a2 * self.band + a3 * v3;
+
+            self.band = 2. * band - self.band;
+            self.low = 2. * low - self.low;
+
+            *sample = match filter.mode {
+                FilterMode::LowPass => low,
+                FilterMode::HighPass => input - k * band - low,
+                FilterMode::BandPass => band,
+                FilterMode::Notch => input - k * band,
+            };
+        }
+    }
+}
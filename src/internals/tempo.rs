@@ -0,0 +1,346 @@
+use crate::internals::timeline::{PPQ, Tick};
+
+/// The bpm of newly created projects.
+pub const DEFAULT_BPM: f32 = 120.;
+
+/// The range of tempos which can be set.
+pub const BPM_RANGE: std::ops::RangeInclusive<f32> = 10.0..=522.0;
+
+/// The denominators offered when changing the meter, every one of them divides a beat into whole ticks.
+pub const METER_DENOMINATORS: &[u8] = &[1, 2, 4, 8, 16];
+
+/// The time signature of a bar, e.g. `7/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct Meter {
+    /// The amount of counted beats in a bar.
+    pub numerator: u8,
+
+    /// The note value of a counted beat, a `4` counts quarter notes.
+    pub denominator: u8,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self {
+            numerator: 4,
+            denominator: 4,
+        }
+    }
+}
+
+impl std::fmt::Display for Meter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+impl Meter {
+    /// The length of a counted beat, the ticks of the timeline are measured in quarter notes.
+    pub fn beat_ticks(&self) -> u64 {
+        PPQ * 4 / self.denominator.max(1) as u64
+    }
+
+    pub fn bar_ticks(&self) -> u64 {
+        self.beat_ticks() * self.numerator.max(1) as u64
+    }
+}
+
+/// Changes the tempo of the song from the position on.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TempoChange {
+    pub position: Tick,
+    pub bpm: f32,
+
+    /// The tempo changes gradually from this change until the next one, instead of staying the same.
+    pub ramp: bool,
+}
+
+/// Changes the meter of the song from the start of the bar on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MeterChange {
+    /// The bar the meter changes on, counted from 0.
+    pub bar: usize,
+    pub meter: Meter,
+}
+
+/// A bar of the song.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bar {
+    /// The index of the bar, counted from 0.
+    pub index: usize,
+    pub start: Tick,
+    pub meter: Meter,
+}
+
+impl Bar {
+    pub fn end(&self) -> Tick {
+        Tick(self.start.0 + self.meter.bar_ticks())
+    }
+}
+
+/// The tempo and the meter of the song at every position.
+/// The changes are kept sorted, there is at most one tempo change on a tick and one meter change on a bar.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TempoMap {
+    /// The tempo the song starts with, can be modified with the bpm slider.
+    pub bpm: f32,
+    tempo_changes: Vec<TempoChange>,
+
+    /// The meter the song starts with.
+    pub meter: Meter,
+    meter_changes: Vec<MeterChange>,
+}
+
+impl Default for TempoMap {
+    fn default() -> Self {
+        Self::new(DEFAULT_BPM)
+    }
+}
+
+/// A part of the song which has a steady or linearly changing tempo.
+struct TempoSegment {
+    /// The beat the segment starts on.
+    start: f64,
+
+    /// The beat the segment ends on, the last segment never ends.
+    end: f64,
+
+    /// The tempo at the start and at the end of the segment.
+    bpm: (f64, f64),
+}
+
+impl TempoSegment {
+    /// The change of the tempo over a single beat.
+    fn slope(&self) -> f64 {
+        match self.end.is_finite() && self.end > self.start {
+            true => (self.bpm.1 - self.bpm.0) / (self.end - self.start),
+            false => 0.,
+        }
+    }
+
+    /// The amount of seconds it takes to get from the start of the segment to the beat.
+    fn seconds_until(&self, beat: f64) -> f64 {
+        let beats = beat - self.start;
+        let slope = self.slope();
+
+        match slope.abs() < f64::EPSILON {
+            true => beats * 60. / self.bpm.0,
+            // The integral of 60 / (bpm + slope * beats)
+            false => 60. / slope * ((self.bpm.0 + slope * beats) / self.bpm.0).ln(),
+        }
+    }
+
+    /// The beat reached after the amount of seconds from the start of the segment.
+    fn beat_after(&self, seconds: f64) -> f64 {
+        let slope = self.slope();
+
+        match slope.abs() < f64::EPSILON {
+            true => self.start + seconds * self.bpm.0 / 60.,
+            false => self.start + self.bpm.0 * ((seconds * slope / 60.).exp() - 1.) / slope,
+        }
+    }
+}
+
+impl TempoMap {
+    /// A map with a steady tempo in 4/4.
+    pub fn new(bpm: f32) -> Self {
+        Self {
+            bpm,
+            tempo_changes: Vec::new(),
+            meter: Meter::default(),
+            meter_changes: Vec::new(),
+        }
+    }
+
+    pub fn tempo_changes(&self) -> &[TempoChange] {
+        &self.tempo_changes
+    }
+
+    pub fn meter_changes(&self) -> &[MeterChange] {
+        &self.meter_changes
+    }
+
+    /// Adds the tempo change, a change which is on the same position is replaced.
+    /// A change at the start of the song sets the starting tempo instead.
+    pub fn set_tempo_change(&mut self, change: TempoChange) {
+        if change.position == Tick(0) {
+            self.bpm = change.bpm;
+            return;
+        }
+
+        match self
+            .tempo_changes
+            .binary_search_by_key(&change.position, |change| change.position)
+        {
+            Ok(idx) => self.tempo_changes[idx] = change,
+            Err(idx) => self.tempo_changes.insert(idx, change),
+        }
+    }
+
+    pub fn remove_tempo_change(&mut self, position: Tick) -> Option<TempoChange> {
+        let idx = self
+            .tempo_changes
+            .binary_search_by_key(&position, |change| change.position)
+            .ok()?;
+
+        Some(self.tempo_changes.remove(idx))
+    }
+
+    /// Changes the meter from the start of the bar on, a change on the first bar sets the starting meter instead.
+    /// The bars after the change keep their index, so they move on the timeline.
+    pub fn set_meter_change(&mut self, bar: usize, meter: Meter) {
+        if bar == 0 {
+            self.meter = meter;
+            return;
+        }
+
+        let change = MeterChange { bar, meter };
+
+        match self
+            .meter_changes
+            .binary_search_by_key(&bar, |change| change.bar)
+        {
+            Ok(idx) => self.meter_changes[idx] = change,
+            Err(idx) => self.meter_changes.insert(idx, change),
+        }
+    }
+
+    pub fn remove_meter_change(&mut self, bar: usize) -> Option<MeterChange> {
+        let idx = self
+            .meter_changes
+            .binary_search_by_key(&bar, |change| change.bar)
+            .ok()?;
+
+        Some(self.meter_changes.remove(idx))
+    }
+
+    /// The parts of the song with a steady or ramping tempo, in order.
+    fn segments(&self) -> impl Iterator<Item = TempoSegment> + '_ {
+        let starts = std::iter::once((0., self.bpm as f64, false)).chain(
+            self.tempo_changes
+                .iter()
+                .map(|change| (change.position.as_beats(), change.bpm as f64, change.ramp)),
+        );
+        let ends = self
+            .tempo_changes
+            .iter()
+            .map(|change| (change.position.as_beats(), change.bpm as f64))
+            .chain(std::iter::once((f64::INFINITY, 0.)));
+
+        starts
+            .zip(ends)
+            .map(|((start, bpm, ramp), (end, next_bpm))| TempoSegment {
+                start,
+                end,
+                bpm: (
+                    bpm.max(f64::EPSILON),
+                    match ramp && end.is_finite() {
+                        true => next_bpm.max(f64::EPSILON),
+                        false => bpm.max(f64::EPSILON),
+                    },
+                ),
+            })
+    }
+
+    /// The tempo at the beat.
+    pub fn bpm_at(&self, beat: f64) -> f32 {
+        self.segments()
+            .find(|segment| beat < segment.end)
+            .map(|segment| segment.bpm.0 + segment.slope() * (beat.max(0.) - segment.start))
+            .unwrap_or(self.bpm as f64) as f32
+    }
+
+    /// The amount of seconds from the start of the song until the beat.
+    pub fn seconds_at(&self, beat: f64) -> f64 {
+        let beat = beat.max(0.);
+        let mut seconds = 0.;
+
+        for segment in self.segments() {
+            if beat < segment.end {
+                return seconds + segment.seconds_until(beat);
+            }
+
+            seconds += segment.seconds_until(segment.end);
+        }
+
+        seconds
+    }
+
+    /// The beat the song reaches after the amount of seconds.
+    pub fn beats_at(&self, seconds: f64) -> f64 {
+        let mut remaining = seconds.max(0.);
+
+        for segment in self.segments() {
+            let length = match segment.end.is_finite() {
+                true => segment.seconds_until(segment.end),
+                false => f64::INFINITY,
+            };
+
+            if remaining < length {
+                return segment.beat_after(remaining);
+            }
+
+            remaining -= length;
+        }
+
+        0.
+    }
+
+    /// The amount of beats it takes to play the amount of seconds, starting from the beat.
+    pub fn beats_in(&self, beat: f64, seconds: f64) -> f64 {
+        self.beats_at(self.seconds_at(beat) + seconds) - beat
+    }
+
+    /// The last bar the meter starts or changes on for which `is_before` is true, the first bar of the song always is.
+    /// This does not allocate, since it is used while rendering the metronome.
+    fn meter_bar(&self, is_before: impl Fn(&Bar) -> bool) -> Bar {
+        let first = Bar {
+            index: 0,
+            start: Tick(0),
+            meter: self.meter,
+        };
+
+        self.meter_changes
+            .iter()
+            .scan(first, |previous, change| {
+                *previous = Bar {
+                    index: change.bar,
+                    start: Tick(
+                        previous.start.0
+                            + (change.bar - previous.index) as u64 * previous.meter.bar_ticks(),
+                    ),
+                    meter: change.meter,
+                };
+
+                Some(*previous)
+            })
+            .take_while(is_before)
+            .last()
+            .unwrap_or(first)
+    }
+
+    /// The bar with the index, bars are counted from 0.
+    pub fn bar(&self, index: usize) -> Bar {
+        let last = self.meter_bar(|bar| bar.index <= index);
+
+        Bar {
+            index,
+            start: Tick(last.start.0 + (index - last.index) as u64 * last.meter.bar_ticks()),
+            meter: last.meter,
+        }
+    }
+
+    /// The bar the position is in.
+    pub fn bar_at(&self, position: Tick) -> Bar {
+        let last = self.meter_bar(|bar| bar.start <= position);
+
+        self.bar(last.index + ((position.0 - last.start.0) / last.meter.bar_ticks()) as usize)
+    }
+
+    /// The bars from the one containing the position on, this never ends.
+    pub fn bars_from(&self, position: Tick) -> impl Iterator<Item = Bar> + '_ {
+        let first = self.bar_at(position).index;
+
+        (first..).map(|index| self.bar(index))
+    }
+}
@@ -0,0 +1,178 @@
+use crate::internals::tempo::TempoMap;
+
+/// The resolution of the timeline, this is the amount of ticks a single beat is divided into.
+/// It is divisible by 2, 3 and 16, so that every grid of [`SnapGrid`] lines up with whole ticks.
+pub const PPQ: u64 = 960;
+
+/// A position on the timeline, measured in ticks from the start of the song.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct Tick(pub u64);
+
+impl Tick {
+    /// Converts a beat position into the nearest tick, positions before the start of the song are clamped to it.
+    pub fn from_beats(beats: f64) -> Self {
+        Self((beats * PPQ as f64).round().max(0.) as u64)
+    }
+
+    pub fn as_beats(&self) -> f64 {
+        self.0 as f64 / PPQ as f64
+    }
+
+    /// Moves the position back onto the closest line of the grid which is not after it.
+    pub fn snap(self, grid: SnapGrid) -> Self {
+        match grid.ticks() {
+            Some(step) => Self(self.0 - self.0 % step),
+            None => self,
+        }
+    }
+}
+
+/// The lines which the clips are snapped to when they are placed or moved.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum SnapGrid {
+    #[default]
+    #[strum(to_string = "Beat")]
+    Beat,
+    #[strum(to_string = "1/2 beat")]
+    Half,
+    #[strum(to_string = "1/4 beat")]
+    Quarter,
+    #[strum(to_string = "1/8 beat")]
+    Eighth,
+    #[strum(to_string = "1/16 beat")]
+    Sixteenth,
+    /// Triplets of half beats.
+    #[strum(to_string = "1/3 beat")]
+    Third,
+    /// Triplets of quarter beats.
+    #[strum(to_string = "1/6 beat")]
+    Sixth,
+    /// Triplets of eighth beats.
+    #[strum(to_string = "1/12 beat")]
+    Twelfth,
+    /// The clips are placed wherever they are dropped.
+    #[strum(to_string = "None")]
+    Off,
+}
+
+impl SnapGrid {
+    /// The distance between two lines of the grid, `None` if snapping is turned off.
+    pub fn ticks(&self) -> Option<u64> {
+        match self {
+            SnapGrid::Beat => Some(PPQ),
+            SnapGrid::Half => Some(PPQ / 2),
+            SnapGrid::Quarter => Some(PPQ / 4),
+            SnapGrid::Eighth => Some(PPQ / 8),
+            SnapGrid::Sixteenth => Some(PPQ / 16),
+            SnapGrid::Third => Some(PPQ / 3),
+            SnapGrid::Sixth => Some(PPQ / 6),
+            SnapGrid::Twelfth => Some(PPQ / 12),
+            SnapGrid::Off => None,
+        }
+    }
+}
+
+/// The frame rate of the SMPTE timecode.
+pub const SMPTE_FPS: u32 = 30;
+
+/// How positions are displayed in the readout of the ruler.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum TimeFormat {
+    /// `bar.beat.tick`, bars and beats are counted from 1, the beats follow the meter of the bar.
+    #[default]
+    #[strum(to_string = "Bars")]
+    Bars,
+    /// `mm:ss.mmm`
+    #[strum(to_string = "Clock")]
+    Clock,
+    /// `hh:mm:ss:ff`
+    #[strum(to_string = "SMPTE")]
+    Smpte,
+}
+
+impl TimeFormat {
+    /// The format which comes after this one when the readout is clicked.
+    pub fn next(&self) -> Self {
+        match self {
+            TimeFormat::Bars => TimeFormat::Clock,
+            TimeFormat::Clock => TimeFormat::Smpte,
+            TimeFormat::Smpte => TimeFormat::Bars,
+        }
+    }
+
+    pub fn format(&self, position: Tick, tempo: &TempoMap) -> String {
+        let seconds = tempo.seconds_at(position.as_beats());
+
+        match self {
+            TimeFormat::Bars => {
+                // The beats are counted in the note value of the meter, e.g. in eighths in 7/8
+                let bar = tempo.bar_at(position);
+                let ticks = position.0 - bar.start.0;
+                let beat_ticks = bar.meter.beat_ticks();
+
+                format!(
+                    "{}.{}.{:03}",
+                    bar.index + 1,
+                    ticks / beat_ticks + 1,
+                    ticks % beat_ticks
+                )
+            }
+            TimeFormat::Clock => {
+                let millis = (seconds * 1000.).round() as u64;
+
+                format!(
+                    "{:02}:{:02}.{:03}",
+                    millis / 60_000,
+                    millis / 1000 % 60,
+                    millis % 1000
+                )
+            }
+            TimeFormat::Smpte => {
+                let frames = (seconds * SMPTE_FPS as f64).floor() as u64;
+                let seconds = frames / SMPTE_FPS as u64;
+
+                format!(
+                    "{:02}:{:02}:{:02}:{:02}",
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60,
+                    frames % SMPTE_FPS as u64
+                )
+            }
+        }
+    }
+}
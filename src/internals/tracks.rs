@@ -0,0 +1,156 @@
+use crate::{
+    internals::{instruments::Instrument, utils::SerializedStore},
+    ui::panels::playlist::{SampleInstance, TrackCustomization},
+};
+
+/// Identifies a track for as long as it exists, reordering the tracks does not change their ids.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct TrackId(pub u64);
+
+/// A track of the playlist, the clips and the channel strip of the track refer to it by its id.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Track {
+    pub id: TrackId,
+    pub customization: TrackCustomization,
+
+    /// Plays the note clips of the track.
+    #[serde(default)]
+    pub instrument: Instrument,
+}
+
+/// The tracks of the playlist in the order they are displayed.
+/// The playlist displays empty rows after the last track, these become tracks once something is placed on them.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SerializedStore<Track>", into = "SerializedStore<Track>")]
+pub struct TrackList {
+    tracks: Vec<Track>,
+
+    /// The id given to the next created track, ids are never handed out twice.
+    /// The counter is only persisted with the app state, opening a project file restarts it after the highest id in the project.
+    /// The ids of tracks removed before the project was saved can then be handed out again, the history which could still refer to them is cleared on opening.
+    next_id: u64,
+}
+
+impl TrackList {
+    /// Creates a track with a fresh id, the track is named after the row it is going to be inserted to.
+    /// The track is not inserted, so that it can be inserted by an edit.
+    pub fn create(&mut self, row: usize) -> Track {
+        let id = TrackId(self.next_id);
+
+        self.next_id += 1;
+
+        Track {
+            id,
+            customization: TrackCustomization::named_default(row),
+            instrument: Instrument::default(),
+        }
+    }
+
+    /// Inserts the track before the row, the track is added to the end if the row is after the last track.
+    pub fn insert(&mut self, row: usize, track: Track) {
+        self.next_id = self.next_id.max(track.id.0 + 1);
+
+        self.tracks.insert(row.min(self.tracks.len()), track);
+    }
+
+    pub fn remove(&mut self, row: usize) -> Option<Track> {
+        (row < self.tracks.len()).then(|| self.tracks.remove(row))
+    }
+
+    /// Moves the track from its row to another row, the tracks between the two rows are shifted by one.
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.tracks.len() {
+            return;
+        }
+
+        let track = self.tracks.remove(from);
+
+        self.tracks.insert(to.min(self.tracks.len()), track);
+    }
+
+    /// The track displayed on the row.
+    pub fn get(&self, row: usize) -> Option<&Track> {
+        self.tracks.get(row)
+    }
+
+    /// The row the track is displayed on.
+    pub fn row(&self, id: TrackId) -> Option<usize> {
+        self.tracks.iter().position(|track| track.id == id)
+    }
+
+    pub fn find(&self, id: TrackId) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.id == id)
+    }
+
+    pub fn find_mut(&mut self, id: TrackId) -> Option<&mut Track> {
+        self.tracks.iter_mut().find(|track| track.id == id)
+    }
+
+    /// The customization displayed on the row, the empty rows after the last track are displayed with the default one.
+    pub fn customization(&self, row: usize) -> TrackCustomization {
+        self.get(row).map_or_else(
+            || TrackCustomization::named_default(row),
+            |track| track.customization.clone(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// The tracks in the order they are displayed.
+    pub fn iter(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.iter()
+    }
+
+    /// The samples played by the instruments of the tracks, the path of the samples can be changed.
+    pub fn samples_mut(&mut self) -> impl Iterator<Item = &mut SampleInstance> {
+        self.tracks
+            .iter_mut()
+            .filter_map(|track| track.instrument.sample_mut())
+    }
+}
+
+impl From<Vec<Track>> for TrackList {
+    fn from(tracks: Vec<Track>) -> Self {
+        Self {
+            next_id: tracks.iter().map(|track| track.id.0 + 1).max().unwrap_or(0),
+            tracks,
+        }
+    }
+}
+
+impl From<SerializedStore<Track>> for TrackList {
+    fn from(serialized: SerializedStore<Track>) -> Self {
+        let mut list = Self::from(serialized.items);
+
+        list.next_id = list.next_id.max(serialized.next_id);
+
+        list
+    }
+}
+
+impl From<TrackList> for SerializedStore<Track> {
+    fn from(list: TrackList) -> Self {
+        Self {
+            items: list.tracks,
+            next_id: list.next_id,
+        }
+    }
+}
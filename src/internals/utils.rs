@@ -31,3 +31,10 @@ pub fn find_value_inbetween<T: PartialOrd>(
     }
     None
 }
+
+/// How the stores handing out ids are serialized, the id counter is stored along with the items so that the ids of removed items are not handed out again after loading.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SerializedStore<T> {
+    pub items: Vec<T>,
+    pub next_id: u64,
+}
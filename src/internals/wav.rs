@@ -0,0 +1,126 @@
+use std::{fs, path::Path};
+
+/// The format of the samples written into the wav file.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum::Display,
+    strum::EnumIter,
+)]
+pub enum BitDepth {
+    #[strum(to_string = "16 bit")]
+    Int16,
+    #[default]
+    #[strum(to_string = "24 bit")]
+    Int24,
+    #[strum(to_string = "32 bit float")]
+    Float32,
+}
+
+impl BitDepth {
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            BitDepth::Int16 => 2,
+            BitDepth::Int24 => 3,
+            BitDepth::Float32 => 4,
+        }
+    }
+
+    /// The format tag of the `fmt ` chunk.
+    fn format_tag(&self) -> u16 {
+        match self {
+            // PCM
+            BitDepth::Int16 | BitDepth::Int24 => 1,
+            // IEEE float
+            BitDepth::Float32 => 3,
+        }
+    }
+
+    /// Appends the sample in this format, integer formats are clipped.
+    fn write_sample(&self, bytes: &mut Vec<u8>, sample: f32) {
+        match self {
+            BitDepth::Int16 => {
+                let value = (sample.clamp(-1., 1.) * i16::MAX as f32).round() as i16;
+
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            BitDepth::Int24 => {
+                const MAX: f32 = ((1 << 23) - 1) as f32;
+
+                let value = (sample.clamp(-1., 1.) * MAX).round() as i32;
+
+                // The lowest three bytes of the little endian integer
+                bytes.extend_from_slice(&value.to_le_bytes()[..3]);
+            }
+            BitDepth::Float32 => bytes.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+}
+
+/// Encodes interleaved samples into the bytes of a wav file.
+pub fn encode_wav(
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> Vec<u8> {
+    let bytes_per_sample = bit_depth.bytes_per_sample();
+    let data_len = samples.len() * bytes_per_sample;
+    let block_align = channels as usize * bytes_per_sample;
+
+    // Float files have to contain the size of the format extension and a fact chunk
+    let is_float = bit_depth == BitDepth::Float32;
+    let fmt_len: u32 = if is_float { 18 } else { 16 };
+    let fact_len: u32 = if is_float { 12 } else { 0 };
+
+    let mut bytes = Vec::with_capacity(data_len + 58);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(4 + (8 + fmt_len) + fact_len + 8 + data_len as u32).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&fmt_len.to_le_bytes());
+    bytes.extend_from_slice(&bit_depth.format_tag().to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&(block_align as u16).to_le_bytes());
+    bytes.extend_from_slice(&(bytes_per_sample as u16 * 8).to_le_bytes());
+
+    if is_float {
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+
+        bytes.extend_from_slice(b"fact");
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&((samples.len() / channels.max(1) as usize) as u32).to_le_bytes());
+    }
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&(data_len as u32).to_le_bytes());
+
+    for sample in samples {
+        bit_depth.write_sample(&mut bytes, *sample);
+    }
+
+    bytes
+}
+
+/// Writes interleaved samples into a wav file, overwriting the file if it already exists.
+pub fn write_wav(
+    path: &Path,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> anyhow::Result<()> {
+    fs::write(path, encode_wav(samples, channels, sample_rate, bit_depth))?;
+
+    Ok(())
+}
@@ -1,5 +0,0 @@
-use std::path::PathBuf;
-
-pub fn open_project(path: &PathBuf) {}
-pub fn save_project(path: &PathBuf) {}
-pub fn new_project() {}
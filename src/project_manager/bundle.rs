@@ -0,0 +1,214 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+
+use crate::project_manager::{PROJECT_EXTENSION, Project, fingerprinted_dto, save_project, schema};
+
+/// The file extension used by single file project bundles.
+pub const BUNDLE_EXTENSION: &str = "btrb";
+
+/// Every bundle archive starts with these bytes.
+pub const BUNDLE_MAGIC: &[u8; 4] = b"BTRB";
+
+/// The name of the folder the samples are collected into, this is next to the project file.
+pub const SAMPLES_FOLDER: &str = "samples";
+
+/// Picks a name for every referenced file of the project inside of the samples folder.
+/// Different files with the same name get a number appended to their name, e.g. `kick (1).wav`.
+/// If the samples folder already exists, the names of the files inside of it are taken too, so that they are not overwritten.
+/// Files which are already inside of the folder keep their name.
+fn bundled_names(project: &Project, samples_folder: Option<&Path>) -> HashMap<PathBuf, OsString> {
+    let mut names = HashMap::new();
+
+    // Names are compared in lowercase since some filesystems are case insensitive
+    let mut taken: HashSet<String> = samples_folder
+        .and_then(|folder| fs::read_dir(folder).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().to_lowercase()))
+        .collect();
+
+    let samples_folder = samples_folder.and_then(|folder| fs::canonicalize(folder).ok());
+
+    for path in project.referenced_media() {
+        let is_bundled = samples_folder.is_some()
+            && path
+                .parent()
+                .and_then(|parent| fs::canonicalize(parent).ok())
+                == samples_folder;
+
+        if let Some(name) = path.file_name().filter(|_| is_bundled) {
+            names.insert(path.clone(), name.to_os_string());
+            continue;
+        }
+
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| String::from("sample"));
+        let extension = path
+            .extension()
+            .map(|extension| format!(".{}", extension.to_string_lossy()))
+            .unwrap_or_default();
+
+        let mut name = format!("{stem}{extension}");
+        let mut nth = 0;
+
+        while !taken.insert(name.to_lowercase()) {
+            nth += 1;
+            name = format!("{stem} ({nth}){extension}");
+        }
+
+        names.insert(path, OsString::from(name));
+    }
+
+    names
+}
+
+/// Copies every referenced file of the project into the samples folder next to `project_path` and saves the project there.
+/// Returns the path every referenced file has been collected to.
+pub fn save_bundle(
+    project_path: &Path,
+    project: &Project,
+) -> anyhow::Result<HashMap<PathBuf, PathBuf>> {
+    let samples_folder = project_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(SAMPLES_FOLDER);
+
+    fs::create_dir_all(&samples_folder)?;
+
+    let collected: HashMap<PathBuf, PathBuf> = bundled_names(project, Some(&samples_folder))
+        .into_iter()
+        .map(|(path, name)| {
+            let target = samples_folder.join(name);
+
+            (path, target)
+        })
+        .collect();
+
+    for (path, target) in collected.iter() {
+        // The file might already be inside of the bundle if the project had been bundled before, copying a file onto itself would truncate it
+        let is_same_file = target.exists() && fs::canonicalize(path)? == fs::canonicalize(target)?;
+
+        if !is_same_file {
+            fs::copy(path, target)
+                .with_context(|| format!("Failed to collect `{}`.", path.display()))?;
+        }
+    }
+
+    let mut bundled = project.clone();
+
+    bundled.map_media_paths(|path| {
+        collected
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| path.to_path_buf())
+    });
+
+    save_project(project_path, &bundled)?;
+
+    Ok(collected)
+}
+
+/// Writes the project and every referenced file into a single archive.
+///
+/// The archive is laid out as [`BUNDLE_MAGIC`], the length of the project file (u64), the project file, and the files one after the other.
+/// Every file is stored as the length of its name (u32), its name, the length of its contents (u64) and its contents, all numbers are little endian.
+pub fn save_bundle_archive(archive_path: &Path, project: &Project) -> anyhow::Result<()> {
+    let names = bundled_names(project, None);
+
+    // Point the project at the files as they are going to be extracted, relative to the project file
+    let mut dto = fingerprinted_dto(project);
+
+    dto.map_paths(|path| match names.get(path) {
+        Some(name) => Path::new(SAMPLES_FOLDER).join(name),
+        None => path.to_path_buf(),
+    });
+
+    let project_bytes = schema::encode_project(&dto)?;
+
+    let mut bytes = BUNDLE_MAGIC.to_vec();
+
+    bytes.extend((project_bytes.len() as u64).to_le_bytes());
+    bytes.extend(project_bytes);
+
+    for (path, name) in names.iter() {
+        let contents =
+            fs::read(path).with_context(|| format!("Failed to collect `{}`.", path.display()))?;
+        let name = name.to_string_lossy();
+
+        bytes.extend((name.len() as u32).to_le_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.extend((contents.len() as u64).to_le_bytes());
+        bytes.extend(contents);
+    }
+
+    fs::write(archive_path, bytes)?;
+
+    Ok(())
+}
+
+/// Checks whether the bytes are of a bundle archive.
+pub fn is_bundle_archive(bytes: &[u8]) -> bool {
+    bytes.starts_with(BUNDLE_MAGIC)
+}
+
+/// Takes `len` bytes from the start of the slice.
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> anyhow::Result<&'a [u8]> {
+    if bytes.len() < len {
+        bail!("The bundle archive is truncated.");
+    }
+
+    let (taken, rest) = bytes.split_at(len);
+    *bytes = rest;
+
+    Ok(taken)
+}
+
+/// Extracts the archive into a temporary folder, then returns the path of the extracted project file.
+pub fn extract_bundle_archive(archive_path: &Path, bytes: &[u8]) -> anyhow::Result<PathBuf> {
+    let mut bytes = bytes
+        .strip_prefix(BUNDLE_MAGIC.as_slice())
+        .context("The file is not a bundle archive.")?;
+
+    let stem = archive_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("project"));
+
+    // Every extraction gets its own folder, so that opening the same archive twice cannot overwrite the files in use
+    let target = std::env::temp_dir()
+        .join(crate::APP_NAME)
+        .join(format!("{stem}-{}", chrono::Utc::now().timestamp_millis()));
+
+    let samples_folder = target.join(SAMPLES_FOLDER);
+
+    fs::create_dir_all(&samples_folder)?;
+
+    let project_len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into()?) as usize;
+    let project_path = target.join(&stem).with_extension(PROJECT_EXTENSION);
+
+    fs::write(&project_path, take(&mut bytes, project_len)?)?;
+
+    while !bytes.is_empty() {
+        let name_len = u32::from_le_bytes(take(&mut bytes, 4)?.try_into()?) as usize;
+        let name = String::from_utf8(take(&mut bytes, name_len)?.to_vec())?;
+
+        // Names are only ever file names, anything else could escape the samples folder
+        if Path::new(&name).file_name() != Some(name.as_ref()) {
+            bail!("The bundle archive contains an invalid file name `{name}`.");
+        }
+
+        let contents_len = u64::from_le_bytes(take(&mut bytes, 8)?.try_into()?) as usize;
+
+        fs::write(samples_folder.join(name), take(&mut bytes, contents_len)?)?;
+    }
+
+    Ok(project_path)
+}
@@ -0,0 +1,593 @@
+use std::path::PathBuf;
+
+use egui::Id;
+
+use crate::{
+    internals::{
+        clips::{Clip, ClipId},
+        instruments::Instrument,
+        patterns::Pattern,
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::{Track, TrackId},
+    },
+    ui::panels::{
+        lib::PanelStates,
+        media::{BookmarkedObject, WorkspaceSampleAttributes},
+        mixer::ChannelStrip,
+        playlist::TrackCustomization,
+    },
+};
+
+/// The maximum amount of entries which can be undone.
+const HISTORY_LIMIT: usize = 200;
+
+/// A single reversible modification of the panel states.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// A sample has been dropped into the playlist.
+    PlaceClip {
+        clip: Clip,
+
+        /// If the sample has not been in the workspace yet, it gets imported when placed.
+        imported: Option<(PathBuf, WorkspaceSampleAttributes)>,
+    },
+
+    /// A clip has been dragged to another track and start, the positions are stored as `(track, start)`.
+    MoveClip {
+        id: ClipId,
+        from: (TrackId, Tick),
+        to: (TrackId, Tick),
+    },
+
+    /// A clip has been dragged out of the playlist.
+    RemoveClip { clip: Clip },
+
+    /// A clip has been trimmed, faded, reversed or its gain has been changed.
+    ChangeClip { before: Clip, after: Clip },
+
+    /// Tempo or meter changes have been added, modified or removed.
+    ChangeTempo { before: TempoMap, after: TempoMap },
+
+    /// The label, the color or the height of a track has been changed.
+    CustomizeTrack {
+        id: TrackId,
+        before: TrackCustomization,
+        after: TrackCustomization,
+    },
+
+    /// The instrument of a track has been changed or replaced.
+    ChangeInstrument {
+        id: TrackId,
+        before: Instrument,
+        after: Instrument,
+    },
+
+    /// A track has been inserted before the row.
+    InsertTrack { row: usize, track: Track },
+
+    /// A track has been deleted along with its clips and its channel strip.
+    RemoveTrack {
+        row: usize,
+        track: Track,
+        clips: Vec<Clip>,
+        channel: Option<ChannelStrip>,
+    },
+
+    /// A track has been dragged from a row to another one.
+    MoveTrack { from: usize, to: usize },
+
+    /// A pattern has been created.
+    InsertPattern { pattern: Pattern },
+
+    /// A pattern has been deleted, its clips are removed by their own edits.
+    RemovePattern { pattern: Pattern },
+
+    /// The steps, the rows, the name or the color of a pattern have been changed.
+    ChangePattern { before: Pattern, after: Pattern },
+
+    RemoveBookmark {
+        index: usize,
+        path: PathBuf,
+        bookmark: BookmarkedObject,
+    },
+
+    RemoveWorkspaceSample {
+        index: usize,
+        path: PathBuf,
+        attributes: WorkspaceSampleAttributes,
+    },
+}
+
+impl Edit {
+    /// Performs the edit.
+    pub fn apply(&self, states: &PanelStates) {
+        match self {
+            Edit::PlaceClip { clip, imported } => {
+                if let Some((path, attributes)) = imported {
+                    states
+                        .media_panel
+                        .write()
+                        .workspace_selector
+                        .workspace_samples
+                        .insert(path.clone(), attributes.clone());
+                }
+
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .insert_clip(clip.clone());
+            }
+            Edit::MoveClip {
+                id,
+                to: (track, start),
+                ..
+            } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .move_clip(*id, *track, *start);
+            }
+            Edit::RemoveClip { clip } => {
+                states.playlist_panel.write().clips.remove(clip.id);
+            }
+            Edit::ChangeClip { after, .. } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .insert_clip(after.clone());
+            }
+            Edit::ChangeTempo { after, .. } => {
+                states.playlist_panel.write().tempo = after.clone();
+            }
+            Edit::CustomizeTrack { id, after, .. } => {
+                set_track_customization(states, *id, after.clone());
+            }
+            Edit::ChangeInstrument { id, after, .. } => {
+                set_track_instrument(states, *id, after.clone());
+            }
+            Edit::InsertTrack { row, track } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .tracks
+                    .insert(*row, track.clone());
+            }
+            Edit::RemoveTrack {
+                row, track, clips, ..
+            } => {
+                {
+                    let mut playlist = states.playlist_panel.write();
+
+                    playlist.tracks.remove(*row);
+
+                    for clip in clips {
+                        playlist.clips.remove(clip.id);
+                    }
+                }
+
+                states.mixer_panel.write().channels.remove(&track.id);
+            }
+            Edit::MoveTrack { from, to } => {
+                states.playlist_panel.write().tracks.move_track(*from, *to);
+            }
+            Edit::InsertPattern { pattern } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .patterns
+                    .insert(pattern.clone());
+            }
+            Edit::RemovePattern { pattern } => {
+                states.playlist_panel.write().patterns.remove(pattern.id);
+            }
+            Edit::ChangePattern { after, .. } => {
+                states.playlist_panel.write().patterns.insert(after.clone());
+            }
+            Edit::RemoveBookmark { path, .. } => {
+                states
+                    .media_panel
+                    .write()
+                    .bookmark_selector
+                    .bookmarks
+                    .shift_remove(path);
+            }
+            Edit::RemoveWorkspaceSample { path, .. } => {
+                states
+                    .media_panel
+                    .write()
+                    .workspace_selector
+                    .workspace_samples
+                    .shift_remove(path);
+            }
+        }
+    }
+
+    /// Undoes the edit, this expects the edit to be the last one applied.
+    pub fn revert(&self, states: &PanelStates) {
+        match self {
+            Edit::PlaceClip { clip, imported } => {
+                states.playlist_panel.write().clips.remove(clip.id);
+
+                if let Some((path, _)) = imported {
+                    states
+                        .media_panel
+                        .write()
+                        .workspace_selector
+                        .workspace_samples
+                        .shift_remove(path);
+                }
+            }
+            Edit::MoveClip {
+                id,
+                from: (track, start),
+                ..
+            } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .move_clip(*id, *track, *start);
+            }
+            Edit::RemoveClip { clip } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .insert_clip(clip.clone());
+            }
+            Edit::ChangeClip { before, .. } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .clips
+                    .insert_clip(before.clone());
+            }
+            Edit::ChangeTempo { before, .. } => {
+                states.playlist_panel.write().tempo = before.clone();
+            }
+            Edit::CustomizeTrack { id, before, .. } => {
+                set_track_customization(states, *id, before.clone());
+            }
+            Edit::ChangeInstrument { id, before, .. } => {
+                set_track_instrument(states, *id, before.clone());
+            }
+            Edit::InsertTrack { row, .. } => {
+                states.playlist_panel.write().tracks.remove(*row);
+            }
+            Edit::RemoveTrack {
+                row,
+                track,
+                clips,
+                channel,
+            } => {
+                {
+                    let mut playlist = states.playlist_panel.write();
+
+                    playlist.tracks.insert(*row, track.clone());
+
+                    for clip in clips {
+                        playlist.clips.insert_clip(clip.clone());
+                    }
+                }
+
+                if let Some(channel) = channel {
+                    states
+                        .mixer_panel
+                        .write()
+                        .channels
+                        .insert(track.id, *channel);
+                }
+            }
+            Edit::MoveTrack { from, to } => {
+                states.playlist_panel.write().tracks.move_track(*to, *from);
+            }
+            Edit::InsertPattern { pattern } => {
+                states.playlist_panel.write().patterns.remove(pattern.id);
+            }
+            Edit::RemovePattern { pattern } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .patterns
+                    .insert(pattern.clone());
+            }
+            Edit::ChangePattern { before, .. } => {
+                states
+                    .playlist_panel
+                    .write()
+                    .patterns
+                    .insert(before.clone());
+            }
+            Edit::RemoveBookmark {
+                index,
+                path,
+                bookmark,
+            } => {
+                let bookmarks = &mut states.media_panel.write().bookmark_selector.bookmarks;
+                let index = (*index).min(bookmarks.len());
+
+                bookmarks.shift_insert(index, path.clone(), bookmark.clone());
+            }
+            Edit::RemoveWorkspaceSample {
+                index,
+                path,
+                attributes,
+            } => {
+                let workspace_samples = &mut states
+                    .media_panel
+                    .write()
+                    .workspace_selector
+                    .workspace_samples;
+                let index = (*index).min(workspace_samples.len());
+
+                workspace_samples.shift_insert(index, path.clone(), attributes.clone());
+            }
+        }
+    }
+
+    /// Tries to fold the next edit into this one, this is used to turn continuous modifications into a single edit.
+    /// Returns whether the edits could be merged.
+    fn merge(&mut self, next: &Edit) -> bool {
+        match (self, next) {
+            (
+                Edit::CustomizeTrack { id, after, .. },
+                Edit::CustomizeTrack {
+                    id: next_id,
+                    after: next_after,
+                    ..
+                },
+            ) if id == next_id => {
+                *after = next_after.clone();
+
+                true
+            }
+            (
+                Edit::ChangeInstrument { id, after, .. },
+                Edit::ChangeInstrument {
+                    id: next_id,
+                    after: next_after,
+                    ..
+                },
+            ) if id == next_id => {
+                *after = next_after.clone();
+
+                true
+            }
+            (
+                Edit::ChangeClip { after, .. },
+                Edit::ChangeClip {
+                    after: next_after, ..
+                },
+            ) if after.id == next_after.id => {
+                *after = next_after.clone();
+
+                true
+            }
+            (
+                Edit::ChangePattern { after, .. },
+                Edit::ChangePattern {
+                    after: next_after, ..
+                },
+            ) if after.id == next_after.id => {
+                *after = next_after.clone();
+
+                true
+            }
+            (
+                Edit::ChangeTempo { after, .. },
+                Edit::ChangeTempo {
+                    after: next_after, ..
+                },
+            ) => {
+                *after = next_after.clone();
+
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn set_track_customization(states: &PanelStates, id: TrackId, customization: TrackCustomization) {
+    if let Some(track) = states.playlist_panel.write().tracks.find_mut(id) {
+        track.customization = customization;
+    }
+}
+
+fn set_track_instrument(states: &PanelStates, id: TrackId, instrument: Instrument) {
+    if let Some(track) = states.playlist_panel.write().tracks.find_mut(id) {
+        track.instrument = instrument;
+    }
+}
+
+/// A group of edits which are undone and redone together.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// Displayed in the history window.
+    pub label: String,
+
+    pub edits: Vec<Edit>,
+
+    /// Entries with a group can be extended by the following edits of the same group, until the group is closed.
+    group: Option<Id>,
+}
+
+/// Records the edits of the user so that they can be undone and redone.
+///
+/// Continuous modifications (e.g. dragging the height of a track) should be recorded with [`History::record_grouped`] every frame,
+/// then closed with [`History::close_group`] once the drag has stopped. This way the whole drag is a single entry.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+
+    /// The group which the last entry can still be extended with.
+    open_group: Option<Id>,
+
+    /// Counts the changes of the project, see [`History::generation`].
+    generation: u64,
+
+    /// Counts the changes of what the playlist plays, see [`History::timeline_generation`].
+    timeline_generation: u64,
+}
+
+impl History {
+    /// Records an edit which has already been applied.
+    pub fn record(&mut self, label: impl ToString, edit: Edit) {
+        self.open_group = None;
+
+        self.push(HistoryEntry {
+            label: label.to_string(),
+            edits: vec![edit],
+            group: None,
+        });
+    }
+
+    /// Records edits which have already been applied as a single entry, e.g. when every selected clip is moved at once.
+    pub fn record_all(&mut self, label: impl ToString, edits: Vec<Edit>) {
+        if edits.is_empty() {
+            return;
+        }
+
+        self.open_group = None;
+
+        self.push(HistoryEntry {
+            label: label.to_string(),
+            edits,
+            group: None,
+        });
+    }
+
+    /// Records an edit which has already been applied, consecutive edits of the same open group are merged into one entry.
+    pub fn record_grouped(&mut self, group: Id, label: impl ToString, edit: Edit) {
+        if self.open_group == Some(group)
+            && let Some(entry) = self.undo.last_mut()
+            && entry.group == Some(group)
+        {
+            // The redo stack is cleared by the first edit of the group, there is nothing else to do
+            if !entry.edits.last_mut().is_some_and(|last| last.merge(&edit)) {
+                entry.edits.push(edit);
+            }
+
+            self.mark_timeline_changed();
+
+            return;
+        }
+
+        self.open_group = Some(group);
+
+        self.push(HistoryEntry {
+            label: label.to_string(),
+            edits: vec![edit],
+            group: Some(group),
+        });
+    }
+
+    /// Stops extending the entry of the group, the following edits of the group are going to create a new entry.
+    pub fn close_group(&mut self, group: Id) {
+        if self.open_group == Some(group) {
+            self.open_group = None;
+        }
+    }
+
+    fn push(&mut self, entry: HistoryEntry) {
+        self.mark_timeline_changed();
+        self.redo.clear();
+        self.undo.push(entry);
+
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Entries which can be undone, the last one is the most recent.
+    pub fn undo_entries(&self) -> &[HistoryEntry] {
+        &self.undo
+    }
+
+    /// Entries which can be redone, the last one is the next one to be redone.
+    pub fn redo_entries(&self) -> &[HistoryEntry] {
+        &self.redo
+    }
+
+    /// Removes every entry, this should be called when the edited project is replaced.
+    pub fn clear(&mut self) {
+        *self = Self {
+            generation: self.generation + 1,
+            timeline_generation: self.timeline_generation + 1,
+            ..Default::default()
+        };
+    }
+
+    /// Changes every time the project is edited, undone, redone or replaced, including the changes which are not recorded.
+    /// Comparing it with an earlier value tells whether the project has changed since then, without comparing the project itself.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Like [`History::generation`], but it is left as it is by the changes marked with [`History::mark_changed`].
+    /// The playback engine only rebuilds the clips and the notes it plays once this changes.
+    pub fn timeline_generation(&self) -> u64 {
+        self.timeline_generation
+    }
+
+    /// Marks a change of the project which is not recorded and leaves the clips and the notes as they are, e.g. the changes of the mixer.
+    pub fn mark_changed(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Marks a change of the project which is not recorded but changes what the clips and the notes play, e.g. relinking media.
+    pub fn mark_timeline_changed(&mut self) {
+        self.generation += 1;
+        self.timeline_generation += 1;
+    }
+
+    /// Reverts the most recent entry.
+    /// The history lock is not held while editing, so the panel states are free to be locked by the edits.
+    pub fn undo(states: &PanelStates) -> Option<String> {
+        let entry = {
+            let mut history = states.history.write();
+
+            history.open_group = None;
+            history.undo.pop()?
+        };
+
+        for edit in entry.edits.iter().rev() {
+            edit.revert(states);
+        }
+
+        let label = entry.label.clone();
+        let mut history = states.history.write();
+
+        history.mark_timeline_changed();
+        history.redo.push(entry);
+
+        Some(label)
+    }
+
+    /// Applies the most recently undone entry again.
+    pub fn redo(states: &PanelStates) -> Option<String> {
+        let entry = {
+            let mut history = states.history.write();
+
+            history.open_group = None;
+            history.redo.pop()?
+        };
+
+        for edit in entry.edits.iter() {
+            edit.apply(states);
+        }
+
+        let label = entry.label.clone();
+        let mut history = states.history.write();
+
+        history.mark_timeline_changed();
+        history.undo.push(entry);
+
+        Some(label)
+    }
+}
@@ -0,0 +1,254 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::bail;
+
+use crate::{
+    internals::{
+        clips::{Clip, ClipShape, ClipSource},
+        midi::{DRUM_CHANNEL, MidiSong, MidiTrack, read_midi, write_midi},
+        notes::{KEY_COUNT, Note, NoteSequence},
+        patterns::STEP_TICKS,
+        playback::clip_notes,
+        tempo::{BPM_RANGE, Meter, TempoChange},
+        timeline::{PPQ, Tick},
+        tracks::TrackId,
+    },
+    project_manager::history::Edit,
+    ui::panels::{
+        lib::{PanelStates, random_color_with_opacity},
+        playlist::PlaylistState,
+    },
+};
+
+/// The key the first row of a pattern is exported on, this is the bass drum of General MIDI.
+pub const PATTERN_FIRST_KEY: u8 = 36;
+
+/// Reads the MIDI file, then places its notes into the playlist as note clips.
+/// Every part of the file gets a track of its own, the tracks are inserted before `row` and the clips start at `start`.
+/// The tempo and the meter of the file are applied to the song from `start` on, everything is recorded as a single entry in the history.
+pub fn import_midi(
+    global_state: &PanelStates,
+    path: &Path,
+    row: usize,
+    start: Tick,
+) -> anyhow::Result<()> {
+    let song = read_midi(&fs::read(path)?)?;
+
+    let name = path
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    if song.tracks.is_empty() {
+        bail!("`{name}` does not contain any notes.");
+    }
+
+    let edits = import_edits(&mut global_state.playlist_panel.write(), &song, row, start);
+
+    for edit in &edits {
+        edit.apply(global_state);
+    }
+
+    global_state
+        .history
+        .write()
+        .record_all(format!("Import {name}"), edits);
+
+    Ok(())
+}
+
+/// The edits placing the song into the playlist, see [`import_midi`].
+fn import_edits(
+    playlist: &mut PlaylistState,
+    song: &MidiSong,
+    row: usize,
+    start: Tick,
+) -> Vec<Edit> {
+    let row = row.min(playlist.tracks.len());
+    let mut edits = Vec::new();
+    let mut clips = Vec::new();
+
+    for (nth, part) in song.tracks.iter().enumerate() {
+        let mut track = playlist.tracks.create(row + nth);
+        track.customization.label_text = part.name.clone();
+
+        let end = part.notes.iter().map(Note::end).max().unwrap_or_default();
+
+        // The clips end on the beat after their last note
+        let length = end.0.div_ceil(PPQ).max(1) as f64;
+
+        clips.push(Clip {
+            id: playlist.clips.allocate_id(),
+            track: track.id,
+            start,
+            length,
+            source: ClipSource::Notes(NoteSequence {
+                name: part.name.clone(),
+                color: random_color_with_opacity(120),
+                notes: part.notes.clone(),
+            }),
+            shape: ClipShape::default(),
+        });
+
+        edits.push(Edit::InsertTrack {
+            row: row + nth,
+            track,
+        });
+    }
+
+    edits.extend(clips.into_iter().map(|clip| Edit::PlaceClip {
+        clip,
+        imported: None,
+    }));
+
+    let before = playlist.tempo.clone();
+    let mut after = before.clone();
+
+    for (tick, bpm) in &song.tempo_changes {
+        after.set_tempo_change(TempoChange {
+            position: Tick(start.0 + tick.0),
+            bpm: bpm.clamp(*BPM_RANGE.start(), *BPM_RANGE.end()),
+            ramp: false,
+        });
+    }
+
+    // The meter changes of the file are counted in its own bars, which start on the bar of `start`
+    let first_bar = before.bar_at(start).index;
+    let mut bar = (0, Tick(0), Meter::default());
+
+    for (tick, meter) in &song.meter_changes {
+        let (index, bar_start, current) = bar;
+        let index = index + (tick.0 - bar_start.0).div_ceil(current.bar_ticks()) as usize;
+
+        bar = (index, *tick, *meter);
+        after.set_meter_change(first_bar + index, *meter);
+    }
+
+    if after != before {
+        edits.push(Edit::ChangeTempo { before, after });
+    }
+
+    edits
+}
+
+/// Writes the note clips and the pattern clips of the playlist into a Standard MIDI File of type 1.
+pub fn export_midi(playlist: &PlaylistState, path: &Path) -> anyhow::Result<()> {
+    let song = playlist_midi(playlist);
+
+    if song.tracks.is_empty() {
+        bail!("There are no note clips or patterns to export.");
+    }
+
+    fs::write(path, write_midi(&song)?)?;
+
+    Ok(())
+}
+
+/// The notes of the playlist along with the tempo and the meter of the song.
+/// Every track of the playlist which plays notes gets a MIDI track, the patterns of a track are written onto a drum track of their own.
+/// The rows of the patterns are played on the keys from [`PATTERN_FIRST_KEY`] on.
+pub fn playlist_midi(playlist: &PlaylistState) -> MidiSong {
+    let mut notes: BTreeMap<TrackId, Vec<Note>> = BTreeMap::new();
+    let mut hits: BTreeMap<TrackId, Vec<Note>> = BTreeMap::new();
+
+    for note in clip_notes(playlist) {
+        let start = Tick::from_beats(note.beat);
+
+        notes.entry(note.track).or_default().push(Note {
+            key: note.key,
+            start,
+            length: Tick(Tick::from_beats(note.beat + note.length).0 - start.0).max(Tick(1)),
+            velocity: note.velocity.min(1.),
+        });
+    }
+
+    for clip in playlist.clips.iter() {
+        let Some(pattern) = clip
+            .source
+            .pattern()
+            .and_then(|id| playlist.patterns.get(id))
+        else {
+            continue;
+        };
+
+        hits.entry(clip.track).or_default().extend(
+            pattern
+                .hits(clip.shape.offset, clip.length)
+                .into_iter()
+                .filter_map(|hit| {
+                    let key = PATTERN_FIRST_KEY as usize + hit.row;
+
+                    // Patterns with more rows than there are keys left lose their last rows
+                    (key < KEY_COUNT as usize).then(|| Note {
+                        key: key as u8,
+                        start: Tick::from_beats(clip.start.as_beats() + hit.beat),
+                        length: Tick(STEP_TICKS),
+                        velocity: (hit.velocity * clip.shape.gain).min(1.),
+                    })
+                }),
+        );
+    }
+
+    // The melodic tracks take turns on the channels other than the drum channel
+    let mut channels = (0..16).filter(|channel| *channel != DRUM_CHANNEL).cycle();
+    let mut tracks = Vec::new();
+
+    for track in playlist.tracks.iter() {
+        let name = &track.customization.label_text;
+
+        if let Some(mut notes) = notes.remove(&track.id) {
+            notes.sort_by_key(|note| (note.start, note.key));
+
+            tracks.push(MidiTrack {
+                name: name.clone(),
+                channel: channels.next().unwrap_or_default(),
+                notes,
+            });
+        }
+
+        if let Some(mut notes) = hits.remove(&track.id) {
+            notes.sort_by_key(|note| (note.start, note.key));
+
+            tracks.push(MidiTrack {
+                name: format!("{name} (Patterns)"),
+                channel: DRUM_CHANNEL,
+                notes,
+            });
+        }
+    }
+
+    let tempo = &playlist.tempo;
+    let mut tempo_changes = vec![(Tick(0), tempo.bpm)];
+    let changes = tempo.tempo_changes();
+
+    for (idx, change) in changes.iter().enumerate() {
+        tempo_changes.push((change.position, change.bpm));
+
+        // MIDI files cannot ramp the tempo, so the ramp is written as a change on every beat
+        if change.ramp
+            && let Some(next) = changes.get(idx + 1)
+        {
+            let mut position = Tick(change.position.0 + PPQ);
+
+            while position < next.position {
+                tempo_changes.push((position, tempo.bpm_at(position.as_beats())));
+                position = Tick(position.0 + PPQ);
+            }
+        }
+    }
+
+    let meter_changes = std::iter::once((Tick(0), tempo.meter))
+        .chain(
+            tempo
+                .meter_changes()
+                .iter()
+                .map(|change| (tempo.bar(change.bar).start, change.meter)),
+        )
+        .collect();
+
+    MidiSong {
+        tracks,
+        tempo_changes,
+        meter_changes,
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+use indexmap::IndexSet;
+
+use crate::{
+    internals::{
+        fs::{MediaFingerprint, fingerprint_file, relative_path},
+        sample::generate_sample_waveform,
+    },
+    ui::panels::{
+        lib::PanelStates, media::WorkspaceSelector, mixer::MixerState, playlist::PlaylistState,
+    },
+};
+
+/// Collecting every referenced file of the project next to it or into a single archive.
+pub mod bundle;
+/// Undoing and redoing the edits of the user.
+pub mod history;
+/// Importing and exporting Standard MIDI Files.
+pub mod midi;
+/// Saving and loading the settings of synths.
+pub mod presets;
+/// Periodic recovery snapshots and detecting crashed sessions.
+pub mod recovery;
+/// Finding and relinking media which has been moved since the project was saved.
+pub mod relink;
+
+/// The versioned on-disk representation of projects.
+/// The live ui structs are converted into the types of the newest version when saving, older versions are migrated step by step when loading.
+pub mod schema;
+
+/// The file extension used by Beatroot projects.
+pub const PROJECT_EXTENSION: &str = "btrt";
+
+/// Everything that gets stored inside of a `.btrt` project file.
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// The arrangement of the song, this includes the bpm, the clips and the track customizations.
+    pub playlist: PlaylistState,
+
+    /// The samples imported into the workspace of the project.
+    pub workspace: WorkspaceSelector,
+
+    /// The fingerprints of the referenced files when the project was last saved.
+    /// These are used to find the files again if they have been moved.
+    pub media: HashMap<PathBuf, MediaFingerprint>,
+
+    /// The channel strips of the tracks and the master.
+    pub mixer: MixerState,
+}
+
+impl Project {
+    /// Takes a snapshot of the project related parts of the panel states.
+    pub fn capture(states: &PanelStates) -> Self {
+        Self {
+            playlist: states.playlist_panel.read().clone(),
+            workspace: states.media_panel.read().workspace_selector.clone(),
+            media: states.media_fingerprints.read().clone(),
+            mixer: states.mixer_panel.read().clone(),
+        }
+    }
+
+    /// Lists every file referenced by the project without duplicates.
+    pub fn referenced_media(&self) -> IndexSet<PathBuf> {
+        self.workspace
+            .workspace_samples
+            .keys()
+            .cloned()
+            .chain(
+                self.playlist
+                    .clips
+                    .iter()
+                    .filter_map(|clip| Some(clip.source.sample()?.path.clone())),
+            )
+            .chain(
+                self.playlist
+                    .patterns
+                    .iter()
+                    .flat_map(|pattern| pattern.rows.iter().map(|row| row.sample.path.clone())),
+            )
+            .chain(
+                self.playlist
+                    .tracks
+                    .iter()
+                    .filter_map(|track| Some(track.instrument.sample()?.path.clone())),
+            )
+            .collect()
+    }
+
+    /// Overwrites the project related parts of the panel states with this project.
+    /// The history is cleared, since its edits belong to the replaced project.
+    pub fn restore(self, states: &PanelStates) {
+        // The restored playlist is stopped, so the engine has to be stopped too
+        states.playback.stop();
+
+        *states.playlist_panel.write() = self.playlist;
+        states.media_panel.write().workspace_selector = self.workspace;
+        *states.mixer_panel.write() = self.mixer;
+        *states.media_fingerprints.write() = self.media;
+        states.history.write().clear();
+    }
+
+    /// Rewrites every path referencing a file in the project.
+    pub fn map_media_paths(&mut self, map: impl FnMut(&Path) -> PathBuf) {
+        map_media_paths(
+            &mut self.playlist,
+            &mut self.workspace,
+            &mut self.media,
+            map,
+        );
+    }
+
+    /// Rewrites every path referencing a file in the project related parts of the panel states in place.
+    /// Unlike [`Project::restore`] this keeps playing and keeps the history.
+    pub fn map_state_media_paths(states: &PanelStates, map: impl FnMut(&Path) -> PathBuf) {
+        // Hold every lock so that the project is never seen half rewritten
+        let mut media_panel = states.media_panel.write();
+        let mut playlist = states.playlist_panel.write();
+        let mut media = states.media_fingerprints.write();
+
+        map_media_paths(
+            &mut playlist,
+            &mut media_panel.workspace_selector,
+            &mut media,
+            map,
+        );
+
+        states.history.write().mark_timeline_changed();
+    }
+
+    /// Waveform maps are not stored in the project files, so they have to be generated again after loading.
+    /// If a sample cannot be read its waveform is left empty.
+    pub fn regenerate_waveforms(&mut self) {
+        let mut waveforms = HashMap::new();
+
+        for (path, sample) in self.workspace.workspace_samples.iter_mut() {
+            sample.waveform_map = waveforms
+                .entry(path.clone())
+                .or_insert_with(|| generate_sample_waveform(path).ok())
+                .clone();
+        }
+
+        for sample in self.playlist.clips.samples_mut() {
+            sample.waveform_map = waveforms
+                .entry(sample.path.clone())
+                .or_insert_with(|| generate_sample_waveform(&sample.path).ok())
+                .clone();
+        }
+    }
+}
+
+fn map_media_paths(
+    playlist: &mut PlaylistState,
+    workspace: &mut WorkspaceSelector,
+    media: &mut HashMap<PathBuf, MediaFingerprint>,
+    mut map: impl FnMut(&Path) -> PathBuf,
+) {
+    for sample in playlist.samples_mut() {
+        sample.path = map(&sample.path);
+    }
+
+    workspace.workspace_samples = workspace
+        .workspace_samples
+        .drain(..)
+        .map(|(path, sample)| (map(&path), sample))
+        .collect();
+
+    workspace.selected_object = workspace.selected_object.as_deref().map(&mut map);
+
+    *media = media
+        .drain()
+        .map(|(path, fingerprint)| (map(&path), fingerprint))
+        .collect();
+}
+
+/// Reads the project file found at `path` and migrates it to the newest version.
+pub fn open_project(path: &Path) -> anyhow::Result<Project> {
+    let bytes = fs::read(path)?;
+
+    // Archives are extracted first, then the project inside of them is opened
+    if bundle::is_bundle_archive(&bytes) {
+        let extracted = bundle::extract_bundle_archive(path, &bytes)?;
+
+        return open_project_file(&extracted, &fs::read(&extracted)?);
+    }
+
+    open_project_file(path, &bytes)
+}
+
+/// Reads the project from the bytes of a `.btrt` file found at `path`.
+fn open_project_file(path: &Path, bytes: &[u8]) -> anyhow::Result<Project> {
+    // An archive can only contain a project, opening archives inside of archives would never end for a crafted file
+    if bundle::is_bundle_archive(bytes) {
+        bail!("The bundle archive contains another bundle archive instead of a project.");
+    }
+
+    let mut dto = schema::decode_project(bytes)?;
+
+    // Paths are stored relative to the project file
+    if let Some(base) = path.parent() {
+        dto.resolve_paths(base);
+    }
+
+    let mut project = Project::from(dto);
+
+    project.regenerate_waveforms();
+
+    Ok(project)
+}
+
+/// Converts the project into its on-disk representation with the fingerprints of the available files updated.
+/// The old fingerprints are kept for the missing files.
+fn fingerprinted_dto(project: &Project) -> schema::ProjectDto {
+    let mut dto = schema::ProjectDto::from(project);
+
+    for media in dto.media.iter_mut() {
+        if let Ok(fingerprint) = fingerprint_file(&media.absolute) {
+            media.fingerprint = Some(schema::v3::FingerprintDto {
+                size: fingerprint.size,
+                hash: fingerprint.hash,
+            });
+        }
+    }
+
+    dto
+}
+
+/// Serializes the project and writes it to `path`, overwriting the file if it already exists.
+pub fn save_project(path: &Path, project: &Project) -> anyhow::Result<()> {
+    let mut dto = fingerprinted_dto(project);
+
+    // Store the paths relative to the project file so that the project can be moved together with its samples
+    if let Some(base) = path.parent() {
+        dto.map_paths(|media_path| {
+            relative_path(media_path, base).unwrap_or_else(|| media_path.to_path_buf())
+        });
+    }
+
+    let bytes = schema::encode_project(&dto)?;
+
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+/// Creates an empty project.
+pub fn new_project() -> Project {
+    Project::default()
+}
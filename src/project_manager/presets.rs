@@ -0,0 +1,57 @@
+use std::{fs, path::Path};
+
+use anyhow::bail;
+
+use crate::{
+    internals::synth::Synth,
+    project_manager::schema::{CURRENT_VERSION, ProjectHeader, UnsupportedVersion, v12},
+};
+
+/// The file extension used by synth presets.
+pub const PRESET_EXTENSION: &str = "btsyn";
+
+/// Every synth preset starts with these bytes.
+pub const PRESET_MAGIC: &[u8; 4] = b"BTSY";
+
+/// The first project version which has synths, older presets cannot exist.
+const FIRST_PRESET_VERSION: u32 = 12;
+
+/// Writes the header and the settings of the synth.
+/// The settings are stored with the types of the project version they were written with, so that presets migrate like projects do.
+pub fn encode_preset(synth: &Synth) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = PRESET_MAGIC.to_vec();
+
+    rmp_serde::encode::write_named(&mut bytes, &ProjectHeader::default())?;
+    rmp_serde::encode::write_named(&mut bytes, &v12::SynthDto::from(synth))?;
+
+    Ok(bytes)
+}
+
+/// Reads the header and the settings of the synth.
+pub fn decode_preset(bytes: &[u8]) -> anyhow::Result<Synth> {
+    let Some(mut body) = bytes.strip_prefix(PRESET_MAGIC.as_slice()) else {
+        bail!("This file is not a synth preset.");
+    };
+
+    // Reading the header advances the slice to the start of the body
+    let header: ProjectHeader = rmp_serde::decode::from_read(&mut body)?;
+
+    match header.version {
+        found if found > CURRENT_VERSION => Err(UnsupportedVersion {
+            found,
+            supported: CURRENT_VERSION,
+        })?,
+        found if found < FIRST_PRESET_VERSION => bail!("Invalid preset version {found}."),
+        _ => Ok(rmp_serde::from_slice::<v12::SynthDto>(body)?.into()),
+    }
+}
+
+pub fn save_preset(path: &Path, synth: &Synth) -> anyhow::Result<()> {
+    fs::write(path, encode_preset(synth)?)?;
+
+    Ok(())
+}
+
+pub fn open_preset(path: &Path) -> anyhow::Result<Synth> {
+    decode_preset(&fs::read(path)?)
+}
@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::RwLock;
+use tokio::task::JoinHandle;
+
+use crate::{
+    APP_NAME,
+    project_manager::{PROJECT_EXTENSION, Project, save_project},
+    ui::panels::lib::PanelStates,
+};
+
+/// Every session keeps this file in its folder while it is running, if it is still present on launch the session has crashed.
+const SESSION_MARKER: &str = "session.lock";
+
+/// Every snapshot's file name starts with this.
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+
+/// Every session writes its snapshots into a folder of its own, the names of these folders start with this.
+/// This way a session never rotates away the snapshots of a crashed session, which might still be recovered.
+const SESSION_PREFIX: &str = "session-";
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutosaveSettings {
+    /// Whether recovery snapshots should be written at all.
+    pub enabled: bool,
+
+    /// How many seconds should pass between two snapshots.
+    pub interval_secs: u64,
+
+    /// How many snapshots should be kept, the oldest one is deleted first.
+    pub snapshot_count: usize,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 120,
+            snapshot_count: 5,
+        }
+    }
+}
+
+/// The folder the recovery snapshots and the session marker are stored in.
+pub fn recovery_dir() -> PathBuf {
+    eframe::storage_dir(APP_NAME)
+        .unwrap_or_else(|| std::env::temp_dir().join(APP_NAME))
+        .join("recovery")
+}
+
+/// Prepares the folder for a new session.
+/// Returns whether an earlier session has exited without cleaning up after itself.
+pub fn begin_session(dir: &Path) -> anyhow::Result<bool> {
+    fs::create_dir_all(dir)?;
+
+    Ok(session_dirs(dir)
+        .iter()
+        .any(|session| session.join(SESSION_MARKER).exists()))
+}
+
+/// Marks a clean exit of the session by deleting its folder together with its snapshots and its marker.
+/// The folders of the other sessions are left alone, they might still be running.
+pub fn end_session(session_dir: &Path) -> anyhow::Result<()> {
+    fs::remove_dir_all(session_dir)?;
+
+    Ok(())
+}
+
+/// Creates the folder the snapshots of a new session are written to, the session is marked as running until [`end_session`] is called.
+pub fn create_session_dir(dir: &Path) -> anyhow::Result<PathBuf> {
+    let session = dir.join(format!(
+        "{SESSION_PREFIX}{:020}-{}",
+        chrono::Utc::now().timestamp_millis(),
+        std::process::id()
+    ));
+
+    fs::create_dir_all(&session)?;
+    fs::write(session.join(SESSION_MARKER), std::process::id().to_string())?;
+
+    Ok(session)
+}
+
+/// Lists the folders of the sessions which have written snapshots into the folder.
+fn session_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(SESSION_PREFIX))
+        })
+        .collect()
+}
+
+/// Deletes the snapshots of every session except the `kept` one, this is done once the crashed sessions will not be recovered anymore.
+/// The snapshots written straight into the folder by older versions are deleted too.
+pub fn discard_sessions(dir: &Path, kept: Option<&Path>) -> anyhow::Result<()> {
+    for snapshot in list_snapshots(dir) {
+        fs::remove_file(snapshot)?;
+    }
+
+    for session in session_dirs(dir) {
+        if Some(session.as_path()) != kept {
+            fs::remove_dir_all(session)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Lists the snapshots in the folder from the oldest to the newest.
+pub fn list_snapshots(dir: &Path) -> Vec<PathBuf> {
+    let mut snapshots: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension() == Some(PROJECT_EXTENSION.as_ref())
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(SNAPSHOT_PREFIX))
+        })
+        .collect();
+
+    // The names contain a zero padded timestamp, so sorting them sorts them by age.
+    snapshots.sort();
+
+    snapshots
+}
+
+/// Returns the most recently written snapshot of any session.
+pub fn newest_snapshot(dir: &Path) -> Option<PathBuf> {
+    session_dirs(dir)
+        .into_iter()
+        .chain([dir.to_path_buf()])
+        .filter_map(|folder| list_snapshots(&folder).pop())
+        .max_by(|a, b| a.file_name().cmp(&b.file_name()))
+}
+
+/// Saves the project into a new snapshot, then deletes the oldest snapshots so that at most `snapshot_count` are kept.
+pub fn write_snapshot(
+    dir: &Path,
+    project: &Project,
+    snapshot_count: usize,
+) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let path = dir.join(format!(
+        "{SNAPSHOT_PREFIX}{:020}.{PROJECT_EXTENSION}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+
+    save_project(&path, project)?;
+
+    let snapshots = list_snapshots(dir);
+    let excess = snapshots.len().saturating_sub(snapshot_count.max(1));
+
+    for snapshot in &snapshots[..excess] {
+        fs::remove_file(snapshot)?;
+    }
+
+    Ok(path)
+}
+
+/// Spawns a task on the tokio runtime which periodically writes the snapshots of the panel states into the folder of the session.
+/// A snapshot is only written if the project has changed since the last one, see [`History::generation`](crate::project_manager::history::History::generation).
+/// The settings are re-read after every snapshot, so changing them takes effect after the current interval.
+pub fn spawn_autosave(
+    states: Arc<PanelStates>,
+    settings: Arc<RwLock<AutosaveSettings>>,
+    session_dir: PathBuf,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        // The project the session starts with is already stored by the application
+        let mut saved_generation = states.history.read().generation();
+
+        loop {
+            let interval = settings.read().interval_secs.max(1);
+
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            let AutosaveSettings {
+                enabled,
+                snapshot_count,
+                ..
+            } = settings.read().clone();
+
+            let generation = states.history.read().generation();
+
+            if !enabled || generation == saved_generation {
+                continue;
+            }
+
+            // Capturing is fast, writing the file is done on a thread that is allowed to block.
+            let project = Project::capture(&states);
+            let dir = session_dir.clone();
+
+            // A failed snapshot is simply retried after the next interval
+            if let Ok(Ok(_)) =
+                tokio::task::spawn_blocking(move || write_snapshot(&dir, &project, snapshot_count))
+                    .await
+            {
+                saved_generation = generation;
+            }
+        }
+    })
+}
@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    internals::{
+        fs::{MediaFingerprint, fingerprint_file, list_files_recursively},
+        sample::generate_sample_waveform,
+    },
+    project_manager::Project,
+    ui::panels::lib::PanelStates,
+};
+
+/// A file referenced by the project which could not be found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingMedia {
+    /// The path the project is referencing the file with.
+    pub path: PathBuf,
+
+    /// The fingerprint of the file when the project was last saved.
+    pub fingerprint: Option<MediaFingerprint>,
+
+    /// Files which match the missing file based on the [`MatchCriteria`] of the last search.
+    pub candidates: Vec<PathBuf>,
+}
+
+/// Decides which properties of a file have to match for it to be considered a candidate.
+/// Every enabled criterium has to match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchCriteria {
+    pub file_name: bool,
+    pub size: bool,
+    pub hash: bool,
+}
+
+impl Default for MatchCriteria {
+    fn default() -> Self {
+        Self {
+            file_name: true,
+            size: false,
+            hash: false,
+        }
+    }
+}
+
+impl MatchCriteria {
+    /// Checks whether the file at `path` matches the missing media.
+    /// The cheap checks are done first, so that we only hash the files which could match.
+    pub fn matches(&self, missing: &MissingMedia, path: &Path) -> bool {
+        // If nothing is enabled nothing can match
+        if !(self.file_name || self.size || self.hash) {
+            return false;
+        }
+
+        if self.file_name && missing.path.file_name() != path.file_name() {
+            return false;
+        }
+
+        if self.size || self.hash {
+            // We cannot compare the contents without knowing how the original file looked like
+            let Some(fingerprint) = missing.fingerprint else {
+                return false;
+            };
+
+            let Ok(metadata) = fs::metadata(path) else {
+                return false;
+            };
+
+            // Files with different sizes cannot have the same contents
+            if metadata.len() != fingerprint.size {
+                return false;
+            }
+
+            if self.hash && !fingerprint_file(path).is_ok_and(|candidate| candidate == fingerprint)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Lists every file referenced by the project which does not exist.
+pub fn find_missing_media(project: &Project) -> Vec<MissingMedia> {
+    project
+        .referenced_media()
+        .into_iter()
+        .filter(|path| !path.exists())
+        .map(|path| MissingMedia {
+            fingerprint: project.media.get(&path).copied(),
+            path,
+            candidates: vec![],
+        })
+        .collect()
+}
+
+/// Searches the folders (recursively) for files matching the missing media, the candidates of the media are overwritten.
+pub fn search_candidates(
+    missing: &mut [MissingMedia],
+    folders: &[PathBuf],
+    criteria: MatchCriteria,
+) {
+    for media in missing.iter_mut() {
+        media.candidates.clear();
+    }
+
+    for folder in folders {
+        for file in list_files_recursively(folder) {
+            for media in missing.iter_mut() {
+                if !media.candidates.contains(&file) && criteria.matches(media, &file) {
+                    media.candidates.push(file.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Replaces every reference of `old` with `new` in the playlist and in the workspace at once.
+pub fn relink(states: &PanelStates, old: &Path, new: &Path) {
+    let waveform_map = generate_sample_waveform(&new.to_path_buf()).ok();
+
+    // Hold both locks so that the project is never seen half relinked
+    let mut media_panel = states.media_panel.write();
+    let mut playlist = states.playlist_panel.write();
+
+    for sample in playlist.samples_mut() {
+        if sample.path == old {
+            sample.path = new.to_path_buf();
+            sample.waveform_map = waveform_map.clone();
+        }
+    }
+
+    let workspace = &mut media_panel.workspace_selector;
+
+    // Replace the key in place, so that the order of the workspace is kept
+    workspace.workspace_samples = workspace
+        .workspace_samples
+        .drain(..)
+        .map(|(path, mut sample)| {
+            if path == old {
+                sample.waveform_map = waveform_map.clone();
+                (new.to_path_buf(), sample)
+            } else {
+                (path, sample)
+            }
+        })
+        .collect();
+
+    if workspace.selected_object.as_deref() == Some(old) {
+        workspace.selected_object = Some(new.to_path_buf());
+    }
+
+    // The relinked file is fingerprinted again when the project is saved
+    states.media_fingerprints.write().remove(old);
+    states.history.write().mark_timeline_changed();
+}
@@ -0,0 +1,167 @@
+use std::fmt::Display;
+
+use anyhow::bail;
+
+/// The layout of the first project files, these files did not have a header.
+pub mod v1;
+/// Note clips are stored, tracks store the instrument playing their notes.
+pub mod v10;
+/// Tracks can play their notes with a sampler.
+pub mod v11;
+/// Tracks can play their notes with a synth.
+pub mod v12;
+/// Waveform maps are no longer stored, samples are stored in a list.
+pub mod v2;
+/// Paths are stored relative to the project file, referenced files are fingerprinted.
+pub mod v3;
+/// The channel strips of the mixer are stored.
+pub mod v4;
+/// Samples are stored as clips with stable ids, clips are allowed to overlap.
+pub mod v5;
+/// The tempo and the meter of the song are able to change.
+pub mod v6;
+/// Clips can be trimmed, faded, reversed and have their own gain.
+pub mod v7;
+/// Tracks have stable ids and are stored in the order they are displayed.
+pub mod v8;
+/// Patterns and the clips playing them are stored.
+pub mod v9;
+
+/// The body of the newest project version.
+pub use v12::ProjectDto;
+
+/// Every project file which has a header starts with these bytes.
+pub const MAGIC: &[u8; 4] = b"BTRT";
+
+/// The version of the project files written by this build.
+pub const CURRENT_VERSION: u32 = 12;
+
+/// Written before the body of the project.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProjectHeader {
+    /// The version of the body of the project.
+    pub version: u32,
+
+    /// The version of the application which has written this project.
+    pub app_version: String,
+}
+
+impl Default for ProjectHeader {
+    fn default() -> Self {
+        Self {
+            version: CURRENT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+}
+
+/// Returned when the project was created by a newer version of the application.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedVersion {
+    pub found: u32,
+    pub supported: u32,
+}
+
+impl Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "This project was saved by a newer version of Beatroot (project version {}, newest supported version {}). Please update Beatroot to open it.",
+            self.found, self.supported
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/// A project body of any known version.
+pub enum VersionedProject {
+    V1(v1::ProjectDto),
+    V2(v2::ProjectDto),
+    V3(v3::ProjectDto),
+    V4(v4::ProjectDto),
+    V5(v5::ProjectDto),
+    V6(v6::ProjectDto),
+    V7(v7::ProjectDto),
+    V8(v8::ProjectDto),
+    V9(v9::ProjectDto),
+    V10(v10::ProjectDto),
+    V11(v11::ProjectDto),
+    V12(v12::ProjectDto),
+}
+
+impl VersionedProject {
+    /// Decodes the body of the project with the types of the given version.
+    pub fn decode(version: u32, body: &[u8]) -> anyhow::Result<Self> {
+        Ok(match version {
+            1 => Self::V1(rmp_serde::from_slice(body)?),
+            2 => Self::V2(rmp_serde::from_slice(body)?),
+            3 => Self::V3(rmp_serde::from_slice(body)?),
+            4 => Self::V4(rmp_serde::from_slice(body)?),
+            5 => Self::V5(rmp_serde::from_slice(body)?),
+            6 => Self::V6(rmp_serde::from_slice(body)?),
+            7 => Self::V7(rmp_serde::from_slice(body)?),
+            8 => Self::V8(rmp_serde::from_slice(body)?),
+            9 => Self::V9(rmp_serde::from_slice(body)?),
+            10 => Self::V10(rmp_serde::from_slice(body)?),
+            11 => Self::V11(rmp_serde::from_slice(body)?),
+            12 => Self::V12(rmp_serde::from_slice(body)?),
+            0 => bail!("Invalid project version 0."),
+            found => Err(UnsupportedVersion {
+                found,
+                supported: CURRENT_VERSION,
+            })?,
+        })
+    }
+
+    /// Migrates the project to the next version, the newest version is left untouched.
+    pub fn upgrade(self) -> Self {
+        match self {
+            Self::V1(project) => Self::V2(project.into()),
+            Self::V2(project) => Self::V3(project.into()),
+            Self::V3(project) => Self::V4(project.into()),
+            Self::V4(project) => Self::V5(project.into()),
+            Self::V5(project) => Self::V6(project.into()),
+            Self::V6(project) => Self::V7(project.into()),
+            Self::V7(project) => Self::V8(project.into()),
+            Self::V8(project) => Self::V9(project.into()),
+            Self::V9(project) => Self::V10(project.into()),
+            Self::V10(project) => Self::V11(project.into()),
+            Self::V11(project) => Self::V12(project.into()),
+            Self::V12(project) => Self::V12(project),
+        }
+    }
+
+    /// Upgrades the project step by step until it reaches the newest version.
+    pub fn into_latest(mut self) -> ProjectDto {
+        loop {
+            match self {
+                Self::V12(project) => return project,
+                outdated => self = outdated.upgrade(),
+            }
+        }
+    }
+}
+
+/// Reads the header (if there is one) and the body of a project file, the body is migrated to the newest version.
+pub fn decode_project(bytes: &[u8]) -> anyhow::Result<ProjectDto> {
+    // Projects without the magic bytes were written before headers were introduced
+    let Some(mut body) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        return Ok(VersionedProject::decode(1, bytes)?.into_latest());
+    };
+
+    // Reading the header advances the slice to the start of the body
+    let header: ProjectHeader = rmp_serde::decode::from_read(&mut body)?;
+
+    Ok(VersionedProject::decode(header.version, body)?.into_latest())
+}
+
+/// Writes the header and the body of the project.
+pub fn encode_project(project: &ProjectDto) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = MAGIC.to_vec();
+
+    rmp_serde::encode::write_named(&mut bytes, &ProjectHeader::default())?;
+    rmp_serde::encode::write_named(&mut bytes, project)?;
+
+    Ok(bytes)
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use indexmap::IndexMap;
+
+/// The project files written before headers were introduced.
+/// These were the live ui structs serialized directly, only the fields we migrate are listed here.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: WorkspaceDto,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    pub bpm: f32,
+    pub grid_offset: Vec2Dto,
+    pub custom_tracks: HashMap<usize, TrackCustomizationDto>,
+    pub samples: IndexMap<PositionDto, SampleInstanceDto>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Vec2Dto {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackCustomizationDto {
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+    pub height_set: bool,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, Hash, PartialEq, Eq)]
+pub struct PositionDto {
+    pub track: usize,
+    pub beat: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SampleInstanceDto {
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub properties: SamplePropertiesDto,
+    pub waveform_map: Option<Vec<[f32; 2]>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SamplePropertiesDto {
+    pub sample_rate: u32,
+    pub length: i128,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceDto {
+    pub workspace_samples: IndexMap<PathBuf, WorkspaceSampleDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSampleDto {
+    pub alias: String,
+    pub is_color_synced: bool,
+    pub color: [u8; 4],
+    pub waveform_map: Option<Vec<[f32; 2]>>,
+}
@@ -0,0 +1,145 @@
+use crate::{
+    internals::{notes::Note, timeline::Tick},
+    project_manager::schema::{
+        v2::WorkspaceSampleDto,
+        v3::MediaDto,
+        v4::MixerDto,
+        v6::{MeterChangeDto, MeterDto, TempoChangeDto},
+        v7::ClipDto,
+        v8,
+        v9::{self, PatternClipDto, PatternDto},
+    },
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+
+    /// The tracks in the order they are displayed.
+    pub tracks: Vec<TrackDto>,
+
+    /// The clips playing samples, their `track` is the id of the track.
+    pub clips: Vec<ClipDto>,
+    pub pattern_clips: Vec<PatternClipDto>,
+    pub note_clips: Vec<NoteClipDto>,
+    pub patterns: Vec<PatternDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackDto {
+    pub id: u64,
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+    pub instrument: InstrumentDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub enum InstrumentDto {
+    #[default]
+    Sine,
+}
+
+/// A clip playing its own notes, its position and its length are measured in beats.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct NoteClipDto {
+    pub id: u64,
+    pub track: u64,
+    pub start: f64,
+    pub length: f64,
+
+    /// The amount of beats skipped from the start of the notes.
+    pub offset: f64,
+    pub gain: f32,
+    pub name: String,
+    pub color: [u8; 4],
+    pub notes: Vec<NoteDto>,
+}
+
+/// The start and the length of a note are measured in ticks.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct NoteDto {
+    pub key: u8,
+    pub start: u64,
+    pub length: u64,
+    pub velocity: f32,
+}
+
+impl From<&Note> for NoteDto {
+    fn from(note: &Note) -> Self {
+        Self {
+            key: note.key,
+            start: note.start.0,
+            length: note.length.0,
+            velocity: note.velocity,
+        }
+    }
+}
+
+impl From<NoteDto> for Note {
+    fn from(note: NoteDto) -> Self {
+        Self {
+            key: note.key,
+            start: Tick(note.start),
+            length: Tick(note.length),
+            velocity: note.velocity,
+        }
+    }
+}
+
+impl From<v9::ProjectDto> for ProjectDto {
+    fn from(project: v9::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // There were no note clips yet, every track played its notes with the default instrument
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks.into_iter().map(TrackDto::from).collect(),
+                clips: playlist.clips,
+                pattern_clips: playlist.pattern_clips,
+                note_clips: Vec::new(),
+                patterns: playlist.patterns,
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
+
+impl From<v8::TrackDto> for TrackDto {
+    fn from(track: v8::TrackDto) -> Self {
+        Self {
+            id: track.id,
+            label_text: track.label_text,
+            label_text_color: track.label_text_color,
+            label_color: track.label_color,
+            height: track.height,
+            instrument: InstrumentDto::default(),
+        }
+    }
+}
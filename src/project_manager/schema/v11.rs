@@ -0,0 +1,192 @@
+use std::path::PathBuf;
+
+use crate::{
+    internals::{
+        instruments::{Envelope, LoopPoints, Sampler, SamplerMode},
+        sample::SampleProperties,
+    },
+    project_manager::schema::{
+        v2::WorkspaceSampleDto,
+        v3::MediaDto,
+        v4::MixerDto,
+        v6::{MeterChangeDto, MeterDto, TempoChangeDto},
+        v7::ClipDto,
+        v8::color_from_dto,
+        v9::{PatternClipDto, PatternDto},
+        v10::{self, NoteClipDto},
+    },
+    ui::panels::playlist::SampleInstance,
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+
+    /// The tracks in the order they are displayed.
+    pub tracks: Vec<TrackDto>,
+
+    /// The clips playing samples, their `track` is the id of the track.
+    pub clips: Vec<ClipDto>,
+    pub pattern_clips: Vec<PatternClipDto>,
+    pub note_clips: Vec<NoteClipDto>,
+    pub patterns: Vec<PatternDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackDto {
+    pub id: u64,
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+    pub instrument: InstrumentDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub enum InstrumentDto {
+    #[default]
+    Sine,
+    Sampler(SamplerDto),
+}
+
+/// The times of the envelope and of the loop are measured in seconds.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SamplerDto {
+    pub sample: Option<SamplerSampleDto>,
+    pub root: u8,
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f32,
+    pub release: f64,
+    pub one_shot: bool,
+
+    /// The start and the end of the loop.
+    pub loop_points: Option<[f64; 2]>,
+    pub polyphony: usize,
+    pub choke_group: Option<u8>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SamplerSampleDto {
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+}
+
+impl From<&Sampler> for SamplerDto {
+    fn from(sampler: &Sampler) -> Self {
+        Self {
+            sample: sampler.sample.as_ref().map(|sample| SamplerSampleDto {
+                name: sample.name.clone(),
+                color: sample.color.to_array(),
+                path: sample.path.clone(),
+                sample_rate: sample.properties.sample_rate,
+                length_ms: sample.properties.length as i64,
+            }),
+            root: sampler.root,
+            attack: sampler.envelope.attack,
+            decay: sampler.envelope.decay,
+            sustain: sampler.envelope.sustain,
+            release: sampler.envelope.release,
+            one_shot: sampler.mode == SamplerMode::OneShot,
+            loop_points: sampler
+                .loop_points
+                .map(|loop_points| [loop_points.start, loop_points.end]),
+            polyphony: sampler.polyphony,
+            choke_group: sampler.choke_group,
+        }
+    }
+}
+
+impl From<SamplerDto> for Sampler {
+    fn from(sampler: SamplerDto) -> Self {
+        Self {
+            sample: sampler.sample.map(|sample| SampleInstance {
+                name: sample.name,
+                color: color_from_dto(sample.color),
+                path: sample.path,
+                properties: SampleProperties {
+                    sample_rate: sample.sample_rate,
+                    length: sample.length_ms as i128,
+                },
+                waveform_map: None,
+            }),
+            root: sampler.root,
+            envelope: Envelope {
+                attack: sampler.attack,
+                decay: sampler.decay,
+                sustain: sampler.sustain,
+                release: sampler.release,
+            },
+            mode: match sampler.one_shot {
+                true => SamplerMode::OneShot,
+                false => SamplerMode::Gate,
+            },
+            loop_points: sampler
+                .loop_points
+                .map(|[start, end]| LoopPoints { start, end }),
+            polyphony: sampler.polyphony,
+            choke_group: sampler.choke_group,
+        }
+    }
+}
+
+impl From<v10::ProjectDto> for ProjectDto {
+    fn from(project: v10::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // There were no samplers yet, the tracks keep playing their notes with a sine
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks.into_iter().map(TrackDto::from).collect(),
+                clips: playlist.clips,
+                pattern_clips: playlist.pattern_clips,
+                note_clips: playlist.note_clips,
+                patterns: playlist.patterns,
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
+
+impl From<v10::TrackDto> for TrackDto {
+    fn from(track: v10::TrackDto) -> Self {
+        Self {
+            id: track.id,
+            label_text: track.label_text,
+            label_text_color: track.label_text_color,
+            label_color: track.label_color,
+            height: track.height,
+            instrument: match track.instrument {
+                v10::InstrumentDto::Sine => InstrumentDto::Sine,
+            },
+        }
+    }
+}
@@ -0,0 +1,766 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use egui::vec2;
+
+use crate::{
+    internals::{
+        clips::{Clip, ClipId, ClipShape, ClipSource},
+        fs::{MediaFingerprint, normalize_path},
+        instruments::{Envelope, Instrument},
+        notes::{Note, NoteSequence},
+        patterns::{Pattern, PatternId},
+        sample::SampleProperties,
+        synth::{Filter, FilterMode, Lfo, LfoShape, LfoTarget, Oscillator, Synth, Waveform},
+        tempo::{TempoChange, TempoMap},
+        timeline::Tick,
+        tracks::{Track, TrackId},
+    },
+    project_manager::{
+        Project,
+        schema::{
+            v2::WorkspaceSampleDto,
+            v3::{FingerprintDto, MediaDto},
+            v4::{ChannelStripDto, MixerDto},
+            v6::{MeterChangeDto, MeterDto, TempoChangeDto},
+            v7::{self, FadeDto},
+            v8::color_from_dto,
+            v9::{PatternClipDto, PatternDto},
+            v10::{NoteClipDto, NoteDto},
+            v11::{self, SamplerDto},
+        },
+    },
+    ui::panels::{
+        media::{WorkspaceSampleAttributes, WorkspaceSelector},
+        mixer::{ChannelStrip, MixerState},
+        playlist::{PlaylistState, SampleInstance, TrackCustomization},
+    },
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+
+    /// The tracks in the order they are displayed.
+    pub tracks: Vec<TrackDto>,
+
+    /// The clips playing samples, their `track` is the id of the track.
+    pub clips: Vec<ClipDto>,
+    pub pattern_clips: Vec<PatternClipDto>,
+    pub note_clips: Vec<NoteClipDto>,
+    pub patterns: Vec<PatternDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClipDto {
+    pub id: u64,
+    pub track: u64,
+
+    /// The beat the clip starts on, clips placed between two beats are stored as a fraction.
+    pub start: f64,
+
+    /// How long the clip plays in seconds.
+    pub length: f64,
+
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+
+    /// The amount of seconds skipped from the start of the sample.
+    pub offset: f64,
+    pub gain: f32,
+    pub reverse: bool,
+    pub fade_in: FadeDto,
+    pub fade_out: FadeDto,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackDto {
+    pub id: u64,
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+    pub instrument: InstrumentDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub enum InstrumentDto {
+    #[default]
+    Sine,
+    Sampler(SamplerDto),
+    Synth(SynthDto),
+}
+
+/// The times of the envelopes are measured in seconds, the frequencies in Hz.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SynthDto {
+    pub oscillators: [OscillatorDto; 2],
+    pub filter: FilterDto,
+    pub amp_envelope: EnvelopeDto,
+    pub filter_envelope: EnvelopeDto,
+    pub lfo: LfoDto,
+    pub polyphony: usize,
+}
+
+/// The detune is measured in cents.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct OscillatorDto {
+    pub waveform: WaveformDto,
+    pub octave: i8,
+    pub detune: f32,
+    pub level: f32,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum WaveformDto {
+    Saw,
+    Square,
+    Sine,
+    Noise,
+}
+
+/// The envelope amount is measured in octaves.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct FilterDto {
+    pub mode: FilterModeDto,
+    pub cutoff: f32,
+    pub resonance: f32,
+    pub envelope_amount: f32,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum FilterModeDto {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct EnvelopeDto {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f32,
+    pub release: f64,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct LfoDto {
+    pub shape: LfoShapeDto,
+    pub rate: f32,
+    pub depth: f32,
+    pub target: LfoTargetDto,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum LfoShapeDto {
+    Sine,
+    Triangle,
+    Square,
+    Saw,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum LfoTargetDto {
+    Pitch,
+    Cutoff,
+    Volume,
+}
+
+impl From<&Track> for TrackDto {
+    fn from(track: &Track) -> Self {
+        let customization = &track.customization;
+
+        Self {
+            id: track.id.0,
+            label_text: customization.label_text.clone(),
+            label_text_color: customization.label_text_color.to_array(),
+            label_color: customization.label_color.to_array(),
+            height: customization.height,
+            instrument: (&track.instrument).into(),
+        }
+    }
+}
+
+impl From<TrackDto> for Track {
+    fn from(track: TrackDto) -> Self {
+        Self {
+            id: TrackId(track.id),
+            customization: TrackCustomization {
+                label_text: track.label_text,
+                label_text_color: color_from_dto(track.label_text_color),
+                label_color: color_from_dto(track.label_color),
+                height: track.height,
+            },
+            instrument: track.instrument.into(),
+        }
+    }
+}
+
+impl From<&Instrument> for InstrumentDto {
+    fn from(instrument: &Instrument) -> Self {
+        match instrument {
+            Instrument::Sine => InstrumentDto::Sine,
+            Instrument::Sampler(sampler) => InstrumentDto::Sampler(sampler.into()),
+            Instrument::Synth(synth) => InstrumentDto::Synth(synth.into()),
+        }
+    }
+}
+
+impl From<InstrumentDto> for Instrument {
+    fn from(instrument: InstrumentDto) -> Self {
+        match instrument {
+            InstrumentDto::Sine => Instrument::Sine,
+            InstrumentDto::Sampler(sampler) => Instrument::Sampler(sampler.into()),
+            InstrumentDto::Synth(synth) => Instrument::Synth(synth.into()),
+        }
+    }
+}
+
+impl From<&Synth> for SynthDto {
+    fn from(synth: &Synth) -> Self {
+        Self {
+            oscillators: synth.oscillators.map(|oscillator| OscillatorDto {
+                waveform: match oscillator.waveform {
+                    Waveform::Saw => WaveformDto::Saw,
+                    Waveform::Square => WaveformDto::Square,
+                    Waveform::Sine => WaveformDto::Sine,
+                    Waveform::Noise => WaveformDto::Noise,
+                },
+                octave: oscillator.octave,
+                detune: oscillator.detune,
+                level: oscillator.level,
+            }),
+            filter: FilterDto {
+                mode: match synth.filter.mode {
+                    FilterMode::LowPass => FilterModeDto::LowPass,
+                    FilterMode::HighPass => FilterModeDto::HighPass,
+                    FilterMode::BandPass => FilterModeDto::BandPass,
+                    FilterMode::Notch => FilterModeDto::Notch,
+                },
+                cutoff: synth.filter.cutoff,
+                resonance: synth.filter.resonance,
+                envelope_amount: synth.filter.envelope_amount,
+            },
+            amp_envelope: synth.amp_envelope.into(),
+            filter_envelope: synth.filter_envelope.into(),
+            lfo: LfoDto {
+                shape: match synth.lfo.shape {
+                    LfoShape::Sine => LfoShapeDto::Sine,
+                    LfoShape::Triangle => LfoShapeDto::Triangle,
+                    LfoShape::Square => LfoShapeDto::Square,
+                    LfoShape::Saw => LfoShapeDto::Saw,
+                },
+                rate: synth.lfo.rate,
+                depth: synth.lfo.depth,
+                target: match synth.lfo.target {
+                    LfoTarget::Pitch => LfoTargetDto::Pitch,
+                    LfoTarget::Cutoff => LfoTargetDto::Cutoff,
+                    LfoTarget::Volume => LfoTargetDto::Volume,
+                },
+            },
+            polyphony: synth.polyphony,
+        }
+    }
+}
+
+impl From<SynthDto> for Synth {
+    fn from(synth: SynthDto) -> Self {
+        Self {
+            oscillators: synth.oscillators.map(|oscillator| Oscillator {
+                waveform: match oscillator.waveform {
+                    WaveformDto::Saw => Waveform::Saw,
+                    WaveformDto::Square => Waveform::Square,
+                    WaveformDto::Sine => Waveform::Sine,
+                    WaveformDto::Noise => Waveform::Noise,
+                },
+                octave: oscillator.octave,
+                detune: oscillator.detune,
+                level: oscillator.level,
+            }),
+            filter: Filter {
+                mode: match synth.filter.mode {
+                    FilterModeDto::LowPass => FilterMode::LowPass,
+                    FilterModeDto::HighPass => FilterMode::HighPass,
+                    FilterModeDto::BandPass => FilterMode::BandPass,
+                    FilterModeDto::Notch => FilterMode::Notch,
+                },
+                cutoff: synth.filter.cutoff,
+                resonance: synth.filter.resonance,
+                envelope_amount: synth.filter.envelope_amount,
+            },
+            amp_envelope: synth.amp_envelope.into(),
+            filter_envelope: synth.filter_envelope.into(),
+            lfo: Lfo {
+                shape: match synth.lfo.shape {
+                    LfoShapeDto::Sine => LfoShape::Sine,
+                    LfoShapeDto::Triangle => LfoShape::Triangle,
+                    LfoShapeDto::Square => LfoShape::Square,
+                    LfoShapeDto::Saw => LfoShape::Saw,
+                },
+                rate: synth.lfo.rate,
+                depth: synth.lfo.depth,
+                target: match synth.lfo.target {
+                    LfoTargetDto::Pitch => LfoTarget::Pitch,
+                    LfoTargetDto::Cutoff => LfoTarget::Cutoff,
+                    LfoTargetDto::Volume => LfoTarget::Volume,
+                },
+            },
+            polyphony: synth.polyphony,
+        }
+    }
+}
+
+impl From<Envelope> for EnvelopeDto {
+    fn from(envelope: Envelope) -> Self {
+        Self {
+            attack: envelope.attack,
+            decay: envelope.decay,
+            sustain: envelope.sustain,
+            release: envelope.release,
+        }
+    }
+}
+
+impl From<EnvelopeDto> for Envelope {
+    fn from(envelope: EnvelopeDto) -> Self {
+        Self {
+            attack: envelope.attack,
+            decay: envelope.decay,
+            sustain: envelope.sustain,
+            release: envelope.release,
+        }
+    }
+}
+
+fn channel_to_dto(track: TrackId, channel: &ChannelStrip) -> ChannelStripDto {
+    ChannelStripDto {
+        track: track.0 as usize,
+        volume: channel.volume,
+        pan: channel.pan,
+        mute: channel.mute,
+        solo: channel.solo,
+    }
+}
+
+fn channel_from_dto(channel: &ChannelStripDto) -> ChannelStrip {
+    ChannelStrip {
+        volume: channel.volume,
+        pan: channel.pan,
+        mute: channel.mute,
+        solo: channel.solo,
+    }
+}
+
+impl From<v11::ProjectDto> for ProjectDto {
+    fn from(project: v11::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // There were no synths yet, the instruments of the tracks are kept as they are
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks.into_iter().map(TrackDto::from).collect(),
+                clips: playlist.clips.into_iter().map(ClipDto::from).collect(),
+                pattern_clips: playlist.pattern_clips,
+                note_clips: playlist.note_clips,
+                patterns: playlist.patterns,
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
+
+impl From<v7::ClipDto> for ClipDto {
+    fn from(clip: v7::ClipDto) -> Self {
+        Self {
+            id: clip.id,
+            track: clip.track as u64,
+            start: clip.start,
+            length: clip.length,
+            name: clip.name,
+            color: clip.color,
+            path: clip.path,
+            sample_rate: clip.sample_rate,
+            length_ms: clip.length_ms,
+            offset: clip.offset,
+            gain: clip.gain,
+            reverse: clip.reverse,
+            fade_in: clip.fade_in,
+            fade_out: clip.fade_out,
+        }
+    }
+}
+
+impl From<v11::TrackDto> for TrackDto {
+    fn from(track: v11::TrackDto) -> Self {
+        Self {
+            id: track.id,
+            label_text: track.label_text,
+            label_text_color: track.label_text_color,
+            label_color: track.label_color,
+            height: track.height,
+            instrument: match track.instrument {
+                v11::InstrumentDto::Sine => InstrumentDto::Sine,
+                v11::InstrumentDto::Sampler(sampler) => InstrumentDto::Sampler(sampler),
+            },
+        }
+    }
+}
+
+impl ProjectDto {
+    /// Rewrites every path referencing a file in the project.
+    pub fn map_paths(&mut self, mut map: impl FnMut(&Path) -> PathBuf) {
+        for clip in self.playlist.clips.iter_mut() {
+            clip.path = map(&clip.path);
+        }
+
+        for row in self
+            .playlist
+            .patterns
+            .iter_mut()
+            .flat_map(|pattern| pattern.rows.iter_mut())
+        {
+            row.path = map(&row.path);
+        }
+
+        for sample in
+            self.playlist
+                .tracks
+                .iter_mut()
+                .filter_map(|track| match &mut track.instrument {
+                    InstrumentDto::Sampler(sampler) => sampler.sample.as_mut(),
+                    _ => None,
+                })
+        {
+            sample.path = map(&sample.path);
+        }
+
+        for sample in self.workspace.iter_mut() {
+            sample.path = map(&sample.path);
+        }
+
+        for media in self.media.iter_mut() {
+            media.path = map(&media.path);
+        }
+    }
+
+    /// Turns the paths (which may be relative to `base`) back into absolute paths.
+    /// If a file cannot be found relative to `base` the absolute path it was saved with is tried.
+    pub fn resolve_paths(&mut self, base: &Path) {
+        let absolute_paths: HashMap<PathBuf, PathBuf> = self
+            .media
+            .iter()
+            .map(|media| (media.path.clone(), media.absolute.clone()))
+            .collect();
+
+        self.map_paths(|path| {
+            let joined = normalize_path(&base.join(path));
+
+            match absolute_paths.get(path) {
+                Some(absolute) if !joined.exists() && absolute.exists() => absolute.clone(),
+                _ => joined,
+            }
+        });
+    }
+}
+
+impl From<&Project> for ProjectDto {
+    fn from(project: &Project) -> Self {
+        let playlist = &project.playlist;
+
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.tempo.bpm,
+                grid_offset: [playlist.grid_offset.x, playlist.grid_offset.y],
+                tracks: playlist.tracks.iter().map(TrackDto::from).collect(),
+                clips: playlist
+                    .clips
+                    .iter()
+                    .filter_map(|clip| {
+                        let sample = clip.source.sample()?;
+
+                        Some(ClipDto {
+                            id: clip.id.0,
+                            track: clip.track.0,
+                            start: clip.start.as_beats(),
+                            length: clip.length,
+                            name: sample.name.clone(),
+                            color: sample.color.to_array(),
+                            path: sample.path.clone(),
+                            sample_rate: sample.properties.sample_rate,
+                            length_ms: sample.properties.length as i64,
+                            offset: clip.shape.offset,
+                            gain: clip.shape.gain,
+                            reverse: clip.shape.reverse,
+                            fade_in: clip.shape.fade_in.into(),
+                            fade_out: clip.shape.fade_out.into(),
+                        })
+                    })
+                    .collect(),
+                pattern_clips: playlist
+                    .clips
+                    .iter()
+                    .filter_map(|clip| {
+                        Some(PatternClipDto {
+                            id: clip.id.0,
+                            track: clip.track.0,
+                            start: clip.start.as_beats(),
+                            length: clip.length,
+                            pattern: clip.source.pattern()?.0,
+                            offset: clip.shape.offset,
+                            gain: clip.shape.gain,
+                        })
+                    })
+                    .collect(),
+                note_clips: playlist
+                    .clips
+                    .iter()
+                    .filter_map(|clip| {
+                        let notes = clip.source.notes()?;
+
+                        Some(NoteClipDto {
+                            id: clip.id.0,
+                            track: clip.track.0,
+                            start: clip.start.as_beats(),
+                            length: clip.length,
+                            offset: clip.shape.offset,
+                            gain: clip.shape.gain,
+                            name: notes.name.clone(),
+                            color: notes.color.to_array(),
+                            notes: notes.notes.iter().map(NoteDto::from).collect(),
+                        })
+                    })
+                    .collect(),
+                patterns: playlist.patterns.iter().map(PatternDto::from).collect(),
+                tempo_changes: playlist
+                    .tempo
+                    .tempo_changes()
+                    .iter()
+                    .map(|change| TempoChangeDto {
+                        position: change.position.as_beats(),
+                        bpm: change.bpm,
+                        ramp: change.ramp,
+                    })
+                    .collect(),
+                meter: playlist.tempo.meter.into(),
+                meter_changes: playlist
+                    .tempo
+                    .meter_changes()
+                    .iter()
+                    .map(|change| MeterChangeDto {
+                        bar: change.bar,
+                        meter: change.meter.into(),
+                    })
+                    .collect(),
+            },
+            workspace: project
+                .workspace
+                .workspace_samples
+                .iter()
+                .map(|(path, sample)| WorkspaceSampleDto {
+                    path: path.clone(),
+                    alias: sample.alias.clone(),
+                    is_color_synced: sample.is_color_synced,
+                    color: sample.color.to_array(),
+                })
+                .collect(),
+            media: project
+                .referenced_media()
+                .into_iter()
+                .map(|path| MediaDto {
+                    fingerprint: project.media.get(&path).map(|fingerprint| FingerprintDto {
+                        size: fingerprint.size,
+                        hash: fingerprint.hash,
+                    }),
+                    absolute: path.clone(),
+                    path,
+                })
+                .collect(),
+            mixer: MixerDto {
+                master: channel_to_dto(TrackId::default(), &project.mixer.master),
+                channels: project
+                    .mixer
+                    .channels
+                    .iter()
+                    .map(|(track, channel)| channel_to_dto(*track, channel))
+                    .collect(),
+            },
+        }
+    }
+}
+
+impl From<ProjectDto> for Project {
+    fn from(project: ProjectDto) -> Self {
+        let ProjectDto {
+            playlist,
+            workspace,
+            media,
+            mixer,
+        } = project;
+
+        let mut tempo = TempoMap::new(playlist.bpm);
+
+        tempo.meter = playlist.meter.into();
+
+        for change in playlist.tempo_changes {
+            tempo.set_tempo_change(TempoChange {
+                position: Tick::from_beats(change.position),
+                bpm: change.bpm,
+                ramp: change.ramp,
+            });
+        }
+
+        for change in playlist.meter_changes {
+            tempo.set_meter_change(change.bar, change.meter.into());
+        }
+
+        let sample_clips = playlist.clips.into_iter().map(|clip| Clip {
+            id: ClipId(clip.id),
+            track: TrackId(clip.track),
+            start: Tick::from_beats(clip.start),
+            length: clip.length,
+            source: ClipSource::Sample(SampleInstance {
+                name: clip.name,
+                color: color_from_dto(clip.color),
+                path: clip.path,
+                properties: SampleProperties {
+                    sample_rate: clip.sample_rate,
+                    length: clip.length_ms as i128,
+                },
+                waveform_map: None,
+            }),
+            shape: ClipShape {
+                offset: clip.offset,
+                gain: clip.gain,
+                reverse: clip.reverse,
+                fade_in: clip.fade_in.into(),
+                fade_out: clip.fade_out.into(),
+            },
+        });
+
+        let pattern_clips = playlist.pattern_clips.into_iter().map(|clip| Clip {
+            id: ClipId(clip.id),
+            track: TrackId(clip.track),
+            start: Tick::from_beats(clip.start),
+            length: clip.length,
+            source: ClipSource::Pattern(PatternId(clip.pattern)),
+            shape: ClipShape {
+                offset: clip.offset,
+                gain: clip.gain,
+                ..Default::default()
+            },
+        });
+
+        let note_clips = playlist.note_clips.into_iter().map(|clip| Clip {
+            id: ClipId(clip.id),
+            track: TrackId(clip.track),
+            start: Tick::from_beats(clip.start),
+            length: clip.length,
+            source: ClipSource::Notes(NoteSequence {
+                name: clip.name,
+                color: color_from_dto(clip.color),
+                notes: clip.notes.into_iter().map(Note::from).collect(),
+            }),
+            shape: ClipShape {
+                offset: clip.offset,
+                gain: clip.gain,
+                ..Default::default()
+            },
+        });
+
+        Self {
+            playlist: PlaylistState {
+                tempo,
+                grid_offset: vec2(playlist.grid_offset[0], playlist.grid_offset[1]),
+                tracks: playlist
+                    .tracks
+                    .into_iter()
+                    .map(Track::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+                clips: sample_clips
+                    .chain(pattern_clips)
+                    .chain(note_clips)
+                    .collect::<Vec<_>>()
+                    .into(),
+                patterns: playlist
+                    .patterns
+                    .into_iter()
+                    .map(Pattern::from)
+                    .collect::<Vec<_>>()
+                    .into(),
+                ..Default::default()
+            },
+            workspace: WorkspaceSelector {
+                workspace_samples: workspace
+                    .into_iter()
+                    .map(|sample| {
+                        (
+                            sample.path,
+                            WorkspaceSampleAttributes {
+                                alias: sample.alias,
+                                is_color_synced: sample.is_color_synced,
+                                color: color_from_dto(sample.color),
+                                waveform_map: None,
+                            },
+                        )
+                    })
+                    .collect(),
+                selected_object: None,
+            },
+            media: media
+                .into_iter()
+                .filter_map(|media| {
+                    let fingerprint = media.fingerprint?;
+
+                    Some((
+                        media.path,
+                        MediaFingerprint {
+                            size: fingerprint.size,
+                            hash: fingerprint.hash,
+                        },
+                    ))
+                })
+                .collect(),
+            mixer: MixerState {
+                master: channel_from_dto(&mixer.master),
+                channels: mixer
+                    .channels
+                    .iter()
+                    .map(|channel| (TrackId(channel.track as u64), channel_from_dto(channel)))
+                    .collect(),
+                ..Default::default()
+            },
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use crate::project_manager::schema::v1;
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+    pub tracks: Vec<TrackCustomizationDto>,
+    pub samples: Vec<SampleInstanceDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackCustomizationDto {
+    pub index: usize,
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+    pub height_set: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SampleInstanceDto {
+    pub track: usize,
+    pub beat: usize,
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSampleDto {
+    pub path: PathBuf,
+    pub alias: String,
+    pub is_color_synced: bool,
+    pub color: [u8; 4],
+}
+
+impl From<v1::ProjectDto> for ProjectDto {
+    fn from(project: v1::ProjectDto) -> Self {
+        let v1::ProjectDto {
+            playlist,
+            workspace,
+        } = project;
+
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: [playlist.grid_offset.x, playlist.grid_offset.y],
+                tracks: playlist
+                    .custom_tracks
+                    .into_iter()
+                    .map(|(index, track)| TrackCustomizationDto {
+                        index,
+                        label_text: track.label_text,
+                        label_text_color: track.label_text_color,
+                        label_color: track.label_color,
+                        height: track.height,
+                        height_set: track.height_set,
+                    })
+                    .collect(),
+                samples: playlist
+                    .samples
+                    .into_iter()
+                    .map(|(position, sample)| SampleInstanceDto {
+                        track: position.track,
+                        beat: position.beat,
+                        name: sample.name,
+                        color: sample.color,
+                        path: sample.path,
+                        sample_rate: sample.properties.sample_rate,
+                        length_ms: sample.properties.length as i64,
+                    })
+                    .collect(),
+            },
+            // Waveform maps are dropped, they are regenerated when the project is loaded
+            workspace: workspace
+                .workspace_samples
+                .into_iter()
+                .map(|(path, sample)| WorkspaceSampleDto {
+                    path,
+                    alias: sample.alias,
+                    is_color_synced: sample.is_color_synced,
+                    color: sample.color,
+                })
+                .collect(),
+        }
+    }
+}
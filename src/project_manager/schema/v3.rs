@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use crate::project_manager::schema::v2::{self, PlaylistDto, WorkspaceSampleDto};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MediaDto {
+    /// The path used to reference this file in the rest of the project, this is relative to the project file if possible.
+    pub path: PathBuf,
+
+    /// The absolute path of the file when the project was saved.
+    pub absolute: PathBuf,
+
+    pub fingerprint: Option<FingerprintDto>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct FingerprintDto {
+    pub size: u64,
+    pub hash: u64,
+}
+
+impl From<v2::ProjectDto> for ProjectDto {
+    fn from(project: v2::ProjectDto) -> Self {
+        // Paths were always absolute before this version, so there is nothing to resolve
+        Self {
+            playlist: project.playlist,
+            workspace: project.workspace,
+            media: vec![],
+        }
+    }
+}
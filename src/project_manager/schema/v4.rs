@@ -0,0 +1,62 @@
+use crate::{
+    project_manager::schema::{
+        v2::{PlaylistDto, WorkspaceSampleDto},
+        v3::{self, MediaDto},
+    },
+    ui::panels::mixer::ChannelStrip,
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MixerDto {
+    pub master: ChannelStripDto,
+
+    /// Only the tracks which differ from the default strip are stored.
+    pub channels: Vec<ChannelStripDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChannelStripDto {
+    /// The index of the track, this is unused for the master strip.
+    pub track: usize,
+    pub volume: f32,
+    pub pan: f32,
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl Default for ChannelStripDto {
+    fn default() -> Self {
+        let channel = ChannelStrip::default();
+
+        Self {
+            track: 0,
+            volume: channel.volume,
+            pan: channel.pan,
+            mute: channel.mute,
+            solo: channel.solo,
+        }
+    }
+}
+
+impl From<v3::ProjectDto> for ProjectDto {
+    fn from(project: v3::ProjectDto) -> Self {
+        // Projects saved before the mixer existed play every track untouched
+        Self {
+            playlist: project.playlist,
+            workspace: project.workspace,
+            media: project.media,
+            mixer: MixerDto::default(),
+        }
+    }
+}
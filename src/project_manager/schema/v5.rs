@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use crate::project_manager::schema::{
+    v2::{TrackCustomizationDto, WorkspaceSampleDto},
+    v3::MediaDto,
+    v4::{self, MixerDto},
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+    pub tracks: Vec<TrackCustomizationDto>,
+    pub clips: Vec<ClipDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClipDto {
+    pub id: u64,
+    pub track: usize,
+
+    /// The beat the clip starts on, clips placed between two beats are stored as a fraction.
+    pub start: f64,
+
+    /// How long the clip plays in seconds.
+    pub length: f64,
+
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+}
+
+impl From<v4::ProjectDto> for ProjectDto {
+    fn from(project: v4::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // Samples used to be keyed by their position, every one of them becomes a clip playing the whole sample
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks,
+                clips: playlist
+                    .samples
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, sample)| ClipDto {
+                        id: id as u64,
+                        track: sample.track,
+                        start: sample.beat as f64,
+                        length: sample.length_ms as f64 / 1000.,
+                        name: sample.name,
+                        color: sample.color,
+                        path: sample.path,
+                        sample_rate: sample.sample_rate,
+                        length_ms: sample.length_ms,
+                    })
+                    .collect(),
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
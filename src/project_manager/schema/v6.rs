@@ -0,0 +1,101 @@
+use crate::{
+    internals::tempo::Meter,
+    project_manager::schema::{
+        v2::{TrackCustomizationDto, WorkspaceSampleDto},
+        v3::MediaDto,
+        v4::MixerDto,
+        v5::{self, ClipDto},
+    },
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+    pub tracks: Vec<TrackCustomizationDto>,
+    pub clips: Vec<ClipDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TempoChangeDto {
+    /// The beat the tempo changes on, the same way as the start of the clips.
+    pub position: f64,
+    pub bpm: f32,
+    pub ramp: bool,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct MeterDto {
+    pub numerator: u8,
+    pub denominator: u8,
+}
+
+impl Default for MeterDto {
+    fn default() -> Self {
+        Meter::default().into()
+    }
+}
+
+impl From<Meter> for MeterDto {
+    fn from(meter: Meter) -> Self {
+        Self {
+            numerator: meter.numerator,
+            denominator: meter.denominator,
+        }
+    }
+}
+
+impl From<MeterDto> for Meter {
+    fn from(meter: MeterDto) -> Self {
+        Self {
+            numerator: meter.numerator,
+            denominator: meter.denominator,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MeterChangeDto {
+    /// The bar the meter changes on, counted from 0.
+    pub bar: usize,
+    pub meter: MeterDto,
+}
+
+impl From<v5::ProjectDto> for ProjectDto {
+    fn from(project: v5::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // Projects saved before the tempo map existed have a steady tempo in 4/4
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks,
+                clips: playlist.clips,
+                tempo_changes: Vec::new(),
+                meter: MeterDto::default(),
+                meter_changes: Vec::new(),
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use crate::{
+    internals::clips::{Fade, FadeCurve},
+    project_manager::schema::{
+        v2::{TrackCustomizationDto, WorkspaceSampleDto},
+        v3::MediaDto,
+        v4::MixerDto,
+        v6::{self, MeterChangeDto, MeterDto, TempoChangeDto},
+    },
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+    pub tracks: Vec<TrackCustomizationDto>,
+    pub clips: Vec<ClipDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ClipDto {
+    pub id: u64,
+    pub track: usize,
+
+    /// The beat the clip starts on, clips placed between two beats are stored as a fraction.
+    pub start: f64,
+
+    /// How long the clip plays in seconds.
+    pub length: f64,
+
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+
+    /// The amount of seconds skipped from the start of the sample.
+    pub offset: f64,
+    pub gain: f32,
+    pub reverse: bool,
+    pub fade_in: FadeDto,
+    pub fade_out: FadeDto,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct FadeDto {
+    /// The length of the fade in seconds.
+    pub length: f64,
+    pub curve: FadeCurveDto,
+}
+
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub enum FadeCurveDto {
+    #[default]
+    Linear,
+    EqualPower,
+    Exponential,
+    Logarithmic,
+}
+
+impl From<Fade> for FadeDto {
+    fn from(fade: Fade) -> Self {
+        Self {
+            length: fade.length,
+            curve: match fade.curve {
+                FadeCurve::Linear => FadeCurveDto::Linear,
+                FadeCurve::EqualPower => FadeCurveDto::EqualPower,
+                FadeCurve::Exponential => FadeCurveDto::Exponential,
+                FadeCurve::Logarithmic => FadeCurveDto::Logarithmic,
+            },
+        }
+    }
+}
+
+impl From<FadeDto> for Fade {
+    fn from(fade: FadeDto) -> Self {
+        Self {
+            length: fade.length,
+            curve: match fade.curve {
+                FadeCurveDto::Linear => FadeCurve::Linear,
+                FadeCurveDto::EqualPower => FadeCurve::EqualPower,
+                FadeCurveDto::Exponential => FadeCurve::Exponential,
+                FadeCurveDto::Logarithmic => FadeCurve::Logarithmic,
+            },
+        }
+    }
+}
+
+impl From<v6::ProjectDto> for ProjectDto {
+    fn from(project: v6::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // Clips used to play their whole sample
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks,
+                clips: playlist
+                    .clips
+                    .into_iter()
+                    .map(|clip| ClipDto {
+                        id: clip.id,
+                        track: clip.track,
+                        start: clip.start,
+                        length: clip.length,
+                        name: clip.name,
+                        color: clip.color,
+                        path: clip.path,
+                        sample_rate: clip.sample_rate,
+                        length_ms: clip.length_ms,
+                        offset: 0.,
+                        gain: 1.,
+                        reverse: false,
+                        fade_in: FadeDto::default(),
+                        fade_out: FadeDto::default(),
+                    })
+                    .collect(),
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
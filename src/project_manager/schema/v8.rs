@@ -0,0 +1,135 @@
+use std::collections::{BTreeSet, HashMap};
+
+use egui::Color32;
+
+use crate::{
+    internals::{
+        instruments::Instrument,
+        tracks::{Track, TrackId},
+    },
+    project_manager::schema::{
+        v2::WorkspaceSampleDto,
+        v3::MediaDto,
+        v4::MixerDto,
+        v6::{MeterChangeDto, MeterDto, TempoChangeDto},
+        v7::{self, ClipDto},
+    },
+    ui::panels::playlist::TrackCustomization,
+};
+
+/// The `track` of the clips and the channel strips is the id of the track.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+
+    /// The tracks in the order they are displayed.
+    pub tracks: Vec<TrackDto>,
+    pub clips: Vec<ClipDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TrackDto {
+    pub id: u64,
+    pub label_text: String,
+    pub label_text_color: [u8; 4],
+    pub label_color: [u8; 4],
+    pub height: f32,
+}
+
+impl From<&Track> for TrackDto {
+    fn from(track: &Track) -> Self {
+        let customization = &track.customization;
+
+        Self {
+            id: track.id.0,
+            label_text: customization.label_text.clone(),
+            label_text_color: customization.label_text_color.to_array(),
+            label_color: customization.label_color.to_array(),
+            height: customization.height,
+        }
+    }
+}
+
+/// Colors are stored as premultiplied RGBA.
+pub fn color_from_dto([r, g, b, a]: [u8; 4]) -> Color32 {
+    Color32::from_rgba_premultiplied(r, g, b, a)
+}
+
+impl From<v7::ProjectDto> for ProjectDto {
+    fn from(project: v7::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // Every row used to be a track, the rows up to the last used one become tracks which are identified by their old index.
+        // This way the clips and the channel strips keep referring to the same tracks.
+        let used_rows: BTreeSet<usize> = playlist
+            .tracks
+            .iter()
+            .map(|track| track.index)
+            .chain(playlist.clips.iter().map(|clip| clip.track))
+            .chain(project.mixer.channels.iter().map(|channel| channel.track))
+            .collect();
+
+        let mut customizations: HashMap<usize, TrackDto> = playlist
+            .tracks
+            .into_iter()
+            .map(|track| {
+                (
+                    track.index,
+                    TrackDto {
+                        id: track.index as u64,
+                        label_text: track.label_text,
+                        label_text_color: track.label_text_color,
+                        label_color: track.label_color,
+                        height: track.height,
+                    },
+                )
+            })
+            .collect();
+
+        let tracks = used_rows.last().map_or(0, |last| last + 1);
+
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: (0..tracks)
+                    .map(|row| {
+                        customizations.remove(&row).unwrap_or_else(|| {
+                            (&Track {
+                                id: TrackId(row as u64),
+                                customization: TrackCustomization::named_default(row),
+                                instrument: Instrument::default(),
+                            })
+                                .into()
+                        })
+                    })
+                    .collect(),
+                clips: playlist.clips,
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+use crate::{
+    internals::{
+        patterns::{Pattern, PatternId, PatternRow, Step},
+        sample::SampleProperties,
+    },
+    project_manager::schema::{
+        v2::WorkspaceSampleDto,
+        v3::MediaDto,
+        v4::MixerDto,
+        v6::{MeterChangeDto, MeterDto, TempoChangeDto},
+        v7::ClipDto,
+        v8::{self, TrackDto, color_from_dto},
+    },
+    ui::panels::playlist::SampleInstance,
+};
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ProjectDto {
+    pub playlist: PlaylistDto,
+    pub workspace: Vec<WorkspaceSampleDto>,
+
+    /// Every file referenced by the project.
+    pub media: Vec<MediaDto>,
+
+    pub mixer: MixerDto,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistDto {
+    /// The tempo the song starts with.
+    pub bpm: f32,
+    pub grid_offset: [f32; 2],
+
+    /// The tracks in the order they are displayed.
+    pub tracks: Vec<TrackDto>,
+
+    /// The clips playing samples, their `track` is the id of the track.
+    pub clips: Vec<ClipDto>,
+    pub pattern_clips: Vec<PatternClipDto>,
+    pub patterns: Vec<PatternDto>,
+    pub tempo_changes: Vec<TempoChangeDto>,
+
+    /// The meter the song starts with.
+    pub meter: MeterDto,
+    pub meter_changes: Vec<MeterChangeDto>,
+}
+
+/// A clip playing a pattern, its position and its length are measured in beats.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PatternClipDto {
+    pub id: u64,
+    pub track: u64,
+    pub start: f64,
+    pub length: f64,
+    pub pattern: u64,
+
+    /// The amount of beats skipped from the start of the pattern.
+    pub offset: f64,
+    pub gain: f32,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PatternDto {
+    pub id: u64,
+    pub name: String,
+    pub color: [u8; 4],
+    pub step_count: usize,
+    pub rows: Vec<PatternRowDto>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PatternRowDto {
+    pub name: String,
+    pub color: [u8; 4],
+    pub path: PathBuf,
+    pub sample_rate: u32,
+    pub length_ms: i64,
+
+    /// `None` where the sample is not triggered.
+    pub steps: Vec<Option<StepDto>>,
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct StepDto {
+    pub velocity: f32,
+    pub muted: bool,
+}
+
+impl From<&Pattern> for PatternDto {
+    fn from(pattern: &Pattern) -> Self {
+        Self {
+            id: pattern.id.0,
+            name: pattern.name.clone(),
+            color: pattern.color.to_array(),
+            step_count: pattern.step_count(),
+            rows: pattern
+                .rows
+                .iter()
+                .map(|row| PatternRowDto {
+                    name: row.sample.name.clone(),
+                    color: row.sample.color.to_array(),
+                    path: row.sample.path.clone(),
+                    sample_rate: row.sample.properties.sample_rate,
+                    length_ms: row.sample.properties.length as i64,
+                    steps: row
+                        .steps
+                        .iter()
+                        .map(|step| {
+                            step.map(|step| StepDto {
+                                velocity: step.velocity,
+                                muted: step.muted,
+                            })
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<PatternDto> for Pattern {
+    fn from(pattern: PatternDto) -> Self {
+        let mut converted = Pattern::new(
+            PatternId(pattern.id),
+            pattern.name,
+            color_from_dto(pattern.color),
+        );
+
+        converted.rows = pattern
+            .rows
+            .into_iter()
+            .map(|row| PatternRow {
+                sample: SampleInstance {
+                    name: row.name,
+                    color: color_from_dto(row.color),
+                    path: row.path,
+                    properties: SampleProperties {
+                        sample_rate: row.sample_rate,
+                        length: row.length_ms as i128,
+                    },
+                    waveform_map: None,
+                },
+                steps: row
+                    .steps
+                    .into_iter()
+                    .map(|step| {
+                        step.map(|step| Step {
+                            velocity: step.velocity,
+                            muted: step.muted,
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        // Every row is resized to the step count, in case the file has been written incorrectly
+        converted.set_step_count(pattern.step_count);
+
+        converted
+    }
+}
+
+impl From<v8::ProjectDto> for ProjectDto {
+    fn from(project: v8::ProjectDto) -> Self {
+        let playlist = project.playlist;
+
+        // There were no patterns yet
+        Self {
+            playlist: PlaylistDto {
+                bpm: playlist.bpm,
+                grid_offset: playlist.grid_offset,
+                tracks: playlist.tracks,
+                clips: playlist.clips,
+                pattern_clips: Vec::new(),
+                patterns: Vec::new(),
+                tempo_changes: playlist.tempo_changes,
+                meter: playlist.meter,
+                meter_changes: playlist.meter_changes,
+            },
+            workspace: project.workspace,
+            media: project.media,
+            mixer: project.mixer,
+        }
+    }
+}
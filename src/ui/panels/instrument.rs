@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use egui::{DragValue, Frame, Id, RichText, Ui};
+use egui_toast::ToastStyle;
+use strum::IntoEnumIterator;
+
+use crate::{
+    internals::{
+        instruments::{CHOKE_GROUPS, Envelope, Instrument, LoopPoints, Sampler, SamplerMode},
+        notes::{KEY_COUNT, key_name},
+        synth::{
+            FilterMode, LfoShape, LfoTarget, MAX_CUTOFF, MAX_ENVELOPE_AMOUNT, MIN_CUTOFF, Synth,
+            Waveform,
+        },
+        tracks::TrackId,
+    },
+    project_manager::{
+        history::Edit,
+        presets::{PRESET_EXTENSION, open_preset, save_preset},
+    },
+    ui::panels::{
+        lib::{Panel, PanelStates, display_error_as_toast},
+        playlist::SampleInstance,
+    },
+};
+
+/// The longest the stages of an envelope can be dragged to, in seconds.
+const MAX_ENVELOPE_SECONDS: f64 = 10.;
+
+/// The most notes a sampler or a synth can be set to play at once.
+const MAX_POLYPHONY: usize = 64;
+
+/// How far the oscillators of a synth can be moved, in octaves and in cents.
+const MAX_OCTAVES: i8 = 3;
+const MAX_DETUNE: f32 = 100.;
+
+/// The fastest the LFO of a synth can be set to, in Hz.
+const MAX_LFO_RATE: f32 = 20.;
+
+/// The state of the instrument panel, the instruments themselves are stored in their tracks.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InstrumentPanelState {
+    /// The track whose instrument is being edited.
+    pub track: Option<TrackId>,
+}
+
+/// Loads the sample into the sampler of the track, then opens the track in the instrument panel.
+/// The track starts playing its notes with a sampler if it has not been doing so, the rest of the settings of an existing sampler are kept.
+pub fn load_sample(global_state: &PanelStates, track: TrackId, sample: &SampleInstance) {
+    let Some(before) = global_state
+        .playlist_panel
+        .read()
+        .tracks
+        .find(track)
+        .map(|track| track.instrument.clone())
+    else {
+        return;
+    };
+
+    let mut sampler = match &before {
+        Instrument::Sampler(sampler) => sampler.clone(),
+        _ => Sampler::default(),
+    };
+
+    // The waveform is not displayed by the sampler, leaving it out keeps the instrument cheap to clone for the engine
+    sampler.sample = Some(SampleInstance {
+        waveform_map: None,
+        ..sample.clone()
+    });
+
+    // The loop of the previous sample does not fit the new one
+    sampler.loop_points = None;
+
+    let edit = Edit::ChangeInstrument {
+        id: track,
+        before,
+        after: Instrument::Sampler(sampler),
+    };
+
+    edit.apply(global_state);
+    global_state
+        .history
+        .write()
+        .record(format!("Load {} into sampler", sample.name), edit);
+    global_state.instrument_panel.write().track = Some(track);
+}
+
+/// Picks and edits the instrument of a track, samples dropped onto the panel are loaded into a sampler.
+/// Every change made until the pointer is released is a single entry in the history.
+pub fn instrument_ui(this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
+    let track = global_state
+        .instrument_panel
+        .read()
+        .track
+        .and_then(|id| global_state.playlist_panel.read().tracks.find(id).cloned());
+
+    let Some(track) = track else {
+        ui.label(
+            RichText::from(
+                "Right click on the label of a track and pick \"Edit instrument\", or drop a sample onto the label of a track to play its notes with a sampler.",
+            )
+            .weak(),
+        );
+
+        return;
+    };
+
+    let before = track.instrument.clone();
+    let mut instrument = before.clone();
+
+    // The name of the preset loaded into the synth, loading a preset is an entry of its own in the history
+    let mut loaded_preset = None;
+
+    let (_, dropped) = ui.dnd_drop_zone::<SampleInstance, ()>(Frame::default(), |ui| {
+        ui.set_min_width(ui.available_width());
+
+        ui.horizontal(|ui| {
+            ui.label(RichText::from(&track.customization.label_text).strong());
+
+            egui::ComboBox::from_id_salt("instrument_kind")
+                .selected_text(instrument.to_string())
+                .show_ui(ui, |ui| {
+                    for kind in Instrument::iter() {
+                        let selected =
+                            std::mem::discriminant(&kind) == std::mem::discriminant(&instrument);
+
+                        // Picking the instrument which is already played keeps its settings
+                        if ui.selectable_label(selected, kind.to_string()).clicked() && !selected {
+                            instrument = kind;
+                        }
+                    }
+                });
+
+            if let Instrument::Synth(synth) = &mut instrument {
+                if ui.button("Load preset...").clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Beatroot Synth Preset", &[PRESET_EXTENSION])
+                        .pick_file()
+                    && let Some(preset) = display_error_as_toast(
+                        open_preset(&path),
+                        ToastStyle::default(),
+                        this.toasts.clone(),
+                    )
+                {
+                    *synth = preset;
+                    loaded_preset = path
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().to_string());
+                }
+
+                if ui.button("Save preset...").clicked()
+                    && let Some(path) = rfd::FileDialog::new()
+                        .add_filter("Beatroot Synth Preset", &[PRESET_EXTENSION])
+                        .save_file()
+                {
+                    display_error_as_toast(
+                        save_preset(&path.with_extension(PRESET_EXTENSION), synth),
+                        ToastStyle::default(),
+                        this.toasts.clone(),
+                    );
+                }
+            }
+        });
+
+        ui.separator();
+
+        match &mut instrument {
+            Instrument::Sine => {
+                ui.label(
+                    RichText::from(
+                        "Plays the notes with a plain sine wave. Drop a sample here to play them with a sampler instead.",
+                    )
+                    .weak(),
+                );
+            }
+            Instrument::Sampler(sampler) => sampler_ui(ui, sampler),
+            Instrument::Synth(synth) => synth_ui(ui, synth),
+        }
+    });
+
+    let history_group = Id::new(("instrument", track.id));
+
+    if instrument != before {
+        if let Some(track) = global_state
+            .playlist_panel
+            .write()
+            .tracks
+            .find_mut(track.id)
+        {
+            track.instrument = instrument.clone();
+        }
+
+        let edit = Edit::ChangeInstrument {
+            id: track.id,
+            before,
+            after: instrument,
+        };
+
+        match loaded_preset {
+            Some(name) => global_state
+                .history
+                .write()
+                .record(format!("Load preset {name}"), edit),
+            None => global_state.history.write().record_grouped(
+                history_group,
+                "Change instrument",
+                edit,
+            ),
+        }
+    }
+
+    if !ui.input(|input| input.pointer.any_down()) {
+        global_state.history.write().close_group(history_group);
+    }
+
+    if let Some(sample) = dropped {
+        load_sample(&global_state, track.id, &sample);
+    }
+}
+
+/// The settings of a sampler, the times are edited in seconds.
+fn sampler_ui(ui: &mut Ui, sampler: &mut Sampler) {
+    let sample_seconds = sampler
+        .sample
+        .as_ref()
+        .map_or(0., |sample| sample.properties.length().as_secs_f64());
+
+    egui::Grid::new("sampler_settings")
+        .num_columns(2)
+        .spacing([12., 6.])
+        .show(ui, |ui| {
+            ui.label("Sample");
+            match &sampler.sample {
+                Some(sample) => ui.label(format!("{} ({sample_seconds:.2} s)", sample.name)),
+                None => ui.label(RichText::from("Drop a sample here").weak()),
+            };
+            ui.end_row();
+
+            ui.label("Root key")
+                .on_hover_text("The key which plays the sample at its own pitch.");
+            ui.add(
+                DragValue::new(&mut sampler.root)
+                    .range(0..=KEY_COUNT - 1)
+                    .custom_formatter(|key, _| key_name(key as u8)),
+            );
+            ui.end_row();
+
+            ui.label("Mode");
+            ui.horizontal(|ui| {
+                for mode in SamplerMode::iter() {
+                    ui.radio_value(&mut sampler.mode, mode, mode.to_string());
+                }
+            });
+            ui.end_row();
+
+            ui.label("Envelope");
+            envelope_ui(ui, &mut sampler.envelope);
+            ui.end_row();
+
+            // Looping only matters while the key is held
+            ui.label("Loop");
+            ui.add_enabled_ui(sampler.mode == SamplerMode::Gate, |ui| {
+                ui.horizontal(|ui| {
+                    let mut looped = sampler.loop_points.is_some();
+
+                    if ui.checkbox(&mut looped, "").changed() {
+                        sampler.loop_points = looped.then_some(LoopPoints {
+                            start: 0.,
+                            end: sample_seconds,
+                        });
+                    }
+
+                    if let Some(loop_points) = &mut sampler.loop_points {
+                        ui.add(seconds_value(&mut loop_points.start, loop_points.end));
+                        ui.label("to");
+                        ui.add(seconds_value(&mut loop_points.end, sample_seconds));
+
+                        loop_points.start = loop_points.start.min(loop_points.end);
+                    }
+                });
+            });
+            ui.end_row();
+
+            ui.label("Polyphony")
+                .on_hover_text("The amount of notes played at once, the oldest notes are stopped to make room for new ones.");
+            ui.add(DragValue::new(&mut sampler.polyphony).range(1..=MAX_POLYPHONY));
+            ui.end_row();
+
+            ui.label("Choke group")
+                .on_hover_text("Notes stop the notes of the samplers of the other tracks in the same group, e.g. a closed hi-hat stops an open one.");
+            egui::ComboBox::from_id_salt("sampler_choke_group")
+                .selected_text(
+                    sampler
+                        .choke_group
+                        .map_or_else(|| String::from("None"), |group| group.to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut sampler.choke_group, None, "None");
+
+                    for group in 1..=CHOKE_GROUPS {
+                        ui.selectable_value(
+                            &mut sampler.choke_group,
+                            Some(group),
+                            group.to_string(),
+                        );
+                    }
+                });
+            ui.end_row();
+        });
+}
+
+/// The settings of a synth, every oscillator and the filter are a row of their own.
+fn synth_ui(ui: &mut Ui, synth: &mut Synth) {
+    egui::Grid::new("synth_settings")
+        .num_columns(2)
+        .spacing([12., 6.])
+        .show(ui, |ui| {
+            for (index, oscillator) in synth.oscillators.iter_mut().enumerate() {
+                ui.label(format!("Oscillator {}", index + 1));
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt(("synth_waveform", index))
+                        .selected_text(oscillator.waveform.to_string())
+                        .show_ui(ui, |ui| {
+                            for waveform in Waveform::iter() {
+                                ui.selectable_value(
+                                    &mut oscillator.waveform,
+                                    waveform,
+                                    waveform.to_string(),
+                                );
+                            }
+                        });
+
+                    ui.label("Octave");
+                    ui.add(DragValue::new(&mut oscillator.octave).range(-MAX_OCTAVES..=MAX_OCTAVES));
+
+                    ui.label("Detune")
+                        .on_hover_text("Slightly detuned oscillators sound fuller together.");
+                    ui.add(
+                        DragValue::new(&mut oscillator.detune)
+                            .range(-MAX_DETUNE..=MAX_DETUNE)
+                            .speed(0.1)
+                            .fixed_decimals(1)
+                            .suffix(" ct"),
+                    );
+
+                    ui.label("Level");
+                    ui.add(level_value(&mut oscillator.level));
+                });
+                ui.end_row();
+            }
+
+            let filter = &mut synth.filter;
+
+            ui.label("Filter");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("synth_filter_mode")
+                    .selected_text(filter.mode.to_string())
+                    .show_ui(ui, |ui| {
+                        for mode in FilterMode::iter() {
+                            ui.selectable_value(&mut filter.mode, mode, mode.to_string());
+                        }
+                    });
+
+                // The cutoff is dragged faster the higher it is, since the octaves get wider
+                let speed = filter.cutoff * 0.01;
+
+                ui.label("Cutoff");
+                ui.add(
+                    DragValue::new(&mut filter.cutoff)
+                        .range(MIN_CUTOFF..=MAX_CUTOFF)
+                        .speed(speed)
+                        .fixed_decimals(0)
+                        .suffix(" Hz"),
+                );
+
+                ui.label("Resonance");
+                ui.add(level_value(&mut filter.resonance));
+
+                ui.label("Envelope")
+                    .on_hover_text("How far the filter envelope moves the cutoff at its full level.");
+                ui.add(
+                    DragValue::new(&mut filter.envelope_amount)
+                        .range(-MAX_ENVELOPE_AMOUNT..=MAX_ENVELOPE_AMOUNT)
+                        .speed(0.01)
+                        .fixed_decimals(2)
+                        .suffix(" oct"),
+                );
+            });
+            ui.end_row();
+
+            ui.label("Filter envelope");
+            envelope_ui(ui, &mut synth.filter_envelope);
+            ui.end_row();
+
+            ui.label("Amp envelope");
+            envelope_ui(ui, &mut synth.amp_envelope);
+            ui.end_row();
+
+            let lfo = &mut synth.lfo;
+
+            ui.label("LFO");
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("synth_lfo_shape")
+                    .selected_text(lfo.shape.to_string())
+                    .show_ui(ui, |ui| {
+                        for shape in LfoShape::iter() {
+                            ui.selectable_value(&mut lfo.shape, shape, shape.to_string());
+                        }
+                    });
+
+                ui.label("Rate");
+                ui.add(
+                    DragValue::new(&mut lfo.rate)
+                        .range(0.01..=MAX_LFO_RATE)
+                        .speed(0.01)
+                        .fixed_decimals(2)
+                        .suffix(" Hz"),
+                );
+
+                ui.label("Depth");
+                ui.add(level_value(&mut lfo.depth));
+
+                egui::ComboBox::from_id_salt("synth_lfo_target")
+                    .selected_text(lfo.target.to_string())
+                    .show_ui(ui, |ui| {
+                        for target in LfoTarget::iter() {
+                            ui.selectable_value(&mut lfo.target, target, target.to_string());
+                        }
+                    });
+            });
+            ui.end_row();
+
+            ui.label("Polyphony")
+                .on_hover_text("The amount of notes played at once, the oldest notes are stopped to make room for new ones.");
+            ui.add(DragValue::new(&mut synth.polyphony).range(1..=MAX_POLYPHONY));
+            ui.end_row();
+        });
+}
+
+/// The attack, the decay, the sustain and the release of an envelope in a single row.
+fn envelope_ui(ui: &mut Ui, envelope: &mut Envelope) {
+    ui.horizontal(|ui| {
+        for (name, seconds) in [("A", &mut envelope.attack), ("D", &mut envelope.decay)] {
+            ui.label(name);
+            ui.add(seconds_value(seconds, MAX_ENVELOPE_SECONDS));
+        }
+
+        ui.label("S");
+        ui.add(level_value(&mut envelope.sustain));
+
+        ui.label("R");
+        ui.add(seconds_value(&mut envelope.release, MAX_ENVELOPE_SECONDS));
+    });
+}
+
+/// Drags a level between zero and one.
+fn level_value(level: &mut f32) -> DragValue<'_> {
+    DragValue::new(level)
+        .range(0.0..=1.0)
+        .speed(0.01)
+        .fixed_decimals(2)
+}
+
+/// Drags a time in seconds between zero and `max`.
+fn seconds_value(seconds: &mut f64, max: f64) -> DragValue<'_> {
+    DragValue::new(seconds)
+        .range(0.0..=max.max(0.))
+        .speed(0.001)
+        .fixed_decimals(3)
+        .suffix(" s")
+}
@@ -1,4 +1,6 @@
 use std::{
+    collections::HashMap,
+    path::PathBuf,
     sync::{Arc, atomic::AtomicBool},
     time::Duration,
 };
@@ -9,9 +11,13 @@ use parking_lot::{Mutex, RwLock};
 use strum::IntoDiscriminant;
 
 use crate::{
-    internals::utils::random_value,
+    internals::{fs::MediaFingerprint, playback::PlaybackEngine, utils::random_value},
+    project_manager::history::History,
     ui::panels::{
+        instrument::{InstrumentPanelState, instrument_ui},
         media::{MediaPanel, mediapicker_ui},
+        mixer::{MixerState, mixer_ui},
+        piano_roll::{PianoRollState, piano_roll_ui},
         playlist::{PlaylistState, playlist_ui},
     },
 };
@@ -20,6 +26,24 @@ use crate::{
 pub struct PanelStates {
     pub media_panel: RwLock<MediaPanel>,
     pub playlist_panel: RwLock<PlaylistState>,
+    pub mixer_panel: RwLock<MixerState>,
+    #[serde(default)]
+    pub piano_roll_panel: RwLock<PianoRollState>,
+    #[serde(default)]
+    pub instrument_panel: RwLock<InstrumentPanelState>,
+
+    /// The fingerprints of the files referenced by the opened project, as they were when it was last saved.
+    /// These are kept for the files which are missing, since they cannot be fingerprinted again.
+    #[serde(default)]
+    pub media_fingerprints: RwLock<HashMap<PathBuf, MediaFingerprint>>,
+
+    /// The edits made to the panel states which can be undone.
+    #[serde(skip)]
+    pub history: RwLock<History>,
+
+    /// The audio engine playing the playlist.
+    #[serde(skip)]
+    pub playback: PlaybackEngine,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, strum::EnumDiscriminants)]
@@ -36,7 +60,17 @@ pub enum PanelId {
     /// This is where we assemble the music from the clips
     Playlist,
 
+    /// Mixer
+    /// The volume, the pan, and the meters of every track
     Mixer,
+
+    /// Piano roll
+    /// The notes of the opened note clip
+    PianoRoll,
+
+    /// Instrument
+    /// The instrument playing the notes of a track
+    Instrument,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy)]
@@ -88,8 +122,14 @@ impl Panel {
             PanelId::Playlist => {
                 display_panel(self, ui, global_state.clone(), "Playlist", playlist_ui)
             }
+            PanelId::Mixer => display_panel(self, ui, global_state.clone(), "Mixer", mixer_ui),
+            PanelId::PianoRoll => {
+                display_panel(self, ui, global_state.clone(), "Piano Roll", piano_roll_ui)
+            }
+            PanelId::Instrument => {
+                display_panel(self, ui, global_state.clone(), "Instrument", instrument_ui)
+            }
             PanelId::Root => todo!(),
-            PanelId::Mixer => todo!(),
         };
     }
 }
@@ -148,6 +188,117 @@ pub fn create_panels() -> Vec<Panel> {
             },
             PanelType::Left,
         ),
+        // Mixer
+        Panel::new(
+            PanelId::Mixer,
+            ViewportBuilder {
+                title: Some(String::from("Mixer")),
+                app_id: None,
+                position: None,
+                inner_size: None,
+                min_inner_size: None,
+                max_inner_size: None,
+                clamp_size_to_monitor_size: None,
+                fullscreen: None,
+                maximized: None,
+                resizable: Some(true),
+                transparent: Some(false),
+                decorations: Some(true),
+                icon: None,
+                active: Some(true),
+                visible: Some(true),
+                fullsize_content_view: None,
+                title_shown: Some(false),
+                titlebar_buttons_shown: Some(false),
+                titlebar_shown: Some(false),
+                drag_and_drop: Some(false),
+                taskbar: Some(false),
+                close_button: Some(false),
+                minimize_button: Some(true),
+                maximize_button: Some(true),
+                window_level: Some(egui::WindowLevel::Normal),
+                mouse_passthrough: None,
+                window_type: Some(egui::X11WindowType::Normal),
+                movable_by_window_background: None,
+                has_shadow: None,
+                override_redirect: None,
+            },
+            PanelType::Bottom,
+        ),
+        // Piano roll
+        Panel::new(
+            PanelId::PianoRoll,
+            ViewportBuilder {
+                title: Some(String::from("Piano Roll")),
+                app_id: None,
+                position: None,
+                inner_size: None,
+                min_inner_size: None,
+                max_inner_size: None,
+                clamp_size_to_monitor_size: None,
+                fullscreen: None,
+                maximized: None,
+                resizable: Some(true),
+                transparent: Some(false),
+                decorations: Some(true),
+                icon: None,
+                active: Some(true),
+                visible: Some(true),
+                fullsize_content_view: None,
+                title_shown: Some(false),
+                titlebar_buttons_shown: Some(false),
+                titlebar_shown: Some(false),
+                drag_and_drop: Some(false),
+                taskbar: Some(false),
+                close_button: Some(false),
+                minimize_button: Some(true),
+                maximize_button: Some(true),
+                window_level: Some(egui::WindowLevel::Normal),
+                mouse_passthrough: None,
+                window_type: Some(egui::X11WindowType::Normal),
+                movable_by_window_background: None,
+                has_shadow: None,
+                override_redirect: None,
+            },
+            PanelType::Bottom,
+        ),
+        // Instrument
+        Panel::new(
+            PanelId::Instrument,
+            ViewportBuilder {
+                title: Some(String::from("Instrument")),
+                app_id: None,
+                position: None,
+                inner_size: None,
+                min_inner_size: None,
+                max_inner_size: None,
+                clamp_size_to_monitor_size: None,
+                fullscreen: None,
+                maximized: None,
+                resizable: Some(true),
+                transparent: Some(false),
+                decorations: Some(true),
+                icon: None,
+                active: Some(true),
+                visible: Some(true),
+                fullsize_content_view: None,
+                title_shown: Some(false),
+                titlebar_buttons_shown: Some(false),
+                titlebar_shown: Some(false),
+                drag_and_drop: Some(false),
+                taskbar: Some(false),
+                close_button: Some(false),
+                minimize_button: Some(true),
+                maximize_button: Some(true),
+                window_level: Some(egui::WindowLevel::Normal),
+                mouse_passthrough: None,
+                window_type: Some(egui::X11WindowType::Normal),
+                movable_by_window_background: None,
+                has_shadow: None,
+                override_redirect: None,
+            },
+            PanelType::Bottom,
+        ),
         // Playlist
         Panel::new(
             PanelId::Playlist,
@@ -188,6 +339,31 @@ pub fn create_panels() -> Vec<Panel> {
     ]
 }
 
+/// Adds the default panels which are missing from a stored layout, e.g. panels introduced after the layout was saved.
+/// The new panels are placed before the central panel, since that one takes up the remaining space.
+pub fn restore_missing_panels(panels: &mut Vec<Panel>) {
+    for panel in create_panels() {
+        let discriminant = panel.id.discriminant();
+
+        if panels
+            .iter()
+            .any(|existing| existing.id.discriminant() == discriminant)
+        {
+            continue;
+        }
+
+        let idx = match panel.panel_type {
+            PanelType::Central => panels.len(),
+            _ => panels
+                .iter()
+                .position(|existing| matches!(existing.panel_type, PanelType::Central))
+                .unwrap_or(panels.len()),
+        };
+
+        panels.insert(idx, panel);
+    }
+}
+
 pub fn display_panel_title(this: &Panel, ui: &mut Ui, title: &str) {
     egui::Sides::new().show(
         ui,
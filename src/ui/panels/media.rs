@@ -9,9 +9,11 @@ use parking_lot::{Mutex, RwLock};
 use crate::{
     internals::{
         fs::{FsMap, create_entry_map},
+        midi::is_midi_file,
         sample::{SampleProperties, fetch_sample_properties, generate_sample_waveform},
         utils::CacheState,
     },
+    project_manager::history::Edit,
     ui::panels::{
         lib::{Panel, PanelStates, display_error_as_toast, random_color_with_opacity},
         playlist::SampleInstance,
@@ -82,8 +84,7 @@ pub struct MediaPanel {
     /// The state of the FilesystemSelector
     pub filesystem_selector: FileSystemSelector,
 
-    /// These are dependent on the specific workspace we are working in so we can skip saving this. (This is what bookmarks are for)
-    #[serde(skip)]
+    /// These are dependent on the specific workspace we are working in, they are saved with the project.
     pub workspace_selector: WorkspaceSelector,
 
     /// The state of the BookmarkSelector
@@ -118,7 +119,7 @@ pub fn mediapicker_ui(this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>)
 
     ui.separator();
 
-    picker_toolbar(this, ui, state, media_selector_state);
+    picker_toolbar(this, ui, &global_state, media_selector_state);
 
     ui.separator();
 
@@ -241,9 +242,11 @@ pub fn mediapicker_ui(this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>)
 fn picker_toolbar(
     this: &Panel,
     ui: &mut Ui,
-    state: &RwLock<MediaPanel>,
+    global_state: &PanelStates,
     media_selector_state: MediaSelectorState,
 ) {
+    let state = &global_state.media_panel;
+
     // Make sure to update the object count so that all widgets are properly sized.
     let toolbar_btn_count: f32 = {
         (match media_selector_state {
@@ -281,11 +284,21 @@ fn picker_toolbar(
                         let selected_object = bookmark_selector.selected_object.as_ref().unwrap();
 
                         // Remove the bookmarked object from the bookmarks and reset the selected object field
-                        state
-                            .write()
-                            .bookmark_selector
-                            .bookmarks
-                            .swap_remove(selected_object);
+                        if let Some((index, path, bookmark)) =
+                            bookmark_selector.bookmarks.get_full(selected_object)
+                        {
+                            let edit = Edit::RemoveBookmark {
+                                index,
+                                path: path.clone(),
+                                bookmark: bookmark.clone(),
+                            };
+
+                            edit.apply(global_state);
+                            global_state
+                                .history
+                                .write()
+                                .record(format!("Remove bookmark {}", bookmark.alias), edit);
+                        }
                         state.write().bookmark_selector.selected_object = None;
                     };
 
@@ -387,12 +400,23 @@ fn picker_toolbar(
                         // Its safe to unwrap here due to the check above
                         let selected_object = workspace_selector.selected_object.as_ref().unwrap();
 
-                        // Remove the bookmarked object from the bookmarks and reset the selected object field
-                        state
-                            .write()
-                            .workspace_selector
+                        // Remove the sample from the workspace and reset the selected object field
+                        if let Some((index, path, attributes)) = workspace_selector
                             .workspace_samples
-                            .swap_remove(selected_object);
+                            .get_full(selected_object)
+                        {
+                            let edit = Edit::RemoveWorkspaceSample {
+                                index,
+                                path: path.clone(),
+                                attributes: attributes.clone(),
+                            };
+
+                            edit.apply(global_state);
+                            global_state.history.write().record(
+                                format!("Remove {} from workspace", attributes.alias),
+                                edit,
+                            );
+                        }
                         state.write().workspace_selector.selected_object = None;
                     };
 
@@ -545,6 +569,12 @@ fn display_filesystem_map(
     for entry in &mut map.objects {
         match entry {
             crate::internals::fs::FsObject::File { name, path } => {
+                // MIDI files are dropped onto the playlist as notes instead of as samples
+                if is_midi_file(path) {
+                    draggable_midi_file(ui, selected_object, name.clone(), path.clone());
+                    continue;
+                }
+
                 // Create an entry where the users cannot copy the text from it directly
                 // Make this object draggable and the payload should the the path of the object we are referencing in the ui.
                 draggable_sample(
@@ -645,6 +675,54 @@ fn draggable_sample(
     entry_response
 }
 
+/// The payload of a MIDI file dragged from the file system, dropping it onto the playlist imports its notes.
+#[derive(Debug, Clone)]
+pub struct MidiFilePayload(pub PathBuf);
+
+fn draggable_midi_file(
+    ui: &mut Ui,
+    selected_object: &mut Option<PathBuf>,
+    name: std::ffi::OsString,
+    path: PathBuf,
+) -> Response {
+    let entry = ui.dnd_drag_source(Id::new(&*path), MidiFilePayload(path.clone()), |ui| {
+        ui.scope(|ui| {
+            // Set this so we cannot select text
+            ui.style_mut().interaction.selectable_labels = false;
+
+            ui.label(
+                RichText::from(name.to_string_lossy())
+                    .strong()
+                    .background_color({
+                        // Highlight the label if the user has clicked on it
+                        if *selected_object != Some(path.clone()) {
+                            Color32::TRANSPARENT
+                        } else {
+                            Color32::GRAY
+                        }
+                    }),
+            )
+        })
+    });
+
+    // Catch both clicks and dragging in the ui
+    let entry_response = ui.interact(
+        entry.response.rect,
+        Id::new(&*path),
+        Sense::click_and_drag(),
+    );
+
+    if entry_response.clicked() {
+        if *selected_object != Some(path.clone()) {
+            *selected_object = Some(path);
+        } else {
+            *selected_object = None;
+        }
+    };
+
+    entry_response
+}
+
 fn draggable_sample_label(
     ui: &mut Ui,
     selected_object: &Option<PathBuf>,
@@ -0,0 +1,329 @@
+use std::{collections::HashMap, sync::Arc};
+
+use egui::{Color32, Rect, RichText, ScrollArea, Sense, Stroke, Ui, vec2};
+
+use crate::{
+    internals::tracks::{Track, TrackId},
+    ui::panels::lib::{Panel, PanelStates},
+};
+
+/// The loudest gain a fader can be set to, this is about +6 dB.
+const MAX_VOLUME: f32 = 2.0;
+
+/// The width of a single channel strip.
+const STRIP_WIDTH: f32 = 70.0;
+const FADER_HEIGHT: f32 = 120.0;
+const METER_WIDTH: f32 = 12.0;
+
+/// How much of the displayed peak is kept every frame, this makes the meters fall back smoothly.
+const METER_DECAY: f32 = 0.9;
+
+/// The color of a meter whose peak has reached 1.0, the channel is clipping then.
+const METER_CLIP_COLOR: Color32 = Color32::RED;
+const METER_COLOR: Color32 = Color32::LIGHT_GREEN;
+const METER_BACKGROUND: Color32 = Color32::from_gray(20);
+
+/// The settings of a single channel of the mixer.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChannelStrip {
+    /// Linear gain, 1.0 leaves the signal untouched.
+    pub volume: f32,
+
+    /// -1.0 is fully left, 1.0 is fully right.
+    pub pan: f32,
+
+    pub mute: bool,
+    pub solo: bool,
+}
+
+impl Default for ChannelStrip {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            pan: 0.0,
+            mute: false,
+            solo: false,
+        }
+    }
+}
+
+impl ChannelStrip {
+    /// The gains of the left and the right channel.
+    /// Panning only attenuates the opposite side, so a centered channel is left at unity gain.
+    pub fn gains(&self) -> [f32; 2] {
+        if self.mute {
+            return [0.; 2];
+        }
+
+        let pan = self.pan.clamp(-1., 1.);
+
+        [
+            self.volume * (1. - pan).min(1.),
+            self.volume * (1. + pan).min(1.),
+        ]
+    }
+}
+
+/// State of the mixer panel.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct MixerState {
+    /// The channel strips of the tracks, tracks without an entry use the default strip.
+    pub channels: HashMap<TrackId, ChannelStrip>,
+
+    pub master: ChannelStrip,
+
+    /// The peaks currently displayed by the meters, these fall back slowly after the engine reports them.
+    #[serde(skip)]
+    pub displayed_peaks: HashMap<TrackId, [f32; 2]>,
+
+    #[serde(skip)]
+    pub displayed_master_peak: [f32; 2],
+}
+
+impl MixerState {
+    /// The strip of the track, or the default strip if the track has not been modified.
+    pub fn channel(&self, track: TrackId) -> ChannelStrip {
+        self.channels.get(&track).copied().unwrap_or_default()
+    }
+
+    /// Whether any of the tracks are soloed, this makes every other track silent.
+    pub fn is_soloing(&self) -> bool {
+        self.channels.values().any(|channel| channel.solo)
+    }
+
+    /// The gains of the left and the right channel of the track, taking mute and solo into account.
+    /// The master strip is not included.
+    pub fn track_gains(&self, track: TrackId) -> [f32; 2] {
+        let channel = self.channel(track);
+
+        if self.is_soloing() && !channel.solo {
+            return [0.; 2];
+        }
+
+        channel.gains()
+    }
+
+    /// A mixer which only keeps the volume and the pan of the track, this is used to render the track on its own.
+    pub fn isolated(&self, track: TrackId) -> MixerState {
+        let channel = ChannelStrip {
+            mute: false,
+            solo: false,
+            ..self.channel(track)
+        };
+
+        MixerState {
+            channels: HashMap::from([(track, channel)]),
+            ..Default::default()
+        }
+    }
+
+    /// Modifies the strip of the track, the strip is only stored if it differs from the default one.
+    pub fn set_channel(&mut self, track: TrackId, channel: ChannelStrip) {
+        if channel == ChannelStrip::default() {
+            self.channels.remove(&track);
+        } else {
+            self.channels.insert(track, channel);
+        }
+    }
+}
+
+pub fn mixer_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
+    let (peaks, master_peak) = global_state.playback.take_peaks();
+
+    // Every track of the playlist has a strip, in the same order as the tracks
+    let tracks: Vec<Track> = global_state
+        .playlist_panel
+        .read()
+        .tracks
+        .iter()
+        .cloned()
+        .collect();
+
+    let mut state = global_state.mixer_panel.write();
+
+    // Let the meters fall back slowly instead of jumping around every block
+    for track in &tracks {
+        let peak = peaks.get(&track.id).copied().unwrap_or_default();
+        let displayed = state.displayed_peaks.entry(track.id).or_default();
+
+        *displayed = decay_peak(*displayed, peak);
+    }
+
+    state.displayed_master_peak = decay_peak(state.displayed_master_peak, master_peak);
+
+    // The meters have to keep falling even if nothing else changes
+    if state
+        .displayed_peaks
+        .values()
+        .chain([&state.displayed_master_peak])
+        .any(|peak| peak.iter().any(|level| *level > f32::EPSILON))
+    {
+        ui.ctx().request_repaint();
+    }
+
+    let mut is_changed = false;
+
+    ui.horizontal(|ui| {
+        ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for track in &tracks {
+                    let mut channel = state.channel(track.id);
+                    let peak = state
+                        .displayed_peaks
+                        .get(&track.id)
+                        .copied()
+                        .unwrap_or_default();
+
+                    channel_strip(
+                        ui,
+                        &track.customization.label_text,
+                        track.customization.label_color,
+                        &mut channel,
+                        peak,
+                        true,
+                    );
+
+                    if channel != state.channel(track.id) {
+                        state.set_channel(track.id, channel);
+                        is_changed = true;
+                    }
+
+                    ui.separator();
+                }
+            });
+        });
+
+        ui.separator();
+
+        let peak = state.displayed_master_peak;
+        let master = state.master;
+
+        channel_strip(
+            ui,
+            "Master",
+            Color32::DARK_GRAY,
+            &mut state.master,
+            peak,
+            false,
+        );
+
+        is_changed |= state.master != master;
+    });
+
+    // The mixer is not recorded in the history, but it is still a part of the project
+    if is_changed {
+        global_state.history.write().mark_changed();
+    }
+}
+
+fn decay_peak(displayed: [f32; 2], peak: [f32; 2]) -> [f32; 2] {
+    [
+        peak[0].max(displayed[0] * METER_DECAY),
+        peak[1].max(displayed[1] * METER_DECAY),
+    ]
+}
+
+/// Draws a single channel strip, the master strip cannot be soloed.
+fn channel_strip(
+    ui: &mut Ui,
+    label: &str,
+    color: Color32,
+    channel: &mut ChannelStrip,
+    peak: [f32; 2],
+    can_solo: bool,
+) {
+    ui.allocate_ui(vec2(STRIP_WIDTH, 0.), |ui| {
+        ui.vertical_centered(|ui| {
+            ui.set_width(STRIP_WIDTH);
+
+            ui.label(RichText::from(label).background_color(color).strong());
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::Slider::new(&mut channel.volume, 0.0..=MAX_VOLUME)
+                        .vertical()
+                        .show_value(false),
+                )
+                .on_hover_text("Volume");
+
+                peak_meter(ui, peak);
+            });
+
+            ui.label(RichText::from(format_decibels(channel.volume)).small());
+
+            ui.add(
+                egui::DragValue::new(&mut channel.pan)
+                    .range(-1.0..=1.0)
+                    .speed(0.01)
+                    .custom_formatter(|pan, _| format_pan(pan as f32)),
+            )
+            .on_hover_text("Pan, double click to center")
+            .double_clicked()
+            .then(|| channel.pan = 0.);
+
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut channel.mute, "M")
+                    .on_hover_text("Mute");
+
+                if can_solo {
+                    ui.toggle_value(&mut channel.solo, "S")
+                        .on_hover_text("Solo");
+                }
+            });
+        });
+    });
+}
+
+/// Draws the peaks of the left and the right channel next to each other.
+fn peak_meter(ui: &mut Ui, peak: [f32; 2]) {
+    let (rect, _) = ui.allocate_exact_size(vec2(METER_WIDTH, FADER_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+
+    painter.rect_filled(rect, 0., METER_BACKGROUND);
+
+    let channel_width = rect.width() / 2.;
+
+    for (idx, level) in peak.iter().enumerate() {
+        let left = rect.left() + channel_width * idx as f32;
+        let height = level.clamp(0., 1.) * rect.height();
+
+        let level_rect = Rect::from_min_max(
+            egui::pos2(left + 1., rect.bottom() - height),
+            egui::pos2(left + channel_width - 1., rect.bottom()),
+        );
+
+        painter.rect_filled(
+            level_rect,
+            0.,
+            if *level >= 1. {
+                METER_CLIP_COLOR
+            } else {
+                METER_COLOR
+            },
+        );
+    }
+
+    painter.rect_stroke(
+        rect,
+        0.,
+        Stroke::new(1.0_f32, Color32::GRAY),
+        egui::StrokeKind::Inside,
+    );
+}
+
+/// Formats a linear gain in decibels, e.g. `+6.0 dB`.
+pub fn format_decibels(gain: f32) -> String {
+    if gain <= f32::EPSILON {
+        return String::from("-inf dB");
+    }
+
+    format!("{:+.1} dB", 20. * gain.log10())
+}
+
+fn format_pan(pan: f32) -> String {
+    match pan {
+        pan if pan.abs() < 0.005 => String::from("C"),
+        pan if pan < 0. => format!("L{:.0}", -pan * 100.),
+        pan => format!("R{:.0}", pan * 100.),
+    }
+}
@@ -1,5 +1,13 @@
+/// Picks and edits the instruments playing the notes of the tracks
+pub mod instrument;
 /// Serves as a way to import media into the project.
 pub mod media;
+/// Sets the volume and the pan of the tracks
+pub mod mixer;
+/// Step sequences which can be placed into the playlist like clips
+pub mod patterns;
+/// Draws and edits the notes of note clips
+pub mod piano_roll;
 /// Where you arrange patterns and clips into a full song
 pub mod playlist;
 /// Acts as the root for the application, this is the lowest layer of ui.
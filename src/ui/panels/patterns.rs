@@ -0,0 +1,400 @@
+use egui::{
+    Color32, Id, Rect, RichText, Sense, Stroke, Ui, pos2, vec2,
+    widgets::color_picker::{Alpha, color_edit_button_srgba},
+};
+use egui_toast::ToastStyle;
+
+use crate::{
+    internals::{
+        patterns::{Pattern, STEP_COUNTS, STEP_TICKS, Step},
+        sample::fetch_sample_properties,
+        timeline::PPQ,
+    },
+    project_manager::history::Edit,
+    ui::panels::{
+        lib::{Panel, PanelStates, display_error_as_toast, random_color_with_opacity},
+        playlist::SampleInstance,
+    },
+};
+
+/// The size of a single step of the grid.
+const STEP_SIZE: f32 = 22.;
+
+/// The width of the sample names displayed before the steps of the rows.
+const ROW_LABEL_WIDTH: f32 = 120.;
+
+/// The width of the list of patterns on the left side of the editor.
+const PATTERN_LIST_WIDTH: f32 = 140.;
+
+/// The steps of every other beat are shaded differently, so that the beats can be told apart.
+const STEPS_PER_BEAT: usize = (PPQ / STEP_TICKS) as usize;
+
+/// Dragging a step by this many points changes its velocity from silent to full.
+const VELOCITY_DRAG_RANGE: f32 = STEP_SIZE * 4.;
+
+/// The window of the step sequencer, it is toggled by the patterns button of the playlist.
+/// The patterns are placed by dragging them from the list of the editor into the playlist.
+pub fn pattern_editor(this: &Panel, ctx: &egui::Context, global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+    let mut open = state.read().show_patterns;
+
+    if !open {
+        return;
+    }
+
+    egui::Window::new("Patterns")
+        .open(&mut open)
+        .default_width(640.)
+        .show(ctx, |ui| {
+            ui.horizontal_top(|ui| {
+                ui.vertical(|ui| {
+                    ui.set_width(PATTERN_LIST_WIDTH);
+
+                    pattern_list(ui, global_state);
+                });
+
+                ui.separator();
+
+                ui.vertical(|ui| edit_pattern(this, ui, global_state));
+            });
+        });
+
+    state.write().show_patterns = open;
+}
+
+/// Lists the patterns, clicking on a pattern opens it in the editor.
+fn pattern_list(ui: &mut Ui, global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+
+    if ui.button("New pattern").clicked() {
+        let pattern = state
+            .write()
+            .patterns
+            .create(random_color_with_opacity(120));
+
+        state.write().edited_pattern = Some(pattern.id);
+
+        let edit = Edit::InsertPattern { pattern };
+
+        edit.apply(global_state);
+        global_state.history.write().record("New pattern", edit);
+    }
+
+    ui.separator();
+
+    let (patterns, edited) = {
+        let state = state.read();
+
+        let patterns: Vec<_> = state
+            .patterns
+            .iter()
+            .map(|pattern| (pattern.id, pattern.name.clone(), pattern.color))
+            .collect();
+
+        (patterns, state.edited_pattern)
+    };
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (id, name, color) in patterns {
+            // The pattern is placed by dropping it into the playlist
+            let label = ui.dnd_drag_source(Id::new(("pattern_source", id)), id, |ui| {
+                ui.horizontal(|ui| {
+                    let (swatch, _) = ui.allocate_exact_size(vec2(10., 10.), Sense::hover());
+
+                    ui.painter().rect_filled(swatch, 2., color);
+                    ui.selectable_label(edited == Some(id), name)
+                })
+                .inner
+            });
+
+            if label
+                .inner
+                .on_hover_text("Drag the pattern into the playlist to place it.")
+                .clicked()
+            {
+                state.write().edited_pattern = Some(id);
+            }
+        }
+    });
+}
+
+/// Displays the pattern opened in the editor, the changes are recorded as a single entry until the pointer is released.
+fn edit_pattern(this: &Panel, ui: &mut Ui, global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+
+    let before = {
+        let state = state.read();
+
+        state
+            .edited_pattern
+            .and_then(|id| state.patterns.get(id).cloned())
+    };
+
+    let Some(before) = before else {
+        ui.label(RichText::from("Select a pattern or create a new one.").weak());
+
+        return;
+    };
+
+    let mut after = before.clone();
+    let mut changed = false;
+    let mut label = "Change pattern";
+    let mut delete = false;
+
+    let name = ui
+        .horizontal(|ui| {
+            let name = ui.add(egui::TextEdit::singleline(&mut after.name).desired_width(120.));
+
+            if name.changed() {
+                changed = true;
+                label = "Rename pattern";
+            }
+
+            changed |= color_edit_button_srgba(ui, &mut after.color, Alpha::OnlyBlend).changed();
+
+            let mut step_count = after.step_count();
+
+            egui::ComboBox::from_id_salt("pattern_step_count")
+                .selected_text(format!("{step_count} steps"))
+                .show_ui(ui, |ui| {
+                    for count in STEP_COUNTS {
+                        ui.selectable_value(&mut step_count, *count, format!("{count} steps"));
+                    }
+                });
+
+            if step_count != after.step_count() {
+                after.set_step_count(step_count);
+                changed = true;
+            }
+
+            if ui
+                .button("Delete")
+                .on_hover_text("Delete the pattern along with the clips playing it.")
+                .clicked()
+            {
+                delete = true;
+            }
+
+            name
+        })
+        .inner;
+
+    ui.separator();
+
+    let color = after.color;
+    let mut removed_row = None;
+
+    for (idx, row) in after.rows.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 2.;
+
+            ui.add_sized(
+                [ROW_LABEL_WIDTH, STEP_SIZE],
+                egui::Label::new(RichText::from(&row.sample.name).color(row.sample.color))
+                    .truncate()
+                    .sense(Sense::click()),
+            )
+            .context_menu(|ui| {
+                if ui.button("Remove row").clicked() {
+                    removed_row = Some(idx);
+                    ui.close();
+                }
+            });
+
+            changed |= step_row(ui, &mut row.steps, color);
+        });
+    }
+
+    if let Some(idx) = removed_row {
+        after.rows.remove(idx);
+        changed = true;
+        label = "Remove row";
+    }
+
+    ui.separator();
+
+    // Rows are added from the samples of the workspace, or by dropping a sample onto the editor
+    ui.horizontal(|ui| {
+        let workspace_samples: Vec<_> = global_state
+            .media_panel
+            .read()
+            .workspace_selector
+            .workspace_samples
+            .iter()
+            .map(|(path, attributes)| (path.clone(), attributes.clone()))
+            .collect();
+
+        ui.menu_button("Add sample", |ui| {
+            if workspace_samples.is_empty() {
+                ui.label(RichText::from("The workspace is empty.").weak());
+            }
+
+            for (path, attributes) in workspace_samples {
+                if !ui.button(&attributes.alias).clicked() {
+                    continue;
+                }
+
+                if let Some(properties) = display_error_as_toast(
+                    fetch_sample_properties(&path),
+                    ToastStyle::default(),
+                    this.toasts.clone(),
+                ) {
+                    after.add_row(SampleInstance {
+                        name: attributes.alias,
+                        color: attributes.color,
+                        path,
+                        properties,
+                        waveform_map: attributes.waveform_map,
+                    });
+
+                    changed = true;
+                    label = "Add row";
+                }
+
+                ui.close();
+            }
+        });
+
+        let (_, dropped) =
+            ui.dnd_drop_zone::<SampleInstance, ()>(egui::Frame::default().inner_margin(4.), |ui| {
+                ui.label(RichText::from("Drop a sample here to add a row").weak());
+            });
+
+        if let Some(sample) = dropped {
+            after.add_row((*sample).clone());
+            changed = true;
+            label = "Add row";
+        }
+    });
+
+    if delete {
+        delete_pattern(global_state, before);
+
+        return;
+    }
+
+    let history_group = Id::new(("pattern", before.id));
+
+    if changed {
+        state.write().patterns.insert(after.clone());
+        global_state.history.write().record_grouped(
+            history_group,
+            label,
+            Edit::ChangePattern { before, after },
+        );
+    }
+
+    // Dragging a step or a color is a single entry, so is typing the name
+    if !ui.input(|input| input.pointer.any_down()) && !name.has_focus() {
+        global_state.history.write().close_group(history_group);
+    }
+}
+
+/// Draws the steps of a row, returns whether any of them has been changed.
+/// Clicking on a step sets or clears it, right clicking mutes it and dragging it changes its velocity.
+fn step_row(ui: &mut Ui, steps: &mut [Option<Step>], color: Color32) -> bool {
+    let mut changed = false;
+
+    for (idx, step) in steps.iter_mut().enumerate() {
+        let (rect, response) =
+            ui.allocate_exact_size(vec2(STEP_SIZE, STEP_SIZE), Sense::click_and_drag());
+
+        if response.clicked() {
+            *step = match step {
+                Some(_) => None,
+                None => Some(Step::default()),
+            };
+            changed = true;
+        }
+
+        if let Some(step) = step.as_mut() {
+            if response.secondary_clicked() {
+                step.muted = !step.muted;
+                changed = true;
+            }
+
+            let delta = -response.drag_delta().y / VELOCITY_DRAG_RANGE;
+
+            if response.dragged() && delta != 0. {
+                step.velocity = (step.velocity + delta).clamp(0., 1.);
+                changed = true;
+            }
+        }
+
+        let painter = ui.painter();
+
+        let background = match (idx / STEPS_PER_BEAT) % 2 {
+            0 => ui.visuals().faint_bg_color,
+            _ => ui.visuals().extreme_bg_color,
+        };
+
+        painter.rect_filled(rect, 2., background);
+
+        if let Some(step) = *step {
+            let fill = match step.muted {
+                true => ui.visuals().weak_text_color(),
+                false => color,
+            };
+
+            // The filled part of the step shows its velocity
+            let filled = Rect::from_min_max(
+                pos2(rect.left(), rect.bottom() - rect.height() * step.velocity),
+                rect.max,
+            );
+
+            painter.rect_filled(filled, 2., fill);
+            painter.rect_stroke(
+                rect,
+                2.,
+                Stroke::new(1.0_f32, fill),
+                egui::StrokeKind::Inside,
+            );
+        }
+
+        if response.hovered() {
+            painter.rect_stroke(
+                rect,
+                2.,
+                ui.visuals().widgets.hovered.bg_stroke,
+                egui::StrokeKind::Inside,
+            );
+        }
+
+        if let Some(step) = step {
+            response.on_hover_text(format!("Velocity {:.0}%", step.velocity * 100.));
+        }
+    }
+
+    changed
+}
+
+/// Removes the pattern along with every clip playing it.
+fn delete_pattern(global_state: &PanelStates, pattern: Pattern) {
+    let mut edits: Vec<Edit> = {
+        let mut state = global_state.playlist_panel.write();
+
+        state.edited_pattern = None;
+
+        let clips: Vec<_> = state.clips.pattern_clips(pattern.id).cloned().collect();
+
+        clips
+            .into_iter()
+            .map(|clip| {
+                state.selected_clips.remove(&clip.id);
+
+                Edit::RemoveClip { clip }
+            })
+            .collect()
+    };
+
+    edits.push(Edit::RemovePattern { pattern });
+
+    for edit in &edits {
+        edit.apply(global_state);
+    }
+
+    global_state
+        .history
+        .write()
+        .record_all("Delete pattern", edits);
+}
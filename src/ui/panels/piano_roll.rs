@@ -0,0 +1,900 @@
+use std::{collections::BTreeSet, ops::RangeInclusive, sync::Arc};
+
+use egui::{
+    Align2, Color32, CursorIcon, FontId, Id, Key, Modifiers, Pos2, Rect, RichText, Sense, Stroke,
+    Ui, pos2, vec2,
+    widgets::color_picker::{Alpha, color_edit_button_srgba},
+};
+use strum::IntoEnumIterator;
+
+use crate::{
+    internals::{
+        clips::{Clip, ClipId, ClipSource},
+        notes::{
+            DEFAULT_VELOCITY, KEY_COUNT, KEY_NAMES, MIDDLE_C, Note, NoteSequence, Scale, ScaleKind,
+            is_black_key, key_name,
+        },
+        timeline::{PPQ, SnapGrid, Tick},
+    },
+    project_manager::history::Edit,
+    ui::panels::lib::{Panel, PanelStates},
+};
+
+/// The width of the keyboard on the left side of the notes.
+const KEYBOARD_WIDTH: f32 = 48.;
+
+/// The height of the lane below the notes which displays their velocities.
+const VELOCITY_LANE_HEIGHT: f32 = 60.;
+
+/// The height of the keyboard, the notes and the velocity lane together.
+const PIANO_ROLL_HEIGHT: f32 = 320.;
+
+/// The range the width of a beat can be zoomed in.
+const BEAT_WIDTH: RangeInclusive<f32> = 10.0..=400.;
+
+/// The range the height of a key can be zoomed in.
+const KEY_HEIGHT: RangeInclusive<f32> = 6.0..=30.;
+
+/// Grabbing a note this close to its right edge resizes it instead of moving it.
+const RESIZE_HANDLE_WIDTH: f32 = 6.;
+
+/// Dragging on the velocity lane changes the notes which start this close to the pointer.
+const VELOCITY_STEM_REACH: f32 = 4.;
+
+/// The shortest a note can be made while snapping is turned off.
+const MIN_NOTE_LENGTH: u64 = PPQ / 64;
+
+/// The grid lines are left out if they would be closer to each other than this.
+const MIN_GRID_LINE_SPACING: f32 = 6.;
+
+const SELECTION_COLOR: Color32 = Color32::YELLOW;
+const PLAYHEAD_COLOR: Color32 = Color32::LIGHT_GREEN;
+const OUTSIDE_CLIP: Color32 = Color32::from_rgba_premultiplied(0, 0, 0, 110);
+const MARQUEE_FILL: Color32 = Color32::from_rgba_premultiplied(40, 40, 10, 40);
+const WHITE_KEY: Color32 = Color32::from_gray(220);
+const BLACK_KEY: Color32 = Color32::from_gray(40);
+
+/// The state of the piano roll, the notes themselves are stored in their clip.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PianoRollState {
+    /// The note clip which is being edited.
+    pub clip: Option<ClipId>,
+
+    /// The grid the notes are drawn, moved and resized on.
+    pub snap: SnapGrid,
+
+    /// The keys of the scale are highlighted, without a scale the black keys are shaded like on a keyboard.
+    pub scale: Option<Scale>,
+
+    pub beat_width: f32,
+    pub key_height: f32,
+
+    /// The beat displayed at the left edge of the notes, counted from the start of the notes of the clip.
+    pub first_beat: f32,
+
+    /// The key displayed at the top edge of the notes, this is fractional so that scrolling is smooth.
+    pub top_key: f32,
+
+    /// The length of newly drawn notes, this follows the last resized note.
+    pub note_length: Tick,
+
+    /// The indices of the selected notes of the clip.
+    #[serde(skip)]
+    pub selected: BTreeSet<usize>,
+}
+
+impl Default for PianoRollState {
+    fn default() -> Self {
+        Self {
+            clip: None,
+            snap: SnapGrid::Quarter,
+            scale: None,
+            beat_width: 80.,
+            key_height: 14.,
+            first_beat: 0.,
+            top_key: (MIDDLE_C + 12) as f32,
+            note_length: Tick(PPQ / 4),
+            selected: BTreeSet::new(),
+        }
+    }
+}
+
+impl PianoRollState {
+    /// Opens the note clip in the piano roll, the view is scrolled to the start of the clip and to its highest note.
+    pub fn open(&mut self, clip: &Clip) {
+        if self.clip != Some(clip.id) {
+            self.selected.clear();
+        }
+
+        self.clip = Some(clip.id);
+        self.first_beat = clip.shape.offset as f32;
+
+        if let Some(highest) = clip
+            .source
+            .notes()
+            .and_then(|notes| notes.notes.iter().map(|note| note.key).max())
+        {
+            self.top_key = (highest as f32 + 2.).min((KEY_COUNT - 1) as f32);
+        }
+    }
+}
+
+/// Converts between the positions of the notes and the screen.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    /// Where the notes are drawn.
+    grid: Rect,
+    beat_width: f32,
+    key_height: f32,
+    first_beat: f32,
+    top_key: f32,
+}
+
+impl Layout {
+    fn x(&self, tick: Tick) -> f32 {
+        self.grid.left() + (tick.as_beats() as f32 - self.first_beat) * self.beat_width
+    }
+
+    /// The position at `x`, positions before the start of the notes are clamped to it.
+    fn tick(&self, x: f32) -> Tick {
+        Tick::from_beats((self.first_beat + (x - self.grid.left()) / self.beat_width) as f64)
+    }
+
+    /// The top of the row of the key.
+    fn y(&self, key: u8) -> f32 {
+        self.grid.top() + (self.top_key - key as f32) * self.key_height
+    }
+
+    /// The key of the row at `y`.
+    fn key(&self, y: f32) -> u8 {
+        (self.top_key + 1. - (y - self.grid.top()) / self.key_height)
+            .floor()
+            .clamp(0., (KEY_COUNT - 1) as f32) as u8
+    }
+
+    /// The keys which are at least partially visible, from the lowest to the highest.
+    fn visible_keys(&self) -> RangeInclusive<u8> {
+        self.key(self.grid.bottom())..=self.key(self.grid.top())
+    }
+
+    fn note_rect(&self, note: &Note) -> Rect {
+        Rect::from_min_max(
+            pos2(self.x(note.start), self.y(note.key)),
+            pos2(self.x(note.end()), self.y(note.key) + self.key_height),
+        )
+    }
+
+    /// The note under the position along with whether its resize handle is grabbed, the notes drawn last are on top.
+    fn note_at(&self, notes: &[Note], position: Pos2) -> Option<(usize, bool)> {
+        notes.iter().enumerate().rev().find_map(|(idx, note)| {
+            let rect = self.note_rect(note);
+            let handle = RESIZE_HANDLE_WIDTH.min(rect.width() / 3.);
+
+            rect.contains(position)
+                .then_some((idx, position.x >= rect.right() - handle))
+        })
+    }
+}
+
+/// What is being done by dragging on the notes, the notes are stored as they were when the drag started.
+#[derive(Debug, Clone)]
+enum NoteDrag {
+    Move {
+        origin: Tick,
+        origin_key: u8,
+        grabbed: Note,
+        notes: Vec<(usize, Note)>,
+    },
+    Resize {
+        grabbed: Note,
+        notes: Vec<(usize, Note)>,
+    },
+
+    /// Selects the notes touched by the rectangle, they are added to the selection from before the drag.
+    Select { initial: BTreeSet<usize> },
+}
+
+/// Draws and edits the notes of a note clip, the notes are played by the instrument of the track of the clip.
+/// Every change made until the pointer is released is a single entry in the history.
+pub fn piano_roll_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
+    let state = &global_state.piano_roll_panel;
+    let playlist = &global_state.playlist_panel;
+
+    let opened = {
+        let playlist = playlist.read();
+
+        state
+            .read()
+            .clip
+            .and_then(|id| playlist.clips.get(id))
+            .filter(|clip| clip.source.notes().is_some())
+            .map(|clip| {
+                let instrument = playlist
+                    .tracks
+                    .find(clip.track)
+                    .map(|track| track.instrument.clone())
+                    .unwrap_or_default();
+                let meter = playlist.tempo.bar_at(clip.start).meter;
+
+                (
+                    clip.clone(),
+                    instrument,
+                    meter,
+                    playlist.cursor_offset as f64,
+                )
+            })
+    };
+
+    let Some((clip, instrument, meter, cursor)) = opened else {
+        ui.label(
+            RichText::from(
+                "Double click on a track of the playlist to create a note clip, or double click on a note clip to edit its notes.",
+            )
+            .weak(),
+        );
+
+        return;
+    };
+
+    let Some(before) = clip.source.notes() else {
+        return;
+    };
+
+    let mut roll = state.read().clone();
+    let mut notes = before.clone();
+    let mut label = "Change notes";
+
+    roll.selected.retain(|idx| *idx < notes.notes.len());
+
+    let name = ui
+        .horizontal(|ui| {
+            let name = ui.add(egui::TextEdit::singleline(&mut notes.name).desired_width(120.));
+
+            if name.changed() {
+                label = "Rename note clip";
+            }
+
+            color_edit_button_srgba(ui, &mut notes.color, Alpha::OnlyBlend);
+
+            ui.separator();
+
+            ui.label("Snap");
+            egui::ComboBox::from_id_salt("piano_roll_snap")
+                .selected_text(roll.snap.to_string())
+                .show_ui(ui, |ui| {
+                    for grid in SnapGrid::iter() {
+                        ui.selectable_value(&mut roll.snap, grid, grid.to_string());
+                    }
+                });
+
+            if ui
+                .button("Quantize")
+                .on_hover_text(
+                    "Move the selected notes onto the grid, or every note if none are selected.",
+                )
+                .clicked()
+            {
+                let selected: Vec<usize> = match roll.selected.is_empty() {
+                    true => (0..notes.notes.len()).collect(),
+                    false => roll.selected.iter().copied().collect(),
+                };
+
+                notes.quantize(selected, roll.snap);
+                label = "Quantize notes";
+            }
+
+            ui.separator();
+
+            scale_picker(ui, &mut roll.scale);
+
+            ui.separator();
+
+            if ui
+                .button(format!("Instrument: {instrument}"))
+                .on_hover_text("Edit the instrument of the track of the clip.")
+                .clicked()
+            {
+                global_state.instrument_panel.write().track = Some(clip.track);
+            }
+
+            name
+        })
+        .inner;
+
+    let (area, _) = ui.allocate_exact_size(
+        vec2(ui.available_width(), PIANO_ROLL_HEIGHT),
+        Sense::hover(),
+    );
+
+    let keyboard = Rect::from_min_max(
+        area.min,
+        pos2(
+            area.left() + KEYBOARD_WIDTH,
+            area.bottom() - VELOCITY_LANE_HEIGHT,
+        ),
+    );
+    let lane = Rect::from_min_max(pos2(keyboard.right(), keyboard.bottom()), area.max);
+
+    let layout = Layout {
+        grid: Rect::from_min_max(
+            pos2(keyboard.right(), area.top()),
+            pos2(area.right(), keyboard.bottom()),
+        ),
+        beat_width: roll.beat_width,
+        key_height: roll.key_height,
+        first_beat: roll.first_beat,
+        top_key: roll.top_key,
+    };
+
+    // The notes are edited before they are drawn, so that the changes are displayed right away
+    let grid_response = ui.allocate_rect(layout.grid, Sense::click_and_drag());
+    let lane_response = ui.allocate_rect(lane, Sense::click_and_drag());
+
+    edit_notes(
+        ui,
+        &grid_response,
+        &layout,
+        &mut roll,
+        &mut notes,
+        &mut label,
+    );
+    edit_velocities(&lane_response, &layout, &roll, &mut notes, &mut label);
+
+    // The selected notes are deleted with the keys which delete the selected clips of the playlist, while the pointer is over the piano roll
+    if ui.rect_contains_pointer(area)
+        && ui.memory(|memory| memory.focused().is_none())
+        && ui.input_mut(|input| {
+            input.consume_key(Modifiers::NONE, Key::Delete)
+                || input.consume_key(Modifiers::NONE, Key::Backspace)
+        })
+        && !roll.selected.is_empty()
+    {
+        for idx in roll.selected.iter().rev() {
+            if *idx < notes.notes.len() {
+                notes.notes.remove(*idx);
+            }
+        }
+
+        roll.selected.clear();
+        label = "Remove notes";
+    }
+
+    if ui.rect_contains_pointer(area) {
+        scroll(ui, &mut roll, &layout);
+    }
+
+    // The playhead is displayed relative to the notes of the clip
+    let playhead = cursor - clip.start.as_beats() + clip.shape.offset;
+
+    draw_keyboard(ui, keyboard, &layout);
+    draw_grid(ui, &layout, &roll, meter.bar_ticks());
+    draw_notes(ui, &layout, &roll, &notes);
+
+    // The parts of the notes which are not played by the clip are shaded
+    let painter = ui.painter_at(layout.grid);
+    let clip_start = layout.x(Tick::from_beats(clip.shape.offset));
+    let clip_end = layout.x(Tick::from_beats(clip.shape.offset + clip.length));
+
+    painter.rect_filled(
+        Rect::from_x_y_ranges(layout.grid.left()..=clip_start, layout.grid.y_range()),
+        0.,
+        OUTSIDE_CLIP,
+    );
+    painter.rect_filled(
+        Rect::from_x_y_ranges(clip_end..=layout.grid.right(), layout.grid.y_range()),
+        0.,
+        OUTSIDE_CLIP,
+    );
+
+    if (clip.shape.offset..clip.shape.offset + clip.length).contains(&playhead) {
+        let x = layout.x(Tick::from_beats(playhead));
+
+        painter.vline(
+            x,
+            layout.grid.y_range(),
+            Stroke::new(1.0_f32, PLAYHEAD_COLOR),
+        );
+    }
+
+    draw_velocities(ui, lane, &layout, &roll, &notes);
+
+    grid_response.on_hover_text_at_pointer(
+        "Click to draw a note, drag a note to move it or its right edge to resize it. Right click a note to delete it.",
+    );
+
+    *state.write() = roll;
+
+    let history_group = Id::new(("piano_roll", clip.id));
+
+    if &notes != before {
+        let after = Clip {
+            source: ClipSource::Notes(notes),
+            ..clip.clone()
+        };
+
+        playlist.write().clips.insert_clip(after.clone());
+        global_state.history.write().record_grouped(
+            history_group,
+            label,
+            Edit::ChangeClip {
+                before: clip,
+                after,
+            },
+        );
+    }
+
+    // Dragging a note is a single entry, so is typing the name
+    if !ui.input(|input| input.pointer.any_down()) && !name.has_focus() {
+        global_state.history.write().close_group(history_group);
+    }
+}
+
+/// Picks the scale which is highlighted, along with the key it starts from.
+fn scale_picker(ui: &mut Ui, scale: &mut Option<Scale>) {
+    let mut kind = scale.map(|scale| scale.kind);
+    let mut root = scale.map_or(0, |scale| scale.root);
+
+    ui.add_enabled_ui(kind.is_some(), |ui| {
+        egui::ComboBox::from_id_salt("piano_roll_scale_root")
+            .width(40.)
+            .selected_text(KEY_NAMES[root as usize % 12])
+            .show_ui(ui, |ui| {
+                for (idx, name) in KEY_NAMES.iter().enumerate() {
+                    ui.selectable_value(&mut root, idx as u8, *name);
+                }
+            });
+    });
+
+    egui::ComboBox::from_id_salt("piano_roll_scale")
+        .selected_text(kind.map_or_else(|| String::from("No scale"), |kind| kind.to_string()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut kind, None, "No scale");
+
+            for scale_kind in ScaleKind::iter() {
+                ui.selectable_value(&mut kind, Some(scale_kind), scale_kind.to_string());
+            }
+        });
+
+    *scale = kind.map(|kind| Scale { root, kind });
+}
+
+/// Moves the position to the closest line of the grid, unless snapping is turned off or Alt is held.
+fn snap_round(ui: &Ui, grid: SnapGrid, ticks: i64) -> i64 {
+    match grid.ticks() {
+        Some(step) if !ui.input(|input| input.modifiers.alt) => {
+            let step = step as i64;
+
+            (ticks + step / 2).div_euclid(step) * step
+        }
+        _ => ticks,
+    }
+}
+
+/// The shortest a note can be resized to, this is a single line of the grid.
+fn min_note_length(ui: &Ui, grid: SnapGrid) -> u64 {
+    match ui.input(|input| input.modifiers.alt) {
+        true => MIN_NOTE_LENGTH,
+        false => grid.ticks().unwrap_or(MIN_NOTE_LENGTH),
+    }
+}
+
+/// Draws, selects, moves, resizes and deletes the notes with the pointer.
+fn edit_notes(
+    ui: &Ui,
+    response: &egui::Response,
+    layout: &Layout,
+    roll: &mut PianoRollState,
+    notes: &mut NoteSequence,
+    label: &mut &'static str,
+) {
+    let drag_id = response.id.with("note_drag");
+    let shift = ui.input(|input| input.modifiers.shift);
+    let alt = ui.input(|input| input.modifiers.alt);
+
+    if let Some(pointer) = response.hover_pos()
+        && let Some((_, resize)) = layout.note_at(&notes.notes, pointer)
+    {
+        ui.ctx().set_cursor_icon(match resize {
+            true => CursorIcon::ResizeHorizontal,
+            false => CursorIcon::Grab,
+        });
+    }
+
+    if response.clicked()
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        match layout.note_at(&notes.notes, pointer) {
+            // Clicking on a note selects only that note, Shift adds it to the selection or removes it from it
+            Some((idx, _)) => {
+                if !shift {
+                    roll.selected.clear();
+                }
+
+                if !roll.selected.remove(&idx) || !shift {
+                    roll.selected.insert(idx);
+                }
+            }
+            None => {
+                let start = layout.tick(pointer.x);
+                let start = match alt {
+                    true => start,
+                    false => start.snap(roll.snap),
+                };
+
+                notes.notes.push(Note {
+                    key: layout.key(pointer.y),
+                    start,
+                    length: roll.note_length,
+                    velocity: DEFAULT_VELOCITY,
+                });
+
+                roll.selected = BTreeSet::from([notes.notes.len() - 1]);
+                *label = "Add note";
+            }
+        }
+    }
+
+    if response.secondary_clicked()
+        && let Some(pointer) = response.interact_pointer_pos()
+        && let Some((idx, _)) = layout.note_at(&notes.notes, pointer)
+    {
+        notes.notes.remove(idx);
+        roll.selected.clear();
+        *label = "Remove note";
+    }
+
+    if response.drag_started()
+        && let Some(origin) = ui.input(|input| input.pointer.press_origin())
+    {
+        let drag = match layout.note_at(&notes.notes, origin) {
+            Some((idx, resize)) => {
+                // Grabbing a note which is not selected selects it, so that the selection is what gets dragged
+                if !roll.selected.contains(&idx) {
+                    if !shift {
+                        roll.selected.clear();
+                    }
+
+                    roll.selected.insert(idx);
+                }
+
+                let grabbed = notes.notes[idx];
+                let dragged = roll
+                    .selected
+                    .iter()
+                    .filter_map(|idx| Some((*idx, *notes.notes.get(*idx)?)))
+                    .collect();
+
+                match resize {
+                    true => NoteDrag::Resize {
+                        grabbed,
+                        notes: dragged,
+                    },
+                    false => NoteDrag::Move {
+                        origin: layout.tick(origin.x),
+                        origin_key: layout.key(origin.y),
+                        grabbed,
+                        notes: dragged,
+                    },
+                }
+            }
+            None => NoteDrag::Select {
+                initial: match shift {
+                    true => roll.selected.clone(),
+                    false => BTreeSet::new(),
+                },
+            },
+        };
+
+        ui.data_mut(|data| data.insert_temp(drag_id, drag));
+    }
+
+    let drag: Option<NoteDrag> = ui.data(|data| data.get_temp(drag_id));
+
+    if response.dragged()
+        && let Some(drag) = drag
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        match drag {
+            NoteDrag::Move {
+                origin,
+                origin_key,
+                grabbed,
+                notes: dragged,
+            } => {
+                // The grabbed note is snapped, the rest of the notes keep their distance to it
+                let moved =
+                    grabbed.start.0 as i64 + layout.tick(pointer.x).0 as i64 - origin.0 as i64;
+                let earliest = dragged
+                    .iter()
+                    .map(|(_, note)| note.start.0)
+                    .min()
+                    .unwrap_or(0) as i64;
+                let ticks =
+                    (snap_round(ui, roll.snap, moved) - grabbed.start.0 as i64).max(-earliest);
+
+                let lowest = dragged.iter().map(|(_, note)| note.key).min().unwrap_or(0) as i32;
+                let highest = dragged.iter().map(|(_, note)| note.key).max().unwrap_or(0) as i32;
+                let keys = (layout.key(pointer.y) as i32 - origin_key as i32)
+                    .clamp(-lowest, KEY_COUNT as i32 - 1 - highest);
+
+                for (idx, note) in dragged {
+                    if let Some(moved) = notes.notes.get_mut(idx) {
+                        moved.start = Tick((note.start.0 as i64 + ticks) as u64);
+                        moved.key = (note.key as i32 + keys) as u8;
+                    }
+                }
+
+                *label = "Move notes";
+            }
+            NoteDrag::Resize {
+                grabbed,
+                notes: dragged,
+            } => {
+                let min_length = min_note_length(ui, roll.snap) as i64;
+                let end = snap_round(ui, roll.snap, layout.tick(pointer.x).0 as i64);
+                let ticks = end.max(grabbed.start.0 as i64 + min_length) - grabbed.end().0 as i64;
+
+                for (idx, note) in dragged {
+                    if let Some(resized) = notes.notes.get_mut(idx) {
+                        resized.length =
+                            Tick((note.length.0 as i64 + ticks).max(min_length) as u64);
+                    }
+                }
+
+                // The next drawn note is as long as the resized one
+                roll.note_length = Tick((grabbed.length.0 as i64 + ticks).max(min_length) as u64);
+                *label = "Resize notes";
+            }
+            NoteDrag::Select { mut initial } => {
+                if let Some(origin) = ui.input(|input| input.pointer.press_origin()) {
+                    let rect = Rect::from_two_pos(origin, pointer);
+
+                    initial.extend(
+                        notes
+                            .notes
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, note)| layout.note_rect(note).intersects(rect))
+                            .map(|(idx, _)| idx),
+                    );
+
+                    roll.selected = initial;
+
+                    ui.painter_at(layout.grid).rect(
+                        rect,
+                        0.,
+                        MARQUEE_FILL,
+                        Stroke::new(1.0_f32, SELECTION_COLOR),
+                        egui::StrokeKind::Inside,
+                    );
+                }
+            }
+        }
+    }
+
+    if response.drag_stopped() {
+        ui.data_mut(|data| data.remove::<NoteDrag>(drag_id));
+    }
+}
+
+/// Dragging on the velocity lane sets the velocity of the notes starting under the pointer.
+fn edit_velocities(
+    response: &egui::Response,
+    layout: &Layout,
+    roll: &PianoRollState,
+    notes: &mut NoteSequence,
+    label: &mut &'static str,
+) {
+    if !(response.clicked() || response.dragged()) {
+        return;
+    }
+
+    let Some(pointer) = response.interact_pointer_pos() else {
+        return;
+    };
+
+    let velocity = ((response.rect.bottom() - pointer.y) / response.rect.height()).clamp(0., 1.);
+    let under_pointer = |idx: usize, note: &Note| {
+        (layout.x(note.start) - pointer.x).abs() <= VELOCITY_STEM_REACH
+            && (roll.selected.is_empty() || roll.selected.contains(&idx))
+    };
+
+    // Only the selected notes are changed if there is a selection, so that the notes starting together can be told apart
+    for (idx, note) in notes.notes.iter_mut().enumerate() {
+        if under_pointer(idx, note) && note.velocity != velocity {
+            note.velocity = velocity;
+            *label = "Change velocity";
+        }
+    }
+}
+
+/// Scrolls the notes with the wheel, Ctrl zooms horizontally and Alt zooms vertically instead.
+fn scroll(ui: &Ui, roll: &mut PianoRollState, layout: &Layout) {
+    let (scroll_delta, zoom_delta, alt, pointer) = ui.input(|input| {
+        (
+            input.smooth_scroll_delta(),
+            input.zoom_delta(),
+            input.modifiers.alt,
+            input.pointer.hover_pos(),
+        )
+    });
+
+    if zoom_delta != 1. {
+        // The beat under the pointer stays in place
+        let x = pointer.map_or(0., |pointer| pointer.x - layout.grid.left());
+        let hovered_beat = roll.first_beat + x / roll.beat_width;
+
+        roll.beat_width =
+            (roll.beat_width * zoom_delta).clamp(*BEAT_WIDTH.start(), *BEAT_WIDTH.end());
+        roll.first_beat = (hovered_beat - x / roll.beat_width).max(0.);
+    } else if alt {
+        roll.key_height = (roll.key_height * (scroll_delta.y * 0.005).exp())
+            .clamp(*KEY_HEIGHT.start(), *KEY_HEIGHT.end());
+    } else {
+        let visible_keys = layout.grid.height() / roll.key_height;
+
+        roll.first_beat = (roll.first_beat - scroll_delta.x / roll.beat_width).max(0.);
+        roll.top_key = (roll.top_key + scroll_delta.y / roll.key_height)
+            .clamp(visible_keys - 1., (KEY_COUNT - 1) as f32);
+    }
+}
+
+/// Draws the keys of the visible rows, the C keys are labeled with their octave.
+fn draw_keyboard(ui: &Ui, keyboard: Rect, layout: &Layout) {
+    let painter = ui.painter_at(keyboard);
+
+    for key in layout.visible_keys() {
+        let top = layout.y(key);
+        let rect = Rect::from_x_y_ranges(keyboard.x_range(), top..=top + layout.key_height);
+
+        let (fill, text) = match is_black_key(key) {
+            true => (BLACK_KEY, WHITE_KEY),
+            false => (WHITE_KEY, BLACK_KEY),
+        };
+
+        painter.rect_filled(rect.shrink2(vec2(0., 0.5)), 0., fill);
+
+        if key % 12 == 0 && layout.key_height >= 10. {
+            painter.text(
+                pos2(rect.right() - 4., rect.center().y),
+                Align2::RIGHT_CENTER,
+                key_name(key),
+                FontId::proportional((layout.key_height - 2.).min(12.)),
+                text,
+            );
+        }
+    }
+}
+
+/// Shades the rows of the keys and draws the lines of the grid, the bars are counted from the start of the notes.
+fn draw_grid(ui: &Ui, layout: &Layout, roll: &PianoRollState, bar_ticks: u64) {
+    let painter = ui.painter_at(layout.grid);
+    let visuals = ui.visuals();
+
+    for key in layout.visible_keys() {
+        let top = layout.y(key);
+        let rect = Rect::from_x_y_ranges(layout.grid.x_range(), top..=top + layout.key_height);
+
+        let highlighted = match roll.scale {
+            Some(scale) => scale.contains(key),
+            None => !is_black_key(key),
+        };
+
+        let fill = match highlighted {
+            true => visuals.faint_bg_color,
+            false => visuals.extreme_bg_color,
+        };
+
+        painter.rect_filled(rect, 0., fill);
+
+        // The root of the scale is marked, so that the scale can be followed across the octaves
+        if roll.scale.is_some_and(|scale| scale.is_root(key)) {
+            painter.rect_filled(rect, 0., visuals.selection.bg_fill.gamma_multiply(0.25));
+        }
+
+        if key % 12 == 0 {
+            painter.hline(
+                layout.grid.x_range(),
+                rect.bottom(),
+                visuals.widgets.noninteractive.bg_stroke,
+            );
+        }
+    }
+
+    let grid_step = roll.snap.ticks().unwrap_or(PPQ);
+
+    // Dense grids only display the beats, so that the lines do not cover the notes
+    let step = match grid_step as f32 / PPQ as f32 * layout.beat_width >= MIN_GRID_LINE_SPACING {
+        true => grid_step,
+        false => PPQ,
+    };
+
+    let first = layout.tick(layout.grid.left()).0 / step * step;
+    let last = layout.tick(layout.grid.right()).0;
+
+    for tick in (first..=last).step_by(step as usize) {
+        let stroke = match (tick % bar_ticks.max(1) == 0, tick % PPQ == 0) {
+            (true, _) => Stroke::new(1.0_f32, visuals.strong_text_color()),
+            (false, true) => Stroke::new(1.0_f32, visuals.weak_text_color()),
+            (false, false) => visuals.widgets.noninteractive.bg_stroke,
+        };
+
+        painter.vline(layout.x(Tick(tick)), layout.grid.y_range(), stroke);
+    }
+}
+
+/// Draws the notes in the color of the clip, the selected notes are outlined.
+fn draw_notes(ui: &Ui, layout: &Layout, roll: &PianoRollState, notes: &NoteSequence) {
+    let painter = ui.painter_at(layout.grid);
+
+    for (idx, note) in notes.notes.iter().enumerate() {
+        let rect = layout.note_rect(note);
+
+        if !rect.intersects(layout.grid) {
+            continue;
+        }
+
+        // Quieter notes are drawn fainter
+        painter.rect_filled(
+            rect,
+            2.,
+            notes
+                .color
+                .to_opaque()
+                .gamma_multiply(0.4 + 0.6 * note.velocity),
+        );
+
+        let stroke = match roll.selected.contains(&idx) {
+            true => Stroke::new(2.0_f32, SELECTION_COLOR),
+            false => Stroke::new(1.0_f32, Color32::BLACK),
+        };
+
+        painter.rect_stroke(rect, 2., stroke, egui::StrokeKind::Inside);
+
+        if rect.width() > 30. && layout.key_height >= 10. {
+            painter.text(
+                pos2(rect.left() + 3., rect.center().y),
+                Align2::LEFT_CENTER,
+                key_name(note.key),
+                FontId::proportional((layout.key_height - 2.).min(11.)),
+                Color32::WHITE,
+            );
+        }
+    }
+}
+
+/// Draws a stem for every note on the velocity lane, the stems are as high as the velocity of their note.
+fn draw_velocities(
+    ui: &Ui,
+    lane: Rect,
+    layout: &Layout,
+    roll: &PianoRollState,
+    notes: &NoteSequence,
+) {
+    let painter = ui.painter_at(lane);
+
+    painter.rect_filled(lane, 0., ui.visuals().extreme_bg_color);
+    painter.hline(
+        lane.x_range(),
+        lane.top(),
+        ui.visuals().widgets.noninteractive.bg_stroke,
+    );
+
+    for (idx, note) in notes.notes.iter().enumerate() {
+        let x = layout.x(note.start);
+
+        if !lane.x_range().contains(x) {
+            continue;
+        }
+
+        let color = match roll.selected.contains(&idx) {
+            true => SELECTION_COLOR,
+            false => notes.color.to_opaque(),
+        };
+        let top = lane.bottom() - note.velocity * (lane.height() - 4.);
+
+        painter.vline(x, top..=lane.bottom(), Stroke::new(2.0_f32, color));
+        painter.circle_filled(pos2(x, top), 3., color);
+    }
+}
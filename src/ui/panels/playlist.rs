@@ -1,19 +1,41 @@
-use std::{collections::HashMap, ops::Add, path::PathBuf, sync::Arc};
+use std::{
+    collections::BTreeSet,
+    ops::{Add, Range, RangeInclusive},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use crate::{
     internals::{
+        clips::{Clip, ClipId, ClipShape, ClipSource, ClipStore, FadeCurve, MAX_CLIP_GAIN},
+        metronome::{ClickSound, MAX_COUNT_IN_BARS, MetronomeSettings},
+        notes::NoteSequence,
+        patterns::{Pattern, PatternId, PatternStore, STEP_TICKS},
         sample::{SampleProperties, generate_sample_waveform},
+        tempo::{BPM_RANGE, METER_DENOMINATORS, TempoChange, TempoMap},
+        timeline::{PPQ, SnapGrid, Tick, TimeFormat},
+        tracks::{Track, TrackId, TrackList},
         utils::find_value_inbetween,
     },
+    project_manager::{
+        history::{Edit, History},
+        midi::import_midi,
+    },
     ui::panels::{
+        instrument::load_sample,
         lib::{Panel, PanelStates, display_error_as_toast, random_color_with_opacity},
-        media::WorkspaceSampleAttributes,
+        media::{MidiFilePayload, WorkspaceSampleAttributes},
+        mixer::format_decibels,
+        patterns::pattern_editor,
     },
 };
-use egui::{Align2, Color32, FontId, Pos2, Rect, RichText, Sense, Stroke, Ui, Vec2, vec2};
+use egui::{
+    Align2, Color32, FontId, Id, Key, KeyboardShortcut, Modifiers, Pos2, Rect, RichText, Sense,
+    Stroke, Ui, Vec2, vec2,
+};
 use egui_toast::{Toast, ToastStyle};
-use indexmap::IndexMap;
 use parking_lot::RwLock;
+use strum::IntoEnumIterator;
 
 const TRACK_LABEL: Color32 = Color32::ORANGE;
 const TRACK_LABEL_TEXT: Color32 = Color32::WHITE;
@@ -21,12 +43,77 @@ const TRACK_HEIGHT: f32 = 100.0;
 const MINIMUM_TRACK_HEIGHT: f32 = 10.;
 
 // Set the height of the tracks (the horizontal space between two lines in the "grid")
+// This is the width of a beat when the playlist is not zoomed.
 const BEAT_WIDTH: usize = 25;
 
+/// The range the horizontal zoom of the playlist can be set in.
+pub const HORIZONTAL_ZOOM: RangeInclusive<f32> = 0.05..=16.;
+/// The range the vertical zoom of the playlist can be set in.
+const VERTICAL_ZOOM: RangeInclusive<f32> = 0.1..=4.;
+
 // Colors
 const BAR_TRACK_SEPARATOR: Color32 = Color32::GRAY;
 const STROKE_WIDTH: f32 = 1.0f32;
 const CURSOR_COLOR: Color32 = Color32::LIGHT_GREEN;
+const SELECTION_COLOR: Color32 = Color32::YELLOW;
+const BAR_LINE: Color32 = Color32::LIGHT_GRAY;
+const RULER_BACKGROUND: Color32 = Color32::from_gray(30);
+const RULER_TEXT: Color32 = Color32::LIGHT_GRAY;
+const TIME_SELECTION: Color32 = Color32::from_rgba_premultiplied(20, 20, 20, 20);
+const TEMPO_MARKER: Color32 = Color32::LIGHT_RED;
+const LOOP_ACTIVE: Color32 = Color32::from_rgb(70, 150, 230);
+const LOOP_INACTIVE: Color32 = Color32::from_gray(90);
+const METER_MARKER: Color32 = Color32::LIGHT_BLUE;
+const FADE_LINE: Color32 = Color32::from_gray(220);
+const CLIP_HANDLE: Color32 = Color32::WHITE;
+
+/// The empty rows after the last track are displayed with this much of the opacity of a track.
+const EMPTY_ROW_OPACITY: f32 = 0.4;
+const MARQUEE_FILL: Color32 = Color32::from_rgba_premultiplied(40, 40, 10, 40);
+
+/// The height of the ruler above the tracks.
+const RULER_HEIGHT: f32 = 20.;
+
+/// The beat lines are only drawn if they are at least this far apart, otherwise only the bars are.
+const MIN_BEAT_LINE_SPACING: f32 = 6.;
+
+/// The minimum distance between two labels of the ruler.
+const MIN_RULER_LABEL_SPACING: f32 = 40.;
+
+/// The height of the loop range drawn on the top of the ruler.
+const LOOP_HEIGHT: f32 = 4.;
+
+/// The size of the handles of the clips, the edges of the clips are this wide.
+const CLIP_HANDLE_SIZE: f32 = 6.;
+
+/// The amount of points the curves of the fades are drawn with.
+const FADE_CURVE_POINTS: usize = 16;
+
+/// How much the gain of a clip changes for every point its gain handle is dragged by.
+const GAIN_DRAG_SPEED: f32 = 0.01;
+
+// Transport shortcuts, these are only handled if nothing has the keyboard focus
+const PLAY_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Space);
+const SONG_START_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Home);
+const SONG_END_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::End);
+const PREVIOUS_BAR_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::NONE, Key::ArrowLeft);
+const NEXT_BAR_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::ArrowRight);
+const PREVIOUS_MARKER_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::ArrowLeft);
+const NEXT_MARKER_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND, Key::ArrowRight);
+
+// Editing shortcuts, these are applied to the selected clips
+const COPY_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::C);
+const PASTE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::V);
+const DUPLICATE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::D);
+const SPLIT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::E);
+const DELETE_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::NONE, Key::Delete);
+
+/// The size of the mute, solo and color buttons of the track labels, and the space between them.
+const TRACK_BUTTON_SIZE: f32 = 18.;
+const TRACK_BUTTON_GAP: f32 = 4.;
 
 // This indicates that the track label is 4 bars wide
 const TRACK_LABEL_WIDTH: usize = BEAT_WIDTH * 4;
@@ -36,12 +123,9 @@ pub struct TrackCustomization {
     pub label_text_color: Color32,
     pub label_color: Color32,
     pub height: f32,
-
-    /// This just makes it so that if the track's height has ever been set this will be true and it wont be automatically deleted
-    pub height_set: bool,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct SampleInstance {
     pub name: String,
     pub color: Color32,
@@ -51,22 +135,81 @@ pub struct SampleInstance {
 }
 
 impl TrackCustomization {
-    fn named_default(nth: usize) -> Self {
+    /// The customization of tracks which have not been customized by the user.
+    pub fn named_default(nth: usize) -> Self {
         Self {
             label_text: format!("Track {nth}"),
             label_text_color: TRACK_LABEL_TEXT,
             label_color: TRACK_LABEL,
             height: TRACK_HEIGHT,
+        }
+    }
+}
+
+/// How much the playlist is zoomed in, `1.0` displays everything in its original size.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistZoom {
+    /// Scales the width of the beats, changed with Ctrl + scroll.
+    pub horizontal: f32,
+
+    /// Scales the height of every track, changed with Alt + scroll.
+    pub vertical: f32,
+}
 
-            height_set: false,
+impl Default for PlaylistZoom {
+    fn default() -> Self {
+        Self {
+            horizontal: 1.,
+            vertical: 1.,
         }
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, Hash, Eq, PartialEq)]
-pub struct Position {
-    pub track: usize,
-    pub beat: usize,
+impl PlaylistZoom {
+    /// The width of a single beat on the screen.
+    pub fn beat_width(&self) -> f32 {
+        BEAT_WIDTH as f32 * self.horizontal
+    }
+
+    /// The height of the track on the screen.
+    pub fn track_height(&self, track: &TrackCustomization) -> f32 {
+        track.height * self.vertical
+    }
+
+    /// Multiplies the horizontal zoom by `factor`, the beat at `anchor_x` stays in place.
+    /// The coordinates are measured from the left of the beats, `scroll_x` is where beat 0 is drawn.
+    /// Returns where beat 0 is drawn after zooming, it is never drawn to the right of the start.
+    pub fn zoom_around(&mut self, scroll_x: f32, anchor_x: f32, factor: f32) -> f32 {
+        let anchor_beat = (anchor_x - scroll_x) / self.beat_width();
+
+        self.horizontal =
+            (self.horizontal * factor).clamp(*HORIZONTAL_ZOOM.start(), *HORIZONTAL_ZOOM.end());
+
+        (anchor_x - anchor_beat * self.beat_width()).min(0.)
+    }
+
+    /// Sets the horizontal zoom so that the beats fill `width`, an empty range is displayed as a single beat.
+    /// Returns where beat 0 has to be drawn for the first of the beats to be at the start.
+    pub fn fit_beats(&mut self, width: f32, beats: Range<f64>) -> f32 {
+        let length = (beats.end - beats.start).max(1.) as f32;
+
+        self.horizontal = (width / (length * BEAT_WIDTH as f32))
+            .clamp(*HORIZONTAL_ZOOM.start(), *HORIZONTAL_ZOOM.end());
+
+        (-(beats.start as f32) * self.beat_width()).min(0.)
+    }
+
+    /// Where beat 0 has to be drawn for the `cursor` to stay inside of the `visible_width`.
+    /// Once the cursor has left it, it is scrolled to the start, otherwise `scroll_x` is kept.
+    pub fn follow(&self, scroll_x: f32, visible_width: f32, cursor: f32) -> f32 {
+        let cursor_x = cursor * self.beat_width() + scroll_x;
+
+        if (0.0..visible_width).contains(&cursor_x) {
+            scroll_x
+        } else {
+            (-cursor * self.beat_width()).min(0.)
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
@@ -80,24 +223,132 @@ pub enum PlaybackState {
     Stopped,
 }
 
-#[derive(Default, Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PlaylistState {
-    /// Can be modified with the bpm slider.
-    pub bpm: f32,
+    /// The tempo and the meter of the song, the starting tempo can be modified with the bpm slider.
+    #[serde(default)]
+    pub tempo: TempoMap,
 
     #[serde(skip)]
-    /// Indicates the position of the cursor.
+    /// Indicates the position of the cursor in beats.
     pub cursor_offset: f32,
 
     /// This indicates how much the user has scrolled.
     pub grid_offset: Vec2,
 
-    /// Track customization
-    pub custom_tracks: HashMap<usize, TrackCustomization>,
+    #[serde(default)]
+    pub zoom: PlaylistZoom,
+
+    /// The tracks in the order they are displayed, the rows after the last track are empty.
+    #[serde(default)]
+    pub tracks: TrackList,
+
+    /// The clips placed on the tracks.
+    #[serde(default)]
+    pub clips: ClipStore,
 
-    pub samples: IndexMap<Position, SampleInstance>,
+    /// The patterns which can be placed as clips, they are edited in the pattern editor.
+    #[serde(default)]
+    pub patterns: PatternStore,
 
+    /// Whether the pattern editor is open, it is toggled by the patterns button of the toolbar.
+    #[serde(default)]
+    pub show_patterns: bool,
+
+    /// The pattern displayed by the pattern editor.
+    #[serde(default)]
+    pub edited_pattern: Option<PatternId>,
+
+    /// The grid the clips are snapped to, holding Alt places them freely.
+    #[serde(default)]
+    pub snap: SnapGrid,
+
+    /// The clips selected by clicking on them, Shift adds clips to the selection.
+    #[serde(skip)]
+    pub selected_clips: BTreeSet<ClipId>,
+
+    /// The clips copied with the copy shortcut, they are pasted at the cursor.
+    #[serde(skip)]
+    pub clipboard: Vec<Clip>,
+
+    /// The range of time selected by dragging on the ruler.
+    #[serde(skip)]
+    pub time_selection: Option<Range<Tick>>,
+
+    /// The format of the cursor position displayed next to the ruler.
+    #[serde(default)]
+    pub time_format: TimeFormat,
+
+    /// The range of time which is played over and over again while `looping` is turned on.
+    #[serde(default)]
+    pub loop_range: Option<Range<Tick>>,
+
+    /// Toggled with the loop button of the toolbar.
+    #[serde(default)]
+    pub looping: bool,
+
+    /// Scrolls the playlist during playback, so that the cursor stays visible.
+    #[serde(default)]
+    pub follow_playhead: bool,
+
+    #[serde(default)]
+    pub metronome: MetronomeSettings,
+
+    /// The engine is not running when the application starts, so this is not persisted.
+    #[serde(skip)]
     pub playback_state: PlaybackState,
+
+    /// The clip which has been dragged away from its place in this frame.
+    /// If the clip isnt dropped back into the playlist it gets removed.
+    #[serde(skip)]
+    pub dragged_from: Option<ClipId>,
+
+    /// The track which is being dragged by its label, it is moved to where it is released.
+    #[serde(skip)]
+    pub dragged_track: Option<TrackId>,
+}
+
+impl Default for PlaylistState {
+    fn default() -> Self {
+        Self {
+            tempo: TempoMap::default(),
+            cursor_offset: 0.,
+            grid_offset: Vec2::default(),
+            zoom: PlaylistZoom::default(),
+            tracks: TrackList::default(),
+            clips: ClipStore::default(),
+            patterns: PatternStore::default(),
+            show_patterns: false,
+            edited_pattern: None,
+            snap: SnapGrid::default(),
+            selected_clips: BTreeSet::new(),
+            clipboard: Vec::new(),
+            time_selection: None,
+            time_format: TimeFormat::default(),
+            loop_range: None,
+            looping: false,
+            follow_playhead: false,
+            metronome: MetronomeSettings::default(),
+            playback_state: PlaybackState::default(),
+            dragged_from: None,
+            dragged_track: None,
+        }
+    }
+}
+
+impl PlaylistState {
+    /// The range which is looped by the playback, `None` if looping is turned off.
+    pub fn active_loop(&self) -> Option<Range<Tick>> {
+        self.loop_range.clone().filter(|_| self.looping)
+    }
+
+    /// The samples of the clips, of the patterns and of the instruments, the path of the samples can be changed.
+    pub fn samples_mut(&mut self) -> impl Iterator<Item = &mut SampleInstance> {
+        self.clips
+            .samples_mut()
+            .chain(self.patterns.samples_mut())
+            .chain(self.tracks.samples_mut())
+    }
 }
 
 const BPM_PRESETS: &[f32] = &[
@@ -107,57 +358,201 @@ const BPM_PRESETS: &[f32] = &[
 pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
     let state = &global_state.playlist_panel;
 
+    // The transport can be controlled from the keyboard, unless something else has the focus (e.g. a text field)
+    if ui.memory(|memory| memory.focused().is_none()) {
+        transport_shortcuts(ui, &global_state);
+        clip_shortcuts(ui, &global_state);
+    }
+
+    // The beats which should fill the playlist, this can only be applied once the size of the playlist is known
+    let mut zoom_to = None;
+
     // Draw the main options / tools for this ui
     ui.horizontal(|ui| {
         let current_playback_state = state.read().playback_state.clone();
+        let engine = &global_state.playback;
 
         // Display playback main controls based on current state
         match current_playback_state {
             PlaybackState::Playing => {
                 if ui.button("Pause").clicked() {
+                    engine.pause();
                     state.write().playback_state = PlaybackState::Paused;
                 };
             }
             PlaybackState::Paused => {
                 if ui.button("Unpause").clicked() {
+                    let generation = global_state.history.read().timeline_generation();
+
+                    engine.sync(&state.read(), &global_state.mixer_panel.read(), generation);
+                    engine.play();
                     state.write().playback_state = PlaybackState::Playing;
                 };
             }
             PlaybackState::Stopped => {
-                if ui.button("Play").clicked() {
-                    state.write().playback_state = PlaybackState::Paused;
+                if ui
+                    .button("Play")
+                    .on_hover_text(ui.ctx().format_shortcut(&PLAY_SHORTCUT))
+                    .clicked()
+                {
+                    start_playback(&global_state);
                 }
             }
         }
 
         // Only enable this button if its not stopped
         ui.add_enabled_ui(current_playback_state != PlaybackState::Stopped, |ui| {
-            if ui.button("Stop").clicked() {
-                state.write().playback_state = PlaybackState::Stopped;
+            if ui
+                .button("Stop")
+                .on_hover_text(ui.ctx().format_shortcut(&PLAY_SHORTCUT))
+                .clicked()
+            {
+                stop_playback(&global_state);
             }
         });
 
+        if engine.is_counting_in() {
+            ui.label(RichText::from("Count-in").weak());
+        }
+
+        ui.separator();
+
+        // Turning on looping without a loop range loops the selected time
+        let (looping, can_loop, mut follow_playhead) = {
+            let state = state.read();
+
+            (
+                state.looping,
+                state.loop_range.is_some() || state.time_selection.is_some(),
+                state.follow_playhead,
+            )
+        };
+
+        let loop_button = ui
+            .add_enabled(can_loop, egui::Button::selectable(looping, "Loop"))
+            .on_hover_text("Shift + drag on the ruler to set the looped range.")
+            .on_disabled_hover_text("Select a range of time on the ruler first.");
+
+        if loop_button.clicked() {
+            let mut state = state.write();
+
+            if state.loop_range.is_none() {
+                state.loop_range = state.time_selection.clone();
+            }
+
+            state.looping = !looping;
+
+            // The settings of the playlist are not recorded in the history, but they are still a part of the project
+            global_state.history.write().mark_changed();
+        }
+
+        ui.toggle_value(&mut follow_playhead, "Follow")
+            .on_hover_text("Scroll the playlist along with the cursor during playback.");
+
+        state.write().follow_playhead = follow_playhead;
+
+        let mut metronome = state.read().metronome.clone();
+
+        ui.toggle_value(&mut metronome.enabled, "Metronome")
+            .on_hover_text("Right click to change the sound, the volume and the count-in.")
+            .context_menu(|ui| metronome_settings(ui, &global_state, &mut metronome));
+
+        if state.read().metronome != metronome {
+            state.write().metronome = metronome;
+            global_state.history.write().mark_changed();
+        }
+
+        ui.separator();
+
+        let mut show_patterns = state.read().show_patterns;
+
+        ui.toggle_value(&mut show_patterns, "Patterns")
+            .on_hover_text(
+                "Open the step sequencer, patterns are dragged from it into the playlist.",
+            );
+
+        state.write().show_patterns = show_patterns;
+
+        ui.separator();
+
+        if ui.button("Zoom to fit").clicked() {
+            let state = state.read();
+
+            zoom_to = Some(0.0..state.clips.end(&state.tempo).as_beats());
+        }
+
+        let selection = selection_range(&state.read());
+
+        if ui
+            .add_enabled(selection.is_some(), egui::Button::new("Zoom to selection"))
+            .clicked()
+        {
+            zoom_to = selection;
+        }
+
         ui.separator();
 
-        if ui.button("Patterns").clicked() {};
+        ui.label("snap");
+
+        let mut snap = state.read().snap;
+
+        egui::ComboBox::from_id_salt("playlist_snap")
+            .selected_text(snap.to_string())
+            .show_ui(ui, |ui| {
+                for grid in SnapGrid::iter() {
+                    ui.selectable_value(&mut snap, grid, grid.to_string());
+                }
+            })
+            .response
+            .on_hover_text("Hold Alt while dropping to place clips freely.");
+
+        if state.read().snap != snap {
+            state.write().snap = snap;
+            global_state.history.write().mark_changed();
+        }
 
         ui.label("bpm");
 
-        let playlist_bpm = &mut state.write().bpm;
-        ui.add(egui::Slider::new(playlist_bpm, 10.0..=522.0).fixed_decimals(3))
-            .context_menu(|ui| {
-                ui.label("Presets");
+        // This is the tempo the song starts with, the later tempo changes are set on the ruler
+        let history_group = Id::new("bpm_slider");
+        let before = state.read().tempo.clone();
+        let mut tempo = before.clone();
 
-                ui.separator();
+        let slider = ui.add(egui::Slider::new(&mut tempo.bpm, BPM_RANGE).fixed_decimals(3));
 
-                for bpm in BPM_PRESETS {
-                    if ui.button(format!("{bpm} bpm")).clicked() {
-                        *playlist_bpm = *bpm;
-                    }
+        slider.context_menu(|ui| {
+            ui.label("Presets");
+
+            ui.separator();
+
+            for bpm in BPM_PRESETS {
+                if ui.button(format!("{bpm} bpm")).clicked() {
+                    tempo.bpm = *bpm;
                 }
-            });
+            }
+        });
+
+        if tempo != before {
+            state.write().tempo = tempo.clone();
+
+            global_state.history.write().record_grouped(
+                history_group,
+                "Change tempo",
+                Edit::ChangeTempo {
+                    before,
+                    after: tempo,
+                },
+            );
+        }
+
+        // The whole drag is undone at once
+        if !slider.dragged() {
+            global_state.history.write().close_group(history_group);
+        }
     });
 
+    pattern_editor(_this, ui.ctx(), &global_state);
+
     ui.separator();
 
     // Paint the background black, and draw on top of that
@@ -166,6 +561,16 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
     ui.painter_at(playlist_rect)
         .rect_filled(ui.available_rect_before_wrap(), 0., Color32::BLACK);
 
+    // The ruler takes up the top of the playlist, the tracks are displayed below it
+    let ruler_rect = playlist_rect.with_max_y(playlist_rect.top() + RULER_HEIGHT);
+    let playlist_rect = playlist_rect.with_min_y(ruler_rect.bottom());
+
+    if let Some(beats) = zoom_to {
+        zoom_to_beats(&mut state.write(), playlist_rect, beats);
+    }
+
+    let zoom = state.read().zoom;
+
     // The total grid's offset (the amount the user has scrolled.)
     let grid_offset = state.read().grid_offset;
 
@@ -175,8 +580,22 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
     // Track the positions of the lines drawn so that we can visualize the preview of a sample in the playlist.
     // `first_visible_beat` tells us which absolute beat number `beat_lines[0]` corresponds to,
     // since the vec itself is scroll-relative (index 0 = "first beat currently on screen").
-    let (first_visible_beat, beat_lines) =
-        beat_outlines(ui, playlist_rect, x_offset_ratio, BEAT_WIDTH as f32);
+    let (first_visible_beat, beat_lines) = beat_outlines(
+        ui,
+        playlist_rect,
+        x_offset_ratio,
+        zoom.beat_width(),
+        &state.read().tempo,
+    );
+
+    ruler(ui, &global_state, ruler_rect, playlist_rect, x_offset_ratio);
+
+    // The space after the track labels
+    let usable_playlist_rect =
+        playlist_rect.with_min_x(playlist_rect.min.x + TRACK_LABEL_WIDTH as f32);
+
+    // Dragging on the empty space selects clips, this is allocated before anything else so that everything is on top of it
+    let marquee = ui.allocate_rect(usable_playlist_rect, Sense::click_and_drag());
 
     // Initalize the track lines list with the topmost line first.
     let mut track_lines = vec![[
@@ -201,12 +620,12 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
     while current_height < max_height {
         let y_coord = current_height;
 
-        // Try getting the customization state for the current label
-        let label_customization = get_track_customization(state, idx);
+        // The empty rows after the last track are displayed with the default customization
+        let label_customization = state.read().tracks.customization(idx);
+        let height = zoom.track_height(&label_customization);
 
         let top = (y_coord + y_offset_ratio).max(playlist_rect.top());
-        let bottom =
-            (y_coord + y_offset_ratio + label_customization.height).min(playlist_rect.bottom());
+        let bottom = (y_coord + y_offset_ratio + height).min(playlist_rect.bottom());
 
         let is_visible = !(top >= playlist_rect.bottom() || bottom <= playlist_rect.top());
 
@@ -220,30 +639,29 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
         if is_visible {
             is_first_track_visible = true;
 
-            // Get access to the track customizations
-            let custom_tracks: &mut HashMap<usize, TrackCustomization> =
-                &mut state.write().custom_tracks;
-
             // Draw track labels
             track_label(
                 ui,
+                &global_state,
                 playlist_rect,
                 idx,
                 &label_customization,
                 top,
                 bottom,
-                custom_tracks,
             );
 
             // Draw separator lines
             let separator_line = track_separator(
                 ui,
+                &global_state,
                 playlist_rect,
                 y_offset_ratio,
-                idx,
-                y_coord,
-                &label_customization,
-                custom_tracks,
+                TrackRow {
+                    idx,
+                    y_coord,
+                    height,
+                },
+                zoom.vertical,
             );
 
             // This will automatically set the index to the last visible track's index
@@ -253,28 +671,40 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
         }
 
         // Add the consumed height to the current height
-        current_height += label_customization.height;
+        current_height += height;
 
         // Track indexes too
         idx += 1;
     }
 
-    // The space after the track labels
-    let usable_playlist_rect =
-        playlist_rect.with_min_x(playlist_rect.min.x + TRACK_LABEL_WIDTH as f32);
+    let grid = PlaylistGrid {
+        track_lines: &track_lines,
+        beat_lines: &beat_lines,
+        first_visible_track_idx,
+        first_visible_beat,
+    };
 
     // Render currently present samples in the playlist
     // We should render the samples because when we are creating them we are also allocation responses
     // These responses would steal the input from the user if created after checking for input over the entire playlist.
-    render_samples(
+    let clip_rects = render_samples(
         ui,
-        state,
-        first_visible_track_idx,
+        &global_state,
+        grid,
         last_visible_track_idx,
-        first_visible_beat,
         usable_playlist_rect,
+    );
+
+    select_with_marquee(ui, state, &marquee, &clip_rects, usable_playlist_rect);
+
+    create_note_clip(ui, &global_state, &marquee, grid);
+
+    reorder_tracks(
+        ui,
+        &global_state,
+        playlist_rect,
         &track_lines,
-        &beat_lines,
+        first_visible_track_idx,
     );
 
     // We are going to have multiple layers of responses each capturing something different
@@ -283,110 +713,173 @@ pub fn playlist_ui(_this: &Panel, ui: &mut Ui, global_state: Arc<PanelStates>) {
     let ui_base = ui.allocate_rect(playlist_rect, Sense::hover());
 
     // If there is something dragged over the playlist preview the location of the sample
-    hover_sample(
-        ui,
-        state,
-        playlist_rect,
-        &track_lines,
-        &beat_lines,
-        first_visible_track_idx,
-        &ui_base,
-    );
+    hover_sample(ui, state, playlist_rect, grid, &ui_base);
 
     // Handle the sample if it is dropped into the playlist.
-    drop_sample(
-        _this,
-        ui,
-        state,
-        global_state.clone(),
-        &track_lines,
-        &beat_lines,
-        first_visible_track_idx,
-        first_visible_beat,
-        &ui_base,
-    );
+    drop_sample(_this, ui, state, global_state.clone(), grid, &ui_base);
+
+    // If a sample has been dragged away but it hasnt been dropped back into the playlist, remove it along with the rest of the selection.
+    let dragged_from = state.write().dragged_from.take();
+
+    if let Some(id) = dragged_from {
+        let ids = dragged_clips(&state.read(), id);
+
+        delete_clips(&global_state, ids);
+    }
+
+    // Keep the engine up to date with the playlist, and move the cursor with the audio clock
+    if state.read().playback_state == PlaybackState::Playing {
+        let generation = global_state.history.read().timeline_generation();
+
+        global_state
+            .playback
+            .sync(&state.read(), &global_state.mixer_panel.read(), generation);
+
+        let mut state = state.write();
+
+        state.cursor_offset = global_state.playback.position() as f32;
+
+        if state.follow_playhead {
+            follow_cursor(&mut state, playlist_rect);
+        }
+
+        ui.ctx().request_repaint();
+    }
 
     // Get cursor position (offest)
     let cursor_offset = state.read().cursor_offset;
 
     // Draw cursor on playlist
-    draw_cursor(ui, playlist_rect, grid_offset, cursor_offset);
+    draw_cursor(
+        ui,
+        playlist_rect.union(ruler_rect),
+        x_offset_ratio,
+        zoom.beat_width(),
+        cursor_offset,
+    );
 
-    // Capture scroll if hovered
+    // Capture scroll if hovered, Ctrl zooms horizontally and Alt zooms vertically instead
     if ui_base.hovered() {
-        let scroll_delta = ui.input(|reader| reader.smooth_scroll_delta());
-        state.write().grid_offset = grid_offset.add(scroll_delta * 200.).min(Vec2::default());
+        let (scroll_delta, zoom_delta, alt) = ui.input(|reader| {
+            (
+                reader.smooth_scroll_delta(),
+                reader.zoom_delta(),
+                reader.modifiers.alt,
+            )
+        });
+
+        if zoom_delta != 1.
+            && let Some(pointer) = ui_base.hover_pos()
+        {
+            zoom_horizontally(&mut state.write(), playlist_rect, pointer.x, zoom_delta);
+        } else if alt {
+            let zoom = &mut state.write().zoom;
+
+            zoom.vertical = (zoom.vertical * (scroll_delta.y * 0.005).exp())
+                .clamp(*VERTICAL_ZOOM.start(), *VERTICAL_ZOOM.end());
+        } else {
+            state.write().grid_offset = grid_offset.add(scroll_delta * 200.).min(Vec2::default());
+        }
     }
 }
 
+/// Where the visible tracks and beats of the playlist have been drawn in this frame.
+#[derive(Debug, Clone, Copy)]
+struct PlaylistGrid<'a> {
+    /// The lines between the visible tracks, the first one is the top of the playlist.
+    track_lines: &'a [[Pos2; 2]],
+    beat_lines: &'a [[Pos2; 2]],
+    first_visible_track_idx: usize,
+    first_visible_beat: usize,
+}
+
+/// Draws the visible clips and handles their input, returns where the clips have been drawn.
 fn render_samples(
     ui: &mut Ui,
-    state: &RwLock<PlaylistState>,
-    before_first_visible_track_idx: usize,
+    global_state: &PanelStates,
+    grid: PlaylistGrid,
     last_visible_track_idx: usize,
-    first_visible_beat: usize,
     playlist_rect: Rect,
-    track_lines: &[[Pos2; 2]],
-    beat_lines: &[[Pos2; 2]],
-) {
-    // Iterate over the samples and decide which one is in frame.
-    let samples = state.read().samples.clone();
-
-    for (pos, sample) in samples {
-        // Check if the track is visible based on the track
-        if !(pos.track >= before_first_visible_track_idx && pos.track <= last_visible_track_idx) {
-            continue;
-        }
-
-        let line_idx = pos.beat as i64 - first_visible_beat as i64;
-
-        let start_pos = if line_idx >= 0 && (line_idx as usize) < beat_lines.len() {
-            beat_lines[line_idx as usize][0].x
-        } else if !beat_lines.is_empty() {
-            beat_lines[0][0].x + (line_idx as f32) * BEAT_WIDTH as f32
-        } else {
-            continue;
-        };
-
-        // Get track customization
-        let _track_customization = get_track_customization(state, pos.track);
+) -> Vec<(ClipId, Rect)> {
+    let PlaylistGrid {
+        track_lines,
+        beat_lines,
+        first_visible_track_idx: before_first_visible_track_idx,
+        first_visible_beat,
+    } = grid;
+    let state = &global_state.playlist_panel;
+    let history = &global_state.history;
+
+    let Some(first_beat_line) = beat_lines.first() else {
+        return Vec::new();
+    };
+
+    // Only the clips which are on the screen are rendered, the later ones are drawn on top of the earlier ones.
+    let (clips, patterns, tempo, beat_width) = {
+        let state = state.read();
+        let visible_ticks = Tick::from_beats(first_visible_beat as f64)
+            ..Tick::from_beats((first_visible_beat + beat_lines.len()) as f64);
+
+        (
+            state
+                .clips
+                .query(
+                    (before_first_visible_track_idx..=last_visible_track_idx)
+                        .filter_map(|row| state.tracks.get(row).map(|track| track.id)),
+                    visible_ticks,
+                    &state.tempo,
+                )
+                .filter_map(|clip| Some((state.tracks.row(clip.track)?, clip.clone())))
+                .collect::<Vec<_>>(),
+            state.patterns.clone(),
+            state.tempo.clone(),
+            state.zoom.beat_width(),
+        )
+    };
+
+    let mut clip_rects = Vec::with_capacity(clips.len());
+
+    for (row, clip) in clips {
+        let (name, color) = clip_appearance(&patterns, &clip);
+
+        // The clips do not have to start on a beat line
+        let start_pos = tick_to_x(
+            first_beat_line[0].x,
+            first_visible_beat,
+            beat_width,
+            clip.start,
+        );
 
         // Calculate rectangle length
-        let bps = state.read().bpm / 60.;
-
-        // This is basically secs / bps * beat_width
-        let rectangle_length =
-            symphonia::core::units::Time::from_millis(sample.properties.length as i64).as_secs()
-                as f32
-                / bps
-                * BEAT_WIDTH as f32;
-
-        // If the sample isn't long enough to reach onto the screen, skip it.
-        if start_pos + rectangle_length < playlist_rect.left() {
-            continue;
-        }
+        let rectangle_length = clip.length_in_beats(&tempo) as f32 * beat_width;
 
         // Create the rect where the sample might be rendered.
         let sample_rect = Rect::from_min_max(
             Pos2 {
                 x: start_pos,
-                y: (track_lines[pos.track - before_first_visible_track_idx][0].y),
+                y: (track_lines[row - before_first_visible_track_idx][0].y),
             },
             Pos2 {
                 x: (start_pos + rectangle_length),
-                y: (track_lines[pos.track - before_first_visible_track_idx + 1][0].y),
+                y: (track_lines[row - before_first_visible_track_idx + 1][0].y),
             },
         );
 
         // Draw sample rect
         ui.painter()
             .with_clip_rect(playlist_rect)
-            .rect_filled(sample_rect, 0., sample.color);
+            .rect_filled(sample_rect, 0., color);
+
+        // Reversed clips are marked in their label
+        let label = match clip.shape.reverse {
+            true => format!("{name} (reversed)"),
+            false => name,
+        };
 
         // Create galley for sample label
         let galley = ui.fonts_mut(|f| {
             f.layout(
-                sample.name.clone(),
+                label,
                 egui::FontId::proportional(12.0),
                 egui::Color32::WHITE,
                 sample_rect.width(),
@@ -405,334 +898,2077 @@ fn render_samples(
         // Draw the waveform of the sample
         let waveform_rect = sample_rect.shrink2(vec2(0., galley.rect.height()));
 
-        // Only display the waveform if we actually have smth to display
-        if let Some(waveform) = &sample.waveform_map {
-            // Decide each columns width
-            let column_width = waveform_rect.width() / waveform.len() as f32;
+        match &clip.source {
+            ClipSource::Sample(sample) => {
+                // Only display the waveform if we actually have smth to display
+                if let Some(waveform) = &sample.waveform_map {
+                    draw_waveform(ui, playlist_rect, waveform_rect, waveform, &clip);
+                }
+
+                draw_fades(ui, playlist_rect, sample_rect, &clip);
+            }
+            ClipSource::Pattern(id) => {
+                if let Some(pattern) = patterns.get(*id) {
+                    draw_steps(ui, playlist_rect, waveform_rect, pattern, &clip);
+                }
+            }
+            ClipSource::Notes(notes) => {
+                draw_notes(ui, playlist_rect, waveform_rect, notes, &clip);
+            }
+        }
 
-            let baseline_maximum_offset = waveform_rect.height() / 2.0;
-            let middle_y = waveform_rect.top() + baseline_maximum_offset;
+        // Outline the selected clips
+        if state.read().selected_clips.contains(&clip.id) {
+            ui.painter().with_clip_rect(playlist_rect).rect_stroke(
+                sample_rect,
+                0.,
+                Stroke::new(2.0_f32, SELECTION_COLOR),
+                egui::StrokeKind::Inside,
+            );
+        }
 
-            // Fetch positions over sample
-            let start = Pos2::new(waveform_rect.left(), middle_y);
-            let end = Pos2::new(waveform_rect.right(), middle_y);
+        // The clip is moved to where it is dropped, or removed if it is dropped outside of the playlist
+        sample_response.dnd_set_drag_payload(clip.id);
 
-            // Draw a centerline serving as the indication for silence.
-            ui.painter()
-                .with_clip_rect(playlist_rect)
-                .line([start, end].to_vec(), Stroke::new(1.0_f32, Color32::WHITE));
+        let shift = ui.input(|input| input.modifiers.shift);
 
-            // Iter over all the samples and draw them
-            // We are going to ratio this based on the highest/lowest value the output can get which is 1.0 and -1.0
-            // There for the top of this rect is going to serve as 1.0 and the bottom is -1.0
-            let mut idx = 0;
-            let scale_reference = waveform
-                .iter()
-                .flat_map(|[min, max]| [min.abs(), max.abs()])
-                .fold(0.0_f32, f32::max)
-                .max(f32::EPSILON);
+        // Grabbing a clip which is not selected selects it, so that the selection is what gets moved
+        if sample_response.drag_started() {
+            let mut state = state.write();
 
-            // Draw all of the columns on the screen
-            while idx < waveform.len() {
-                // The maximum values goes on top of the baseline and the minimum below it
-                let [min, max] = waveform[idx];
+            if !state.selected_clips.contains(&clip.id) {
+                if !shift {
+                    state.selected_clips.clear();
+                }
 
-                // The x coordinate we are operation on
-                let x_offset = column_width * idx as f32;
+                state.selected_clips.insert(clip.id);
+            }
+        }
 
-                let x = waveform_rect.left() + x_offset;
+        // Remember which clip has been dragged, it is going to be moved or removed when dropped
+        if sample_response.drag_stopped() {
+            let mut state = state.write();
 
-                // Starting location of the column
-                let baseline = Pos2::new(x, middle_y);
+            state.dragged_from = Some(clip.id);
+        }
 
-                let normalized_max = max / scale_reference;
-                let normalized_min = min / scale_reference;
+        // Note clips are edited in the piano roll
+        if sample_response.double_clicked() && clip.source.notes().is_some() {
+            global_state.piano_roll_panel.write().open(&clip);
+        }
 
-                // Height of the column we are drawing
-                let height_max = -normalized_max * baseline_maximum_offset;
-                let height_min = -normalized_min * baseline_maximum_offset;
+        // Clicking on a clip selects only that clip, Shift adds it to the selection or removes it from it
+        if sample_response.clicked() {
+            let mut state = state.write();
 
-                // Draw max
-                ui.painter().with_clip_rect(playlist_rect).line(
-                    [baseline, Pos2::new(x, middle_y + height_max)].to_vec(),
-                    Stroke::new(column_width, Color32::WHITE),
-                );
-                // Draw min
-                ui.painter().with_clip_rect(playlist_rect).line(
-                    [baseline, Pos2::new(x, middle_y + height_min)].to_vec(),
-                    Stroke::new(column_width, Color32::WHITE),
-                );
+            if !shift {
+                state.selected_clips.clear();
+            }
 
-                // Increment index
-                idx += 1;
+            if !state.selected_clips.remove(&clip.id) || !shift {
+                state.selected_clips.insert(clip.id);
             }
         }
 
-        // If the sample is dragged, simulate a dnd again
-        sample_response.dnd_set_drag_payload(sample.clone());
+        // The clip can be split where its context menu has been opened
+        let menu_position = Id::new(("clip_menu_position", clip.id));
 
-        // Remove the old position of the sample
-        if sample_response.drag_stopped() {
-            state.write().samples.swap_remove(&pos);
+        if sample_response.secondary_clicked()
+            && let Some(pointer) = sample_response.interact_pointer_pos()
+        {
+            let beats =
+                clip.start.as_beats() + ((pointer.x - sample_rect.left()) / beat_width) as f64;
+            let position = snap_position(ui, state, beats);
+
+            ui.data_mut(|data| data.insert_temp(menu_position, position));
         }
+
+        clip_menu(&sample_response, global_state, &clip, menu_position);
+
+        // The handles are allocated after the clip, so that they take the input over it
+        clip_handles(
+            ui,
+            state,
+            history,
+            &clip,
+            sample_rect,
+            playlist_rect,
+            &tempo,
+        );
+
+        clip_rects.push((clip.id, sample_rect));
     }
+
+    clip_rects
 }
 
-fn drop_sample(
-    this: &Panel,
-    ui: &mut Ui,
-    state: &RwLock<PlaylistState>,
-    global_state: Arc<PanelStates>,
-    track_lines: &[[Pos2; 2]],
-    beat_lines: &[[Pos2; 2]],
-    first_visible_track_idx: usize,
-    first_visible_beat: usize,
-    ui_base: &egui::Response,
+/// Draws the part of the waveform which is played by the clip, the columns follow the gain and the fades of the clip.
+fn draw_waveform(
+    ui: &Ui,
+    playlist_rect: Rect,
+    waveform_rect: Rect,
+    waveform: &[[f32; 2]],
+    clip: &Clip,
 ) {
-    if let Some(payload) = ui_base.dnd_release_payload::<SampleInstance>() {
-        // Get cursor position
-        if let Some(cursor) = ui.input(|i| i.pointer.hover_pos()) {
-            // Find starting beat position on the x axis (index into beat_lines)
-            let (_, relative_beat_pos) =
-                find_value_inbetween(beat_lines.iter().map(|v| v[0].x), cursor.x)
-                    .unwrap_or_default();
-
-            // Find starting beat position on the y axis
-            let (_, relative_track_pos) =
-                find_value_inbetween(track_lines.iter().map(|v| v[0].y), cursor.y)
-                    .unwrap_or_default();
-
-            // We have to subtract one from the relative position since the first track's position is out of bounds (its the topmost line of the whole playlist)
-            let absolute_track_idx = first_visible_track_idx + relative_track_pos - 1;
-
-            let absolute_beat_pos = relative_beat_pos.max(1) - 1 + first_visible_beat;
-
-            // If anything gets dropped into the "workspace" aka the playlist then add it to the workspace files
-            // Look up if we have already stored this one sample
-            let query = global_state
-                .media_panel
-                .read()
-                .workspace_selector
-                .workspace_samples
-                .get(&payload.path)
-                .cloned();
-
-            // Check if we already have this sample in the workspace tab
-            let sample_instance = if let Some(sample_info) = query {
-                global_state
-                    .media_panel
-                    .write()
-                    .workspace_selector
-                    .workspace_samples
-                    .get(&payload.path);
-
-                // If we do have this sample then insert into playlist accordingly
-                SampleInstance {
-                    name: sample_info.alias.clone(),
-                    color: {
-                        // If the color of this sample has been modified, the new color should be displayed when reinserted.
-                        if payload.color != sample_info.color {
-                            payload.color
-                        } else {
-                            sample_info.color
-                        }
-                    },
-                    path: payload.path.clone(),
-                    properties: payload.properties.clone(),
-                    waveform_map: sample_info.waveform_map,
-                }
-            }
-            // Initalize new sample in workspace
-            // Generate a new random color for it
-            else {
-                this.toasts.lock().add(
-                    Toast::new()
-                        .kind(egui_toast::ToastKind::Info)
-                        .text(format!("Imported sample `{}`", payload.name)),
-                );
+    let baseline_maximum_offset = waveform_rect.height() / 2.0;
+    let middle_y = waveform_rect.top() + baseline_maximum_offset;
 
-                // Map the waveforms of the sample if it hadnt been inserted yet
-                let waveform_map = display_error_as_toast(
-                    generate_sample_waveform(&payload.path),
-                    ToastStyle::default(),
-                    this.toasts.clone(),
-                );
-
-                let random_color = random_color_with_opacity(120);
-
-                global_state
-                    .media_panel
-                    .write()
-                    .workspace_selector
-                    .workspace_samples
-                    .insert(
-                        payload.path.clone(),
-                        WorkspaceSampleAttributes {
-                            alias: payload.name.clone(),
-
-                            // All samples have their color synced by default.
-                            is_color_synced: true,
-                            color: random_color,
-                            waveform_map: waveform_map.clone(),
-                        },
-                    );
+    // Fetch positions over sample
+    let start = Pos2::new(waveform_rect.left(), middle_y);
+    let end = Pos2::new(waveform_rect.right(), middle_y);
 
-                SampleInstance {
-                    name: payload.name.clone(),
-                    color: random_color,
-                    path: payload.path.clone(),
-                    properties: payload.properties.clone(),
-                    waveform_map: waveform_map,
-                }
+    // Draw a centerline serving as the indication for silence.
+    ui.painter()
+        .with_clip_rect(playlist_rect)
+        .line([start, end].to_vec(), Stroke::new(1.0_f32, Color32::WHITE));
+
+    // The columns are scaled to the loudest part of the whole sample, so trimming a clip does not change the scale
+    let scale_reference = waveform
+        .iter()
+        .flat_map(|[min, max]| [min.abs(), max.abs()])
+        .fold(0.0_f32, f32::max)
+        .max(f32::EPSILON);
+
+    // Every column covers the same amount of the whole sample, only the ones played by the clip are drawn
+    let sample_length = clip.sample_length();
+    let column_seconds = sample_length / waveform.len().max(1) as f64;
+    let column_width = (column_seconds / clip.length) as f32 * waveform_rect.width();
+
+    for (idx, [min, max]) in waveform.iter().enumerate() {
+        let seconds = clip
+            .shape
+            .clip_position((idx as f64 + 0.5) * column_seconds, sample_length);
+
+        if !(0.0..clip.length).contains(&seconds) {
+            continue;
+        }
+
+        // The x coordinate we are operation on
+        let x = waveform_rect.left() + (seconds / clip.length) as f32 * waveform_rect.width();
+
+        if x < playlist_rect.left() || x > playlist_rect.right() {
+            continue;
+        }
+
+        // Starting location of the column
+        let baseline = Pos2::new(x, middle_y);
+        let gain = clip.shape.gain_at(seconds, clip.length);
+
+        // The maximum values goes on top of the baseline and the minimum below it, louder clips are cut off at the edges
+        let height_max = -(max / scale_reference * gain).clamp(-1., 1.) * baseline_maximum_offset;
+        let height_min = -(min / scale_reference * gain).clamp(-1., 1.) * baseline_maximum_offset;
+
+        // Draw max
+        ui.painter().with_clip_rect(playlist_rect).line(
+            [baseline, Pos2::new(x, middle_y + height_max)].to_vec(),
+            Stroke::new(column_width, Color32::WHITE),
+        );
+        // Draw min
+        ui.painter().with_clip_rect(playlist_rect).line(
+            [baseline, Pos2::new(x, middle_y + height_min)].to_vec(),
+            Stroke::new(column_width, Color32::WHITE),
+        );
+    }
+}
+
+/// Draws the curves of the fades of the clip, from the bottom of the clip (silence) to its top (full gain).
+fn draw_fades(ui: &Ui, playlist_rect: Rect, sample_rect: Rect, clip: &Clip) {
+    let fades = [(clip.shape.fade_in, true), (clip.shape.fade_out, false)];
+
+    for (fade, is_fade_in) in fades {
+        if fade.length <= 0. {
+            continue;
+        }
+
+        let points = (0..=FADE_CURVE_POINTS)
+            .map(|point| {
+                let progress = point as f32 / FADE_CURVE_POINTS as f32;
+                let seconds = match is_fade_in {
+                    true => progress as f64 * fade.length,
+                    false => clip.length - progress as f64 * fade.length,
+                };
+
+                Pos2::new(
+                    sample_rect.left() + (seconds / clip.length) as f32 * sample_rect.width(),
+                    sample_rect.bottom() - fade.curve.gain(progress) * sample_rect.height(),
+                )
+            })
+            .collect();
+
+        ui.painter()
+            .with_clip_rect(playlist_rect)
+            .line(points, Stroke::new(STROKE_WIDTH, FADE_LINE));
+    }
+}
+
+/// Draws the steps played by the pattern clip, every row of the pattern gets an equal part of the height of the clip.
+fn draw_steps(ui: &Ui, playlist_rect: Rect, steps_rect: Rect, pattern: &Pattern, clip: &Clip) {
+    if pattern.rows.is_empty() || clip.length <= 0. {
+        return;
+    }
+
+    let row_height = steps_rect.height() / pattern.rows.len() as f32;
+    let step_width = (STEP_TICKS as f64 / PPQ as f64 / clip.length) as f32 * steps_rect.width();
+
+    for hit in pattern.hits(clip.shape.offset, clip.length) {
+        let top = steps_rect.top() + hit.row as f32 * row_height;
+        let left = steps_rect.left() + (hit.beat / clip.length) as f32 * steps_rect.width();
+
+        // Quieter steps are drawn shorter
+        let rect = Rect::from_min_size(
+            Pos2::new(left, top + row_height * (1. - hit.velocity.clamp(0.1, 1.))),
+            vec2(
+                (step_width - 1.).max(1.),
+                row_height * hit.velocity.clamp(0.1, 1.),
+            ),
+        );
+
+        ui.painter()
+            .with_clip_rect(playlist_rect)
+            .with_clip_rect(steps_rect)
+            .rect_filled(rect, 0., Color32::WHITE);
+    }
+}
+
+/// Draws the notes played by the note clip, the keys between the lowest and the highest note fill the height of the clip.
+fn draw_notes(
+    ui: &Ui,
+    playlist_rect: Rect,
+    notes_rect: Rect,
+    sequence: &NoteSequence,
+    clip: &Clip,
+) {
+    let played = sequence.played(clip.shape.offset, clip.length);
+
+    let (Some(lowest), Some(highest)) = (
+        played.iter().map(|note| note.key).min(),
+        played.iter().map(|note| note.key).max(),
+    ) else {
+        return;
+    };
+
+    if clip.length <= 0. {
+        return;
+    }
+
+    let key_height = notes_rect.height() / (highest - lowest + 1) as f32;
+
+    for note in played {
+        let top = notes_rect.top() + (highest - note.key) as f32 * key_height;
+        let left = notes_rect.left() + (note.beat / clip.length) as f32 * notes_rect.width();
+        let width = (note.length / clip.length) as f32 * notes_rect.width();
+
+        let rect = Rect::from_min_size(
+            Pos2::new(left, top),
+            vec2((width - 1.).max(1.), key_height.clamp(1., 6.)),
+        );
+
+        ui.painter()
+            .with_clip_rect(playlist_rect)
+            .with_clip_rect(notes_rect)
+            .rect_filled(rect, 0., Color32::WHITE);
+    }
+}
+
+/// The name and the color a clip is displayed with, pattern clips look like their pattern.
+fn clip_appearance(patterns: &PatternStore, clip: &Clip) -> (String, Color32) {
+    match &clip.source {
+        ClipSource::Sample(sample) => (sample.name.clone(), sample.color),
+        ClipSource::Pattern(id) => patterns
+            .get(*id)
+            .map(|pattern| (pattern.name.clone(), pattern.color))
+            .unwrap_or_default(),
+        ClipSource::Notes(notes) => (notes.name.clone(), notes.color),
+    }
+}
+
+/// The handles of the clip: its edges trim it, the squares in its top corners drag its fades and the one in its middle changes its gain.
+/// Pattern clips do not have fades, so they only have the trimming and the gain handles.
+/// A whole drag of a handle is a single entry in the history.
+fn clip_handles(
+    ui: &mut Ui,
+    state: &RwLock<PlaylistState>,
+    history: &RwLock<History>,
+    clip: &Clip,
+    sample_rect: Rect,
+    playlist_rect: Rect,
+    tempo: &TempoMap,
+) {
+    // Only the visible part of the clip has handles
+    let clip_rect = sample_rect.intersect(playlist_rect);
+
+    if clip_rect.width() <= 0. {
+        return;
+    }
+
+    let history_group = Id::new(("clip_handle", clip.id));
+    let beat_width = state.read().zoom.beat_width();
+    let seconds_to_x = |seconds: f64| {
+        sample_rect.left() + tempo.beats_in(clip.start.as_beats(), seconds) as f32 * beat_width
+    };
+    let pointer_beats =
+        |x: f32| clip.start.as_beats() + ((x - sample_rect.left()) / beat_width) as f64;
+
+    let corner = |x: f32| {
+        Rect::from_center_size(
+            Pos2::new(x, clip_rect.top() + CLIP_HANDLE_SIZE / 2.),
+            Vec2::splat(CLIP_HANDLE_SIZE),
+        )
+    };
+
+    let trim_start = ui.allocate_rect(
+        Rect::from_x_y_ranges(
+            clip_rect.left()..=clip_rect.left() + CLIP_HANDLE_SIZE,
+            clip_rect.top() + CLIP_HANDLE_SIZE..=clip_rect.bottom(),
+        ),
+        Sense::drag(),
+    );
+    let trim_end = ui.allocate_rect(
+        Rect::from_x_y_ranges(
+            clip_rect.right() - CLIP_HANDLE_SIZE..=clip_rect.right(),
+            clip_rect.top() + CLIP_HANDLE_SIZE..=clip_rect.bottom(),
+        ),
+        Sense::drag(),
+    );
+
+    let gain_rect = corner(clip_rect.center().x);
+    let gain = ui.allocate_rect(gain_rect, Sense::click_and_drag());
+
+    // The fade handles are clamped into the clip, so that they can be grabbed even without a fade
+    let fade_in_x = seconds_to_x(clip.shape.fade_in.length)
+        .clamp(clip_rect.left() + CLIP_HANDLE_SIZE / 2., clip_rect.right());
+    let fade_out_x = seconds_to_x(clip.length - clip.shape.fade_out.length)
+        .clamp(clip_rect.left(), clip_rect.right() - CLIP_HANDLE_SIZE / 2.);
+
+    let has_fades = clip.source.sample().is_some();
+    let fade_sense = match has_fades {
+        true => Sense::drag(),
+        false => Sense::hover(),
+    };
+
+    let fade_in_rect = corner(fade_in_x);
+    let fade_out_rect = corner(fade_out_x);
+    let fade_in = ui.allocate_rect(fade_in_rect, fade_sense);
+    let fade_out = ui.allocate_rect(fade_out_rect, fade_sense);
+
+    let handles = match has_fades {
+        true => vec![gain_rect, fade_in_rect, fade_out_rect],
+        false => vec![gain_rect],
+    };
+
+    for rect in handles {
+        ui.painter()
+            .with_clip_rect(clip_rect)
+            .rect_filled(rect, 1., CLIP_HANDLE);
+    }
+
+    let mut after = clip.clone();
+    let mut label = "";
+
+    if let Some(pointer) = trim_start.interact_pointer_pos()
+        && trim_start.dragged()
+    {
+        after.trim_start(snap_position(ui, state, pointer_beats(pointer.x)), tempo);
+        label = "Trim clip";
+    }
+
+    if let Some(pointer) = trim_end.interact_pointer_pos()
+        && trim_end.dragged()
+    {
+        after.trim_end(snap_position(ui, state, pointer_beats(pointer.x)), tempo);
+        label = "Trim clip";
+    }
+
+    // The fades are not snapped to the grid, they are measured in seconds from the edges of the clip
+    if let Some(pointer) = fade_in.interact_pointer_pos()
+        && fade_in.dragged()
+    {
+        let start = tempo.seconds_at(clip.start.as_beats());
+
+        after.shape.fade_in.length = tempo.seconds_at(pointer_beats(pointer.x).max(0.)) - start;
+        after.clamp_fades();
+        label = "Fade clip";
+    }
+
+    if let Some(pointer) = fade_out.interact_pointer_pos()
+        && fade_out.dragged()
+    {
+        let end = tempo.seconds_at(clip.start.as_beats()) + clip.length;
+
+        after.shape.fade_out.length = end - tempo.seconds_at(pointer_beats(pointer.x).max(0.));
+
+        // The fade out is clamped first here, so that dragging it does not shorten itself
+        after.shape.fade_out.length = after.shape.fade_out.length.clamp(0., clip.length);
+        after.shape.fade_in.length = after
+            .shape
+            .fade_in
+            .length
+            .min(clip.length - after.shape.fade_out.length);
+        label = "Fade clip";
+    }
+
+    if gain.dragged() {
+        after.shape.gain =
+            (clip.shape.gain - gain.drag_delta().y * GAIN_DRAG_SPEED).clamp(0., MAX_CLIP_GAIN);
+        label = "Change clip gain";
+    }
+
+    let changed = (after.start, after.length, after.shape) != (clip.start, clip.length, clip.shape);
+
+    if gain.double_clicked() && clip.shape.gain != 1. {
+        after.shape.gain = 1.;
+
+        state.write().clips.insert_clip(after.clone());
+        history.write().record(
+            "Reset clip gain",
+            Edit::ChangeClip {
+                before: clip.clone(),
+                after,
+            },
+        );
+    } else if changed {
+        state.write().clips.insert_clip(after.clone());
+        history.write().record_grouped(
+            history_group,
+            label,
+            Edit::ChangeClip {
+                before: clip.clone(),
+                after,
+            },
+        );
+    }
+
+    if [&trim_start, &trim_end, &fade_in, &fade_out, &gain]
+        .iter()
+        .any(|handle| handle.drag_stopped())
+    {
+        history.write().close_group(history_group);
+    }
+
+    // Indicate what the handles do
+    trim_start.on_hover_cursor(egui::CursorIcon::ResizeColumn);
+    trim_end.on_hover_cursor(egui::CursorIcon::ResizeColumn);
+
+    if has_fades {
+        fade_in
+            .on_hover_cursor(egui::CursorIcon::ResizeHorizontal)
+            .on_hover_text("Fade in");
+        fade_out
+            .on_hover_cursor(egui::CursorIcon::ResizeHorizontal)
+            .on_hover_text("Fade out");
+    }
+    gain.on_hover_cursor(egui::CursorIcon::ResizeVertical)
+        .on_hover_text(format!("Gain {}", format_decibels(clip.shape.gain)));
+}
+
+/// The context menu of the clip, every modification made while the menu is open is a single entry in the history.
+/// The clip can be split at the position the menu has been opened on.
+fn clip_menu(
+    response: &egui::Response,
+    global_state: &PanelStates,
+    clip: &Clip,
+    menu_position: Id,
+) {
+    let state = &global_state.playlist_panel;
+    let history = &global_state.history;
+    let history_group = Id::new(("clip_menu", clip.id));
+    let mut after = clip.clone();
+
+    let position = response
+        .ctx
+        .data(|data| data.get_temp::<Tick>(menu_position))
+        .unwrap_or(clip.start);
+
+    let popup = egui::Popup::context_menu(response)
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside);
+
+    let menu = popup.show(|ui| {
+        let (name, _) = clip_appearance(&state.read().patterns, clip);
+
+        ui.label(RichText::from(name).weak());
+
+        let can_split = {
+            let state = state.read();
+
+            clip.split(position, ClipId::default(), &state.tempo)
+                .is_some()
+        };
+
+        if ui
+            .add_enabled(can_split, egui::Button::new("Split here"))
+            .clicked()
+        {
+            split_clips(global_state, [clip.id], position);
+            ui.close();
+
+            return;
+        }
+
+        if clip.source.notes().is_some() && ui.button("Edit notes").clicked() {
+            global_state.piano_roll_panel.write().open(clip);
+            ui.close();
+        }
+
+        if let ClipSource::Pattern(id) = clip.source {
+            if ui.button("Edit pattern").clicked() {
+                let mut state = state.write();
+
+                state.show_patterns = true;
+                state.edited_pattern = Some(id);
+                ui.close();
+            }
+
+            if ui
+                .button("Make unique")
+                .on_hover_text("Play a copy of the pattern, so that it can be edited on its own.")
+                .clicked()
+            {
+                make_pattern_unique(global_state, clip);
+                ui.close();
+
+                return;
+            }
+        }
+
+        ui.separator();
+
+        if clip.source.sample().is_some() {
+            let mut reverse = clip.shape.reverse;
+
+            if ui.checkbox(&mut reverse, "Reverse").changed() {
+                after.set_reverse(reverse);
+            }
+        }
+
+        ui.add(
+            egui::Slider::new(&mut after.shape.gain, 0.0..=MAX_CLIP_GAIN)
+                .text("Gain")
+                .custom_formatter(|gain, _| format_decibels(gain as f32)),
+        );
+
+        if clip.source.sample().is_none() {
+            return;
+        }
+
+        ui.separator();
+
+        for (text, fade) in [
+            ("Fade in", &mut after.shape.fade_in),
+            ("Fade out", &mut after.shape.fade_out),
+        ] {
+            ui.horizontal(|ui| {
+                ui.label(text);
+                ui.add(
+                    egui::DragValue::new(&mut fade.length)
+                        .range(0.0..=clip.length)
+                        .speed(0.01)
+                        .suffix(" s"),
+                );
+
+                egui::ComboBox::from_id_salt((text, clip.id))
+                    .selected_text(fade.curve.to_string())
+                    .show_ui(ui, |ui| {
+                        for curve in FadeCurve::iter() {
+                            ui.selectable_value(&mut fade.curve, curve, curve.to_string());
+                        }
+                    });
+            });
+        }
+
+        after.clamp_fades();
+    });
+
+    if after.shape != clip.shape {
+        state.write().clips.insert_clip(after.clone());
+        history.write().record_grouped(
+            history_group,
+            "Change clip",
+            Edit::ChangeClip {
+                before: clip.clone(),
+                after,
+            },
+        );
+    }
+
+    if menu.is_none() {
+        history.write().close_group(history_group);
+    }
+}
+
+/// Points the clip to a copy of its pattern, the copy is named after the pattern.
+fn make_pattern_unique(global_state: &PanelStates, clip: &Clip) {
+    let edits = {
+        let mut state = global_state.playlist_panel.write();
+
+        let Some(original) = clip
+            .source
+            .pattern()
+            .and_then(|id| state.patterns.get(id).cloned())
+        else {
+            return;
+        };
+
+        let mut copy = state.patterns.create(original.color);
+
+        copy.name = format!("{} (copy)", original.name);
+        copy.set_step_count(original.step_count());
+        copy.rows = original.rows;
+
+        vec![
+            Edit::InsertPattern {
+                pattern: copy.clone(),
+            },
+            Edit::ChangeClip {
+                before: clip.clone(),
+                after: Clip {
+                    source: ClipSource::Pattern(copy.id),
+                    ..clip.clone()
+                },
+            },
+        ]
+    };
+
+    apply_edits(global_state, "Make pattern unique", edits);
+}
+
+/// Double clicking on the empty space of a track creates a note clip which is a bar long, the clip is opened in the piano roll.
+fn create_note_clip(
+    ui: &Ui,
+    global_state: &PanelStates,
+    marquee: &egui::Response,
+    grid: PlaylistGrid,
+) {
+    let PlaylistGrid {
+        track_lines,
+        beat_lines,
+        first_visible_track_idx,
+        first_visible_beat,
+    } = grid;
+    let state = &global_state.playlist_panel;
+
+    if !marquee.double_clicked() {
+        return;
+    }
+
+    let Some(pointer) = marquee.interact_pointer_pos() else {
+        return;
+    };
+
+    let Some(start) = pointer_position(ui, state, pointer.x, beat_lines, first_visible_beat) else {
+        return;
+    };
+
+    let Some((_, relative_track_pos)) =
+        find_value_inbetween(track_lines.iter().map(|v| v[0].y), pointer.y)
+    else {
+        return;
+    };
+
+    let row = first_visible_track_idx + relative_track_pos - 1;
+
+    let (clip, edits) = {
+        let mut state = state.write();
+        let (tracks, mut edits) = tracks_until(&mut state, row);
+
+        let bar = state.tempo.bar_at(start);
+        let clip = Clip {
+            id: state.clips.allocate_id(),
+            track: tracks[row],
+            start,
+            length: Tick(bar.meter.bar_ticks()).as_beats(),
+            source: ClipSource::Notes(NoteSequence::new(
+                String::from("Notes"),
+                random_color_with_opacity(120),
+            )),
+            shape: ClipShape::default(),
+        };
+
+        state.selected_clips.clear();
+        state.selected_clips.insert(clip.id);
+
+        edits.push(Edit::PlaceClip {
+            clip: clip.clone(),
+            imported: None,
+        });
+
+        (clip, edits)
+    };
+
+    apply_edits(global_state, "Create note clip", edits);
+
+    global_state.piano_roll_panel.write().open(&clip);
+}
+
+/// The payload released over the response.
+/// Payloads of other types are left alone, taking the payload would remove it even if its type does not match.
+fn released_payload<T: std::any::Any + Send + Sync>(
+    ui: &Ui,
+    response: &egui::Response,
+) -> Option<Arc<T>> {
+    if !egui::DragAndDrop::has_payload_of_type::<T>(ui.ctx()) {
+        return None;
+    }
+
+    response.dnd_release_payload::<T>()
+}
+
+fn drop_sample(
+    this: &Panel,
+    ui: &mut Ui,
+    state: &RwLock<PlaylistState>,
+    global_state: Arc<PanelStates>,
+    grid: PlaylistGrid,
+    ui_base: &egui::Response,
+) {
+    let PlaylistGrid {
+        track_lines,
+        beat_lines,
+        first_visible_track_idx,
+        first_visible_beat,
+    } = grid;
+    // Get cursor position
+    let Some(cursor) = ui.input(|i| i.pointer.hover_pos()) else {
+        return;
+    };
+
+    let Some(start) = pointer_position(ui, state, cursor.x, beat_lines, first_visible_beat) else {
+        return;
+    };
+
+    // Find starting beat position on the y axis
+    let (_, relative_track_pos) =
+        find_value_inbetween(track_lines.iter().map(|v| v[0].y), cursor.y).unwrap_or_default();
+
+    // We have to subtract one from the relative position since the first track's position is out of bounds (its the topmost line of the whole playlist)
+    let absolute_track_idx = first_visible_track_idx + relative_track_pos - 1;
+
+    let (label, edits) = if let Some(id) = released_payload::<ClipId>(ui, ui_base) {
+        // The clip has been dragged from the playlist itself, so it is moved instead of being removed
+        let mut state = state.write();
+
+        state.dragged_from = None;
+
+        let Some(clip) = state.clips.get(*id).cloned() else {
+            return;
+        };
+
+        let row = state.tracks.row(clip.track).unwrap_or_default();
+
+        // The rest of the selection keeps its position relative to the grabbed clip
+        let moves = state.clips.moved_positions(
+            dragged_clips(&state, clip.id),
+            &state.tracks,
+            absolute_track_idx as i64 - row as i64,
+            start.0 as i64 - clip.start.0 as i64,
+        );
+
+        let last_row = moves.iter().map(|(_, _, (row, _))| *row).max();
+        let (tracks, mut edits) = tracks_until(&mut state, last_row.unwrap_or_default());
+
+        let label = match moves.len() {
+            1 => "Move clip",
+            _ => "Move clips",
+        };
+
+        edits.extend(moves.into_iter().map(|(id, from, to)| Edit::MoveClip {
+            id,
+            from: (tracks[from.0], from.1),
+            to: (tracks[to.0], to.1),
+        }));
+
+        (label, edits)
+    } else if let Some(payload) = released_payload::<SampleInstance>(ui, ui_base) {
+        let (sample_instance, imported) = workspace_instance(this, &global_state, &payload);
+
+        let mut state = state.write();
+        let (tracks, mut edits) = tracks_until(&mut state, absolute_track_idx);
+
+        let clip = Clip {
+            id: state.clips.allocate_id(),
+            track: tracks[absolute_track_idx],
+            start,
+            length: sample_instance.properties.length().as_secs_f64(),
+            source: ClipSource::Sample(sample_instance),
+            shape: ClipShape::default(),
+        };
+
+        edits.push(Edit::PlaceClip { clip, imported });
+
+        ("Place clip", edits)
+    } else if let Some(id) = released_payload::<PatternId>(ui, ui_base) {
+        let mut state = state.write();
+
+        let Some(length) = state.patterns.get(*id).map(Pattern::length_in_beats) else {
+            return;
+        };
+
+        let (tracks, mut edits) = tracks_until(&mut state, absolute_track_idx);
+
+        // A single pass of the pattern is placed, the clip can be trimmed to loop the pattern
+        let clip = Clip {
+            id: state.clips.allocate_id(),
+            track: tracks[absolute_track_idx],
+            start,
+            length,
+            source: ClipSource::Pattern(*id),
+            shape: ClipShape::default(),
+        };
+
+        edits.push(Edit::PlaceClip {
+            clip,
+            imported: None,
+        });
+
+        ("Place pattern", edits)
+    } else if let Some(payload) = released_payload::<MidiFilePayload>(ui, ui_base) {
+        // The parts of the file are inserted as new tracks, starting at the hovered one
+        display_error_as_toast(
+            import_midi(&global_state, &payload.0, absolute_track_idx, start),
+            ToastStyle::default(),
+            this.toasts.clone(),
+        );
+
+        return;
+    } else {
+        return;
+    };
+
+    // Store clip in playlist
+    apply_edits(&global_state, label, edits);
+}
+
+/// The sample as it is going to be placed into the playlist, along with the workspace entry which has to be created for it.
+/// Samples which have not been in the workspace yet are imported with a random color.
+fn workspace_instance(
+    this: &Panel,
+    global_state: &PanelStates,
+    payload: &SampleInstance,
+) -> (SampleInstance, Option<(PathBuf, WorkspaceSampleAttributes)>) {
+    // If anything gets dropped into the "workspace" aka the playlist then add it to the workspace files
+    // Look up if we have already stored this one sample
+    let query = global_state
+        .media_panel
+        .read()
+        .workspace_selector
+        .workspace_samples
+        .get(&payload.path)
+        .cloned();
+
+    // Check if we already have this sample in the workspace tab
+    if let Some(sample_info) = query {
+        // If we do have this sample then insert into playlist accordingly
+        let sample_instance = SampleInstance {
+            name: sample_info.alias.clone(),
+            color: {
+                // If the color of this sample has been modified, the new color should be displayed when reinserted.
+                if payload.color != sample_info.color {
+                    payload.color
+                } else {
+                    sample_info.color
+                }
+            },
+            path: payload.path.clone(),
+            properties: payload.properties.clone(),
+            waveform_map: sample_info.waveform_map,
+        };
+
+        return (sample_instance, None);
+    }
+
+    // Initalize new sample in workspace
+    // Generate a new random color for it
+    this.toasts.lock().add(
+        Toast::new()
+            .kind(egui_toast::ToastKind::Info)
+            .text(format!("Imported sample `{}`", payload.name)),
+    );
+
+    // Map the waveforms of the sample if it hadnt been inserted yet
+    let waveform_map = display_error_as_toast(
+        generate_sample_waveform(&payload.path),
+        ToastStyle::default(),
+        this.toasts.clone(),
+    );
+
+    let random_color = random_color_with_opacity(120);
+
+    let imported = (
+        payload.path.clone(),
+        WorkspaceSampleAttributes {
+            alias: payload.name.clone(),
+
+            // All samples have their color synced by default.
+            is_color_synced: true,
+            color: random_color,
+            waveform_map: waveform_map.clone(),
+        },
+    );
+
+    let sample_instance = SampleInstance {
+        name: payload.name.clone(),
+        color: random_color,
+        path: payload.path.clone(),
+        properties: payload.properties.clone(),
+        waveform_map,
+    };
+
+    (sample_instance, Some(imported))
+}
+
+fn hover_sample(
+    ui: &mut Ui,
+    state: &RwLock<PlaylistState>,
+    playlist_rect: Rect,
+    grid: PlaylistGrid,
+    ui_base: &egui::Response,
+) {
+    let PlaylistGrid {
+        track_lines,
+        beat_lines,
+        first_visible_track_idx,
+        first_visible_beat,
+    } = grid;
+    // Only samples, patterns, MIDI files and the clips of the playlist can be dropped into the playlist
+    let moved = ui_base.dnd_hover_payload::<ClipId>();
+    let sample = ui_base.dnd_hover_payload::<SampleInstance>();
+    let pattern = ui_base.dnd_hover_payload::<PatternId>();
+    let midi = ui_base.dnd_hover_payload::<MidiFilePayload>();
+
+    if moved.is_none() && sample.is_none() && pattern.is_none() && midi.is_none() {
+        return;
+    }
+
+    // Get cursor position
+    let Some(cursor) = ui.input(|i| i.pointer.hover_pos()) else {
+        return;
+    };
+
+    // Samples dropped onto the labels are loaded into the sampler of the track instead, see `track_label`
+    if sample.is_some() && cursor.x < playlist_rect.left() + TRACK_LABEL_WIDTH as f32 {
+        return;
+    }
+
+    let Some(start) = pointer_position(ui, state, cursor.x, beat_lines, first_visible_beat) else {
+        return;
+    };
+
+    // The preview is displayed where the sample would be dropped
+    let beat_width = state.read().zoom.beat_width();
+    let starting_x = tick_to_x(beat_lines[0][0].x, first_visible_beat, beat_width, start);
+
+    // Find starting beat position on the y axis
+    let (starting_y, relative_track_pos) =
+        find_value_inbetween(track_lines.iter().map(|v| v[0].y), cursor.y).unwrap_or_default();
+
+    // We have to subtract one from the relative position since the first track's position is out of bounds (its the topmost line of the whole playlist)
+    let absolute_track_idx = first_visible_track_idx + relative_track_pos - 1;
+
+    // A clip moved inside of the playlist previews the whole selection moving along with it
+    let dragged = {
+        let state = state.read();
+
+        moved.and_then(|id| state.clips.get(*id).cloned())
+    };
+
+    if let Some(clip) = dragged {
+        let state = state.read();
+        let usable_rect = playlist_rect.with_min_x(playlist_rect.left() + TRACK_LABEL_WIDTH as f32);
+
+        let row = state.tracks.row(clip.track).unwrap_or_default();
+        let moves = state.clips.moved_positions(
+            dragged_clips(&state, clip.id),
+            &state.tracks,
+            absolute_track_idx as i64 - row as i64,
+            start.0 as i64 - clip.start.0 as i64,
+        );
+
+        for (id, _, (row, start)) in moves {
+            // Only the visible tracks are previewed
+            let Some((clip, top, bottom)) =
+                row.checked_sub(first_visible_track_idx).and_then(|idx| {
+                    Some((
+                        state.clips.get(id)?,
+                        track_lines.get(idx)?[0].y,
+                        track_lines.get(idx + 1)?[0].y,
+                    ))
+                })
+            else {
+                continue;
+            };
+
+            let x = tick_to_x(beat_lines[0][0].x, first_visible_beat, beat_width, start);
+            let length = Clip {
+                start,
+                ..clip.clone()
+            }
+            .length_in_beats(&state.tempo) as f32;
+            let rect = Rect::from_min_max(
+                Pos2::new(x, top),
+                Pos2::new(x + length * beat_width, bottom),
+            )
+            .intersect(usable_rect);
+
+            if rect.is_positive() {
+                let (_, color) = clip_appearance(&state.patterns, clip);
+
+                ui.painter().rect_filled(rect, 0., color);
+            }
+        }
+
+        return;
+    }
+
+    // The length and the color of the clip which would be placed
+    let (length, color) = {
+        let state = state.read();
+
+        if let Some(payload) = sample {
+            (
+                length_in_beats(&payload.properties, &state.tempo, start),
+                payload.color,
+            )
+        } else if let Some(pattern) = pattern.and_then(|id| state.patterns.get(*id)) {
+            (pattern.length_in_beats() as f32, pattern.color)
+        } else if midi.is_some() {
+            // The length of the notes is only known once the file is read, so a single beat is previewed
+            (1., Color32::from_rgba_unmultiplied(255, 255, 255, 120))
+        } else {
+            return;
+        }
+    };
+
+    // Clamp both x and y for the preview to draw correctly.
+    let starting_x = starting_x.max(playlist_rect.left() + TRACK_LABEL_WIDTH as f32);
+    let starting_y = starting_y.max(playlist_rect.top());
+
+    // Fetch track attributes
+    let track_customization = state.read().tracks.customization(absolute_track_idx);
+
+    // Calculate rectangle length
+    let rectangle_length = length * beat_width;
+
+    if relative_track_pos >= track_lines.len() {
+        return;
+    }
+
+    let rect_points = [
+        Pos2::new(starting_x, starting_y),
+        Pos2::new(
+            (starting_x + rectangle_length).min(playlist_rect.right()),
+            (starting_y + state.read().zoom.track_height(&track_customization))
+                .min(track_lines[relative_track_pos][0].y),
+        ),
+    ];
+
+    // Draw the rectangle indicating how long the sample is
+    ui.painter()
+        .rect_filled(Rect::from_points(&rect_points), 0., color);
+}
+
+/// The clips which are moved or removed along with the grabbed clip, this is the selection if the clip is a part of it.
+fn dragged_clips(state: &PlaylistState, grabbed: ClipId) -> Vec<ClipId> {
+    match state.selected_clips.contains(&grabbed) {
+        true => state.selected_clips.iter().copied().collect(),
+        false => vec![grabbed],
+    }
+}
+
+/// Applies the edits, then records them as a single entry in the history.
+fn apply_edits(global_state: &PanelStates, label: &str, edits: Vec<Edit>) {
+    for edit in &edits {
+        edit.apply(global_state);
+    }
+
+    global_state.history.write().record_all(label, edits);
+}
+
+/// Removes the clips from the playlist and from the selection.
+fn delete_clips(global_state: &PanelStates, ids: impl IntoIterator<Item = ClipId>) {
+    let edits: Vec<Edit> = {
+        let mut state = global_state.playlist_panel.write();
+
+        ids.into_iter()
+            .filter_map(|id| {
+                state.selected_clips.remove(&id);
+
+                Some(Edit::RemoveClip {
+                    clip: state.clips.get(id)?.clone(),
+                })
+            })
+            .collect()
+    };
+
+    let label = match edits.len() {
+        1 => "Remove clip",
+        _ => "Remove clips",
+    };
+
+    apply_edits(global_state, label, edits);
+}
+
+/// Cuts the clips in two at the position, the clips which do not contain the position are left untouched.
+/// Both parts of the split clips are selected.
+fn split_clips(global_state: &PanelStates, ids: impl IntoIterator<Item = ClipId>, position: Tick) {
+    let edits: Vec<Edit> = {
+        let mut state = global_state.playlist_panel.write();
+        let state = &mut *state;
+
+        ids.into_iter()
+            .filter_map(|id| {
+                let clip = state.clips.get(id)?.clone();
+                let (first, second) =
+                    clip.split(position, state.clips.allocate_id(), &state.tempo)?;
+
+                state.selected_clips.extend([first.id, second.id]);
+
+                Some([
+                    Edit::ChangeClip {
+                        before: clip,
+                        after: first,
+                    },
+                    Edit::PlaceClip {
+                        clip: second,
+                        imported: None,
+                    },
+                ])
+            })
+            .flatten()
+            .collect()
+    };
+
+    // Every split clip is made of two edits
+    let label = match edits.len() {
+        2 => "Split clip",
+        _ => "Split clips",
+    };
+
+    apply_edits(global_state, label, edits);
+}
+
+/// Places copies of the clips on their own tracks, the earliest of them starts on `start`.
+/// The copies replace the selection.
+fn place_copies(global_state: &PanelStates, label: &str, clips: &[Clip], start: Tick) {
+    let Some(first_start) = clips.iter().map(|clip| clip.start).min() else {
+        return;
+    };
+
+    let edits: Vec<Edit> = {
+        let mut state = global_state.playlist_panel.write();
+
+        state.selected_clips.clear();
+
+        clips
+            .iter()
+            .map(|clip| {
+                let copy = Clip {
+                    id: state.clips.allocate_id(),
+                    start: Tick(clip.start.0 - first_start.0 + start.0),
+                    ..clip.clone()
+                };
+
+                state.selected_clips.insert(copy.id);
+
+                Edit::PlaceClip {
+                    clip: copy,
+                    imported: None,
+                }
+            })
+            .collect()
+    };
+
+    apply_edits(global_state, label, edits);
+}
+
+/// Copies, pastes, duplicates, splits and deletes the selected clips.
+/// The clips are pasted at the cursor, duplicates are placed right after the selection and the clips are split at the cursor.
+fn clip_shortcuts(ui: &mut Ui, global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+
+    // Most platforms turn the copy and paste shortcuts into clipboard events, so both of them are checked
+    let (copy, paste, duplicate, split, delete) = ui.input_mut(|input| {
+        let copy = input.consume_shortcut(&COPY_SHORTCUT)
+            || input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Copy));
+        let paste = input.consume_shortcut(&PASTE_SHORTCUT)
+            || input
+                .events
+                .iter()
+                .any(|event| matches!(event, egui::Event::Paste(_)));
+
+        (
+            copy,
+            paste,
+            input.consume_shortcut(&DUPLICATE_SHORTCUT),
+            input.consume_shortcut(&SPLIT_SHORTCUT),
+            input.consume_shortcut(&DELETE_SHORTCUT)
+                || input.consume_key(Modifiers::NONE, Key::Backspace),
+        )
+    });
+
+    let (selected, cursor) = {
+        let state = state.read();
+
+        let selected: Vec<Clip> = state
+            .selected_clips
+            .iter()
+            .filter_map(|id| state.clips.get(*id).cloned())
+            .collect();
+
+        (selected, Tick::from_beats(state.cursor_offset as f64))
+    };
+
+    if copy && !selected.is_empty() {
+        state.write().clipboard = selected.clone();
+    }
+
+    if paste {
+        let clipboard = state.read().clipboard.clone();
+
+        place_copies(global_state, "Paste clips", &clipboard, cursor);
+    }
+
+    if duplicate {
+        let end = {
+            let state = state.read();
+
+            selected.iter().map(|clip| clip.end(&state.tempo)).max()
+        };
+
+        if let Some(end) = end {
+            place_copies(global_state, "Duplicate clips", &selected, end);
+        }
+    }
+
+    if split {
+        split_clips(global_state, selected.iter().map(|clip| clip.id), cursor);
+    }
+
+    if delete {
+        delete_clips(global_state, selected.iter().map(|clip| clip.id));
+    }
+}
+
+/// Dragging on the empty space of the playlist selects the clips touched by the dragged rectangle, Shift adds them to the selection.
+/// Clicking on the empty space clears the selection.
+fn select_with_marquee(
+    ui: &mut Ui,
+    state: &RwLock<PlaylistState>,
+    marquee: &egui::Response,
+    clip_rects: &[(ClipId, Rect)],
+    playlist_rect: Rect,
+) {
+    let shift = ui.input(|input| input.modifiers.shift);
+
+    // The selection from before the drag, the touched clips are added to this
+    let initial_selection = marquee.id.with("initial_selection");
+
+    if marquee.clicked() && !shift {
+        state.write().selected_clips.clear();
+    }
+
+    if marquee.drag_started() {
+        let selection = match shift {
+            true => state.read().selected_clips.clone(),
+            false => BTreeSet::new(),
+        };
+
+        ui.data_mut(|data| data.insert_temp(initial_selection, selection));
+    }
+
+    if marquee.dragged()
+        && let Some(origin) = ui.input(|input| input.pointer.press_origin())
+        && let Some(pointer) = marquee.interact_pointer_pos()
+    {
+        let rect = Rect::from_two_pos(origin, pointer);
+
+        let mut selection: BTreeSet<ClipId> = ui
+            .data(|data| data.get_temp(initial_selection))
+            .unwrap_or_default();
+
+        selection.extend(
+            clip_rects
+                .iter()
+                .filter(|(_, clip_rect)| clip_rect.intersects(rect))
+                .map(|(id, _)| *id),
+        );
+
+        state.write().selected_clips = selection;
+
+        ui.painter().with_clip_rect(playlist_rect).rect(
+            rect,
+            0.,
+            MARQUEE_FILL,
+            Stroke::new(STROKE_WIDTH, SELECTION_COLOR),
+            egui::StrokeKind::Inside,
+        );
+    }
+}
+
+/// Starts the playback from wherever the cursor has been left.
+fn start_playback(global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+    let engine = &global_state.playback;
+    let generation = global_state.history.read().timeline_generation();
+
+    engine.sync(&state.read(), &global_state.mixer_panel.read(), generation);
+    engine.seek(state.read().cursor_offset as f64);
+    engine.count_in(&state.read().metronome);
+    engine.play();
+    state.write().playback_state = PlaybackState::Playing;
+}
+
+/// Stops the playback and rewinds the cursor to the start of the song.
+fn stop_playback(global_state: &PanelStates) {
+    global_state.playback.stop();
+
+    let mut state = global_state.playlist_panel.write();
+
+    state.playback_state = PlaybackState::Stopped;
+    state.cursor_offset = 0.;
+}
+
+/// The settings of the metronome, the user can pick any sample of the workspace as the click.
+fn metronome_settings(ui: &mut Ui, global_state: &PanelStates, metronome: &mut MetronomeSettings) {
+    let samples: Vec<PathBuf> = global_state
+        .media_panel
+        .read()
+        .workspace_selector
+        .workspace_samples
+        .keys()
+        .cloned()
+        .collect();
+
+    egui::ComboBox::from_label("Sound")
+        .selected_text(metronome.sound.to_string())
+        .show_ui(ui, |ui| {
+            for sound in ClickSound::built_in() {
+                let text = sound.to_string();
+
+                ui.selectable_value(&mut metronome.sound, sound, text);
+            }
+
+            if !samples.is_empty() {
+                ui.separator();
+            }
+
+            for path in samples {
+                let sound = ClickSound::Sample(path);
+                let text = sound.to_string();
+
+                ui.selectable_value(&mut metronome.sound, sound, text);
+            }
+        });
+
+    ui.add(egui::Slider::new(&mut metronome.volume, 0.0..=1.0).text("Volume"));
+
+    ui.horizontal(|ui| {
+        ui.label("Count-in");
+
+        for bars in 0..=MAX_COUNT_IN_BARS {
+            let text = match bars {
+                0 => String::from("Off"),
+                1 => String::from("1 bar"),
+                bars => format!("{bars} bars"),
             };
 
-            // Store sample in playlist
-            state.write().samples.insert(
-                Position {
-                    track: absolute_track_idx,
-                    beat: absolute_beat_pos,
-                },
-                sample_instance.clone(),
+            ui.selectable_value(&mut metronome.count_in_bars, bars, text);
+        }
+    });
+}
+
+/// Moves the cursor and the playback to the position.
+fn move_cursor(global_state: &PanelStates, position: Tick) {
+    global_state.playback.seek(position.as_beats());
+    global_state.playlist_panel.write().cursor_offset = position.as_beats() as f32;
+}
+
+/// Space starts and stops the playback, the other shortcuts move the cursor.
+fn transport_shortcuts(ui: &mut Ui, global_state: &PanelStates) {
+    let state = &global_state.playlist_panel;
+
+    if ui.input_mut(|input| input.consume_shortcut(&PLAY_SHORTCUT)) {
+        match state.read().playback_state {
+            PlaybackState::Playing => stop_playback(global_state),
+            PlaybackState::Paused | PlaybackState::Stopped => start_playback(global_state),
+        }
+    }
+
+    let cursor = Tick::from_beats(state.read().cursor_offset as f64);
+
+    // The marker shortcuts are checked first, since the bar shortcuts would also match them
+    let position = ui.input_mut(|input| {
+        let state = state.read();
+
+        if input.consume_shortcut(&SONG_START_SHORTCUT) {
+            Some(Tick(0))
+        } else if input.consume_shortcut(&SONG_END_SHORTCUT) {
+            Some(state.clips.end(&state.tempo))
+        } else if input.consume_shortcut(&PREVIOUS_MARKER_SHORTCUT) {
+            Some(
+                markers(&state)
+                    .into_iter()
+                    .rfind(|marker| *marker < cursor)
+                    .unwrap_or_default(),
+            )
+        } else if input.consume_shortcut(&NEXT_MARKER_SHORTCUT) {
+            markers(&state).into_iter().find(|marker| *marker > cursor)
+        } else if input.consume_shortcut(&PREVIOUS_BAR_SHORTCUT) {
+            let bar = state.tempo.bar_at(cursor);
+
+            // Jumps to the start of the current bar first, unless the cursor is already there
+            match bar.start < cursor || bar.index == 0 {
+                true => Some(bar.start),
+                false => Some(state.tempo.bar(bar.index - 1).start),
+            }
+        } else if input.consume_shortcut(&NEXT_BAR_SHORTCUT) {
+            Some(state.tempo.bar_at(cursor).end())
+        } else {
+            None
+        }
+    });
+
+    if let Some(position) = position {
+        move_cursor(global_state, position);
+    }
+}
+
+/// The positions the cursor can jump to with the marker shortcuts, these are the tempo and meter changes and the bounds of the loop.
+fn markers(state: &PlaylistState) -> Vec<Tick> {
+    let tempo = &state.tempo;
+
+    let mut markers: Vec<Tick> = tempo
+        .tempo_changes()
+        .iter()
+        .map(|change| change.position)
+        .chain(
+            tempo
+                .meter_changes()
+                .iter()
+                .map(|change| tempo.bar(change.bar).start),
+        )
+        .chain(
+            state
+                .loop_range
+                .iter()
+                .flat_map(|range| [range.start, range.end]),
+        )
+        .collect();
+
+    markers.sort();
+    markers.dedup();
+
+    markers
+}
+
+/// Scrolls the cursor to the left of the playlist once it has left the visible part of it.
+fn follow_cursor(state: &mut PlaylistState, playlist_rect: Rect) {
+    let visible_width = playlist_rect.width() - TRACK_LABEL_WIDTH as f32;
+
+    // The scroll offset is stored multiplied by the width of the playlist
+    let scroll_x = state.grid_offset.x / playlist_rect.width();
+    let scroll_x = state
+        .zoom
+        .follow(scroll_x, visible_width, state.cursor_offset);
+
+    state.grid_offset.x = scroll_x * playlist_rect.width();
+}
+
+/// The position on the timeline at the `x` coordinate, snapped to the grid unless Alt is held.
+/// Returns `None` if there are no beats visible.
+fn pointer_position(
+    ui: &Ui,
+    state: &RwLock<PlaylistState>,
+    x: f32,
+    beat_lines: &[[Pos2; 2]],
+    first_visible_beat: usize,
+) -> Option<Tick> {
+    let first_beat_line = beat_lines.first()?;
+
+    let beat_width = state.read().zoom.beat_width();
+    let beats = first_visible_beat as f64 + ((x - first_beat_line[0].x) / beat_width) as f64;
+
+    Some(snap_position(ui, state, beats))
+}
+
+/// Snaps the beat position to the grid, unless Alt is held.
+fn snap_position(ui: &Ui, state: &RwLock<PlaylistState>, beats: f64) -> Tick {
+    let position = Tick::from_beats(beats);
+
+    if ui.input(|i| i.modifiers.alt) {
+        position
+    } else {
+        position.snap(state.read().snap)
+    }
+}
+
+/// The `x` coordinate of the position, `first_beat_x` is where the line of `first_visible_beat` is drawn.
+fn tick_to_x(first_beat_x: f32, first_visible_beat: usize, beat_width: f32, tick: Tick) -> f32 {
+    first_beat_x + (tick.as_beats() - first_visible_beat as f64) as f32 * beat_width
+}
+
+/// The beats from the start of the first selected clip until the end of the last one.
+/// A range of time selected on the ruler takes precedence over the clips.
+fn selection_range(state: &PlaylistState) -> Option<Range<f64>> {
+    if let Some(selection) = &state.time_selection {
+        return Some(selection.start.as_beats()..selection.end.as_beats());
+    }
+
+    let selected = state
+        .selected_clips
+        .iter()
+        .filter_map(|id| state.clips.get(*id));
+
+    let start = selected.clone().map(|clip| clip.start).min()?;
+    let end = selected.map(|clip| clip.end(&state.tempo)).max()?;
+
+    Some(start.as_beats()..end.as_beats())
+}
+
+/// Sets the horizontal zoom so that the beats fill the playlist, and scrolls to the first one.
+fn zoom_to_beats(state: &mut PlaylistState, playlist_rect: Rect, beats: Range<f64>) {
+    let usable_width = playlist_rect.width() - TRACK_LABEL_WIDTH as f32;
+
+    // The scroll offset is stored multiplied by the width of the playlist
+    state.grid_offset.x = state.zoom.fit_beats(usable_width, beats) * playlist_rect.width();
+}
+
+/// Multiplies the horizontal zoom by `factor`, the beat under `pointer_x` stays in place.
+fn zoom_horizontally(state: &mut PlaylistState, playlist_rect: Rect, pointer_x: f32, factor: f32) {
+    let beats_start = playlist_rect.left() + TRACK_LABEL_WIDTH as f32;
+
+    // The scroll offset is stored multiplied by the width of the playlist
+    let scroll_x = state.grid_offset.x / playlist_rect.width();
+    let scroll_x = state
+        .zoom
+        .zoom_around(scroll_x, pointer_x - beats_start, factor);
+
+    state.grid_offset.x = scroll_x * playlist_rect.width();
+}
+
+/// The amount of beats the sample takes up in the playlist when it starts on `start`, this matches how long it is played by the engine.
+fn length_in_beats(properties: &SampleProperties, tempo: &TempoMap, start: Tick) -> f32 {
+    tempo.beats_in(start.as_beats(), properties.length().as_secs_f64()) as f32
+}
+
+/// Draws main cursor (Indicates where we are in current playlist)
+/// The cursor is not drawn if it has been scrolled behind the track labels.
+fn draw_cursor(
+    ui: &mut Ui,
+    playlist_rect: Rect,
+    x_offset_ratio: f32,
+    beat_width: f32,
+    cursor_offset: f32,
+) {
+    // Beat 0 is right after the track labels, the same way as in `beat_outlines`
+    let x = playlist_rect.left()
+        + TRACK_LABEL_WIDTH as f32
+        + x_offset_ratio
+        + cursor_offset * beat_width;
+
+    if x < playlist_rect.left() + TRACK_LABEL_WIDTH as f32 || x > playlist_rect.right() {
+        return;
+    }
+
+    ui.painter().line(
+        vec![
+            Pos2::new(x, playlist_rect.top()),
+            Pos2::new(x, playlist_rect.bottom()),
+        ],
+        Stroke::new(STROKE_WIDTH, CURSOR_COLOR),
+    );
+}
+
+/// Draws the bars and the beats of the meter, the positions of every beat are returned with the step of `beat_width`.
+/// The returned beats are quarter notes, regardless of the meter.
+fn beat_outlines(
+    ui: &mut Ui,
+    playlist_rect: Rect,
+    x_offset_ratio: f32,
+    beat_width: f32,
+    tempo: &TempoMap,
+) -> (usize, Vec<[Pos2; 2]>) {
+    let mut line_positions = Vec::new();
+
+    // The position of "beat 0" (first beat after the label region) with no scroll applied.
+    let label_end = playlist_rect.left() + TRACK_LABEL_WIDTH as f32;
+
+    // Shift by the scroll offset to find where beat 0 currently sits on screen.
+    let beat_zero_x = label_end + x_offset_ratio;
+
+    // How many whole beats have scrolled past beat 0 (positive = scrolled right).
+    let beats_past_zero = ((label_end - beat_zero_x) / beat_width).ceil().max(0.0);
+
+    let first_visible_beat = beats_past_zero as usize;
+    let mut x_coord = beat_zero_x + beats_past_zero * beat_width;
+
+    while x_coord <= playlist_rect.right() {
+        // Store the line position
+        line_positions.push([
+            Pos2::new(x_coord, playlist_rect.top()),
+            Pos2::new(x_coord, playlist_rect.bottom()),
+        ]);
+
+        x_coord += beat_width;
+    }
+
+    // Bars are drawn stronger than beats, the beats are left out if they would be too dense
+    let painter = ui.painter_at(playlist_rect.with_min_x(label_end));
+    let line = |x: f32, color: Color32| {
+        painter.line_segment(
+            [
+                Pos2::new(x, playlist_rect.top()),
+                Pos2::new(x, playlist_rect.bottom()),
+            ],
+            Stroke::new(STROKE_WIDTH, color),
+        );
+    };
+
+    for bar in tempo.bars_from(Tick::from_beats(first_visible_beat as f64)) {
+        let bar_x = beat_zero_x + bar.start.as_beats() as f32 * beat_width;
+
+        if bar_x > playlist_rect.right() {
+            break;
+        }
+
+        line(bar_x, BAR_LINE);
+
+        let beat_spacing = ticks_to_width(bar.meter.beat_ticks(), beat_width);
+
+        if beat_spacing >= MIN_BEAT_LINE_SPACING {
+            for beat in 1..bar.meter.numerator {
+                line(bar_x + beat as f32 * beat_spacing, BAR_TRACK_SEPARATOR);
+            }
+        }
+    }
+
+    (first_visible_beat, line_positions)
+}
+
+/// The width of the amount of ticks in the playlist.
+fn ticks_to_width(ticks: u64, beat_width: f32) -> f32 {
+    ticks as f32 / PPQ as f32 * beat_width
+}
+
+/// Draws the ruler above the tracks with the bar numbers, the tempo and meter changes, and the readout of the cursor position left of it.
+/// Clicking on the ruler moves the cursor, dragging on it selects a range of time.
+/// Right clicking on it opens a menu where the tempo and the meter can be changed.
+fn ruler(
+    ui: &mut Ui,
+    global_state: &PanelStates,
+    ruler_rect: Rect,
+    playlist_rect: Rect,
+    x_offset_ratio: f32,
+) {
+    let state = &global_state.playlist_panel;
+    let (beat_width, tempo, time_format, cursor_offset, time_selection, loop_range, looping) = {
+        let state = state.read();
+
+        (
+            state.zoom.beat_width(),
+            state.tempo.clone(),
+            state.time_format,
+            state.cursor_offset,
+            state.time_selection.clone(),
+            state.loop_range.clone(),
+            state.looping,
+        )
+    };
+
+    let readout_rect = ruler_rect.with_max_x(ruler_rect.left() + TRACK_LABEL_WIDTH as f32);
+    let beats_rect = ruler_rect.with_min_x(readout_rect.right());
+
+    // Beat 0 is right after the readout, the same way as in `beat_outlines`
+    let beat_zero_x = beats_rect.left() + x_offset_ratio;
+    let beat_to_x = |beats: f64| beat_zero_x + beats as f32 * beat_width;
+
+    ui.painter().rect_filled(ruler_rect, 0., RULER_BACKGROUND);
+
+    // Display the position of the cursor, clicking on it switches to the next format
+    ui.painter().text(
+        readout_rect.center(),
+        Align2::CENTER_CENTER,
+        time_format.format(Tick::from_beats(cursor_offset as f64), &tempo),
+        FontId::monospace(12.),
+        RULER_TEXT,
+    );
+
+    let readout = ui
+        .allocate_rect(readout_rect, Sense::click())
+        .on_hover_text(format!("{time_format}, click to change the format"));
+
+    if readout.clicked() {
+        state.write().time_format = time_format.next();
+    }
+
+    // Highlight the selected range on the ruler and on the tracks
+    if let Some(selection) = time_selection {
+        let selection_rect = Rect::from_x_y_ranges(
+            beat_to_x(selection.start.as_beats())..=beat_to_x(selection.end.as_beats()),
+            ruler_rect.top()..=playlist_rect.bottom(),
+        );
+
+        ui.painter()
+            .with_clip_rect(beats_rect.union(playlist_rect.with_min_x(beats_rect.left())))
+            .rect_filled(selection_rect, 0., TIME_SELECTION);
+    }
+
+    let painter = ui.painter_at(beats_rect);
+
+    // The loop range is drawn on the top of the ruler, it is dimmed while looping is turned off
+    if let Some(loop_range) = loop_range {
+        let loop_rect = Rect::from_x_y_ranges(
+            beat_to_x(loop_range.start.as_beats())..=beat_to_x(loop_range.end.as_beats()),
+            beats_rect.top()..=beats_rect.top() + LOOP_HEIGHT,
+        );
+
+        painter.rect_filled(
+            loop_rect,
+            0.,
+            match looping {
+                true => LOOP_ACTIVE,
+                false => LOOP_INACTIVE,
+            },
+        );
+    }
+
+    // Only every couple of bars is labeled if the labels would overlap, the shortest bar of the song decides the spacing
+    let shortest_bar = tempo
+        .meter_changes()
+        .iter()
+        .map(|change| change.meter.bar_ticks())
+        .fold(tempo.meter.bar_ticks(), u64::min);
+    let mut bars_per_label = 1;
+
+    while ticks_to_width(shortest_bar, beat_width) * (bars_per_label as f32)
+        < MIN_RULER_LABEL_SPACING
+    {
+        bars_per_label *= 2;
+    }
+
+    let first_visible = Tick::from_beats(((beats_rect.left() - beat_zero_x) / beat_width) as f64);
+
+    for bar in tempo.bars_from(first_visible) {
+        let x = beat_to_x(bar.start.as_beats());
+
+        if x > beats_rect.right() {
+            break;
+        }
+
+        painter.line_segment(
+            [
+                Pos2::new(x, beats_rect.top()),
+                Pos2::new(x, beats_rect.bottom()),
+            ],
+            Stroke::new(STROKE_WIDTH, BAR_LINE),
+        );
+
+        // The meter is displayed next to the number of the bar it changes on
+        let meter_change = bar.index == 0
+            || tempo
+                .meter_changes()
+                .iter()
+                .any(|change| change.bar == bar.index);
+
+        if bar.index % bars_per_label == 0 || meter_change {
+            let label = painter.text(
+                Pos2::new(x + 3., beats_rect.top()),
+                Align2::LEFT_TOP,
+                (bar.index + 1).to_string(),
+                FontId::proportional(12.),
+                RULER_TEXT,
             );
+
+            if meter_change {
+                painter.text(
+                    Pos2::new(label.right() + 3., beats_rect.top()),
+                    Align2::LEFT_TOP,
+                    bar.meter.to_string(),
+                    FontId::proportional(10.),
+                    METER_MARKER,
+                );
+            }
         }
-    }
-}
 
-fn hover_sample(
-    ui: &mut Ui,
-    state: &RwLock<PlaylistState>,
-    playlist_rect: Rect,
-    track_lines: &[[Pos2; 2]],
-    beat_lines: &[[Pos2; 2]],
-    first_visible_track_idx: usize,
-    ui_base: &egui::Response,
-) {
-    if let Some(payload) = ui_base.dnd_hover_payload::<SampleInstance>() {
-        // Get cursor position
-        if let Some(cursor) = ui.input(|i| i.pointer.hover_pos()) {
-            // Find starting beat position on the x axis
-            let (starting_x, _relative_beat_pos) =
-                find_value_inbetween(beat_lines.iter().map(|v| v[0].x), cursor.x)
-                    .unwrap_or_default();
-
-            // Find starting beat position on the y axis
-            let (starting_y, relative_track_pos) =
-                find_value_inbetween(track_lines.iter().map(|v| v[0].y), cursor.y)
-                    .unwrap_or_default();
-
-            // We have to subtract one from the relative position since the first track's position is out of bounds (its the topmost line of the whole playlist)
-            let absolute_track_idx = first_visible_track_idx + relative_track_pos - 1;
-
-            // Clamp both x and y for the preview to draw correctly.
-            let starting_x = starting_x.max(playlist_rect.left() + TRACK_LABEL_WIDTH as f32);
-            let starting_y = starting_y.max(playlist_rect.top());
-
-            // Fetch track attributes
-            let track_customization = get_track_customization(state, absolute_track_idx);
-
-            // Calculate rectangle length
-            let bps = state.read().bpm / 60.;
-
-            // This is basically secs / bps * beat_width
-            let rectangle_length =
-                symphonia::core::units::Time::from_millis(payload.properties.length as i64)
-                    .as_secs() as f32
-                    / bps
-                    * BEAT_WIDTH as f32;
-
-            if relative_track_pos >= track_lines.len() {
-                return;
+        let beat_spacing = ticks_to_width(bar.meter.beat_ticks(), beat_width);
+
+        for beat in 0..bar.meter.numerator {
+            let x = x + beat as f32 * beat_spacing;
+
+            if beat != 0 && beat_spacing >= MIN_BEAT_LINE_SPACING {
+                painter.line_segment(
+                    [
+                        Pos2::new(x, beats_rect.center().y),
+                        Pos2::new(x, beats_rect.bottom()),
+                    ],
+                    Stroke::new(STROKE_WIDTH, BAR_TRACK_SEPARATOR),
+                );
+
+                // The beats are numbered inside of the bar once there is enough space
+                if beat_spacing >= MIN_RULER_LABEL_SPACING {
+                    painter.text(
+                        Pos2::new(x + 3., beats_rect.top()),
+                        Align2::LEFT_TOP,
+                        format!("{}.{}", bar.index + 1, beat + 1),
+                        FontId::proportional(10.),
+                        BAR_TRACK_SEPARATOR,
+                    );
+                }
             }
 
-            let rect_points = [
-                Pos2::new(starting_x, starting_y),
-                Pos2::new(
-                    (starting_x + rectangle_length).min(playlist_rect.right()),
-                    (starting_y + track_customization.height)
-                        .min(track_lines[relative_track_pos][0].y),
-                ),
-            ];
+            // Subdivide the beats into quarters when zoomed in far enough
+            if beat_spacing >= MIN_BEAT_LINE_SPACING * 4. {
+                for quarter in 1..4 {
+                    let x = x + beat_spacing * quarter as f32 / 4.;
+
+                    painter.line_segment(
+                        [
+                            Pos2::new(x, beats_rect.bottom() - RULER_HEIGHT / 4.),
+                            Pos2::new(x, beats_rect.bottom()),
+                        ],
+                        Stroke::new(STROKE_WIDTH, BAR_TRACK_SEPARATOR),
+                    );
+                }
+            }
+        }
+    }
 
-            // Draw the rectangle indicating how long the sample is
-            ui.painter()
-                .rect_filled(Rect::from_points(&rect_points), 0., payload.color);
+    // The tempo changes are marked at the bottom of the ruler, ramps are marked with an arrow
+    for change in tempo.tempo_changes() {
+        let x = beat_to_x(change.position.as_beats());
+
+        painter.line_segment(
+            [
+                Pos2::new(x, beats_rect.center().y),
+                Pos2::new(x, beats_rect.bottom()),
+            ],
+            Stroke::new(STROKE_WIDTH, TEMPO_MARKER),
+        );
+        painter.text(
+            Pos2::new(x + 3., beats_rect.bottom()),
+            Align2::LEFT_BOTTOM,
+            match change.ramp {
+                true => format!("{} bpm \u{2197}", change.bpm),
+                false => format!("{} bpm", change.bpm),
+            },
+            FontId::proportional(10.),
+            TEMPO_MARKER,
+        );
+    }
+
+    // Clicking moves the cursor, dragging selects the time between the start and the end of the drag
+    // Dragging while holding Shift sets the loop range instead
+    let response = ui.allocate_rect(beats_rect, Sense::click_and_drag());
+    let x_to_position = |x: f32| snap_position(ui, state, ((x - beat_zero_x) / beat_width) as f64);
+
+    if response.clicked()
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        move_cursor(global_state, x_to_position(pointer.x));
+
+        state.write().time_selection = None;
+    }
+
+    if response.dragged()
+        && let Some(origin) = ui.input(|i| i.pointer.press_origin())
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        let (from, to) = (x_to_position(origin.x), x_to_position(pointer.x));
+        let range = (from != to).then(|| from.min(to)..from.max(to));
+
+        let mut state = state.write();
+
+        if !ui.input(|i| i.modifiers.shift) {
+            state.time_selection = range;
+        } else if let Some(range) = range {
+            state.loop_range = Some(range);
+            state.looping = true;
+            global_state.history.write().mark_changed();
         }
     }
-}
 
-fn get_track_customization(state: &RwLock<PlaylistState>, idx: usize) -> TrackCustomization {
-    match state.read().custom_tracks.get(&idx) {
-        Some(custom) => custom.clone(),
-        None => TrackCustomization::named_default(idx),
+    // The position the menu has been opened on is kept while it is open
+    let menu_position = Id::new("ruler_menu_position");
+
+    if response.secondary_clicked()
+        && let Some(pointer) = response.interact_pointer_pos()
+    {
+        let position = x_to_position(pointer.x);
+
+        ui.data_mut(|data| data.insert_temp(menu_position, position));
     }
-}
 
-/// Draws main cursor (Indicates where we are in current playlist)
-fn draw_cursor(ui: &mut Ui, playlist_rect: Rect, grid_offset: Vec2, cursor_offset: f32) {
-    ui.painter().line(
-        vec![
-            Pos2::new(
-                (playlist_rect.left() + cursor_offset + grid_offset.x).min(playlist_rect.right()),
-                playlist_rect.top(),
-            ),
-            Pos2::new(
-                (playlist_rect.left() + cursor_offset + grid_offset.x).min(playlist_rect.right()),
-                playlist_rect.bottom(),
-            ),
-        ],
-        Stroke::new(STROKE_WIDTH, CURSOR_COLOR),
-    );
+    ruler_menu(&response, global_state, menu_position);
 }
 
-/// Draws beat outlines from the left of the playlist to the right with the step of `beat_width`.
-fn beat_outlines(
-    ui: &mut Ui,
-    playlist_rect: Rect,
-    x_offset_ratio: f32,
-    beat_width: f32,
-) -> (usize, Vec<[Pos2; 2]>) {
-    let mut line_positions = Vec::new();
+/// The context menu of the ruler, the time selection can be looped here.
+/// The tempo can be changed at the clicked position and the meter of the clicked bar,
+/// every modification of these made while the menu is open is a single entry in the history.
+fn ruler_menu(ruler: &egui::Response, global_state: &PanelStates, menu_position: Id) {
+    let state = &global_state.playlist_panel;
+    let history_group = Id::new("ruler_tempo");
 
-    // The position of "beat 0" (first beat after the label region) with no scroll applied.
-    let label_end = playlist_rect.left() + beat_width * 4.0;
+    let position = ruler
+        .ctx
+        .data(|data| data.get_temp::<Tick>(menu_position))
+        .unwrap_or_default();
 
-    // Shift by the scroll offset to find where beat 0 currently sits on screen.
-    let beat_zero_x = label_end + x_offset_ratio;
+    let before = state.read().tempo.clone();
+    let mut tempo = before.clone();
 
-    // How many whole beats have scrolled past beat 0 (positive = scrolled right).
-    let beats_past_zero = ((label_end - beat_zero_x) / beat_width).ceil().max(0.0);
+    let popup = egui::Popup::context_menu(ruler)
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside);
 
-    let first_visible_beat = beats_past_zero as usize;
-    let mut x_coord = beat_zero_x + beats_past_zero * beat_width;
+    let menu = popup.show(|ui| {
+        let (time_selection, has_loop) = {
+            let state = state.read();
 
-    while x_coord <= playlist_rect.right() {
-        let line_pos = [
-            Pos2::new(x_coord, playlist_rect.top()),
-            Pos2::new(x_coord, playlist_rect.bottom()),
-        ];
-        ui.painter().line(
-            line_pos.to_vec(),
-            Stroke::new(STROKE_WIDTH, BAR_TRACK_SEPARATOR),
+            (state.time_selection.clone(), state.loop_range.is_some())
+        };
+
+        if ui
+            .add_enabled(
+                time_selection.is_some(),
+                egui::Button::new("Loop the time selection"),
+            )
+            .clicked()
+        {
+            let mut state = state.write();
+
+            state.loop_range = time_selection;
+            state.looping = true;
+            global_state.history.write().mark_changed();
+            ui.close();
+        }
+
+        if ui
+            .add_enabled(has_loop, egui::Button::new("Remove loop"))
+            .clicked()
+        {
+            let mut state = state.write();
+
+            state.loop_range = None;
+            state.looping = false;
+            global_state.history.write().mark_changed();
+            ui.close();
+        }
+
+        ui.separator();
+
+        let existing = tempo
+            .tempo_changes()
+            .iter()
+            .find(|change| change.position == position)
+            .copied();
+        let mut change = existing.unwrap_or(TempoChange {
+            position,
+            bpm: tempo.bpm_at(position.as_beats()),
+            ramp: false,
+        });
+
+        ui.label(
+            RichText::from(format!(
+                "Tempo at {}",
+                TimeFormat::Bars.format(position, &tempo)
+            ))
+            .weak(),
         );
 
-        // Store the line position
-        line_positions.push(line_pos);
+        let bpm = ui.add(
+            egui::DragValue::new(&mut change.bpm)
+                .range(BPM_RANGE)
+                .fixed_decimals(3)
+                .suffix(" bpm"),
+        );
 
-        x_coord += beat_width;
+        // The tempo the song starts with is always steady
+        let ramp = ui.add_enabled(
+            position != Tick(0),
+            egui::Checkbox::new(&mut change.ramp, "Ramp to the next change"),
+        );
+
+        if bpm.changed() || ramp.changed() {
+            tempo.set_tempo_change(change);
+        }
+
+        if existing.is_some() && ui.button("Remove tempo change").clicked() {
+            tempo.remove_tempo_change(position);
+            ui.close();
+        }
+
+        ui.separator();
+
+        let bar = tempo.bar_at(position);
+        let mut meter = bar.meter;
+
+        ui.label(RichText::from(format!("Meter from bar {}", bar.index + 1)).weak());
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut meter.numerator).range(1..=32));
+            ui.label("/");
+
+            egui::ComboBox::from_id_salt("ruler_meter_denominator")
+                .selected_text(meter.denominator.to_string())
+                .width(40.)
+                .show_ui(ui, |ui| {
+                    for denominator in METER_DENOMINATORS {
+                        ui.selectable_value(
+                            &mut meter.denominator,
+                            *denominator,
+                            denominator.to_string(),
+                        );
+                    }
+                });
+        });
+
+        if meter != bar.meter {
+            tempo.set_meter_change(bar.index, meter);
+        }
+
+        let has_meter_change = tempo
+            .meter_changes()
+            .iter()
+            .any(|change| change.bar == bar.index);
+
+        if has_meter_change && ui.button("Remove meter change").clicked() {
+            tempo.remove_meter_change(bar.index);
+            ui.close();
+        }
+    });
+
+    if tempo != before {
+        state.write().tempo = tempo.clone();
+
+        global_state.history.write().record_grouped(
+            history_group,
+            "Change tempo",
+            Edit::ChangeTempo {
+                before,
+                after: tempo,
+            },
+        );
     }
 
-    (first_visible_beat, line_positions)
+    if menu.is_none() {
+        global_state.history.write().close_group(history_group);
+    }
 }
 
-fn track_label<'a>(
+/// Draws the header of the track, the label can be dragged to reorder the tracks.
+/// The empty rows after the last track only display their label, clicking on it turns the row into a track.
+fn track_label(
     ui: &mut Ui,
+    global_state: &PanelStates,
     playlist_rect: Rect,
-    idx: usize,
+    row: usize,
     label_customization: &TrackCustomization,
     top: f32,
     bottom: f32,
-    custom_tracks: &mut HashMap<usize, TrackCustomization>,
 ) {
+    let state = &global_state.playlist_panel;
+    let history = &global_state.history;
+
     let label_rect = Rect::from_two_pos(
         Pos2 {
             x: playlist_rect.left(),
@@ -744,9 +2980,26 @@ fn track_label<'a>(
         },
     );
 
+    let track = state.read().tracks.get(row).cloned();
+
+    // The empty rows are dimmed, so that they can be told apart from the tracks
+    let (label_color, text_color) = match track {
+        Some(_) => (
+            label_customization.label_color,
+            label_customization.label_text_color,
+        ),
+        None => (
+            label_customization
+                .label_color
+                .gamma_multiply(EMPTY_ROW_OPACITY),
+            label_customization
+                .label_text_color
+                .gamma_multiply(EMPTY_ROW_OPACITY),
+        ),
+    };
+
     // Draw the label itself
-    ui.painter()
-        .rect_filled(label_rect, 0., label_customization.label_color);
+    ui.painter().rect_filled(label_rect, 0., label_color);
 
     // Draw the label text
     ui.painter().text(
@@ -754,77 +3007,373 @@ fn track_label<'a>(
         Align2::CENTER_TOP,
         label_customization.label_text.clone(),
         FontId::default(),
-        label_customization.label_text_color,
+        text_color,
     );
 
-    // Allocate the response for the given track
-    let label = ui.allocate_rect(label_rect, Sense::click());
+    let Some(track) = track else {
+        let label = ui
+            .allocate_rect(label_rect, Sense::click())
+            .on_hover_text("Click to add a track");
+
+        if label.clicked() {
+            let (_, edits) = tracks_until(&mut state.write(), row);
+
+            let label = match edits.len() {
+                1 => "Add track",
+                _ => "Add tracks",
+            };
+
+            apply_edits(global_state, label, edits);
+        }
+
+        return;
+    };
+
+    // Allocate the response for the given track, it is dragged to reorder the tracks
+    let label = ui.allocate_rect(label_rect, Sense::click_and_drag());
 
-    // Detect if it has been right clicked on and store a entry in the customization list.
-    if label.secondary_clicked() && !custom_tracks.contains_key(&idx) {
-        custom_tracks.insert(idx, TrackCustomization::named_default(idx));
+    if label.drag_started() {
+        state.write().dragged_track = Some(track.id);
     }
 
-    // We should only allow the context menu to be opened if we already have the track customizations saved in the list.
-    if custom_tracks.contains_key(&idx) {
-        // Get mutable access to the created item
-        // Its safe to unwrap here due to the check above
-        let customization_state = custom_tracks.get_mut(&idx).unwrap();
-        // Open ctx menu and access the entry weve created
-        let popup = egui::Popup::context_menu(&label)
-            .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside);
+    // The buttons are allocated after the label, so that they take the input over it
+    track_buttons(ui, global_state, &track, label_rect);
 
-        let ctx_menu = popup.show(|ui| {
-            ui.horizontal(|ui| {
-                ui.label(RichText::from("Label").weak());
-                ui.text_edit_singleline(&mut customization_state.label_text);
-            });
-            ui.separator();
-            ui.label(RichText::from("Label Color"));
-            egui::widgets::color_picker::color_picker_color32(
-                ui,
-                &mut customization_state.label_color,
-                egui::widgets::color_picker::Alpha::Opaque,
-            );
-            ui.separator();
-            ui.label(RichText::from("Label Text Color"));
+    // A sample dropped onto the label is played by the track as a sampler, the label is highlighted while a sample is dragged over it
+    if label.dnd_hover_payload::<SampleInstance>().is_some() {
+        ui.painter().rect_stroke(
+            label_rect,
+            0.,
+            Stroke::new(2.0_f32, ui.visuals().selection.stroke.color),
+            egui::StrokeKind::Inside,
+        );
+    }
+
+    if let Some(sample) = released_payload::<SampleInstance>(ui, &label) {
+        load_sample(global_state, track.id, &sample);
+    }
+
+    // Every modification made while the context menu is open is a single entry in the history
+    let history_group = Id::new(("track_label", track.id));
+    let before = track.customization.clone();
+    let mut customization = before.clone();
+
+    // The actions of the menu are performed after the menu, so that the track is not modified while it is displayed
+    let mut insert_row = None;
+    let mut delete = false;
+
+    let popup = egui::Popup::context_menu(&label)
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside);
+
+    let ctx_menu = popup.show(|ui| {
+        if ui.button("Insert track above").clicked() {
+            insert_row = Some(row);
+        }
+
+        if ui.button("Insert track below").clicked() {
+            insert_row = Some(row + 1);
+        }
+
+        if ui.button("Delete track").clicked() {
+            delete = true;
+        }
+
+        if ui.button("Edit instrument").clicked() {
+            global_state.instrument_panel.write().track = Some(track.id);
+            ui.close();
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(RichText::from("Label").weak());
+            ui.text_edit_singleline(&mut customization.label_text);
+        });
+        ui.separator();
+        ui.label(RichText::from("Label Color"));
+        egui::widgets::color_picker::color_picker_color32(
+            ui,
+            &mut customization.label_color,
+            egui::widgets::color_picker::Alpha::Opaque,
+        );
+        ui.separator();
+        ui.label(RichText::from("Label Text Color"));
+        egui::widgets::color_picker::color_picker_color32(
+            ui,
+            &mut customization.label_text_color,
+            egui::widgets::color_picker::Alpha::Opaque,
+        );
+    });
+
+    // Record the modifications made in this frame
+    customize_track(global_state, history_group, track.id, before, customization);
+
+    // Check if the user has clicked outside of the context menu
+    if ctx_menu.is_none() {
+        history.write().close_group(history_group);
+    }
+
+    if let Some(row) = insert_row {
+        let track = state.write().tracks.create(row);
+
+        apply_edits(
+            global_state,
+            "Insert track",
+            vec![Edit::InsertTrack { row, track }],
+        );
+        ui.close();
+    }
+
+    if delete {
+        let edit = {
+            let state = state.read();
+
+            Edit::RemoveTrack {
+                row,
+                clips: state.clips.track_clips(track.id).cloned().collect(),
+                channel: global_state
+                    .mixer_panel
+                    .read()
+                    .channels
+                    .get(&track.id)
+                    .copied(),
+                track: track.clone(),
+            }
+        };
+
+        apply_edits(
+            global_state,
+            &format!("Delete {}", track.customization.label_text),
+            vec![edit],
+        );
+        ui.close();
+    }
+}
+
+/// Draws the mute, the solo and the color button of the track in the bottom of its label, if the label is tall enough.
+/// Muting and soloing modifies the channel strip of the track in the mixer.
+fn track_buttons(ui: &mut Ui, global_state: &PanelStates, track: &Track, label_rect: Rect) {
+    let size = vec2(TRACK_BUTTON_SIZE, TRACK_BUTTON_SIZE);
+
+    if label_rect.height() < TRACK_BUTTON_SIZE * 2. + TRACK_BUTTON_GAP {
+        return;
+    }
+
+    let button_rect = |nth: usize| {
+        Rect::from_min_size(
+            label_rect.left_bottom()
+                + vec2(
+                    TRACK_BUTTON_GAP + nth as f32 * (TRACK_BUTTON_SIZE + TRACK_BUTTON_GAP),
+                    -(TRACK_BUTTON_SIZE + TRACK_BUTTON_GAP),
+                ),
+            size,
+        )
+    };
+
+    let mixer = &global_state.mixer_panel;
+    let mut channel = mixer.read().channel(track.id);
+
+    if ui
+        .put(button_rect(0), egui::Button::selectable(channel.mute, "M"))
+        .on_hover_text("Mute")
+        .clicked()
+    {
+        channel.mute = !channel.mute;
+        mixer.write().set_channel(track.id, channel);
+        global_state.history.write().mark_changed();
+    }
+
+    if ui
+        .put(button_rect(1), egui::Button::selectable(channel.solo, "S"))
+        .on_hover_text("Solo")
+        .clicked()
+    {
+        channel.solo = !channel.solo;
+        mixer.write().set_channel(track.id, channel);
+        global_state.history.write().mark_changed();
+    }
+
+    // The color is picked in a popup, every modification made while it is open is a single entry in the history
+    let color_button = ui
+        .put(
+            button_rect(2),
+            egui::Button::new("").fill(track.customization.label_color),
+        )
+        .on_hover_text("Color");
+
+    let history_group = Id::new(("track_color", track.id));
+    let mut customization = track.customization.clone();
+
+    let popup = egui::Popup::menu(&color_button)
+        .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+        .show(|ui| {
             egui::widgets::color_picker::color_picker_color32(
                 ui,
-                &mut customization_state.label_text_color,
+                &mut customization.label_color,
                 egui::widgets::color_picker::Alpha::Opaque,
             );
         });
 
-        // Check if the user has clicked outside of the context menu
-        if ctx_menu.is_none() {
-            // If the context menu is closed we should check if the customization entry has been modified
-            // If not just remove it to save up memory
-            if *customization_state == TrackCustomization::named_default(idx) {
-                custom_tracks.remove(&idx);
-            }
+    customize_track(
+        global_state,
+        history_group,
+        track.id,
+        track.customization.clone(),
+        customization,
+    );
+
+    if popup.is_none() {
+        global_state.history.write().close_group(history_group);
+    }
+}
+
+/// Stores the customization of the track and records it in the group, if it has been modified.
+fn customize_track(
+    global_state: &PanelStates,
+    history_group: Id,
+    id: TrackId,
+    before: TrackCustomization,
+    after: TrackCustomization,
+) {
+    if before == after {
+        return;
+    }
+
+    if let Some(track) = global_state.playlist_panel.write().tracks.find_mut(id) {
+        track.customization = after.clone();
+    }
+
+    global_state.history.write().record_grouped(
+        history_group,
+        format!("Customize {}", before.label_text),
+        Edit::CustomizeTrack { id, before, after },
+    );
+}
+
+/// The ids of the tracks on the rows until `last_row`, the empty rows on the way are turned into tracks by the returned edits.
+fn tracks_until(state: &mut PlaylistState, last_row: usize) -> (Vec<TrackId>, Vec<Edit>) {
+    let mut ids: Vec<TrackId> = state.tracks.iter().map(|track| track.id).collect();
+
+    let edits = (state.tracks.len()..=last_row)
+        .map(|row| {
+            let track = state.tracks.create(row);
+
+            ids.push(track.id);
+
+            Edit::InsertTrack { row, track }
+        })
+        .collect();
+
+    (ids, edits)
+}
+
+/// Moves the track dragged by its label to the track line closest to the pointer, once it is released.
+fn reorder_tracks(
+    ui: &mut Ui,
+    global_state: &PanelStates,
+    playlist_rect: Rect,
+    track_lines: &[[Pos2; 2]],
+    first_visible_track_idx: usize,
+) {
+    let state = &global_state.playlist_panel;
+
+    let Some(id) = state.read().dragged_track else {
+        return;
+    };
+
+    let (pointer, released) = ui.input(|input| {
+        (
+            input.pointer.interact_pos(),
+            input.pointer.any_released() || !input.pointer.any_down(),
+        )
+    });
+
+    let (rows, from) = {
+        let state = state.read();
+
+        (state.tracks.len(), state.tracks.row(id))
+    };
+
+    // The line above a track inserts the track before it, lines after the last track are not used
+    let target = pointer.and_then(|pointer| {
+        track_lines
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| first_visible_track_idx + idx <= rows)
+            .min_by(|(_, a), (_, b)| {
+                (a[0].y - pointer.y)
+                    .abs()
+                    .total_cmp(&(b[0].y - pointer.y).abs())
+            })
+            .map(|(idx, line)| (first_visible_track_idx + idx, line[0].y))
+    });
+
+    if let Some((_, y)) = target {
+        ui.painter().with_clip_rect(playlist_rect).hline(
+            playlist_rect.x_range(),
+            y,
+            Stroke::new(STROKE_WIDTH * 2., SELECTION_COLOR),
+        );
+    }
+
+    if !released {
+        return;
+    }
+
+    state.write().dragged_track = None;
+
+    // Removing the track shifts the rows after it up by one
+    if let (Some(from), Some((row, _))) = (from, target) {
+        let to = match row > from {
+            true => row - 1,
+            false => row,
+        };
+
+        if to != from {
+            apply_edits(
+                global_state,
+                "Move track",
+                vec![Edit::MoveTrack { from, to }],
+            );
         }
     }
 }
 
+/// Where a row of the playlist is drawn, the rows after the last track are empty.
+#[derive(Debug, Clone, Copy)]
+struct TrackRow {
+    idx: usize,
+
+    /// The top of the row before the vertical offset of the playlist is applied.
+    y_coord: f32,
+
+    /// The zoomed height of the row.
+    height: f32,
+}
+
 fn track_separator(
     ui: &mut Ui,
+    global_state: &PanelStates,
     playlist_rect: Rect,
     normalized_y_offset: f32,
-    idx: usize,
-    y_coord: f32,
-    label_customization: &TrackCustomization,
-    custom_tracks: &mut HashMap<usize, TrackCustomization>,
+    row: TrackRow,
+    vertical_zoom: f32,
 ) -> [Pos2; 2] {
+    let TrackRow {
+        idx,
+        y_coord,
+        height,
+    } = row;
+
     // Draw track separator lines
     let separator_points = [
         Pos2::new(
             playlist_rect.left(),
-            (y_coord + normalized_y_offset + label_customization.height)
+            (y_coord + normalized_y_offset + height)
                 .clamp(playlist_rect.top(), playlist_rect.bottom()),
         ),
         Pos2::new(
             playlist_rect.right(),
-            (y_coord + normalized_y_offset + label_customization.height)
+            (y_coord + normalized_y_offset + height)
                 .clamp(playlist_rect.top(), playlist_rect.bottom()),
         ),
     ];
@@ -834,6 +3383,11 @@ fn track_separator(
         Stroke::new(STROKE_WIDTH, BAR_TRACK_SEPARATOR),
     );
 
+    // The empty rows after the last track cannot be resized
+    let Some(track) = global_state.playlist_panel.read().tracks.get(idx).cloned() else {
+        return separator_points;
+    };
+
     // Allocate a response for being able to set the height of the tracks
     let separator = ui.allocate_rect(
         Rect::from_points(&separator_points).expand2(vec2(0., 2.5)),
@@ -842,37 +3396,59 @@ fn track_separator(
 
     // Get how much this has been dragged by
     let height_delta = separator.drag_delta().y;
-    let pixel_delta = ui.pixels_per_point() * height_delta;
-
-    // Check if a drag has been started
-    if separator.drag_started() && !custom_tracks.contains_key(&idx) {
-        custom_tracks.insert(idx, TrackCustomization::named_default(idx));
+    // The stored height is not zoomed, so the drag is scaled back
+    let pixel_delta = ui.pixels_per_point() * height_delta / vertical_zoom;
+
+    // A whole drag is a single entry in the history
+    let history_group = Id::new(("track_height", track.id));
+    let before = track.customization;
+    let mut after = before.clone();
+
+    // If it has been double clicked that means that it should minimize the track or if its already minimzed then reset it to the original value
+    if separator.double_clicked() {
+        if after.height != MINIMUM_TRACK_HEIGHT {
+            after.height = MINIMUM_TRACK_HEIGHT;
+        } else {
+            after.height = TRACK_HEIGHT;
+        }
+    } else {
+        after.height = after.height.add(pixel_delta).max(MINIMUM_TRACK_HEIGHT);
     }
 
-    // Check if the item is inside the list
-    if custom_tracks.contains_key(&idx) {
-        // Get mutable access to the created item
-        // Its safe to unwrap here due to the check above
-        let customization_state = custom_tracks.get_mut(&idx).unwrap();
+    // Only the height can be modified here, so that is all we have to check
+    if before.height != after.height {
+        if let Some(track) = global_state
+            .playlist_panel
+            .write()
+            .tracks
+            .find_mut(track.id)
+        {
+            track.customization = after.clone();
+        }
 
-        // Set that it has been modified already
-        customization_state.height_set = true;
+        let edit = Edit::CustomizeTrack {
+            id: track.id,
+            before,
+            after,
+        };
 
-        // If it has been double clicked that means that it should minimize the track or if its already minimzed then reset it to the original value
         if separator.double_clicked() {
-            if customization_state.height != MINIMUM_TRACK_HEIGHT {
-                customization_state.height = MINIMUM_TRACK_HEIGHT;
-            } else {
-                customization_state.height = TRACK_HEIGHT;
-            }
+            global_state
+                .history
+                .write()
+                .record("Toggle track height", edit);
         } else {
-            customization_state.height = customization_state
-                .height
-                .add(pixel_delta)
-                .max(MINIMUM_TRACK_HEIGHT);
+            global_state
+                .history
+                .write()
+                .record_grouped(history_group, "Resize track", edit);
         }
     }
 
+    if separator.drag_stopped() {
+        global_state.history.write().close_group(history_group);
+    }
+
     // Indicate that this can be grabbed
     separator.on_hover_cursor(egui::CursorIcon::ResizeVertical);
 
@@ -0,0 +1,179 @@
+use std::path::PathBuf;
+
+use egui::{RichText, Ui};
+use egui_toast::{Toast, ToastOptions, ToastStyle};
+use strum::IntoEnumIterator;
+
+use crate::{
+    internals::{
+        render::{EXPORT_SAMPLE_RATES, RenderRange, export_stems, export_wav},
+        wav::BitDepth,
+    },
+    ui::{
+        panels::lib::display_error_as_toast,
+        windows::{ExportState, WindowContext},
+    },
+};
+
+/// Lets the user choose the format and the range of the mixdown, then renders it in the background.
+pub fn display_export_window(
+    ui: &mut Ui,
+    open: &mut bool,
+    state: &mut ExportState,
+    context: &WindowContext,
+) {
+    let is_exporting = state.task.as_ref().is_some_and(|task| !task.is_finished());
+
+    // The path is picked inside the window, but the export can only be started once the settings are no longer borrowed
+    let mut export_to = None;
+
+    egui::Window::new("Export Audio")
+        .open(open)
+        .resizable(false)
+        .show(ui.ctx(), |ui| {
+            ui.label(RichText::from("Output").strong());
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.stems, false, "Mixdown");
+                ui.radio_value(&mut state.stems, true, "Stems")
+                    .on_hover_text("Writes every track into its own file.");
+            });
+
+            ui.add_enabled(
+                state.stems,
+                egui::Checkbox::new(&mut state.skip_empty_tracks, "Skip empty tracks"),
+            );
+
+            ui.add_space(5.);
+
+            let settings = &mut state.settings;
+
+            ui.label(RichText::from("Format").strong());
+            ui.separator();
+
+            egui::ComboBox::from_label("Sample rate")
+                .selected_text(format!("{} Hz", settings.sample_rate))
+                .show_ui(ui, |ui| {
+                    for sample_rate in EXPORT_SAMPLE_RATES {
+                        ui.selectable_value(
+                            &mut settings.sample_rate,
+                            *sample_rate,
+                            format!("{sample_rate} Hz"),
+                        );
+                    }
+                });
+
+            egui::ComboBox::from_label("Bit depth")
+                .selected_text(settings.bit_depth.to_string())
+                .show_ui(ui, |ui| {
+                    for bit_depth in BitDepth::iter() {
+                        ui.selectable_value(
+                            &mut settings.bit_depth,
+                            bit_depth,
+                            bit_depth.to_string(),
+                        );
+                    }
+                });
+
+            ui.add_space(5.);
+            ui.label(RichText::from("Range").strong());
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut settings.range, RenderRange::Song, "Whole song");
+
+                if ui
+                    .radio(matches!(settings.range, RenderRange::Bars { .. }), "Bars")
+                    .clicked()
+                    && settings.range == RenderRange::Song
+                {
+                    settings.range = RenderRange::Bars { first: 1, last: 4 };
+                }
+            });
+
+            if let RenderRange::Bars { first, last } = &mut settings.range {
+                ui.horizontal(|ui| {
+                    ui.label("From bar");
+                    ui.add(egui::DragValue::new(first).range(1..=usize::MAX));
+                    ui.label("to bar");
+                    ui.add(egui::DragValue::new(last).range(*first..=usize::MAX));
+                });
+            }
+
+            ui.add_space(5.);
+            ui.label(RichText::from("Metronome").strong());
+            ui.separator();
+
+            ui.add_enabled(
+                !state.stems,
+                egui::Checkbox::new(&mut settings.include_metronome, "Include the metronome"),
+            )
+            .on_hover_text("The clicks are added to the mixdown, stems never contain them.");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!is_exporting, egui::Button::new("Export"))
+                    .clicked()
+                {
+                    // Stems are written into a folder, the mixdown into a single file
+                    export_to = match state.stems {
+                        true => rfd::FileDialog::new().pick_folder(),
+                        false => rfd::FileDialog::new()
+                            .add_filter("Wave Audio", &["wav"])
+                            .save_file()
+                            .map(|path| path.with_extension("wav")),
+                    };
+                }
+
+                if is_exporting {
+                    ui.spinner();
+                    ui.label("Rendering...");
+                }
+            });
+        });
+
+    if let Some(path) = export_to {
+        start_export(state, context, path);
+    }
+}
+
+/// Renders and writes the files on a blocking thread, the result is displayed as a toast.
+/// When exporting stems `path` is the folder the stems are written into.
+fn start_export(state: &mut ExportState, context: &WindowContext, path: PathBuf) {
+    let playlist = context.panel_states.playlist_panel.read().clone();
+    let mixer = context.panel_states.mixer_panel.read().clone();
+    let settings = state.settings.clone();
+    let (stems, skip_empty_tracks) = (state.stems, state.skip_empty_tracks);
+    let toasts = context.toasts.clone();
+
+    state.task = Some(tokio::spawn(async move {
+        let result = tokio::task::spawn_blocking({
+            let path = path.clone();
+
+            move || match stems {
+                true => {
+                    export_stems(&path, &playlist, &mixer, &settings, skip_empty_tracks).map(|_| ())
+                }
+                false => export_wav(&path, &playlist, &mixer, &settings),
+            }
+        })
+        .await;
+
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => Err(err.into()),
+        };
+
+        if display_error_as_toast(result, ToastStyle::default(), toasts.clone()).is_some() {
+            toasts.lock().add(
+                Toast::new()
+                    .kind(egui_toast::ToastKind::Success)
+                    .text(format!("Exported audio to `{}`", path.display()))
+                    .options(ToastOptions::default().duration_in_seconds(3.)),
+            );
+        }
+    }));
+}
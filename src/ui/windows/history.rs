@@ -0,0 +1,56 @@
+use egui::{RichText, ScrollArea, Ui};
+
+use crate::{project_manager::history::History, ui::windows::WindowContext};
+
+/// Lists the recorded edits, clicking on an entry undoes or redoes every edit up to it.
+pub fn display_history_window(ui: &mut Ui, open: &mut bool, context: &WindowContext) {
+    egui::Window::new("History")
+        .open(open)
+        .show(ui.ctx(), |ui| {
+            // How many entries should be undone (negative) or redone (positive)
+            let mut steps: isize = 0;
+
+            ScrollArea::vertical().show(ui, |ui| {
+                let history = context.panel_states.history.read();
+                let undo_entries = history.undo_entries();
+                let redo_entries = history.redo_entries();
+
+                // The state before any of the recorded edits
+                if ui
+                    .selectable_label(
+                        undo_entries.is_empty(),
+                        RichText::from("Initial state").weak(),
+                    )
+                    .clicked()
+                {
+                    steps = -(undo_entries.len() as isize);
+                }
+
+                for (idx, entry) in undo_entries.iter().enumerate() {
+                    let is_current = idx + 1 == undo_entries.len();
+
+                    if ui.selectable_label(is_current, &entry.label).clicked() {
+                        steps = idx as isize + 1 - undo_entries.len() as isize;
+                    }
+                }
+
+                // The redo stack is stored in reverse, the next entry to be redone is the last one
+                for (idx, entry) in redo_entries.iter().rev().enumerate() {
+                    if ui
+                        .selectable_label(false, RichText::from(&entry.label).weak())
+                        .clicked()
+                    {
+                        steps = idx as isize + 1;
+                    }
+                }
+            });
+
+            for _ in 0..steps.unsigned_abs() {
+                if steps < 0 {
+                    History::undo(context.panel_states);
+                } else {
+                    History::redo(context.panel_states);
+                }
+            }
+        });
+}
@@ -0,0 +1,118 @@
+use egui::{RichText, ScrollArea, Ui};
+
+use crate::{
+    project_manager::relink::{relink, search_candidates},
+    ui::{panels::lib::PanelStates, windows::MissingMediaState},
+};
+
+/// Lists the files of the project which could not be found, and allows the user to relink them.
+pub fn display_missing_media_window(
+    ui: &mut Ui,
+    open: &mut bool,
+    state: &mut MissingMediaState,
+    panel_states: &PanelStates,
+) {
+    egui::Window::new("Missing Media")
+        .open(open)
+        .show(ui.ctx(), |ui| {
+            ui.label(format!(
+                "{} file(s) referenced by the project could not be found.",
+                state.missing.len()
+            ));
+
+            ui.separator();
+
+            ui.label(RichText::from("Search folders").strong());
+
+            // Display the folders we are going to search in
+            let mut removed_folder = None;
+
+            for (idx, folder) in state.search_folders.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").clicked() {
+                        removed_folder = Some(idx);
+                    }
+
+                    ui.label(folder.display().to_string());
+                });
+            }
+
+            if let Some(idx) = removed_folder {
+                state.search_folders.remove(idx);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Add Folder").clicked()
+                    && let Some(folder) = rfd::FileDialog::new().pick_folder()
+                {
+                    state.search_folders.push(folder);
+                }
+
+                ui.separator();
+
+                // Every enabled criterium has to match for a file to become a candidate
+                ui.label("Match by");
+                ui.checkbox(&mut state.criteria.file_name, "File name");
+                ui.checkbox(&mut state.criteria.size, "Size");
+                ui.checkbox(&mut state.criteria.hash, "Hash");
+
+                ui.separator();
+
+                ui.add_enabled_ui(!state.search_folders.is_empty(), |ui| {
+                    if ui.button("Search").clicked() {
+                        search_candidates(
+                            &mut state.missing,
+                            &state.search_folders,
+                            state.criteria,
+                        );
+                    }
+                });
+            });
+
+            ui.separator();
+
+            // The missing file's index and the path it should be relinked to
+            let mut relinked = None;
+
+            ScrollArea::vertical().show(ui, |ui| {
+                for (idx, media) in state.missing.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.button("Locate").clicked()
+                            && let Some(path) = rfd::FileDialog::new().pick_file()
+                        {
+                            relinked = Some((idx, path));
+                        }
+
+                        ui.label(RichText::from(media.path.display().to_string()).strong());
+                    });
+
+                    if media.candidates.is_empty() {
+                        ui.label(RichText::from("No candidates found.").weak());
+                    }
+
+                    for candidate in &media.candidates {
+                        ui.horizontal(|ui| {
+                            if ui.button("Relink").clicked() {
+                                relinked = Some((idx, candidate.clone()));
+                            }
+
+                            ui.label(candidate.display().to_string());
+                        });
+                    }
+
+                    ui.separator();
+                }
+            });
+
+            if let Some((idx, new_path)) = relinked {
+                let media = state.missing.remove(idx);
+
+                relink(panel_states, &media.path, &new_path);
+            }
+        });
+
+    // There is nothing left to do once every file has been found
+    if state.missing.is_empty() {
+        *open = false;
+    }
+}
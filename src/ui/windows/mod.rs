@@ -1,7 +1,25 @@
+use std::{path::PathBuf, sync::Arc};
+
 use egui::Ui;
+use egui_toast::Toasts;
+use parking_lot::{Mutex, RwLock};
+use tokio::task::JoinHandle;
 
-pub mod settings;
+use crate::{
+    internals::render::RenderSettings,
+    project_manager::{
+        recovery::AutosaveSettings,
+        relink::{MatchCriteria, MissingMedia},
+    },
+    ui::panels::lib::PanelStates,
+};
+
+pub mod export;
+pub mod history;
+pub mod missing_media;
 pub mod plugins;
+pub mod recovery;
+pub mod settings;
 
 macro_rules! create_window_states {
     ($visibility:vis, $($window_name:ident => { $($state_field:ident : $state_ty:ty),* }),*) => {
@@ -9,13 +27,15 @@ macro_rules! create_window_states {
             #[derive(Default, Debug)]
             $visibility struct WindowsManager {
                 $(
-                    $visibility [<$window_name:lower>]: bool,
+                    $visibility [<$window_name:snake>]: bool,
+                    $visibility [<$window_name:snake _state>]: [<$window_name State>],
                 )*
             }
         }
 
         $(
             paste::paste! {
+                #[derive(Default, Debug)]
                 $visibility struct [<$window_name State>] {
                     $(
                         $visibility $state_field: $state_ty,
@@ -26,11 +46,53 @@ macro_rules! create_window_states {
     };
 }
 
-create_window_states! (pub, Settings => {  }, Plugins => {  }, Help => {  });
+create_window_states! (pub,
+    Settings => {  },
+    Plugins => {  },
+    Help => {  },
+    MissingMedia => { missing: Vec<MissingMedia>, search_folders: Vec<PathBuf>, criteria: MatchCriteria },
+    Recovery => { snapshot: Option<PathBuf>, session_dir: Option<PathBuf> },
+    History => {  },
+    Export => { settings: RenderSettings, stems: bool, skip_empty_tracks: bool, task: Option<JoinHandle<()>> }
+);
+
+/// The parts of the application the windows can access.
+pub struct WindowContext<'a> {
+    pub panel_states: &'a PanelStates,
+    pub toasts: Arc<Mutex<Toasts>>,
+    pub autosave: &'a RwLock<AutosaveSettings>,
+}
 
 impl WindowsManager {
-    pub fn display_windows(&self, ui: &mut Ui) {
-        
+    pub fn display_windows(&mut self, ui: &mut Ui, context: &WindowContext) {
+        if self.settings {
+            settings::display_settings_window(ui, &mut self.settings, context.autosave);
+        }
+
+        if self.missing_media {
+            missing_media::display_missing_media_window(
+                ui,
+                &mut self.missing_media,
+                &mut self.missing_media_state,
+                context.panel_states,
+            );
+        }
+
+        if self.history {
+            history::display_history_window(ui, &mut self.history, context);
+        }
+
+        if self.export {
+            export::display_export_window(ui, &mut self.export, &mut self.export_state, context);
+        }
+
+        if self.recovery {
+            recovery::display_recovery_window(
+                ui,
+                &mut self.recovery,
+                &mut self.recovery_state,
+                context,
+            );
+        }
     }
 }
-
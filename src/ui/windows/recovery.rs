@@ -0,0 +1,74 @@
+use egui::Ui;
+use egui_toast::ToastStyle;
+
+use crate::{
+    project_manager::{
+        open_project,
+        recovery::{discard_sessions, recovery_dir},
+    },
+    ui::{
+        panels::lib::display_error_as_toast,
+        windows::{RecoveryState, WindowContext},
+    },
+};
+
+/// Offers to restore the newest recovery snapshot after the last session has crashed.
+pub fn display_recovery_window(
+    ui: &mut Ui,
+    open: &mut bool,
+    state: &mut RecoveryState,
+    context: &WindowContext,
+) {
+    let Some(snapshot) = state.snapshot.clone() else {
+        *open = false;
+        return;
+    };
+
+    let mut is_resolved = false;
+
+    egui::Window::new("Recover Project")
+        .collapsible(false)
+        .resizable(false)
+        .show(ui.ctx(), |ui| {
+            ui.label("Beatroot did not exit properly last time.");
+            ui.label("Would you like to restore the newest recovery snapshot?");
+            ui.label(
+                egui::RichText::from(snapshot.display().to_string())
+                    .weak()
+                    .small(),
+            );
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                // The snapshots are kept if they cannot be restored, so that they can be recovered by hand
+                if ui.button("Restore").clicked()
+                    && let Some(project) = display_error_as_toast(
+                        open_project(&snapshot),
+                        ToastStyle::default(),
+                        context.toasts.clone(),
+                    )
+                {
+                    project.restore(context.panel_states);
+
+                    is_resolved = true;
+                }
+
+                if ui.button("Discard").clicked() {
+                    is_resolved = true;
+                }
+            });
+        });
+
+    if is_resolved {
+        // The crashed sessions are not offered again, only the snapshots of this session are kept
+        display_error_as_toast(
+            discard_sessions(&recovery_dir(), state.session_dir.as_deref()),
+            ToastStyle::default(),
+            context.toasts.clone(),
+        );
+
+        state.snapshot = None;
+        *open = false;
+    }
+}
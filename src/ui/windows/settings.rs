@@ -1,9 +1,37 @@
-use egui::{InnerResponse, Ui};
+use egui::{InnerResponse, RichText, Ui};
+use parking_lot::RwLock;
 
+use crate::project_manager::recovery::AutosaveSettings;
 
+pub fn display_settings_window(
+    ui: &mut Ui,
+    open: &mut bool,
+    autosave: &RwLock<AutosaveSettings>,
+) -> Option<InnerResponse<Option<()>>> {
+    egui::Window::new("Settings")
+        .open(open)
+        .show(ui.ctx(), |ui| {
+            let mut autosave = autosave.write();
 
-pub fn display_settings_window(ui: &mut Ui) -> Option<InnerResponse<Option<()>>> {
-    egui::Window::new("Settings").show(ui.ctx(), |ui| {
-        
-    })
-}
\ No newline at end of file
+            ui.label(RichText::from("Autosave").strong());
+            ui.separator();
+
+            ui.checkbox(&mut autosave.enabled, "Write recovery snapshots");
+
+            ui.add_enabled_ui(autosave.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Interval");
+                    ui.add(
+                        egui::DragValue::new(&mut autosave.interval_secs)
+                            .range(10..=3600)
+                            .suffix(" s"),
+                    );
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Snapshots kept");
+                    ui.add(egui::DragValue::new(&mut autosave.snapshot_count).range(1..=50));
+                });
+            });
+        })
+}
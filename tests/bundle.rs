@@ -0,0 +1,169 @@
+mod common;
+
+use std::path::Path;
+
+use beatroot::{
+    internals::{sample::SampleProperties, tempo::TempoMap, timeline::Tick, tracks::TrackId},
+    project_manager::{
+        Project,
+        bundle::{BUNDLE_MAGIC, save_bundle, save_bundle_archive},
+        history::Edit,
+        open_project,
+    },
+    ui::panels::{lib::PanelStates, playlist::SampleInstance},
+};
+use common::temp_dir;
+use egui::Color32;
+
+/// Creates a project referencing two different files which are both called `hat.wav`.
+fn project_with_duplicate_names(dir: &Path) -> Project {
+    let mut project = Project::default();
+
+    for (beat, folder) in ["a", "b"].into_iter().enumerate() {
+        let path = dir.join(folder).join("hat.wav");
+
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, folder).unwrap();
+
+        project.playlist.clips.insert(
+            TrackId::default(),
+            Tick::from_beats(beat as f64),
+            0.,
+            SampleInstance {
+                name: String::from("hat"),
+                color: Color32::WHITE,
+                path,
+                properties: SampleProperties::default(),
+                waveform_map: None,
+            },
+        );
+    }
+
+    project
+}
+
+fn sample_contents(project: &Project) -> Vec<String> {
+    project
+        .playlist
+        .clips
+        .iter()
+        .map(|clip| std::fs::read_to_string(&clip.source.sample().unwrap().path).unwrap())
+        .collect()
+}
+
+#[test]
+fn bundle_collects_samples_with_unique_names() {
+    let dir = temp_dir("bundle_folder");
+    let project = project_with_duplicate_names(&dir.join("sources"));
+
+    let collected = save_bundle(&dir.join("bundle/song.btrt"), &project).unwrap();
+
+    let samples = dir.join("bundle/samples");
+    assert_eq!(
+        std::fs::read_to_string(samples.join("hat.wav")).unwrap(),
+        "a"
+    );
+    assert_eq!(
+        std::fs::read_to_string(samples.join("hat (1).wav")).unwrap(),
+        "b"
+    );
+
+    assert_eq!(collected.len(), 2);
+    assert!(collected.values().all(|path| path.starts_with(&samples)));
+
+    // Delete the originals, the bundle should be self-contained
+    std::fs::remove_dir_all(dir.join("sources")).unwrap();
+
+    let loaded = open_project(&dir.join("bundle/song.btrt")).unwrap();
+    assert_eq!(sample_contents(&loaded), ["a", "b"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bundle_archive_opens_directly() {
+    let dir = temp_dir("bundle_archive");
+    let project = project_with_duplicate_names(&dir.join("sources"));
+
+    save_bundle_archive(&dir.join("song.btrb"), &project).unwrap();
+    std::fs::remove_dir_all(dir.join("sources")).unwrap();
+
+    let loaded = open_project(&dir.join("song.btrb")).unwrap();
+    assert_eq!(sample_contents(&loaded), ["a", "b"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn bundle_does_not_overwrite_other_files() {
+    let dir = temp_dir("bundle_existing");
+    let project = project_with_duplicate_names(&dir.join("sources"));
+    let samples = dir.join("bundle/samples");
+
+    std::fs::create_dir_all(&samples).unwrap();
+    std::fs::write(samples.join("hat.wav"), "unrelated").unwrap();
+
+    save_bundle(&dir.join("bundle/song.btrt"), &project).unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(samples.join("hat.wav")).unwrap(),
+        "unrelated"
+    );
+
+    let loaded = open_project(&dir.join("bundle/song.btrt")).unwrap();
+    assert_eq!(sample_contents(&loaded), ["a", "b"]);
+
+    // Bundling the bundle again keeps the names of the collected files
+    let collected = save_bundle(&dir.join("bundle/song.btrt"), &loaded).unwrap();
+    assert!(collected.iter().all(|(from, to)| from == to));
+    assert_eq!(std::fs::read_dir(&samples).unwrap().count(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn archives_inside_of_archives_are_refused() {
+    let dir = temp_dir("bundle_nested");
+    let path = dir.join("song.btrb");
+
+    let mut inner = BUNDLE_MAGIC.to_vec();
+    inner.extend(0u64.to_le_bytes());
+
+    let mut bytes = BUNDLE_MAGIC.to_vec();
+    bytes.extend((inner.len() as u64).to_le_bytes());
+    bytes.extend(inner);
+    std::fs::write(&path, bytes).unwrap();
+
+    let error = open_project(&path).unwrap_err();
+    assert!(error.to_string().contains("another bundle archive"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn collected_paths_are_rewritten_in_place() {
+    let dir = temp_dir("bundle_in_place");
+    let project = project_with_duplicate_names(&dir.join("sources"));
+
+    let states = PanelStates::default();
+    project.clone().restore(&states);
+    states.history.write().record(
+        "Change tempo",
+        Edit::ChangeTempo {
+            before: TempoMap::default(),
+            after: TempoMap::new(100.),
+        },
+    );
+
+    let collected = save_bundle(&dir.join("bundle/song.btrt"), &project).unwrap();
+    Project::map_state_media_paths(&states, |path| collected[path].clone());
+
+    // The history belongs to the same project, so it is kept
+    assert_eq!(states.history.read().undo_entries().len(), 1);
+
+    let captured = Project::capture(&states);
+    std::fs::remove_dir_all(dir.join("sources")).unwrap();
+    assert_eq!(sample_contents(&captured), ["a", "b"]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,287 @@
+mod common;
+
+use std::ops::Range;
+
+use beatroot::{
+    internals::{
+        clips::{Clip, ClipId, ClipStore, FadeCurve, MIN_CLIP_LENGTH},
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::{TrackId, TrackList},
+    },
+    ui::panels::playlist::SampleInstance,
+};
+use common::example_sample;
+
+/// At 60 bpm a second is a single beat, this keeps the lengths easy to follow.
+const BPM: f32 = 60.;
+
+fn kick() -> SampleInstance {
+    example_sample("kick", 1000)
+}
+
+fn beats(range: Range<f64>) -> Range<Tick> {
+    Tick::from_beats(range.start)..Tick::from_beats(range.end)
+}
+
+fn ids<'a>(clips: impl Iterator<Item = &'a Clip>) -> Vec<ClipId> {
+    clips.map(|clip| clip.id).collect()
+}
+
+#[test]
+fn clips_can_overlap() {
+    let mut store = ClipStore::default();
+
+    let first = store.insert(TrackId(0), Tick::from_beats(4.), 1., kick());
+    let second = store.insert(TrackId(0), Tick::from_beats(4.), 2., kick());
+
+    assert_ne!(first, second);
+    assert_eq!(store.len(), 2);
+
+    // Removing one of them leaves the other one untouched
+    store.remove(first);
+    assert_eq!(ids(store.iter()), [second]);
+}
+
+#[test]
+fn query_returns_the_clips_audible_in_the_range() {
+    let mut store = ClipStore::default();
+
+    let long = store.insert(TrackId(0), Tick::from_beats(0.), 8., kick());
+    let short = store.insert(TrackId(0), Tick::from_beats(2.), 1., kick());
+    let later = store.insert(TrackId(0), Tick::from_beats(10.), 1., kick());
+    let other_track = store.insert(TrackId(3), Tick::from_beats(4.), 1., kick());
+
+    // The long clip started before the range but it is still playing
+    assert_eq!(
+        ids(store.query([TrackId(0)], beats(5.0..6.0), &TempoMap::new(BPM))),
+        [long]
+    );
+    assert_eq!(
+        ids(store.query([TrackId(0)], beats(2.5..10.0), &TempoMap::new(BPM))),
+        [long, short]
+    );
+    assert_eq!(
+        ids(store.query((0..=3).map(TrackId), beats(9.0..11.0), &TempoMap::new(BPM))),
+        [later]
+    );
+    assert_eq!(
+        ids(store.query((1..=3).map(TrackId), beats(0.0..100.0), &TempoMap::new(BPM))),
+        [other_track]
+    );
+
+    // Clips end exactly where the next range starts
+    assert!(
+        store
+            .query([TrackId(0)], beats(11.0..12.0), &TempoMap::new(BPM))
+            .next()
+            .is_none()
+    );
+}
+
+#[test]
+fn moved_clips_keep_their_id_and_stay_sorted() {
+    let mut store = ClipStore::default();
+
+    let first = store.insert(TrackId(0), Tick::from_beats(0.), 1., kick());
+    let second = store.insert(TrackId(0), Tick::from_beats(2.), 1., kick());
+
+    let before = store
+        .move_clip(first, TrackId(0), Tick::from_beats(4.))
+        .unwrap();
+    assert_eq!(before.start, Tick(0));
+
+    assert_eq!(ids(store.track_clips(TrackId(0))), [second, first]);
+
+    store.move_clip(second, TrackId(1), Tick(0));
+    assert_eq!(store.tracks().collect::<Vec<_>>(), [TrackId(0), TrackId(1)]);
+
+    // New ids are never reused, not even after the newest clip has been removed
+    store.remove(second);
+    let third = store.insert(TrackId(0), Tick::from_beats(0.), 1., kick());
+    assert!(third > second);
+}
+
+#[test]
+fn trimming_keeps_the_audio_in_place() {
+    let mut store = ClipStore::default();
+    let tempo = TempoMap::new(BPM);
+
+    let id = store.insert(TrackId(0), Tick::from_beats(4.), 1., kick());
+    let mut clip = store.get(id).unwrap().clone();
+
+    // Trimming the start skips the beginning of the sample, the end stays where it was
+    clip.trim_start(Tick::from_beats(4.25), &tempo);
+    assert_eq!(clip.start, Tick::from_beats(4.25));
+    assert_eq!((clip.shape.offset, clip.length), (0.25, 0.75));
+    assert_eq!(clip.end(&tempo), Tick::from_beats(5.));
+
+    // The start can not be moved before the start of the sample, or past the end of the clip
+    clip.trim_start(Tick::from_beats(2.), &tempo);
+    assert_eq!((clip.start, clip.shape.offset), (Tick::from_beats(4.), 0.));
+
+    clip.trim_start(Tick::from_beats(6.), &tempo);
+    assert!((clip.length - MIN_CLIP_LENGTH).abs() < 1e-3);
+    assert_eq!(clip.end(&tempo), Tick::from_beats(5.));
+
+    // The end can not be moved after the end of the sample
+    clip.trim_start(Tick::from_beats(4.5), &tempo);
+    clip.trim_end(Tick::from_beats(8.), &tempo);
+    assert_eq!(clip.length, 0.5);
+
+    clip.trim_end(Tick::from_beats(4.75), &tempo);
+    assert_eq!(clip.length, 0.25);
+}
+
+#[test]
+fn reversed_clips_play_the_same_part_of_the_sample() {
+    let mut store = ClipStore::default();
+    let tempo = TempoMap::new(BPM);
+
+    let id = store.insert(TrackId(0), Tick::from_beats(0.), 1., kick());
+    let mut clip = store.get(id).unwrap().clone();
+
+    // The clip plays the second quarter of the sample
+    clip.trim_start(Tick::from_beats(0.25), &tempo);
+    clip.trim_end(Tick::from_beats(0.5), &tempo);
+
+    clip.set_reverse(true);
+    assert_eq!(clip.shape.offset, 0.5);
+
+    // The clip starts with the end of the part and ends with its start
+    assert_eq!(clip.shape.sample_position(0., clip.sample_length()), 0.5);
+    assert_eq!(clip.shape.sample_position(0.25, clip.sample_length()), 0.25);
+    assert_eq!(clip.shape.clip_position(0.5, clip.sample_length()), 0.);
+
+    clip.set_reverse(false);
+    assert_eq!(clip.shape.offset, 0.25);
+}
+
+#[test]
+fn fades_shape_the_gain_of_the_clip() {
+    let mut store = ClipStore::default();
+
+    let id = store.insert(TrackId(0), Tick::from_beats(0.), 1., kick());
+    let mut clip = store.get(id).unwrap().clone();
+
+    clip.shape.gain = 0.5;
+    clip.shape.fade_in.length = 0.5;
+    clip.shape.fade_out.length = 0.25;
+    clip.shape.fade_out.curve = FadeCurve::EqualPower;
+
+    assert_eq!(clip.shape.gain_at(0., clip.length), 0.);
+    assert_eq!(clip.shape.gain_at(0.25, clip.length), 0.25);
+    assert_eq!(clip.shape.gain_at(0.6, clip.length), 0.5);
+    assert!((clip.shape.gain_at(0.875, clip.length) - 0.5 * 0.5f32.sqrt()).abs() < 1e-6);
+
+    // Fades which do not fit into the clip are shortened, the fade in is kept
+    clip.shape.fade_out.length = 0.75;
+    clip.clamp_fades();
+    assert_eq!(clip.shape.fade_out.length, 0.5);
+}
+
+#[test]
+fn split_clips_play_the_same_audio_as_the_whole_clip() {
+    let mut store = ClipStore::default();
+    let tempo = TempoMap::new(BPM);
+
+    let id = store.insert(TrackId(0), Tick::from_beats(2.), 1., kick());
+    let mut clip = store.get(id).unwrap().clone();
+
+    clip.shape.fade_in.length = 0.25;
+    clip.shape.fade_out.length = 0.25;
+
+    let (first, second) = clip
+        .split(Tick::from_beats(2.5), ClipId(9), &tempo)
+        .unwrap();
+
+    assert_eq!((first.id, first.start, first.length), (id, clip.start, 0.5));
+    assert_eq!(
+        (second.id, second.start, second.length),
+        (ClipId(9), Tick::from_beats(2.5), 0.5)
+    );
+
+    // The second part continues the sample where the first one stops
+    assert_eq!(second.shape.offset, 0.5);
+    assert_eq!(second.end(&tempo), clip.end(&tempo));
+
+    // The fades stay on the outer edges
+    assert_eq!(
+        (first.shape.fade_in.length, first.shape.fade_out.length),
+        (0.25, 0.)
+    );
+    assert_eq!(
+        (second.shape.fade_in.length, second.shape.fade_out.length),
+        (0., 0.25)
+    );
+
+    // Clips can not be split outside of themselves
+    assert!(
+        clip.split(Tick::from_beats(1.), ClipId(9), &tempo)
+            .is_none()
+    );
+    assert!(
+        clip.split(Tick::from_beats(3.), ClipId(9), &tempo)
+            .is_none()
+    );
+}
+
+#[test]
+fn moved_clips_keep_their_relative_positions() {
+    let mut store = ClipStore::default();
+
+    let first = store.insert(TrackId(1), Tick::from_beats(2.), 1., kick());
+    let second = store.insert(TrackId(3), Tick::from_beats(6.), 1., kick());
+
+    let mut tracks = TrackList::default();
+    for row in 0..4 {
+        let track = tracks.create(row);
+        tracks.insert(row, track);
+    }
+
+    // Moving the clips two tracks up and four beats back would move the first clip out of the playlist
+    let moves = store.moved_positions(
+        [first, second],
+        &tracks,
+        -2,
+        -(Tick::from_beats(4.).0 as i64),
+    );
+
+    assert_eq!(
+        moves,
+        [
+            (first, (1, Tick::from_beats(2.)), (0, Tick(0))),
+            (second, (3, Tick::from_beats(6.)), (2, Tick::from_beats(4.))),
+        ]
+    );
+}
+
+#[test]
+fn removed_ids_are_not_handed_out_again_after_loading() {
+    let mut store = ClipStore::default();
+    let kept = store.insert(TrackId(0), Tick(0), 1., kick());
+    let removed = store.insert(TrackId(0), Tick(0), 1., kick());
+    store.remove(removed);
+
+    let mut store: ClipStore =
+        rmp_serde::from_slice(&rmp_serde::to_vec_named(&store).unwrap()).unwrap();
+
+    assert_eq!(ids(store.iter()), [kept]);
+    assert_ne!(store.insert(TrackId(0), Tick(0), 1., kick()), removed);
+}
+
+#[test]
+fn removed_track_ids_are_not_handed_out_again_after_loading() {
+    let mut tracks = TrackList::default();
+    for row in 0..2 {
+        let track = tracks.create(row);
+        tracks.insert(row, track);
+    }
+    let removed = tracks.remove(1).unwrap().id;
+
+    let mut tracks: TrackList =
+        rmp_serde::from_slice(&rmp_serde::to_vec_named(&tracks).unwrap()).unwrap();
+
+    assert_eq!(tracks.len(), 1);
+    assert_ne!(tracks.create(1).id, removed);
+}
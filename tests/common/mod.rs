@@ -0,0 +1,44 @@
+//! Fixtures shared by the integration tests.
+
+// Every test crate compiles this module on its own, none of them uses all of it
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use beatroot::{internals::sample::SampleProperties, ui::panels::playlist::SampleInstance};
+use egui::Color32;
+
+/// The sample rate the tests render in.
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// Creates an empty directory which is unique to this test.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("beatroot_{name}_{}", std::process::id()));
+
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+/// A path to a file which is unique to this test, the file is not created.
+pub fn temp_path(name: &str, extension: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "beatroot_{name}_{}.{extension}",
+        std::process::id()
+    ))
+}
+
+/// A sample which is `length` milliseconds long, its file does not exist.
+pub fn example_sample(name: &str, length: i128) -> SampleInstance {
+    SampleInstance {
+        name: name.to_string(),
+        color: Color32::BLUE,
+        path: PathBuf::from(format!("/samples/{name}.wav")),
+        properties: SampleProperties {
+            sample_rate: SAMPLE_RATE,
+            length,
+        },
+        waveform_map: None,
+    }
+}
@@ -0,0 +1,293 @@
+mod common;
+
+use beatroot::{
+    internals::{
+        clips::{Clip, ClipId, ClipShape, ClipSource},
+        instruments::Instrument,
+        timeline::Tick,
+        tracks::{Track, TrackId},
+    },
+    project_manager::history::{Edit, History},
+    ui::panels::{lib::PanelStates, playlist::TrackCustomization},
+};
+use common::example_sample;
+use egui::{Color32, Id};
+
+fn track_with_height(height: f32) -> TrackCustomization {
+    TrackCustomization {
+        label_text: String::from("Track 0"),
+        label_text_color: Color32::WHITE,
+        label_color: Color32::GRAY,
+        height,
+    }
+}
+
+/// The track and the start of the clip, if it exists.
+fn clip_position(states: &PanelStates, id: ClipId) -> Option<(TrackId, Tick)> {
+    states
+        .playlist_panel
+        .read()
+        .clips
+        .get(id)
+        .map(|clip| (clip.track, clip.start))
+}
+
+#[test]
+fn place_and_move_are_undone_and_redone() {
+    let states = PanelStates::default();
+    let id = ClipId(7);
+
+    let place = Edit::PlaceClip {
+        clip: Clip {
+            id,
+            track: TrackId(0),
+            start: Tick::from_beats(4.),
+            length: 0.5,
+            source: ClipSource::Sample(example_sample("kick", 500)),
+            shape: ClipShape::default(),
+        },
+        imported: None,
+    };
+    place.apply(&states);
+    states.history.write().record("Place clip", place);
+
+    let move_clip = Edit::MoveClip {
+        id,
+        from: (TrackId(0), Tick::from_beats(4.)),
+        to: (TrackId(1), Tick::from_beats(8.)),
+    };
+    move_clip.apply(&states);
+    states.history.write().record("Move clip", move_clip);
+
+    assert_eq!(
+        clip_position(&states, id),
+        Some((TrackId(1), Tick::from_beats(8.)))
+    );
+
+    assert_eq!(History::undo(&states).as_deref(), Some("Move clip"));
+    assert_eq!(
+        clip_position(&states, id),
+        Some((TrackId(0), Tick::from_beats(4.)))
+    );
+
+    assert_eq!(History::undo(&states).as_deref(), Some("Place clip"));
+    assert!(states.playlist_panel.read().clips.is_empty());
+    assert_eq!(History::undo(&states), None);
+
+    // The clip keeps its id when it is placed again
+    History::redo(&states);
+    History::redo(&states);
+    assert_eq!(
+        clip_position(&states, id),
+        Some((TrackId(1), Tick::from_beats(8.)))
+    );
+    assert!(states.history.read().redo_entries().is_empty());
+}
+
+#[test]
+fn grouped_edits_become_a_single_entry() {
+    let states = PanelStates::default();
+    let group = Id::new("resize");
+    let id = TrackId(0);
+
+    states.playlist_panel.write().tracks.insert(
+        0,
+        Track {
+            id,
+            customization: track_with_height(30.0),
+            instrument: Instrument::default(),
+        },
+    );
+
+    for (before, after) in [(30.0, 40.0), (40.0, 50.0), (50.0, 60.0)] {
+        let edit = Edit::CustomizeTrack {
+            id,
+            before: track_with_height(before),
+            after: track_with_height(after),
+        };
+        edit.apply(&states);
+        states
+            .history
+            .write()
+            .record_grouped(group, "Resize track", edit);
+    }
+
+    states.history.write().close_group(group);
+
+    assert_eq!(states.history.read().undo_entries().len(), 1);
+    assert_eq!(states.history.read().undo_entries()[0].edits.len(), 1);
+
+    let height = |states: &PanelStates| {
+        states
+            .playlist_panel
+            .read()
+            .tracks
+            .get(0)
+            .map(|track| track.customization.height)
+    };
+
+    History::undo(&states);
+    assert_eq!(height(&states), Some(30.0));
+
+    History::redo(&states);
+    assert_eq!(height(&states), Some(60.0));
+}
+
+#[test]
+fn edits_recorded_together_are_undone_together() {
+    let states = PanelStates::default();
+
+    let edits: Vec<Edit> = [ClipId(1), ClipId(2)]
+        .into_iter()
+        .map(|id| Edit::PlaceClip {
+            clip: Clip {
+                id,
+                track: TrackId(id.0),
+                start: Tick::from_beats(4.),
+                length: 0.5,
+                source: ClipSource::Sample(example_sample("kick", 500)),
+                shape: ClipShape::default(),
+            },
+            imported: None,
+        })
+        .collect();
+
+    for edit in &edits {
+        edit.apply(&states);
+    }
+    states.history.write().record_all("Paste clips", edits);
+
+    // Nothing is recorded if there is nothing to record
+    states.history.write().record_all("Paste clips", Vec::new());
+    assert_eq!(states.history.read().undo_entries().len(), 1);
+
+    assert_eq!(History::undo(&states).as_deref(), Some("Paste clips"));
+    assert!(states.playlist_panel.read().clips.is_empty());
+
+    History::redo(&states);
+    assert_eq!(
+        clip_position(&states, ClipId(2)),
+        Some((TrackId(2), Tick::from_beats(4.)))
+    );
+    assert_eq!(states.playlist_panel.read().clips.len(), 2);
+}
+
+#[test]
+fn deleted_tracks_are_restored_with_their_clips_and_channel() {
+    let states = PanelStates::default();
+
+    for row in 0..3 {
+        let track = states.playlist_panel.write().tracks.create(row);
+        Edit::InsertTrack { row, track }.apply(&states);
+    }
+
+    let track = states.playlist_panel.read().tracks.get(1).unwrap().clone();
+    let clip = Clip {
+        id: ClipId(1),
+        track: track.id,
+        start: Tick::from_beats(4.),
+        length: 0.5,
+        source: ClipSource::Sample(example_sample("kick", 500)),
+        shape: ClipShape::default(),
+    };
+    Edit::PlaceClip {
+        clip: clip.clone(),
+        imported: None,
+    }
+    .apply(&states);
+
+    let mut channel = states.mixer_panel.read().channel(track.id);
+    channel.mute = true;
+    states.mixer_panel.write().set_channel(track.id, channel);
+
+    let remove = Edit::RemoveTrack {
+        row: 1,
+        track: track.clone(),
+        clips: vec![clip],
+        channel: Some(channel),
+    };
+    remove.apply(&states);
+    states.history.write().record("Delete track", remove);
+
+    assert_eq!(states.playlist_panel.read().tracks.len(), 2);
+    assert!(states.playlist_panel.read().clips.is_empty());
+    assert!(!states.mixer_panel.read().channel(track.id).mute);
+
+    History::undo(&states);
+    assert_eq!(states.playlist_panel.read().tracks.row(track.id), Some(1));
+    assert_eq!(
+        clip_position(&states, ClipId(1)),
+        Some((track.id, Tick::from_beats(4.)))
+    );
+    assert_eq!(states.mixer_panel.read().channel(track.id), channel);
+
+    // Moving the track keeps its clips on it
+    let move_track = Edit::MoveTrack { from: 1, to: 0 };
+    move_track.apply(&states);
+    states.history.write().record("Move track", move_track);
+    assert_eq!(states.playlist_panel.read().tracks.row(track.id), Some(0));
+    assert_eq!(
+        clip_position(&states, ClipId(1)).map(|(id, _)| id),
+        Some(track.id)
+    );
+
+    History::undo(&states);
+    assert_eq!(states.playlist_panel.read().tracks.row(track.id), Some(1));
+}
+
+#[test]
+fn generation_changes_with_the_project() {
+    let states = PanelStates::default();
+    let generation = || states.history.read().generation();
+    let group = Id::new("resize");
+    let start = generation();
+
+    let edit = Edit::CustomizeTrack {
+        id: TrackId(0),
+        before: track_with_height(30.0),
+        after: track_with_height(40.0),
+    };
+
+    // Extending a group changes the project too, even though no entry is added
+    for _ in 0..2 {
+        let before = generation();
+        states
+            .history
+            .write()
+            .record_grouped(group, "Resize track", edit.clone());
+        assert_ne!(generation(), before);
+    }
+
+    let mut seen = vec![start, generation()];
+
+    History::undo(&states);
+    seen.push(generation());
+
+    History::redo(&states);
+    seen.push(generation());
+
+    states.history.write().mark_changed();
+    seen.push(generation());
+
+    states.history.write().clear();
+    seen.push(generation());
+
+    seen.dedup();
+    assert_eq!(seen.len(), 6);
+
+    // Looking at the history is not a change
+    assert_eq!(generation(), *seen.last().unwrap());
+}
+
+#[test]
+fn unrecorded_changes_keep_the_timeline_generation() {
+    let mut history = History::default();
+    let timeline = history.timeline_generation();
+
+    // The mixer and the other settings are followed by the playback engine on its own
+    history.mark_changed();
+    assert_eq!(history.timeline_generation(), timeline);
+
+    history.mark_timeline_changed();
+    assert_ne!(history.timeline_generation(), timeline);
+}
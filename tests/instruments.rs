@@ -0,0 +1,411 @@
+mod common;
+
+use std::{path::PathBuf, sync::Arc};
+
+use beatroot::{
+    internals::{
+        instruments::{Envelope, Instrument, LoopPoints, Sampler, SamplerMode},
+        playback::{OUTPUT_CHANNELS, ScheduledNote, Transport},
+        sample::DecodedSample,
+        synth::{FilterMode, LfoTarget, Synth, Waveform},
+        tempo::TempoMap,
+        tracks::TrackId,
+    },
+    project_manager::{
+        Project,
+        history::{Edit, History},
+        presets::{decode_preset, encode_preset, open_preset, save_preset},
+    },
+    ui::panels::{instrument::load_sample, lib::PanelStates},
+};
+use common::{SAMPLE_RATE, example_sample};
+
+/// A mono sample holding the same value for a second.
+fn constant_audio(value: f32) -> Arc<DecodedSample> {
+    Arc::new(DecodedSample {
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        samples: vec![value; SAMPLE_RATE as usize],
+    })
+}
+
+/// A sampler without any fades, so that the levels of the output can be compared exactly.
+fn example_sampler(mode: SamplerMode) -> Sampler {
+    Sampler {
+        sample: Some(example_sample("pad", 1000)),
+        envelope: Envelope {
+            attack: 0.,
+            decay: 0.,
+            sustain: 1.,
+            release: 0.,
+        },
+        mode,
+        ..Default::default()
+    }
+}
+
+/// Plays the voice until it finishes, returns the amount of frames it has played.
+fn played_frames(
+    instrument: &Instrument,
+    key: u8,
+    gate: usize,
+    audio: Arc<DecodedSample>,
+) -> usize {
+    let mut voice = instrument
+        .voice(key, 1., gate, SAMPLE_RATE, Some(&audio))
+        .unwrap();
+    let mut output = vec![0.; 512 * OUTPUT_CHANNELS];
+    let mut frames = 0;
+
+    while !voice.mix(&mut output) {
+        frames += 512;
+    }
+
+    frames
+}
+
+/// Plays the instrument for the amount of frames in chunks of `chunk` frames, returns the left channel.
+fn played_output(
+    instrument: &Instrument,
+    key: u8,
+    gate: usize,
+    frames: usize,
+    chunk: usize,
+) -> Vec<f32> {
+    let mut voice = instrument.voice(key, 1., gate, SAMPLE_RATE, None).unwrap();
+    let mut output = vec![0.; frames * OUTPUT_CHANNELS];
+
+    for chunk in output.chunks_mut(chunk * OUTPUT_CHANNELS) {
+        voice.mix(chunk);
+    }
+
+    output.iter().step_by(OUTPUT_CHANNELS).copied().collect()
+}
+
+fn loudness(samples: &[f32]) -> f32 {
+    (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+fn sampler_note(
+    track: u64,
+    beat: f64,
+    sampler: &Sampler,
+    audio: Arc<DecodedSample>,
+) -> ScheduledNote {
+    ScheduledNote {
+        beat,
+        length: 4.,
+        track: TrackId(track),
+        key: sampler.root,
+        velocity: 1.,
+        instrument: Arc::new(Instrument::Sampler(sampler.clone())),
+        audio: Some(audio),
+    }
+}
+
+/// Renders two seconds of the notes at 120 beats per minute, returns the left channel.
+fn render_notes(notes: Vec<ScheduledNote>) -> Vec<f32> {
+    let mut transport = Transport::new(SAMPLE_RATE);
+
+    transport.set_tempo(TempoMap::new(120.));
+    transport.set_notes(notes);
+    transport.seek(0.);
+    transport.set_playing(true);
+
+    let mut output = vec![0.; SAMPLE_RATE as usize * 2 * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    output.iter().step_by(OUTPUT_CHANNELS).copied().collect()
+}
+
+#[test]
+fn envelope_goes_through_its_stages() {
+    let envelope = Envelope {
+        attack: 1.,
+        decay: 1.,
+        sustain: 0.5,
+        release: 2.,
+    };
+
+    assert_eq!(envelope.level(0.5, 10.), 0.5);
+    assert_eq!(envelope.level(1.5, 10.), 0.75);
+    assert_eq!(envelope.level(5., 10.), 0.5);
+
+    // The release fades out from the sustain level, or from wherever the key has been released
+    assert_eq!(envelope.level(11., 10.), 0.25);
+    assert_eq!(envelope.level(13., 10.), 0.);
+    assert_eq!(envelope.level(0.5, 0.5), 0.5);
+}
+
+#[test]
+fn sampler_pitches_the_sample_by_the_key() {
+    let instrument = Instrument::Sampler(example_sampler(SamplerMode::OneShot));
+    let audio = constant_audio(0.5);
+    let root = played_frames(&instrument, 60, 0, audio.clone());
+
+    // One-shots play the whole sample, an octave higher plays it twice as fast
+    assert!(root.abs_diff(SAMPLE_RATE as usize) <= 512);
+    assert!(played_frames(&instrument, 72, 0, audio.clone()).abs_diff(root / 2) <= 512);
+
+    // Nothing is played until a sample is decoded
+    assert!(instrument.voice(60, 1., 0, SAMPLE_RATE, None).is_none());
+    assert!(
+        Instrument::Sine
+            .voice(60, 1., 0, SAMPLE_RATE, None)
+            .is_some()
+    );
+}
+
+#[test]
+fn gated_samplers_loop_while_the_key_is_held() {
+    let mut sampler = example_sampler(SamplerMode::Gate);
+    let audio = constant_audio(0.5);
+    let gate = SAMPLE_RATE as usize * 3;
+
+    // Without a loop the sample ends before the key is released
+    let instrument = Instrument::Sampler(sampler.clone());
+    assert!(played_frames(&instrument, 60, gate, audio.clone()) < gate);
+
+    sampler.loop_points = Some(LoopPoints {
+        start: 0.25,
+        end: 0.75,
+    });
+
+    let instrument = Instrument::Sampler(sampler.clone());
+    assert!(played_frames(&instrument, 60, gate, audio.clone()).abs_diff(gate) <= 512);
+
+    // The loop is ignored by one-shots
+    sampler.mode = SamplerMode::OneShot;
+
+    let instrument = Instrument::Sampler(sampler);
+    assert!(played_frames(&instrument, 60, gate, audio).abs_diff(SAMPLE_RATE as usize) <= 512);
+}
+
+#[test]
+fn choke_groups_stop_the_samplers_of_other_tracks() {
+    let mut sampler = example_sampler(SamplerMode::OneShot);
+    let beat = SAMPLE_RATE as usize / 2;
+
+    sampler.choke_group = Some(1);
+
+    let left = render_notes(vec![
+        sampler_note(0, 0., &sampler, constant_audio(0.5)),
+        sampler_note(1, 1., &sampler, constant_audio(0.25)),
+    ]);
+
+    // The second note replaces the first one once it has faded out
+    assert_eq!(left[beat / 2], 0.5);
+    assert_eq!(left[beat + beat / 2], 0.25);
+
+    // Notes of the same track are not choked
+    let left = render_notes(vec![
+        sampler_note(0, 0., &sampler, constant_audio(0.5)),
+        sampler_note(0, 1., &sampler, constant_audio(0.25)),
+    ]);
+
+    assert_eq!(left[beat + beat / 2], 0.75);
+}
+
+#[test]
+fn polyphony_stops_the_oldest_notes() {
+    let mut sampler = example_sampler(SamplerMode::OneShot);
+    let beat = SAMPLE_RATE as usize / 2;
+
+    sampler.polyphony = 2;
+
+    let left = render_notes(vec![
+        sampler_note(0, 0., &sampler, constant_audio(0.1)),
+        sampler_note(0, 0.5, &sampler, constant_audio(0.2)),
+        sampler_note(0, 1., &sampler, constant_audio(0.4)),
+    ]);
+
+    assert!((left[beat / 2 + beat / 4] - 0.3).abs() < 1e-6);
+    assert!((left[beat + beat / 2] - 0.6).abs() < 1e-6);
+}
+
+#[test]
+fn loaded_samples_are_undone_and_referenced() {
+    let states = PanelStates::default();
+    let track = states.playlist_panel.write().tracks.create(0);
+    let id = track.id;
+
+    states.playlist_panel.write().tracks.insert(0, track);
+
+    load_sample(&states, id, &example_sample("kick", 1000));
+
+    let instrument = states
+        .playlist_panel
+        .read()
+        .tracks
+        .find(id)
+        .unwrap()
+        .instrument
+        .clone();
+    assert_eq!(
+        instrument.sample().map(|sample| sample.path.clone()),
+        Some(PathBuf::from("/samples/kick.wav"))
+    );
+    assert_eq!(states.instrument_panel.read().track, Some(id));
+
+    // The sample of the sampler is a part of the project
+    let project = Project {
+        playlist: states.playlist_panel.read().clone(),
+        ..Default::default()
+    };
+    assert!(
+        project
+            .referenced_media()
+            .contains(&PathBuf::from("/samples/kick.wav"))
+    );
+
+    // Loading another sample keeps the settings of the sampler
+    let Instrument::Sampler(mut sampler) = instrument else {
+        panic!("The track should play a sampler");
+    };
+    sampler.root = 48;
+
+    let edit = Edit::ChangeInstrument {
+        id,
+        before: Instrument::Sampler(sampler.clone()),
+        after: Instrument::Sampler(sampler.clone()),
+    };
+    edit.apply(&states);
+
+    load_sample(&states, id, &example_sample("snare", 1000));
+
+    let Instrument::Sampler(loaded) = states
+        .playlist_panel
+        .read()
+        .tracks
+        .find(id)
+        .unwrap()
+        .instrument
+        .clone()
+    else {
+        panic!("The track should play a sampler");
+    };
+    assert_eq!(loaded.root, 48);
+
+    History::undo(&states);
+    History::undo(&states);
+    assert_eq!(
+        states
+            .playlist_panel
+            .read()
+            .tracks
+            .find(id)
+            .unwrap()
+            .instrument,
+        Instrument::Sine
+    );
+}
+
+/// A synth playing a single saw without any modulation.
+fn plain_synth(mode: FilterMode, cutoff: f32) -> Synth {
+    let mut synth = Synth::default();
+
+    synth.oscillators[1].level = 0.;
+    synth.filter.mode = mode;
+    synth.filter.cutoff = cutoff;
+    synth.filter.envelope_amount = 0.;
+
+    synth
+}
+
+#[test]
+fn synth_notes_fade_out_after_their_release() {
+    let synth = Synth::default();
+    let instrument = Instrument::Synth(synth);
+    let gate = SAMPLE_RATE as usize / 2;
+    let release = (synth.amp_envelope.release * SAMPLE_RATE as f64) as usize;
+
+    assert_eq!(
+        instrument.duration(60, 0.5, None),
+        0.5 + synth.amp_envelope.release
+    );
+
+    let left = played_output(&instrument, 60, gate, SAMPLE_RATE as usize, 512);
+
+    assert!(loudness(&left[..gate]) > 0.01);
+    assert!(
+        left[gate + release + 512..]
+            .iter()
+            .all(|sample| *sample == 0.)
+    );
+    assert_eq!(instrument.polyphony(), Some(synth.polyphony));
+}
+
+#[test]
+fn synth_renders_the_same_in_any_chunks() {
+    let mut synth = Synth::default();
+
+    synth.oscillators[1].waveform = Waveform::Noise;
+    synth.lfo.depth = 0.5;
+    synth.lfo.target = LfoTarget::Cutoff;
+
+    let instrument = Instrument::Synth(synth);
+    let whole = played_output(&instrument, 64, 10000, 20000, 20000);
+
+    // Chunks which do not line up with the blocks and the lanes of the synth
+    assert_eq!(played_output(&instrument, 64, 10000, 20000, 37), whole);
+    assert_eq!(played_output(&instrument, 64, 10000, 20000, 1), whole);
+
+    // The noise is the same every time the note is played
+    assert_eq!(played_output(&instrument, 64, 10000, 20000, 512), whole);
+}
+
+#[test]
+fn synth_filter_shapes_the_oscillators() {
+    let frames = SAMPLE_RATE as usize / 4;
+    let loudness_of = |synth: Synth| {
+        loudness(&played_output(&Instrument::Synth(synth), 84, frames, frames, 512)[1000..])
+    };
+
+    let open = loudness_of(plain_synth(FilterMode::LowPass, 20000.));
+    let closed = loudness_of(plain_synth(FilterMode::LowPass, 100.));
+
+    // The key is far above the cutoff, so that hardly anything gets through
+    assert!(closed < open * 0.1, "{closed} {open}");
+    assert!(loudness_of(plain_synth(FilterMode::HighPass, 100.)) > open * 0.8);
+
+    // Every waveform sounds
+    for waveform in [
+        Waveform::Saw,
+        Waveform::Square,
+        Waveform::Sine,
+        Waveform::Noise,
+    ] {
+        let mut synth = plain_synth(FilterMode::LowPass, 20000.);
+        synth.oscillators[0].waveform = waveform;
+
+        assert!(loudness_of(synth) > 0.05, "{waveform}");
+    }
+}
+
+#[test]
+fn synth_presets_round_trip() {
+    let mut synth = Synth::default();
+
+    synth.oscillators[0].waveform = Waveform::Square;
+    synth.oscillators[1].octave = -1;
+    synth.oscillators[1].detune = -12.5;
+    synth.filter.mode = FilterMode::BandPass;
+    synth.filter.resonance = 0.9;
+    synth.filter_envelope.attack = 0.25;
+    synth.lfo.target = LfoTarget::Volume;
+    synth.polyphony = 3;
+
+    assert_eq!(
+        decode_preset(&encode_preset(&synth).unwrap()).unwrap(),
+        synth
+    );
+    assert!(decode_preset(b"BTRT").is_err());
+
+    let path = std::env::temp_dir().join(format!("beatroot_preset_{}.btsyn", std::process::id()));
+
+    save_preset(&path, &synth).unwrap();
+    let loaded = open_preset(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded, synth);
+}
@@ -0,0 +1,253 @@
+mod common;
+
+use std::path::PathBuf;
+
+use beatroot::{
+    internals::{
+        clips::ClipShape,
+        midi::{DRUM_CHANNEL, MidiSong, MidiTrack, is_midi_file, read_midi, write_midi},
+        notes::{Note, NoteSequence},
+        patterns::{Pattern, PatternId, STEP_TICKS, Step},
+        tempo::{Meter, TempoChange, TempoMap},
+        timeline::{PPQ, Tick},
+    },
+    project_manager::{
+        history::History,
+        midi::{PATTERN_FIRST_KEY, import_midi, playlist_midi},
+    },
+    ui::panels::{lib::PanelStates, playlist::PlaylistState},
+};
+use common::{example_sample, temp_path};
+use egui::Color32;
+
+fn note(key: u8, start: u64, length: u64, velocity: f32) -> Note {
+    Note {
+        key,
+        start: Tick(start),
+        length: Tick(length),
+        velocity,
+    }
+}
+
+/// A song with a bass and a lead part, its tempo speeds up on the fifth beat and it changes to 6/8 on the third bar.
+fn example_song() -> MidiSong {
+    MidiSong {
+        tracks: vec![
+            MidiTrack {
+                name: String::from("Bass"),
+                channel: 0,
+                notes: vec![note(36, 0, PPQ * 2, 1.), note(38, PPQ * 2, PPQ, 64. / 127.)],
+            },
+            MidiTrack {
+                name: String::from("Lead"),
+                channel: 1,
+                notes: vec![note(72, PPQ / 2, PPQ * 7, 100. / 127.)],
+            },
+        ],
+        tempo_changes: vec![(Tick(0), 90.), (Tick(PPQ * 4), 140.)],
+        meter_changes: vec![
+            (
+                Tick(0),
+                Meter {
+                    numerator: 3,
+                    denominator: 4,
+                },
+            ),
+            (
+                Tick(PPQ * 6),
+                Meter {
+                    numerator: 6,
+                    denominator: 8,
+                },
+            ),
+        ],
+    }
+}
+
+#[test]
+fn songs_round_trip_through_midi_files() {
+    let song = example_song();
+
+    let bytes = write_midi(&song).unwrap();
+    assert_eq!(&bytes[..4], b"MThd");
+
+    assert_eq!(read_midi(&bytes).unwrap(), song);
+}
+
+#[test]
+fn reads_running_status_and_other_resolutions() {
+    #[rustfmt::skip]
+    let track = [
+        0x00, 0xFF, 0x03, 0x04, b'B', b'a', b's', b's',
+        // 100 bpm in 3/4
+        0x00, 0xFF, 0x51, 0x03, 0x09, 0x27, 0xC0,
+        0x00, 0xFF, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08,
+        0x00, 0x90, 0x3C, 0x64,
+        // A note on without velocity releases the key, both events leave out their status
+        0x83, 0x60, 0x3C, 0x00,
+        0x00, 0x40, 0x50,
+        0x81, 0x70, 0x80, 0x40, 0x00,
+        0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    // Format 0 with a single track and 480 ticks per quarter note
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend([0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xE0]);
+    bytes.extend(b"MTrk");
+    bytes.extend((track.len() as u32).to_be_bytes());
+    bytes.extend(track);
+
+    let song = read_midi(&bytes).unwrap();
+
+    assert_eq!(song.tempo_changes, vec![(Tick(0), 100.)]);
+    assert_eq!(
+        song.meter_changes,
+        vec![(
+            Tick(0),
+            Meter {
+                numerator: 3,
+                denominator: 4
+            }
+        )]
+    );
+
+    assert_eq!(song.tracks.len(), 1);
+    assert_eq!(song.tracks[0].name, "Bass");
+    assert_eq!(
+        song.tracks[0].notes,
+        vec![
+            note(60, 0, PPQ, 100. / 127.),
+            note(64, PPQ, PPQ / 2, 80. / 127.)
+        ]
+    );
+}
+
+#[test]
+fn rejects_meters_the_timeline_cannot_divide() {
+    // A meter of 7/32 at the start of the track
+    let track = [
+        0x00, 0xFF, 0x58, 0x04, 0x07, 0x05, 0x18, 0x08, 0x00, 0xFF, 0x2F, 0x00,
+    ];
+
+    let mut bytes = b"MThd".to_vec();
+    bytes.extend([0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xE0]);
+    bytes.extend(b"MTrk");
+    bytes.extend((track.len() as u32).to_be_bytes());
+    bytes.extend(track);
+
+    let error = read_midi(&bytes).unwrap_err();
+
+    assert!(error.to_string().contains("denominator above 16"));
+}
+
+#[test]
+fn rejects_files_which_are_not_midi() {
+    assert!(read_midi(b"RIFF\0\0\0\0WAVE").is_err());
+    assert!(read_midi(b"MThd").is_err());
+
+    assert!(is_midi_file(&PathBuf::from("/songs/intro.MID")));
+    assert!(!is_midi_file(&PathBuf::from("/songs/intro.wav")));
+}
+
+#[test]
+fn imports_every_part_onto_a_track_of_its_own() {
+    let path = temp_path("import", "mid");
+    std::fs::write(&path, write_midi(&example_song()).unwrap()).unwrap();
+
+    let states = PanelStates::default();
+    import_midi(&states, &path, 0, Tick(0)).unwrap();
+
+    {
+        let playlist = states.playlist_panel.read();
+
+        let names: Vec<&str> = playlist
+            .tracks
+            .iter()
+            .map(|track| track.customization.label_text.as_str())
+            .collect();
+        assert_eq!(names, vec!["Bass", "Lead"]);
+
+        let lead = playlist.tracks.get(1).unwrap().id;
+        let clip = playlist
+            .clips
+            .iter()
+            .find(|clip| clip.track == lead)
+            .unwrap();
+
+        // The clip ends on the beat after the last note
+        assert_eq!(clip.length, 8.);
+        assert_eq!(clip.source.notes().unwrap().notes.len(), 1);
+
+        assert_eq!(playlist.tempo.bpm, 90.);
+        assert_eq!(playlist.tempo.tempo_changes()[0].position, Tick(PPQ * 4));
+        assert_eq!(playlist.tempo.meter.numerator, 3);
+
+        // The sixth beat is the start of the third bar in 3/4
+        assert_eq!(playlist.tempo.meter_changes()[0].bar, 2);
+        assert_eq!(playlist.tempo.meter_changes()[0].meter.denominator, 8);
+    }
+
+    // The whole import is undone at once
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    assert_eq!(History::undo(&states), Some(format!("Import {stem}")));
+
+    let playlist = states.playlist_panel.read();
+    assert!(playlist.tracks.is_empty());
+    assert!(playlist.clips.is_empty());
+    assert_eq!(playlist.tempo, TempoMap::default());
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn exports_notes_and_patterns() {
+    let mut playlist = PlaylistState::default();
+    let track = playlist.tracks.create(0);
+    let id = track.id;
+    playlist.tracks.insert(0, track);
+
+    let mut notes = NoteSequence::new(String::from("Melody"), Color32::RED);
+    notes.notes = vec![note(60, 0, PPQ, 1.)];
+    playlist.clips.insert(id, Tick(PPQ * 4), 4., notes);
+
+    let mut pattern = Pattern::new(PatternId(0), String::from("Drums"), Color32::RED);
+    pattern.add_row(example_sample("snare", 250));
+    pattern.add_row(pattern.rows[0].sample.clone());
+    pattern.rows[1].steps[4] = Some(Step::default());
+    playlist.patterns.insert(pattern);
+
+    let clip = playlist.clips.insert(id, Tick(0), 4., PatternId(0));
+    let mut clip = playlist.clips.get(clip).unwrap().clone();
+    clip.shape = ClipShape {
+        gain: 0.5,
+        ..clip.shape
+    };
+    playlist.clips.insert_clip(clip);
+
+    playlist.tempo.set_tempo_change(TempoChange {
+        position: Tick(PPQ * 2),
+        bpm: 150.,
+        ramp: false,
+    });
+
+    let song = playlist_midi(&playlist);
+
+    assert_eq!(
+        song.tempo_changes,
+        vec![(Tick(0), playlist.tempo.bpm), (Tick(PPQ * 2), 150.)]
+    );
+    assert_eq!(song.meter_changes, vec![(Tick(0), Meter::default())]);
+
+    assert_eq!(song.tracks.len(), 2);
+    assert_eq!(song.tracks[0].notes, vec![note(60, PPQ * 4, PPQ, 1.)]);
+    assert_ne!(song.tracks[0].channel, DRUM_CHANNEL);
+
+    // The second row of the pattern is played on the key after the first one
+    let drums = &song.tracks[1];
+    assert_eq!(drums.channel, DRUM_CHANNEL);
+    assert_eq!(drums.notes.len(), 1);
+    assert_eq!(drums.notes[0].key, PATTERN_FIRST_KEY + 1);
+    assert_eq!(drums.notes[0].start, Tick(PPQ));
+    assert_eq!(drums.notes[0].length, Tick(STEP_TICKS));
+    assert_eq!(drums.notes[0].velocity, Step::default().velocity * 0.5);
+}
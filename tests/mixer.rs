@@ -0,0 +1,175 @@
+mod common;
+
+use std::path::Path;
+
+use beatroot::{
+    internals::{
+        playback::OUTPUT_CHANNELS,
+        render::{RenderSettings, render_playlist, render_stems},
+        sample::SampleProperties,
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::TrackId,
+        wav::{BitDepth, write_wav},
+    },
+    project_manager::{Project, open_project, save_project},
+    ui::panels::{
+        mixer::{ChannelStrip, MixerState},
+        playlist::{PlaylistState, SampleInstance},
+    },
+};
+use common::{SAMPLE_RATE, temp_path};
+use egui::Color32;
+
+/// A playlist with a tenth of a second long mono sample on the first beat of the first two tracks.
+fn two_track_playlist(sample_path: &Path) -> PlaylistState {
+    write_wav(
+        sample_path,
+        &vec![0.5; SAMPLE_RATE as usize / 10],
+        1,
+        SAMPLE_RATE,
+        BitDepth::Int16,
+    )
+    .unwrap();
+
+    let mut playlist = PlaylistState {
+        tempo: TempoMap::new(120.),
+        ..Default::default()
+    };
+
+    for row in 0..2 {
+        let track = playlist.tracks.create(row);
+        let id = track.id;
+
+        playlist.tracks.insert(row, track);
+        playlist.clips.insert(
+            id,
+            Tick(0),
+            0.1,
+            SampleInstance {
+                name: String::from("tone"),
+                color: Color32::WHITE,
+                path: sample_path.to_path_buf(),
+                properties: SampleProperties {
+                    sample_rate: SAMPLE_RATE,
+                    length: 100,
+                },
+                waveform_map: None,
+            },
+        );
+    }
+
+    playlist
+}
+
+/// The first frame of the render.
+fn first_frame(playlist: &PlaylistState, mixer: &MixerState) -> [f32; OUTPUT_CHANNELS] {
+    let rendered = render_playlist(playlist, mixer, &RenderSettings::default()).unwrap();
+
+    [rendered[0], rendered[1]]
+}
+
+fn assert_frame(frame: [f32; OUTPUT_CHANNELS], expected: [f32; OUTPUT_CHANNELS]) {
+    for (channel, expected) in frame.into_iter().zip(expected) {
+        assert!(
+            (channel - expected).abs() < 1e-3,
+            "{frame:?} != {expected:?}"
+        );
+    }
+}
+
+#[test]
+fn channel_strips_are_applied_to_the_render() {
+    let sample_path = temp_path("mixer_render", "wav");
+    let playlist = two_track_playlist(&sample_path);
+
+    let mut mixer = MixerState::default();
+    assert_frame(first_frame(&playlist, &mixer), [1., 1.]);
+
+    // Panning the first track to the left leaves only the second track on the right
+    mixer.channels.insert(
+        TrackId(0),
+        ChannelStrip {
+            pan: -1.,
+            ..Default::default()
+        },
+    );
+    assert_frame(first_frame(&playlist, &mixer), [1., 0.5]);
+
+    // Soloing the second track silences the first one
+    mixer.channels.insert(
+        TrackId(1),
+        ChannelStrip {
+            solo: true,
+            ..Default::default()
+        },
+    );
+    assert_frame(first_frame(&playlist, &mixer), [0.5, 0.5]);
+
+    // The master is applied after the tracks have been summed
+    mixer.master = ChannelStrip {
+        volume: 0.5,
+        ..Default::default()
+    };
+    assert_frame(first_frame(&playlist, &mixer), [0.25, 0.25]);
+
+    mixer.master.mute = true;
+    assert_frame(first_frame(&playlist, &mixer), [0., 0.]);
+
+    std::fs::remove_file(&sample_path).unwrap();
+}
+
+#[test]
+fn stems_ignore_mute_and_solo() {
+    let sample_path = temp_path("mixer_stems", "wav");
+    let playlist = two_track_playlist(&sample_path);
+
+    let mut mixer = MixerState::default();
+    mixer.channels.insert(
+        TrackId(0),
+        ChannelStrip {
+            volume: 0.5,
+            mute: true,
+            ..Default::default()
+        },
+    );
+    mixer.channels.insert(
+        TrackId(1),
+        ChannelStrip {
+            solo: true,
+            ..Default::default()
+        },
+    );
+    mixer.master.volume = 0.;
+
+    let stems = render_stems(&playlist, &mixer, &RenderSettings::default(), false).unwrap();
+    std::fs::remove_file(&sample_path).unwrap();
+
+    // The volume of the track is kept, everything else would make the stems inaudible
+    assert_frame([stems[0].samples[0], stems[0].samples[1]], [0.25, 0.25]);
+    assert_frame([stems[1].samples[0], stems[1].samples[1]], [0.5, 0.5]);
+}
+
+#[test]
+fn mixer_is_saved_with_the_project() {
+    let path = temp_path("mixer_project", "btrt");
+
+    let mut project = Project::default();
+    project.mixer.channels.insert(
+        TrackId(3),
+        ChannelStrip {
+            volume: 0.25,
+            pan: 0.5,
+            mute: true,
+            solo: false,
+        },
+    );
+    project.mixer.master.volume = 1.5;
+
+    save_project(&path, &project).unwrap();
+    let opened = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(opened.mixer.channels, project.mixer.channels);
+    assert_eq!(opened.mixer.master, project.mixer.master);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use std::sync::Arc;
+
+use beatroot::{
+    internals::{
+        instruments::Instrument,
+        notes::{Note, NoteSequence, Scale, ScaleKind},
+        playback::{OUTPUT_CHANNELS, ScheduledNote, Transport, clip_notes},
+        tempo::TempoMap,
+        timeline::{PPQ, SnapGrid, Tick},
+        tracks::TrackId,
+    },
+    ui::panels::playlist::PlaylistState,
+};
+use common::SAMPLE_RATE;
+use egui::Color32;
+
+fn note(key: u8, start: f64, length: f64) -> Note {
+    Note {
+        key,
+        start: Tick::from_beats(start),
+        length: Tick::from_beats(length),
+        velocity: 1.,
+    }
+}
+
+fn example_notes() -> NoteSequence {
+    let mut notes = NoteSequence::new(String::from("Melody"), Color32::RED);
+
+    notes.notes = vec![note(64, 2., 1.), note(60, 0., 4.), note(67, 3.5, 1.)];
+
+    notes
+}
+
+#[test]
+fn clips_play_the_notes_inside_of_them() {
+    let notes = example_notes();
+
+    let keys: Vec<u8> = notes.played(0., 8.).iter().map(|note| note.key).collect();
+    assert_eq!(keys, vec![60, 64, 67]);
+
+    // The note starting before the clip is not played, the last one is cut off at the end of the clip
+    let played = notes.played(1., 3.);
+    assert_eq!(played.len(), 2);
+    assert_eq!((played[0].beat, played[0].length), (1., 1.));
+    assert_eq!((played[1].beat, played[1].length), (2.5, 0.5));
+}
+
+#[test]
+fn clip_notes_are_played_by_the_instrument_of_their_track() {
+    let mut playlist = PlaylistState::default();
+
+    let id = playlist
+        .clips
+        .insert(TrackId(2), Tick::from_beats(4.), 4., example_notes());
+
+    let mut clip = playlist.clips.get(id).unwrap().clone();
+    clip.shape.gain = 0.5;
+    playlist.clips.insert_clip(clip);
+
+    let notes = clip_notes(&playlist);
+    assert_eq!(notes.len(), 3);
+    assert_eq!(notes[1].beat, 6.);
+    assert_eq!(notes[1].track, TrackId(2));
+    assert_eq!(notes[1].velocity, 0.5);
+    assert_eq!(*notes[1].instrument, Instrument::Sine);
+
+    // Note clips follow the tempo like patterns do
+    playlist.tempo = TempoMap::new(60.);
+    assert_eq!(
+        playlist.clips.get(id).unwrap().end(&playlist.tempo),
+        Tick::from_beats(8.)
+    );
+}
+
+#[test]
+fn quantize_moves_notes_onto_the_grid() {
+    let mut notes = NoteSequence::new(String::from("Loose"), Color32::RED);
+    let step = PPQ / 4;
+
+    notes.notes = vec![
+        Note {
+            key: 60,
+            start: Tick(step + step / 3),
+            length: Tick(step * 2),
+            velocity: 1.,
+        },
+        Note {
+            key: 62,
+            start: Tick(step * 4 - 1),
+            length: Tick(1),
+            velocity: 1.,
+        },
+    ];
+
+    let mut unchanged = notes.clone();
+    unchanged.quantize([0, 1], SnapGrid::Off);
+    assert_eq!(unchanged, notes);
+
+    notes.quantize([0, 1, 5], SnapGrid::Quarter);
+
+    assert_eq!(
+        (notes.notes[0].start, notes.notes[0].length),
+        (Tick(step), Tick(step * 2))
+    );
+
+    // Very short notes are kept a single step long
+    assert_eq!(
+        (notes.notes[1].start, notes.notes[1].length),
+        (Tick(step * 4), Tick(step))
+    );
+}
+
+#[test]
+fn scales_contain_their_keys_in_every_octave() {
+    let scale = Scale {
+        root: 9,
+        kind: ScaleKind::Minor,
+    };
+
+    // A minor has the white keys of the keyboard
+    for key in [57, 59, 60, 62, 64, 65, 67, 69, 81] {
+        assert!(scale.contains(key), "{key}");
+    }
+
+    for key in [58, 61, 63, 66, 68] {
+        assert!(!scale.contains(key), "{key}");
+    }
+
+    assert!(scale.is_root(21) && scale.is_root(69));
+    assert!(!scale.is_root(60));
+}
+
+#[test]
+fn notes_sound_from_their_beat() {
+    let mut transport = Transport::new(SAMPLE_RATE);
+
+    transport.set_tempo(TempoMap::new(120.));
+    transport.set_notes(vec![ScheduledNote {
+        beat: 1.,
+        length: 1.,
+        track: TrackId::default(),
+        key: 69,
+        velocity: 1.,
+        instrument: Arc::new(Instrument::Sine),
+        audio: None,
+    }]);
+    transport.seek(0.);
+    transport.set_playing(true);
+
+    // A beat lasts half a second at 120 beats per minute
+    let mut output = vec![0.; SAMPLE_RATE as usize * 2 * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    let left: Vec<f32> = output.iter().step_by(OUTPUT_CHANNELS).copied().collect();
+    let beat = SAMPLE_RATE as usize / 2;
+
+    assert!(left[..beat].iter().all(|sample| *sample == 0.));
+    assert!(left[beat..beat * 2].iter().any(|sample| sample.abs() > 0.1));
+
+    // The note has faded out after it has been released
+    assert!(left[beat * 3..].iter().all(|sample| *sample == 0.));
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use beatroot::{
+    internals::{
+        clips::ClipShape,
+        patterns::{DEFAULT_VELOCITY, Pattern, PatternId, PatternStore, Step},
+        playback::clip_triggers,
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::TrackId,
+    },
+    project_manager::history::{Edit, History},
+    ui::panels::{lib::PanelStates, playlist::PlaylistState},
+};
+use common::example_sample;
+use egui::{Color32, Id};
+
+/// A 16 step pattern with a kick on every beat and a muted snare on the second beat.
+fn example_pattern(id: PatternId) -> Pattern {
+    let mut pattern = Pattern::new(id, String::from("Drums"), Color32::RED);
+
+    pattern.add_row(example_sample("kick", 250));
+    pattern.add_row(example_sample("snare", 250));
+
+    for step in (0..16).step_by(4) {
+        pattern.rows[0].steps[step] = Some(Step::default());
+    }
+
+    pattern.rows[1].steps[4] = Some(Step {
+        velocity: 1.,
+        muted: true,
+    });
+
+    pattern
+}
+
+#[test]
+fn hits_loop_the_pattern_and_skip_muted_steps() {
+    let pattern = example_pattern(PatternId(0));
+
+    assert_eq!(pattern.length_in_beats(), 4.);
+
+    let beats: Vec<f64> = pattern.hits(0., 8.).iter().map(|hit| hit.beat).collect();
+    assert_eq!(beats, vec![0., 1., 2., 3., 4., 5., 6., 7.]);
+    assert!(pattern.hits(0., 8.).iter().all(|hit| hit.row == 0));
+
+    // The hits are counted from the start of the clip, not from the start of the pattern
+    let beats: Vec<f64> = pattern.hits(2.5, 2.).iter().map(|hit| hit.beat).collect();
+    assert_eq!(beats, vec![0.5, 1.5]);
+}
+
+#[test]
+fn pattern_clips_follow_the_tempo() {
+    let mut playlist = PlaylistState::default();
+    let pattern = example_pattern(PatternId(0));
+    let length = pattern.length_in_beats();
+
+    playlist.patterns.insert(pattern);
+    let id = playlist.clips.insert(
+        TrackId::default(),
+        Tick::from_beats(4.),
+        length,
+        PatternId(0),
+    );
+
+    for bpm in [90., 180.] {
+        playlist.tempo = TempoMap::new(bpm);
+
+        let clip = playlist.clips.get(id).unwrap();
+        assert_eq!(clip.length_in_beats(&playlist.tempo), 4.);
+        assert_eq!(clip.end(&playlist.tempo), Tick::from_beats(8.));
+    }
+
+    // Splitting the clip keeps the second half playing the rest of the pattern
+    let clip = playlist.clips.get(id).unwrap().clone();
+    let (first, second) = clip
+        .split(
+            Tick::from_beats(5.),
+            playlist.clips.allocate_id(),
+            &playlist.tempo,
+        )
+        .unwrap();
+    assert_eq!((first.length, second.length), (1., 3.));
+    assert_eq!(second.shape.offset, 1.);
+}
+
+#[test]
+fn triggers_follow_the_edits_of_the_pattern() {
+    let mut playlist = PlaylistState::default();
+    let mut pattern = example_pattern(PatternId(3));
+
+    playlist.patterns.insert(pattern.clone());
+    let id = playlist
+        .clips
+        .insert(TrackId(1), Tick::from_beats(8.), 2., PatternId(3));
+
+    let mut clip = playlist.clips.get(id).unwrap().clone();
+    clip.shape = ClipShape {
+        gain: 0.5,
+        ..Default::default()
+    };
+    playlist.clips.insert_clip(clip);
+
+    let triggers = clip_triggers(&playlist);
+    assert_eq!(triggers.len(), 2);
+    assert_eq!(triggers[1].beat, 9.);
+    assert_eq!(triggers[1].track, TrackId(1));
+    assert_eq!(triggers[1].sample.name, "kick");
+    assert_eq!(triggers[1].shape.gain, 0.5 * DEFAULT_VELOCITY);
+
+    // Unmuting the snare is heard by every clip of the pattern
+    pattern.rows[1].steps[4].as_mut().unwrap().muted = false;
+    playlist.patterns.insert(pattern);
+
+    let triggers = clip_triggers(&playlist);
+    assert_eq!(triggers.len(), 3);
+    assert!(
+        triggers
+            .iter()
+            .any(|trigger| trigger.sample.name == "snare" && trigger.shape.gain == 0.5)
+    );
+}
+
+#[test]
+fn pattern_changes_are_undone() {
+    let states = PanelStates::default();
+    let group = Id::new(("pattern", 0));
+    let before = example_pattern(PatternId(0));
+
+    let insert = Edit::InsertPattern {
+        pattern: before.clone(),
+    };
+    insert.apply(&states);
+    states.history.write().record("New pattern", insert);
+
+    // Dragging the velocity of a step is recorded as a single entry
+    let mut previous = before.clone();
+    for velocity in [0.6, 0.4, 0.2] {
+        let mut after = previous.clone();
+        after.rows[0].steps[0].as_mut().unwrap().velocity = velocity;
+
+        states.playlist_panel.write().patterns.insert(after.clone());
+        states.history.write().record_grouped(
+            group,
+            "Change pattern",
+            Edit::ChangePattern {
+                before: previous,
+                after: after.clone(),
+            },
+        );
+
+        previous = after;
+    }
+    states.history.write().close_group(group);
+
+    assert_eq!(states.history.read().undo_entries().len(), 2);
+
+    assert_eq!(History::undo(&states).as_deref(), Some("Change pattern"));
+    assert_eq!(
+        states
+            .playlist_panel
+            .read()
+            .patterns
+            .get(PatternId(0))
+            .unwrap()
+            .rows[0]
+            .steps[0],
+        Some(Step::default())
+    );
+
+    assert_eq!(History::undo(&states).as_deref(), Some("New pattern"));
+    assert!(states.playlist_panel.read().patterns.is_empty());
+}
+
+#[test]
+fn removed_pattern_ids_are_not_handed_out_again_after_loading() {
+    let mut store = PatternStore::default();
+    let removed = store.create(Color32::RED);
+    store.insert(removed.clone());
+    store.remove(removed.id);
+
+    let mut store: PatternStore =
+        rmp_serde::from_slice(&rmp_serde::to_vec_named(&store).unwrap()).unwrap();
+
+    assert!(store.is_empty());
+    assert_ne!(store.create(Color32::RED).id, removed.id);
+}
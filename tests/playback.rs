@@ -0,0 +1,341 @@
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use beatroot::{
+    internals::{
+        clips::ClipShape,
+        metronome::MetronomeSettings,
+        playback::{OUTPUT_CHANNELS, PlaybackEngine, ScheduledSample, Transport},
+        sample::{DecodedSample, SampleProperties},
+        tempo::TempoMap,
+        timeline::Tick,
+        tracks::TrackId,
+    },
+    ui::panels::{
+        mixer::{ChannelStrip, MixerState},
+        playlist::{PlaylistState, SampleInstance},
+    },
+};
+use common::{SAMPLE_RATE, temp_path};
+use egui::Color32;
+
+/// Writes a mono 16 bit wav file containing a constant signal.
+fn write_test_wav(name: &str, frames: usize) -> PathBuf {
+    let path = temp_path(name, "wav");
+    let data_len = (frames * 2) as u32;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+
+    for _ in 0..frames {
+        bytes.extend_from_slice(&(i16::MAX / 2).to_le_bytes());
+    }
+
+    std::fs::write(&path, bytes).unwrap();
+
+    path
+}
+
+/// Syncs the engine once the samples of the playlist have been decoded in the background.
+fn sync_decoded(engine: &PlaybackEngine, playlist: &PlaylistState, generation: u64) {
+    engine.sync(playlist, &MixerState::default(), generation);
+
+    while engine.is_decoding() {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    engine.sync(playlist, &MixerState::default(), generation);
+}
+
+/// Renders the given amount of frames and returns the left channel.
+fn render_frames(engine: &PlaybackEngine, frames: usize) -> Vec<f32> {
+    let mut buffer = vec![0.; frames * OUTPUT_CHANNELS];
+
+    engine.render(&mut buffer);
+
+    buffer.iter().step_by(OUTPUT_CHANNELS).copied().collect()
+}
+
+/// A playlist at 120 bpm with the sample on the second beat.
+fn playlist_with_sample(path: &Path) -> PlaylistState {
+    let mut playlist = PlaylistState {
+        tempo: TempoMap::new(120.),
+        ..Default::default()
+    };
+    playlist.clips.insert(
+        TrackId::default(),
+        Tick::from_beats(1.),
+        0.1,
+        SampleInstance {
+            name: String::from("tone"),
+            color: Color32::WHITE,
+            path: path.to_path_buf(),
+            properties: SampleProperties {
+                sample_rate: SAMPLE_RATE,
+                length: 100,
+            },
+            waveform_map: None,
+        },
+    );
+
+    playlist
+}
+
+#[test]
+fn engine_plays_samples_on_their_beat() {
+    let path = write_test_wav("engine_plays", SAMPLE_RATE as usize / 10);
+    let engine = PlaybackEngine::new(SAMPLE_RATE);
+
+    // The sample is left out until it has been decoded
+    engine.sync(&playlist_with_sample(&path), &MixerState::default(), 0);
+    engine.play();
+    assert!(
+        render_frames(&engine, SAMPLE_RATE as usize)
+            .iter()
+            .all(|sample| *sample == 0.)
+    );
+    engine.stop();
+
+    sync_decoded(&engine, &playlist_with_sample(&path), 0);
+
+    // Nothing is rendered until the engine is started
+    assert!(
+        render_frames(&engine, 1024)
+            .iter()
+            .all(|sample| *sample == 0.)
+    );
+    assert_eq!(engine.position(), 0.);
+
+    engine.play();
+
+    // At 120 bpm a beat is half a second, so the sample starts at frame 22050
+    let output = render_frames(&engine, SAMPLE_RATE as usize);
+    std::fs::remove_file(&path).unwrap();
+
+    let first_audible = output.iter().position(|sample| *sample != 0.).unwrap();
+
+    assert_eq!(first_audible, SAMPLE_RATE as usize / 2);
+    assert!((engine.position() - 2.).abs() < 1e-9);
+
+    // Pausing keeps the position, stopping rewinds
+    engine.pause();
+    render_frames(&engine, 1024);
+    assert!((engine.position() - 2.).abs() < 1e-9);
+
+    engine.stop();
+    assert_eq!(engine.position(), 0.);
+}
+
+#[test]
+fn engine_is_only_rebuilt_when_the_project_changes() {
+    let path = write_test_wav("engine_generation", SAMPLE_RATE as usize / 10);
+    let engine = PlaybackEngine::new(SAMPLE_RATE);
+    let is_audible = |engine: &PlaybackEngine| {
+        engine.stop();
+        engine.play();
+
+        render_frames(engine, SAMPLE_RATE as usize)
+            .iter()
+            .any(|sample| *sample != 0.)
+    };
+
+    sync_decoded(&engine, &playlist_with_sample(&path), 1);
+    std::fs::remove_file(&path).unwrap();
+    assert!(is_audible(&engine));
+
+    // The playlist is not looked at again as long as the generation stays the same
+    engine.sync(&PlaylistState::default(), &MixerState::default(), 1);
+    assert!(is_audible(&engine));
+
+    engine.sync(&PlaylistState::default(), &MixerState::default(), 2);
+    assert!(!is_audible(&engine));
+}
+
+#[test]
+fn mixer_is_applied_without_rebuilding_the_timeline() {
+    let path = write_test_wav("engine_mixer", SAMPLE_RATE as usize / 10);
+    let playlist = playlist_with_sample(&path);
+    let engine = PlaybackEngine::new(SAMPLE_RATE);
+    let is_audible = |engine: &PlaybackEngine| {
+        engine.stop();
+        engine.play();
+
+        render_frames(engine, SAMPLE_RATE as usize)
+            .iter()
+            .any(|sample| *sample != 0.)
+    };
+
+    sync_decoded(&engine, &playlist, 1);
+    std::fs::remove_file(&path).unwrap();
+
+    let muted = MixerState {
+        master: ChannelStrip {
+            mute: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // Only the mixer changes, the generation stays the same
+    engine.sync(&playlist, &muted, 1);
+    assert!(!is_audible(&engine));
+
+    engine.sync(&playlist, &MixerState::default(), 1);
+    assert!(is_audible(&engine));
+}
+
+#[test]
+fn tempo_is_followed_without_a_new_generation() {
+    let engine = PlaybackEngine::new(SAMPLE_RATE);
+    let mut playlist = PlaylistState::default();
+
+    playlist.tempo.bpm = 120.;
+    engine.sync(&playlist, &MixerState::default(), 0);
+    engine.play();
+    render_frames(&engine, SAMPLE_RATE as usize);
+    assert!((engine.position() - 2.).abs() < 1e-9);
+
+    playlist.tempo.bpm = 60.;
+    engine.sync(&playlist, &MixerState::default(), 0);
+    render_frames(&engine, SAMPLE_RATE as usize);
+    assert!((engine.position() - 3.).abs() < 1e-9);
+}
+
+#[test]
+fn seeking_starts_samples_from_their_middle() {
+    let audio = Arc::new(DecodedSample {
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        samples: (0..SAMPLE_RATE).map(|frame| frame as f32).collect(),
+    });
+
+    let mut transport = Transport::new(SAMPLE_RATE);
+    transport.set_tempo(TempoMap::new(60.));
+    transport.set_samples(vec![ScheduledSample {
+        beat: 0.,
+        track: TrackId::default(),
+        audio,
+        length: 1.,
+        shape: ClipShape::default(),
+    }]);
+
+    // At 60 bpm a beat is a second, so half a beat is half of the sample
+    transport.seek(0.5);
+    transport.set_playing(true);
+
+    let mut output = vec![0.; 2 * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    assert_eq!(output, vec![22050., 22050., 22051., 22051.]);
+}
+
+#[test]
+fn loops_wrap_back_to_their_start() {
+    let audio = Arc::new(DecodedSample {
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        samples: (0..SAMPLE_RATE).map(|frame| frame as f32).collect(),
+    });
+
+    let mut transport = Transport::new(SAMPLE_RATE);
+    transport.set_tempo(TempoMap::new(60.));
+    transport.set_samples(vec![ScheduledSample {
+        beat: 0.,
+        track: TrackId::default(),
+        audio,
+        length: 1.,
+        shape: ClipShape::default(),
+    }]);
+
+    // At 60 bpm the loop is half a second long
+    transport.set_loop(Some(0.0..0.5));
+    transport.set_playing(true);
+
+    let mut output = vec![0.; SAMPLE_RATE as usize * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    let left: Vec<f32> = output.iter().step_by(OUTPUT_CHANNELS).copied().collect();
+    let half = SAMPLE_RATE as usize / 2;
+
+    // The sample is restarted right after the end of the loop, within the same block
+    assert_eq!(left[half - 1], (half - 1) as f32);
+    assert_eq!(left[half], 0.);
+    assert_eq!(left[half + 1], 1.);
+    assert!(transport.position().abs() < 1e-9);
+}
+
+#[test]
+fn count_in_delays_the_playback() {
+    let metronome = MetronomeSettings::default().metronome(|_| None).unwrap();
+
+    let mut transport = Transport::new(SAMPLE_RATE);
+    transport.set_tempo(TempoMap::new(120.));
+    transport.count_in(1, &metronome);
+    transport.set_playing(true);
+
+    // A bar of 4/4 at 120 bpm is two seconds long, the position only moves once it has been clicked
+    let mut output = vec![0.; SAMPLE_RATE as usize * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    assert!(transport.is_counting_in());
+    assert_eq!(transport.position(), 0.);
+    assert!(output.iter().any(|sample| *sample != 0.));
+
+    let mut output = vec![0.; SAMPLE_RATE as usize * 2 * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    assert!(!transport.is_counting_in());
+    assert!((transport.position() - 2.).abs() < 1e-9);
+}
+
+#[test]
+fn clips_play_their_trimmed_and_shaped_part() {
+    let audio = Arc::new(DecodedSample {
+        sample_rate: SAMPLE_RATE,
+        channels: 1,
+        samples: (0..SAMPLE_RATE).map(|frame| frame as f32).collect(),
+    });
+
+    // The second half of the sample is skipped, the rest is played backwards at half the gain
+    let mut transport = Transport::new(SAMPLE_RATE);
+    transport.set_tempo(TempoMap::new(60.));
+    transport.set_samples(vec![ScheduledSample {
+        beat: 0.,
+        track: TrackId::default(),
+        audio,
+        length: 0.5,
+        shape: ClipShape {
+            offset: 0.5,
+            gain: 0.5,
+            reverse: true,
+            ..Default::default()
+        },
+    }]);
+    transport.set_playing(true);
+
+    let mut output = vec![0.; SAMPLE_RATE as usize * OUTPUT_CHANNELS];
+    transport.render(&mut output);
+
+    let left: Vec<f32> = output.iter().step_by(OUTPUT_CHANNELS).copied().collect();
+    let half = SAMPLE_RATE as usize / 2;
+
+    assert_eq!(left[0], half as f32 * 0.5);
+    assert_eq!(left[half - 1], 0.5);
+    assert_eq!(left[half], 0.);
+}
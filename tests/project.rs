@@ -0,0 +1,458 @@
+mod common;
+
+use std::{collections::HashMap, path::PathBuf};
+
+use beatroot::{
+    internals::{
+        clips::{ClipShape, Fade, FadeCurve},
+        instruments::{Envelope, Instrument, LoopPoints, Sampler, SamplerMode},
+        notes::{Note, NoteSequence},
+        patterns::Step,
+        sample::SampleProperties,
+        synth::{FilterMode, Synth, Waveform},
+        tempo::{Meter, TempoChange},
+        timeline::Tick,
+    },
+    project_manager::{
+        Project, open_project, save_project,
+        schema::{self, MAGIC, ProjectHeader, UnsupportedVersion, v1},
+    },
+    ui::panels::{
+        lib::PanelStates,
+        media::WorkspaceSampleAttributes,
+        playlist::{SampleInstance, TrackCustomization},
+    },
+};
+use common::temp_path;
+use egui::{Color32, vec2};
+use indexmap::IndexMap;
+
+fn example_project() -> Project {
+    let mut project = Project::default();
+    let sample_path = PathBuf::from("/samples/kick.wav");
+
+    project.playlist.tempo.bpm = 140.0;
+    project.playlist.tempo.set_tempo_change(TempoChange {
+        position: Tick::from_beats(16.),
+        bpm: 160.0,
+        ramp: true,
+    });
+    project.playlist.tempo.set_meter_change(
+        4,
+        Meter {
+            numerator: 7,
+            denominator: 8,
+        },
+    );
+    project.playlist.grid_offset = vec2(-120.0, -40.0);
+    for row in 0..3 {
+        let track = project.playlist.tracks.create(row);
+        project.playlist.tracks.insert(row, track);
+    }
+    project.playlist.tracks.move_track(0, 2);
+
+    let drums = project.playlist.tracks.get(2).unwrap().id;
+    project
+        .playlist
+        .tracks
+        .find_mut(drums)
+        .unwrap()
+        .customization = TrackCustomization {
+        label_text: String::from("Drums"),
+        label_text_color: Color32::BLACK,
+        label_color: Color32::RED,
+        height: 60.0,
+    };
+    let id = project.playlist.clips.insert(
+        drums,
+        Tick::from_beats(8.),
+        0.5,
+        SampleInstance {
+            name: String::from("kick"),
+            color: Color32::BLUE,
+            path: sample_path.clone(),
+            properties: SampleProperties {
+                sample_rate: 44100,
+                length: 500,
+            },
+            waveform_map: Some(vec![[-0.5, 0.5]]),
+        },
+    );
+
+    let mut clip = project.playlist.clips.get(id).unwrap().clone();
+    clip.shape = ClipShape {
+        offset: 0.125,
+        gain: 0.5,
+        reverse: true,
+        fade_in: Fade {
+            length: 0.05,
+            curve: FadeCurve::EqualPower,
+        },
+        fade_out: Fade {
+            length: 0.1,
+            curve: FadeCurve::Logarithmic,
+        },
+    };
+    project.playlist.clips.insert_clip(clip);
+    project.workspace.workspace_samples.insert(
+        sample_path,
+        WorkspaceSampleAttributes {
+            alias: String::from("kick"),
+            is_color_synced: true,
+            color: Color32::BLUE,
+            waveform_map: None,
+        },
+    );
+
+    project
+}
+
+#[test]
+fn project_round_trips_through_file() {
+    let path = temp_path("round_trip", "btrt");
+    let project = example_project();
+
+    save_project(&path, &project).unwrap();
+    let loaded = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(loaded.playlist.tempo, project.playlist.tempo);
+    assert_eq!(loaded.playlist.grid_offset, vec2(-120.0, -40.0));
+    assert_eq!(loaded.playlist.tracks, project.playlist.tracks);
+
+    // The clip stays on the reordered track
+    let clip = loaded.playlist.clips.iter().next().unwrap();
+    assert_eq!(loaded.playlist.tracks.row(clip.track), Some(2));
+    assert_eq!((clip.start, clip.length), (Tick::from_beats(8.), 0.5));
+    assert_eq!(
+        clip.source.sample().unwrap().path,
+        PathBuf::from("/samples/kick.wav")
+    );
+    assert_eq!(clip.source.sample().unwrap().properties.length, 500);
+    assert_eq!(
+        clip.shape,
+        project.playlist.clips.get(clip.id).unwrap().shape
+    );
+
+    let (path, attributes) = loaded.workspace.workspace_samples.first().unwrap();
+    assert_eq!(*path, PathBuf::from("/samples/kick.wav"));
+    assert_eq!(attributes.alias, "kick");
+}
+
+#[test]
+fn patterns_round_trip_through_file() {
+    let path = temp_path("patterns", "btrt");
+    let mut project = example_project();
+
+    let mut pattern = project.playlist.patterns.create(Color32::GREEN);
+    pattern.set_step_count(32);
+    pattern.add_row(SampleInstance {
+        name: String::from("hat"),
+        color: Color32::YELLOW,
+        path: PathBuf::from("/samples/hat.wav"),
+        properties: SampleProperties {
+            sample_rate: 48000,
+            length: 120,
+        },
+        waveform_map: None,
+    });
+    pattern.rows[0].steps[20] = Some(Step {
+        velocity: 0.25,
+        muted: true,
+    });
+    let pattern_id = pattern.id;
+    project.playlist.patterns.insert(pattern);
+
+    let track = project.playlist.tracks.get(0).unwrap().id;
+    let id = project
+        .playlist
+        .clips
+        .insert(track, Tick::from_beats(2.), 12., pattern_id);
+
+    save_project(&path, &project).unwrap();
+    let loaded = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let pattern = loaded.playlist.patterns.get(pattern_id).unwrap();
+    assert_eq!(pattern.step_count(), 32);
+    assert_eq!(pattern.color, Color32::GREEN);
+    assert_eq!(
+        pattern.rows[0].sample.path,
+        PathBuf::from("/samples/hat.wav")
+    );
+    assert_eq!(
+        pattern.rows[0].steps[20],
+        Some(Step {
+            velocity: 0.25,
+            muted: true,
+        })
+    );
+
+    // The clip keeps playing the pattern, its length stays in beats
+    let clip = loaded.playlist.clips.get(id).unwrap();
+    assert_eq!(clip.source.pattern(), Some(pattern_id));
+    assert_eq!(
+        (clip.track, clip.start, clip.length),
+        (track, Tick::from_beats(2.), 12.)
+    );
+    assert_eq!(loaded.playlist.clips.len(), 2);
+
+    // The sample of the pattern is a part of the project
+    assert!(
+        loaded
+            .referenced_media()
+            .contains(&PathBuf::from("/samples/hat.wav"))
+    );
+}
+
+#[test]
+fn note_clips_round_trip_through_file() {
+    let path = temp_path("notes", "btrt");
+    let mut project = example_project();
+
+    let mut notes = NoteSequence::new(String::from("Chords"), Color32::YELLOW);
+    notes.notes = vec![
+        Note {
+            key: 60,
+            start: Tick::from_beats(0.5),
+            length: Tick::from_beats(1.),
+            velocity: 0.5,
+        },
+        Note {
+            key: 64,
+            start: Tick(7),
+            length: Tick(13),
+            velocity: 1.,
+        },
+    ];
+
+    let track = project.playlist.tracks.get(1).unwrap().id;
+    let id = project
+        .playlist
+        .clips
+        .insert(track, Tick::from_beats(4.), 4., notes.clone());
+
+    save_project(&path, &project).unwrap();
+    let loaded = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    // The notes are stored in the clip, their positions stay in ticks
+    let clip = loaded.playlist.clips.get(id).unwrap();
+    assert_eq!(clip.source.notes(), Some(&notes));
+    assert_eq!(
+        (clip.track, clip.start, clip.length),
+        (track, Tick::from_beats(4.), 4.)
+    );
+    assert_eq!(
+        loaded.playlist.tracks.find(track).unwrap().instrument,
+        Instrument::Sine
+    );
+}
+
+#[test]
+fn samplers_round_trip_through_file() {
+    let path = temp_path("sampler", "btrt");
+    let mut project = example_project();
+    let sample_path = PathBuf::from("/samples/piano.wav");
+
+    let sampler = Sampler {
+        sample: Some(SampleInstance {
+            name: String::from("piano"),
+            color: Color32::GREEN,
+            path: sample_path.clone(),
+            properties: SampleProperties {
+                sample_rate: 48000,
+                length: 2000,
+            },
+            waveform_map: None,
+        }),
+        root: 57,
+        envelope: Envelope {
+            attack: 0.01,
+            decay: 0.5,
+            sustain: 0.75,
+            release: 1.5,
+        },
+        mode: SamplerMode::Gate,
+        loop_points: Some(LoopPoints {
+            start: 0.5,
+            end: 1.75,
+        }),
+        polyphony: 4,
+        choke_group: Some(3),
+    };
+
+    let track = project.playlist.tracks.get(1).unwrap().id;
+    project.playlist.tracks.find_mut(track).unwrap().instrument =
+        Instrument::Sampler(sampler.clone());
+
+    // The sample of the sampler is collected along with the samples of the clips
+    assert!(project.referenced_media().contains(&sample_path));
+
+    save_project(&path, &project).unwrap();
+    let loaded = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        loaded.playlist.tracks.find(track).unwrap().instrument,
+        Instrument::Sampler(sampler)
+    );
+}
+
+#[test]
+fn synths_round_trip_through_file() {
+    let path = temp_path("synth", "btrt");
+    let mut project = example_project();
+
+    let mut synth = Synth::default();
+    synth.oscillators[1].waveform = Waveform::Noise;
+    synth.filter.mode = FilterMode::HighPass;
+    synth.amp_envelope.sustain = 0.5;
+    synth.lfo.rate = 0.25;
+
+    let track = project.playlist.tracks.get(0).unwrap().id;
+    project.playlist.tracks.find_mut(track).unwrap().instrument = Instrument::Synth(synth);
+
+    save_project(&path, &project).unwrap();
+    let loaded = open_project(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        loaded.playlist.tracks.find(track).unwrap().instrument,
+        Instrument::Synth(synth)
+    );
+}
+
+#[test]
+fn project_restores_panel_states() {
+    let states = PanelStates::default();
+
+    example_project().restore(&states);
+
+    assert_eq!(states.playlist_panel.read().tempo.bpm, 140.0);
+    assert_eq!(states.playlist_panel.read().clips.len(), 1);
+    assert_eq!(
+        states
+            .media_panel
+            .read()
+            .workspace_selector
+            .workspace_samples
+            .len(),
+        1
+    );
+
+    // Capturing the restored state should produce the same project
+    let captured = Project::capture(&states);
+    assert_eq!(captured.playlist.tempo.bpm, 140.0);
+    assert_eq!(captured.workspace.workspace_samples.len(), 1);
+}
+
+#[test]
+fn opening_garbage_fails() {
+    let path = temp_path("garbage", "btrt");
+
+    std::fs::write(&path, b"definitely not a project").unwrap();
+    let result = open_project(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn headerless_projects_are_migrated() {
+    let sample_path = PathBuf::from("/samples/snare.wav");
+    let legacy = v1::ProjectDto {
+        playlist: v1::PlaylistDto {
+            bpm: 90.0,
+            grid_offset: v1::Vec2Dto { x: -10.0, y: 0.0 },
+            custom_tracks: HashMap::from([(
+                1,
+                v1::TrackCustomizationDto {
+                    label_text: String::from("Snares"),
+                    label_text_color: [255, 255, 255, 255],
+                    label_color: [255, 0, 0, 255],
+                    height: 80.0,
+                    height_set: true,
+                },
+            )]),
+            samples: IndexMap::from([(
+                v1::PositionDto { track: 1, beat: 4 },
+                v1::SampleInstanceDto {
+                    name: String::from("snare"),
+                    color: [0, 0, 255, 255],
+                    path: sample_path.clone(),
+                    properties: v1::SamplePropertiesDto {
+                        sample_rate: 48000,
+                        length: 250,
+                    },
+                    waveform_map: Some(vec![[-1.0, 1.0]]),
+                },
+            )]),
+        },
+        workspace: v1::WorkspaceDto {
+            workspace_samples: IndexMap::from([(
+                sample_path.clone(),
+                v1::WorkspaceSampleDto {
+                    alias: String::from("snare"),
+                    is_color_synced: false,
+                    color: [0, 0, 255, 255],
+                    waveform_map: None,
+                },
+            )]),
+        },
+    };
+
+    let project =
+        Project::from(schema::decode_project(&rmp_serde::to_vec_named(&legacy).unwrap()).unwrap());
+
+    assert_eq!(project.playlist.tempo.bpm, 90.0);
+    // Every row until the last used one becomes a track
+    let tracks = &project.playlist.tracks;
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks.get(0).unwrap().customization.label_text, "Track 0");
+    assert_eq!(tracks.get(1).unwrap().customization.label_text, "Snares");
+    assert_eq!(
+        tracks.get(1).unwrap().customization.label_color,
+        Color32::RED
+    );
+
+    let clip = project
+        .playlist
+        .clips
+        .track_clips(tracks.get(1).unwrap().id)
+        .next()
+        .unwrap();
+    assert_eq!(clip.start, Tick::from_beats(4.));
+    assert_eq!(clip.length, 0.25);
+    assert_eq!(clip.shape, ClipShape::default());
+
+    let sample = clip.source.sample().unwrap();
+    assert_eq!(sample.path, sample_path);
+    assert_eq!(sample.properties.sample_rate, 48000);
+    assert_eq!(sample.properties.length, 250);
+
+    assert!(!project.workspace.workspace_samples[&sample_path].is_color_synced);
+}
+
+#[test]
+fn newer_project_versions_are_refused() {
+    let mut bytes = MAGIC.to_vec();
+    rmp_serde::encode::write_named(
+        &mut bytes,
+        &ProjectHeader {
+            version: schema::CURRENT_VERSION + 1,
+            app_version: String::from("99.0.0"),
+        },
+    )
+    .unwrap();
+
+    let error = schema::decode_project(&bytes).unwrap_err();
+
+    assert_eq!(
+        error.downcast_ref::<UnsupportedVersion>(),
+        Some(&UnsupportedVersion {
+            found: schema::CURRENT_VERSION + 1,
+            supported: schema::CURRENT_VERSION,
+        })
+    );
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use beatroot::{
+    project_manager::{
+        Project, open_project,
+        recovery::{
+            AutosaveSettings, begin_session, create_session_dir, discard_sessions, end_session,
+            list_snapshots, newest_snapshot, spawn_autosave, write_snapshot,
+        },
+    },
+    ui::panels::lib::PanelStates,
+};
+use common::temp_dir;
+use parking_lot::RwLock;
+
+#[test]
+fn unclean_exits_are_detected() {
+    let dir = temp_dir("recovery_session");
+
+    assert!(!begin_session(&dir).unwrap());
+    let session = create_session_dir(&dir).unwrap();
+    end_session(&session).unwrap();
+
+    // The marker is left behind when the application crashes
+    assert!(!begin_session(&dir).unwrap());
+    create_session_dir(&dir).unwrap();
+    assert!(begin_session(&dir).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ending_a_session_leaves_the_other_sessions_alone() {
+    let dir = temp_dir("recovery_running");
+
+    begin_session(&dir).unwrap();
+    let running = create_session_dir(&dir).unwrap();
+    std::thread::sleep(Duration::from_millis(2));
+    let session = create_session_dir(&dir).unwrap();
+
+    end_session(&session).unwrap();
+
+    assert!(!session.exists());
+    assert!(running.exists());
+
+    // The other session has not ended yet
+    assert!(begin_session(&dir).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn snapshots_are_rotated() {
+    let dir = temp_dir("recovery_snapshots");
+    let mut project = Project::default();
+
+    for bpm in [100.0, 110.0, 120.0, 130.0] {
+        project.playlist.tempo.bpm = bpm;
+        write_snapshot(&dir, &project, 3).unwrap();
+
+        // Make sure that every snapshot gets its own timestamp
+        std::thread::sleep(std::time::Duration::from_millis(2));
+    }
+
+    assert_eq!(list_snapshots(&dir).len(), 3);
+
+    let newest = open_project(&newest_snapshot(&dir).unwrap()).unwrap();
+    assert_eq!(newest.playlist.tempo.bpm, 130.0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sessions_keep_their_snapshots_apart() {
+    let dir = temp_dir("recovery_sessions");
+    let mut project = Project::default();
+
+    // The crashed session has left a snapshot behind
+    begin_session(&dir).unwrap();
+    let crashed = create_session_dir(&dir).unwrap();
+    project.playlist.tempo.bpm = 90.0;
+    write_snapshot(&crashed, &project, 1).unwrap();
+
+    assert!(begin_session(&dir).unwrap());
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let session = create_session_dir(&dir).unwrap();
+
+    for bpm in [100.0, 110.0] {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        project.playlist.tempo.bpm = bpm;
+        write_snapshot(&session, &project, 1).unwrap();
+    }
+
+    // Rotating the snapshots of the new session leaves the crashed one alone
+    assert_eq!(list_snapshots(&crashed).len(), 1);
+    assert_eq!(list_snapshots(&session).len(), 1);
+
+    let newest = open_project(&newest_snapshot(&dir).unwrap()).unwrap();
+    assert_eq!(newest.playlist.tempo.bpm, 110.0);
+
+    discard_sessions(&dir, Some(&session)).unwrap();
+    assert!(!crashed.exists());
+    assert!(session.exists());
+
+    end_session(&session).unwrap();
+    assert_eq!(newest_snapshot(&dir), None);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn unchanged_projects_are_not_snapshotted() {
+    let dir = temp_dir("recovery_autosave");
+    let states = Arc::new(PanelStates::default());
+    let settings = Arc::new(RwLock::new(AutosaveSettings {
+        interval_secs: 1,
+        ..Default::default()
+    }));
+
+    let task = spawn_autosave(states.clone(), settings, dir.clone());
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(list_snapshots(&dir).is_empty());
+
+    states.history.write().mark_changed();
+
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    task.abort();
+    assert_eq!(list_snapshots(&dir).len(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
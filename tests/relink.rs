@@ -0,0 +1,184 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use beatroot::{
+    internals::{fs::relative_path, sample::SampleProperties, timeline::Tick, tracks::TrackId},
+    project_manager::{
+        Project, open_project,
+        relink::{MatchCriteria, find_missing_media, relink, search_candidates},
+        save_project,
+    },
+    ui::panels::{lib::PanelStates, media::WorkspaceSampleAttributes, playlist::SampleInstance},
+};
+use common::temp_dir;
+use egui::Color32;
+
+fn project_with_sample(path: &Path) -> Project {
+    let mut project = Project::default();
+
+    project.playlist.clips.insert(
+        TrackId::default(),
+        Tick(0),
+        0.,
+        SampleInstance {
+            name: String::from("hat"),
+            color: Color32::WHITE,
+            path: path.to_path_buf(),
+            properties: SampleProperties::default(),
+            waveform_map: None,
+        },
+    );
+    project.workspace.workspace_samples.insert(
+        path.to_path_buf(),
+        WorkspaceSampleAttributes {
+            alias: String::from("hat"),
+            ..Default::default()
+        },
+    );
+
+    project
+}
+
+#[test]
+fn relative_paths() {
+    assert_eq!(
+        relative_path(Path::new("/a/b/c.wav"), Path::new("/a/d")),
+        Some(PathBuf::from("../b/c.wav"))
+    );
+    assert_eq!(
+        relative_path(Path::new("/a/b/c.wav"), Path::new("/a")),
+        Some(PathBuf::from("b/c.wav"))
+    );
+    assert_eq!(relative_path(Path::new("b/c.wav"), Path::new("/a")), None);
+}
+
+#[test]
+fn project_moved_with_its_samples_still_resolves() {
+    let original = temp_dir("moved_original");
+    let moved = temp_dir("moved_target");
+
+    std::fs::create_dir_all(original.join("samples")).unwrap();
+    std::fs::write(original.join("samples/hat.wav"), b"hat").unwrap();
+
+    let project = project_with_sample(&original.join("samples/hat.wav"));
+    save_project(&original.join("song.btrt"), &project).unwrap();
+
+    // Move the whole folder
+    std::fs::remove_dir_all(&moved).unwrap();
+    std::fs::rename(&original, &moved).unwrap();
+
+    let loaded = open_project(&moved.join("song.btrt")).unwrap();
+    std::fs::remove_dir_all(&moved).unwrap();
+
+    let expected = moved.join("samples/hat.wav");
+    assert_eq!(
+        loaded
+            .playlist
+            .clips
+            .iter()
+            .next()
+            .unwrap()
+            .source
+            .sample()
+            .unwrap()
+            .path,
+        expected
+    );
+    assert!(loaded.workspace.workspace_samples.contains_key(&expected));
+    assert!(loaded.media.contains_key(&expected));
+}
+
+#[test]
+fn missing_media_is_found_and_relinked() {
+    let dir = temp_dir("missing_media");
+    let original = dir.join("old/hat.wav");
+    let renamed = dir.join("new/renamed_hat.wav");
+
+    std::fs::create_dir_all(original.parent().unwrap()).unwrap();
+    std::fs::write(&original, b"hat sample").unwrap();
+
+    save_project(&dir.join("song.btrt"), &project_with_sample(&original)).unwrap();
+
+    // Rename the sample, after this only its contents can be used to find it
+    std::fs::create_dir_all(renamed.parent().unwrap()).unwrap();
+    std::fs::rename(&original, &renamed).unwrap();
+    std::fs::write(dir.join("new/decoy.wav"), b"different").unwrap();
+
+    let project = open_project(&dir.join("song.btrt")).unwrap();
+    let mut missing = find_missing_media(&project);
+
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].path, original);
+
+    // The file name does not match anymore
+    search_candidates(
+        &mut missing,
+        std::slice::from_ref(&dir),
+        MatchCriteria::default(),
+    );
+    assert!(missing[0].candidates.is_empty());
+
+    let by_contents = MatchCriteria {
+        file_name: false,
+        size: true,
+        hash: true,
+    };
+    search_candidates(&mut missing, std::slice::from_ref(&dir), by_contents);
+    assert_eq!(missing[0].candidates, vec![renamed.clone()]);
+
+    let states = PanelStates::default();
+    project.restore(&states);
+
+    relink(&states, &original, &renamed);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(
+        states
+            .playlist_panel
+            .read()
+            .clips
+            .iter()
+            .next()
+            .unwrap()
+            .source
+            .sample()
+            .unwrap()
+            .path,
+        renamed
+    );
+    assert!(
+        states
+            .media_panel
+            .read()
+            .workspace_selector
+            .workspace_samples
+            .contains_key(&renamed)
+    );
+}
+
+#[test]
+fn fingerprints_of_missing_media_survive_saving() {
+    let dir = temp_dir("missing_fingerprint");
+    let sample = dir.join("hat.wav");
+    let path = dir.join("song.btrt");
+
+    std::fs::write(&sample, b"hat sample").unwrap();
+    save_project(&path, &project_with_sample(&sample)).unwrap();
+    std::fs::remove_file(&sample).unwrap();
+
+    // Saving from the app captures the panel states, the missing file cannot be fingerprinted again
+    let states = PanelStates::default();
+    open_project(&path).unwrap().restore(&states);
+    save_project(&path, &Project::capture(&states)).unwrap();
+
+    let project = open_project(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let missing = find_missing_media(&project);
+    assert_eq!(missing.len(), 1);
+    assert_eq!(
+        missing[0].fingerprint.map(|fingerprint| fingerprint.size),
+        Some(10)
+    );
+}
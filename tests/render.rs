@@ -0,0 +1,244 @@
+mod common;
+
+use std::path::Path;
+
+use beatroot::{
+    internals::{
+        playback::OUTPUT_CHANNELS,
+        render::{
+            RenderRange, RenderSettings, export_stems, export_wav, render_playlist, render_stems,
+        },
+        sample::{SampleProperties, decode_sample},
+        tempo::TempoMap,
+        timeline::Tick,
+        wav::{BitDepth, encode_wav, write_wav},
+    },
+    ui::panels::{
+        mixer::MixerState,
+        playlist::{PlaylistState, SampleInstance, TrackCustomization},
+    },
+};
+use common::{SAMPLE_RATE, temp_path};
+use egui::Color32;
+
+/// A playlist of three tracks, containing a tenth of a second long mono sample on the given beats of the first track, at 120 bpm.
+fn example_playlist(sample_path: &Path, beats: &[usize]) -> PlaylistState {
+    write_wav(
+        sample_path,
+        &vec![0.5; SAMPLE_RATE as usize / 10],
+        1,
+        SAMPLE_RATE,
+        BitDepth::Int16,
+    )
+    .unwrap();
+
+    let mut playlist = PlaylistState {
+        tempo: TempoMap::new(120.),
+        ..Default::default()
+    };
+
+    for row in 0..3 {
+        let track = playlist.tracks.create(row);
+        playlist.tracks.insert(row, track);
+    }
+
+    let first_track = playlist.tracks.get(0).unwrap().id;
+
+    for beat in beats {
+        playlist.clips.insert(
+            first_track,
+            Tick::from_beats(*beat as f64),
+            0.1,
+            SampleInstance {
+                name: String::from("tone"),
+                color: Color32::WHITE,
+                path: sample_path.to_path_buf(),
+                properties: SampleProperties {
+                    sample_rate: SAMPLE_RATE,
+                    length: 100,
+                },
+                waveform_map: None,
+            },
+        );
+    }
+
+    playlist
+}
+
+#[test]
+fn song_is_rendered_until_the_last_sample_ends() {
+    let sample_path = temp_path("render_song", "wav");
+    let playlist = example_playlist(&sample_path, &[0, 2]);
+
+    let rendered = render_playlist(
+        &playlist,
+        &MixerState::default(),
+        &RenderSettings::default(),
+    )
+    .unwrap();
+    std::fs::remove_file(&sample_path).unwrap();
+
+    // The second sample starts after a second and lasts for a tenth of a second
+    let frames = rendered.len() / OUTPUT_CHANNELS;
+    assert_eq!(frames, SAMPLE_RATE as usize * 11 / 10);
+
+    let left: Vec<f32> = rendered.iter().step_by(OUTPUT_CHANNELS).copied().collect();
+    assert!((left[0] - 0.5).abs() < 1e-3);
+    assert_eq!(left[SAMPLE_RATE as usize / 2], 0.);
+    assert!((left[SAMPLE_RATE as usize] - 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn bar_range_starts_at_the_first_bar() {
+    let sample_path = temp_path("render_bars", "wav");
+    let playlist = example_playlist(&sample_path, &[4]);
+
+    let settings = RenderSettings {
+        range: RenderRange::Bars { first: 2, last: 2 },
+        ..Default::default()
+    };
+
+    let rendered = render_playlist(&playlist, &MixerState::default(), &settings).unwrap();
+    std::fs::remove_file(&sample_path).unwrap();
+
+    // A bar at 120 bpm is two seconds long, the sample is on the first beat of the second bar
+    assert_eq!(rendered.len(), SAMPLE_RATE as usize * 2 * OUTPUT_CHANNELS);
+    assert!((rendered[0] - 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn export_resamples_into_the_chosen_format() {
+    let sample_path = temp_path("export_input", "wav");
+    let export_path = temp_path("export_output", "wav");
+    let playlist = example_playlist(&sample_path, &[0]);
+
+    let settings = RenderSettings {
+        sample_rate: 48000,
+        bit_depth: BitDepth::Float32,
+        range: RenderRange::Song,
+        include_metronome: false,
+    };
+
+    export_wav(&export_path, &playlist, &MixerState::default(), &settings).unwrap();
+
+    let exported = decode_sample(&export_path).unwrap();
+    std::fs::remove_file(&sample_path).unwrap();
+    std::fs::remove_file(&export_path).unwrap();
+
+    assert_eq!(exported.sample_rate, 48000);
+    assert_eq!(exported.channels, OUTPUT_CHANNELS);
+    assert_eq!(exported.frames(), 4800);
+}
+
+#[test]
+fn stems_line_up_and_empty_tracks_can_be_skipped() {
+    let sample_path = temp_path("render_stems", "wav");
+    let mut playlist = example_playlist(&sample_path, &[0]);
+
+    // Move a copy of the sample onto the third track, two beats later
+    let sample = playlist
+        .clips
+        .iter()
+        .next()
+        .unwrap()
+        .source
+        .sample()
+        .unwrap()
+        .clone();
+    let third_track = playlist.tracks.get(2).unwrap().id;
+
+    playlist
+        .clips
+        .insert(third_track, Tick::from_beats(2.), 0.1, sample);
+    playlist.tracks.find_mut(third_track).unwrap().customization = TrackCustomization {
+        label_text: String::from("Drums"),
+        ..TrackCustomization::named_default(2)
+    };
+
+    let settings = RenderSettings::default();
+    let mixer = MixerState::default();
+
+    let every_track = render_stems(&playlist, &mixer, &settings, false).unwrap();
+    let used_tracks = render_stems(&playlist, &mixer, &settings, true).unwrap();
+
+    assert_eq!(every_track.len(), 3);
+    assert_eq!(used_tracks.len(), 2);
+    assert!(every_track[1].samples.iter().all(|sample| *sample == 0.));
+
+    // Every stem is as long as the whole song
+    for stem in &every_track {
+        assert_eq!(stem.samples.len(), every_track[0].samples.len());
+    }
+
+    let names: Vec<&str> = used_tracks.iter().map(|stem| stem.name.as_str()).collect();
+    assert_eq!(names, ["Track 0", "Drums"]);
+
+    let folder = std::env::temp_dir().join(format!("beatroot_stems_{}", std::process::id()));
+    let written = export_stems(&folder, &playlist, &mixer, &settings, true).unwrap();
+
+    assert_eq!(
+        written,
+        [folder.join("Track 0.wav"), folder.join("Drums.wav")]
+    );
+
+    std::fs::remove_dir_all(&folder).unwrap();
+    std::fs::remove_file(&sample_path).unwrap();
+}
+
+#[test]
+fn wav_header_matches_the_bit_depth() {
+    let samples = [0., 1., -1., 0.5];
+
+    for (bit_depth, bytes_per_sample) in [
+        (BitDepth::Int16, 2),
+        (BitDepth::Int24, 3),
+        (BitDepth::Float32, 4),
+    ] {
+        let bytes = encode_wav(&samples, 2, SAMPLE_RATE, bit_depth);
+
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize,
+            bytes.len() - 8
+        );
+
+        let data_len = u32::from_le_bytes(
+            bytes[bytes.len() - samples.len() * bytes_per_sample - 4..][..4]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(data_len as usize, samples.len() * bytes_per_sample);
+    }
+}
+
+#[test]
+fn metronome_is_only_rendered_when_opted_in() {
+    let mut playlist = PlaylistState {
+        tempo: TempoMap::new(120.),
+        ..Default::default()
+    };
+    playlist.metronome.enabled = true;
+
+    let mut settings = RenderSettings {
+        range: RenderRange::Bars { first: 1, last: 1 },
+        ..Default::default()
+    };
+
+    let rendered = render_playlist(&playlist, &MixerState::default(), &settings).unwrap();
+    assert!(rendered.iter().all(|sample| *sample == 0.));
+
+    settings.include_metronome = true;
+
+    let rendered = render_playlist(&playlist, &MixerState::default(), &settings).unwrap();
+    let beat_frames = SAMPLE_RATE as usize / 2;
+
+    // Every beat of the bar clicks
+    for beat in 0..4 {
+        let click = &rendered[beat * beat_frames * OUTPUT_CHANNELS..][..beat_frames / 4];
+
+        assert!(click.iter().any(|sample| sample.abs() > 0.1));
+    }
+
+    let silence = &rendered[(beat_frames / 2) * OUTPUT_CHANNELS..beat_frames * OUTPUT_CHANNELS];
+    assert!(silence.iter().all(|sample| sample.abs() < 1e-3));
+}
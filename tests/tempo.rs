@@ -0,0 +1,92 @@
+use beatroot::internals::{
+    tempo::{Meter, TempoChange, TempoMap},
+    timeline::{PPQ, Tick, TimeFormat},
+};
+
+fn meter(numerator: u8, denominator: u8) -> Meter {
+    Meter {
+        numerator,
+        denominator,
+    }
+}
+
+#[test]
+fn tempo_changes_affect_the_following_beats() {
+    let mut tempo = TempoMap::new(120.);
+
+    tempo.set_tempo_change(TempoChange {
+        position: Tick::from_beats(4.),
+        bpm: 60.,
+        ramp: false,
+    });
+
+    // Four beats at 120 bpm take two seconds, every beat after them takes a second
+    assert_eq!(tempo.seconds_at(4.), 2.);
+    assert_eq!(tempo.seconds_at(6.), 4.);
+    assert_eq!(tempo.beats_at(3.), 5.);
+    assert_eq!(tempo.bpm_at(3.), 120.);
+    assert_eq!(tempo.bpm_at(4.), 60.);
+
+    // A second starting on the third beat is split between the two tempos
+    assert_eq!(tempo.beats_in(3., 1.), 1.5);
+}
+
+#[test]
+fn ramps_change_the_tempo_gradually() {
+    let mut tempo = TempoMap::new(60.);
+
+    tempo.set_tempo_change(TempoChange {
+        position: Tick::from_beats(4.),
+        bpm: 60.,
+        ramp: true,
+    });
+    tempo.set_tempo_change(TempoChange {
+        position: Tick::from_beats(8.),
+        bpm: 120.,
+        ramp: false,
+    });
+
+    assert_eq!(tempo.bpm_at(6.), 90.);
+
+    // Speeding up from 60 to 120 bpm takes between two and four seconds for the four beats
+    let ramp = tempo.seconds_at(8.) - tempo.seconds_at(4.);
+    assert!((ramp - 4. * 2f64.ln()).abs() < 1e-9);
+
+    // Converting back and forth lands on the same beat
+    for beat in [0.5, 4., 5.25, 7.9, 12.] {
+        assert!((tempo.beats_at(tempo.seconds_at(beat)) - beat).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn meter_changes_move_the_following_bars() {
+    let mut tempo = TempoMap::new(120.);
+
+    tempo.set_meter_change(2, meter(7, 8));
+    tempo.set_meter_change(3, meter(3, 4));
+
+    // Two bars of 4/4, one of 7/8, then 3/4
+    assert_eq!(tempo.bar(2).start, Tick(8 * PPQ));
+    assert_eq!(tempo.bar(3).start, Tick(8 * PPQ + 7 * PPQ / 2));
+    assert_eq!(tempo.bar(5).start, Tick(8 * PPQ + 7 * PPQ / 2 + 6 * PPQ));
+
+    let bar = tempo.bar_at(Tick::from_beats(11.));
+    assert_eq!((bar.index, bar.meter), (2, meter(7, 8)));
+
+    // Removing the 7/8 bar makes it a regular bar again
+    tempo.remove_meter_change(2);
+    assert_eq!(tempo.bar(3).start, Tick(12 * PPQ));
+}
+
+#[test]
+fn bars_are_counted_in_the_beats_of_the_meter() {
+    let mut tempo = TempoMap::new(120.);
+
+    tempo.meter = meter(6, 8);
+
+    // The second half of the second bar, a bar of 6/8 is three quarter notes long
+    let position = Tick::from_beats(4.5);
+
+    assert_eq!(TimeFormat::Bars.format(position, &tempo), "2.4.000");
+    assert_eq!(TimeFormat::Clock.format(position, &tempo), "00:02.250");
+}
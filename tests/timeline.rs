@@ -0,0 +1,56 @@
+use beatroot::internals::{
+    tempo::TempoMap,
+    timeline::{PPQ, SnapGrid, Tick, TimeFormat},
+};
+use strum::IntoEnumIterator;
+
+#[test]
+fn every_grid_lines_up_with_whole_ticks() {
+    for grid in SnapGrid::iter() {
+        if let Some(step) = grid.ticks() {
+            assert_eq!(PPQ % step, 0, "{grid} does not divide a beat evenly");
+        }
+    }
+}
+
+#[test]
+fn positions_snap_back_to_the_previous_line() {
+    // Just before the second half of the third beat
+    let position = Tick::from_beats(2.49);
+
+    assert_eq!(position.snap(SnapGrid::Beat), Tick::from_beats(2.));
+    assert_eq!(position.snap(SnapGrid::Half), Tick::from_beats(2.));
+    assert_eq!(position.snap(SnapGrid::Quarter), Tick::from_beats(2.25));
+    assert_eq!(position.snap(SnapGrid::Sixteenth), Tick::from_beats(2.4375));
+
+    // Triplets divide the beat into three
+    assert_eq!(position.snap(SnapGrid::Third), Tick(2 * PPQ + PPQ / 3));
+
+    // Without snapping the clip stays where it has been dropped
+    assert_eq!(position.snap(SnapGrid::Off), position);
+}
+
+#[test]
+fn beats_convert_to_the_nearest_tick() {
+    assert_eq!(Tick::from_beats(1. / 3.), Tick(PPQ / 3));
+    assert_eq!(Tick::from_beats(0.75).as_beats(), 0.75);
+
+    // There is nothing before the start of the song
+    assert_eq!(Tick::from_beats(-1.), Tick(0));
+}
+
+#[test]
+fn positions_are_formatted_in_every_time_format() {
+    // At 120 bpm a beat is half a second, this is the second half of the sixth beat
+    let position = Tick::from_beats(5.5);
+    let tempo = TempoMap::new(120.);
+
+    assert_eq!(TimeFormat::Bars.format(position, &tempo), "2.2.480");
+    assert_eq!(TimeFormat::Clock.format(position, &tempo), "00:02.750");
+    assert_eq!(TimeFormat::Smpte.format(position, &tempo), "00:00:02:22");
+
+    // An hour and a minute in
+    let position = Tick::from_beats(2. * 3660.);
+    assert_eq!(TimeFormat::Clock.format(position, &tempo), "61:00.000");
+    assert_eq!(TimeFormat::Smpte.format(position, &tempo), "01:01:00:00");
+}
@@ -0,0 +1,50 @@
+use beatroot::ui::panels::playlist::{HORIZONTAL_ZOOM, PlaylistZoom};
+
+#[test]
+fn zooming_keeps_the_anchored_beat_in_place() {
+    let mut zoom = PlaylistZoom::default();
+    let (scroll_x, anchor_x) = (-500., 300.);
+    let anchor_beat = (anchor_x - scroll_x) / zoom.beat_width();
+
+    let scroll_x = zoom.zoom_around(scroll_x, anchor_x, 2.);
+
+    assert_eq!(zoom.horizontal, 2.);
+    assert!((scroll_x + anchor_beat * zoom.beat_width() - anchor_x).abs() < 1e-3);
+
+    // Zooming out near the start can not scroll before beat 0
+    let mut zoom = PlaylistZoom::default();
+    assert_eq!(zoom.zoom_around(-100., 300., 0.25), 0.);
+}
+
+#[test]
+fn zoom_stays_inside_of_its_limits() {
+    let mut zoom = PlaylistZoom::default();
+
+    zoom.zoom_around(0., 0., 1000.);
+    assert_eq!(zoom.horizontal, *HORIZONTAL_ZOOM.end());
+
+    zoom.zoom_around(0., 0., 0.0001);
+    assert_eq!(zoom.horizontal, *HORIZONTAL_ZOOM.start());
+
+    // A single beat can not be stretched across a whole screen
+    zoom.fit_beats(100_000., 4.0..4.5);
+    assert_eq!(zoom.horizontal, *HORIZONTAL_ZOOM.end());
+}
+
+#[test]
+fn fitting_a_selection_fills_the_width() {
+    let mut zoom = PlaylistZoom::default();
+
+    let scroll_x = zoom.fit_beats(800., 8.0..16.0);
+
+    assert_eq!(zoom.beat_width(), 100.);
+    assert_eq!(scroll_x, -800.);
+}
+
+#[test]
+fn following_only_scrolls_once_the_cursor_has_left() {
+    let zoom = PlaylistZoom::default();
+
+    assert_eq!(zoom.follow(-100., 500., 10.), -100.);
+    assert_eq!(zoom.follow(-100., 500., 40.), -40. * zoom.beat_width());
+}